@@ -1,10 +1,18 @@
-use std::time::Instant;
-
-use onitama_game::game::{
-    card::CARD_NAMES, deck::Deck, done_move::DoneMove, move_result::MoveResult,
-    player_color::PlayerColor, r#move::Move, state::State,
+use std::{cell::RefCell, time::Instant};
+
+use onitama_game::{
+    ai::{
+        analysis::AnalysisLine,
+        mcts::{solver::ProofStatus, SearchInfo},
+    },
+    common::seeded_rng,
+    game::{
+        card::CARD_NAMES, deck::Deck, done_move::DoneMove, move_result::MoveResult,
+        player_color::PlayerColor, r#move::Move, state::State,
+    },
 };
 
+use rand::rngs::SmallRng;
 use rand_distr::{Dirichlet, Distribution};
 use tch::{IndexOp, Tensor};
 
@@ -42,12 +50,15 @@ pub struct MctsArena<'a> {
     pub model: &'a ConvResNet,
     pub options: Options,
     pub reward: fn(MoveResult, PlayerColor) -> f64,
+    /// Selection does not mutate the tree, so the generator of the noise is in the cell
+    pub rng: RefCell<SmallRng>,
 }
 
 impl<'a> MctsArena<'a> {
     pub fn new(
         state: State,
         player_color: PlayerColor,
+        ply: usize,
         config: AlphaZeroMctsConfig,
         model: &'a ConvResNet,
         options: Options,
@@ -56,6 +67,9 @@ impl<'a> MctsArena<'a> {
         // root is always first in the arena
         let root = MctsNode::new(None, 0, None, player_color, 1.);
         let arena = vec![root];
+        // The ply is mixed in, so a position which is repeated later in the game gets new noise
+        let seed = config.seed.map(|seed| seed.wrapping_add(ply as u64));
+        let rng = seeded_rng(seed, state.zobrist_hash(player_color));
 
         Self {
             game_state: MctsState {
@@ -68,14 +82,18 @@ impl<'a> MctsArena<'a> {
             model,
             options,
             reward,
+            rng: RefCell::new(rng),
         }
     }
 
-    /// Seach the best move
+    /// Seach the best move. The search is stopped as soon as the root is proven
     pub fn search(&mut self) -> (DoneMove, Tensor) {
         let now = Instant::now();
 
-        while self.playouts < self.config.max_playouts && now.elapsed() < self.config.search_time {
+        while self.playouts < self.config.max_playouts
+            && now.elapsed() < self.config.search_time
+            && !self.arena[0].proof.is_proven()
+        {
             self.playout();
             self.playouts += 1;
         }
@@ -84,35 +102,102 @@ impl<'a> MctsArena<'a> {
 
         let priors = self.calculate_priors(children);
 
-        let best_child_idx = children
-            .iter()
-            .max_by(|&a, &b| {
-                let a = self.arena[*a].visits as f64 / self.arena[0].visits as f64;
-                let b = self.arena[*b].visits as f64 / self.arena[0].visits as f64;
-                a.total_cmp(&b)
-            })
-            .expect("Must find the best child");
+        let best_child_idx = self.best_child();
 
         (
-            self.arena[*best_child_idx]
+            self.arena[best_child_idx]
                 .mov
                 .expect("A child node must have a move"),
             priors,
         )
     }
 
+    /// The value is the winrate of the best move
+    pub fn search_info(&self) -> SearchInfo {
+        let best_child = &self.arena[self.best_child()];
+        SearchInfo {
+            best_move: best_child.mov.expect("A child node must have a move"),
+            value: best_child.winrate,
+            playouts: self.playouts,
+            proof: best_child.proof,
+        }
+    }
+
+    /// The most visited child of the root. Proven wins and losses go before the visits
+    fn best_child(&self) -> usize {
+        self.best_child_of(0).expect("Must find the best child")
+    }
+
+    fn best_child_of(&self, node_idx: usize) -> Option<usize> {
+        self.arena[node_idx]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&c| self.arena[c].proof.best_move_key(self.arena[c].visits))
+    }
+
+    /// The best root children with their statistics, the first one is the move of the search.
+    /// The continuation follows the best children while they are visited
+    pub fn analysis(&self, lines: usize) -> Vec<AnalysisLine> {
+        // The stable sort keeps the last of the equal children first like `max_by_key`
+        let mut children = self.arena[0].children.clone();
+        children.reverse();
+        children.sort_by_key(|&c| {
+            std::cmp::Reverse(self.arena[c].proof.best_move_key(self.arena[c].visits))
+        });
+
+        children
+            .into_iter()
+            .take(lines)
+            .map(|child| {
+                let node = &self.arena[child];
+                let mut pv = vec![node.mov.expect("A child node must have a move")];
+
+                let mut node_idx = child;
+                while let Some(next) = self.best_child_of(node_idx) {
+                    let next = &self.arena[next];
+                    if next.visits == 0 && !next.proof.is_proven() {
+                        break;
+                    }
+                    pv.push(next.mov.expect("A child node must have a move"));
+                    node_idx = next.idx;
+                }
+
+                AnalysisLine {
+                    done_move: pv[0],
+                    score: node.winrate,
+                    visits: node.visits,
+                    depth: 0,
+                    pv,
+                }
+            })
+            .collect()
+    }
+
     fn calculate_priors(&self, children: &Vec<usize>) -> Tensor {
         let mut priors = Tensor::zeros(&[2, 25], self.options.to_tuple());
 
+        // The search stops after the win is proven, so the visits can be
+        // too few to show it. Then the whole probability goes to the winning move
+        let proven_win = match self.arena[0].proof {
+            ProofStatus::Loss(_) => Some(self.best_child()),
+            _ => None,
+        };
+
         for child_idx in children.iter() {
             let child = &self.arena[*child_idx];
+            let visits = match proven_win {
+                Some(best_child) if best_child == *child_idx => 1,
+                Some(_) => 0,
+                None => child.visits,
+            };
 
             let idx = match child.mov.unwrap().used_card_idx {
                 0 | 2 => 0,
                 1 | 3 => 1,
                 _ => panic!("Incorrect card index"),
             };
-            *(&mut priors.i((idx, child.mov.unwrap().mov.to as i64))) += child.visits as i64;
+            *(&mut priors.i((idx, child.mov.unwrap().mov.to as i64))) += visits as i64;
         }
         let sum = priors.sum(self.options.kind);
         // prevent division by zero
@@ -128,8 +213,12 @@ impl<'a> MctsArena<'a> {
         let mut game_state = self.game_state.clone();
         let mut node_idx = 0; // root node
 
-        // 1. Select the best node and make a move with it
-        while self.arena[node_idx].is_expanded && !self.arena[node_idx].is_terminal {
+        // 1. Select the best node and make a move with it.
+        // Proven nodes are not searched further, their value is known
+        while self.arena[node_idx].is_expanded
+            && !self.arena[node_idx].is_terminal
+            && !self.arena[node_idx].proof.is_proven()
+        {
             node_idx = self.select(&self.arena[node_idx]);
 
             if let Some(mov) = self.arena[node_idx].mov {
@@ -148,10 +237,17 @@ impl<'a> MctsArena<'a> {
 
                 if move_result.is_win() {
                     self.arena[node_idx].is_terminal = true;
+                    self.arena[node_idx].proof = ProofStatus::Win(0);
                 }
             }
         }
 
+        // The exact value of the proven node does not need the neural network
+        if let Some(reward) = self.arena[node_idx].proof.reward() {
+            self.back_propagate(node_idx, reward as f64);
+            return;
+        }
+
         // 2. Evaluate position using the neural network
         let evaluation_result = self.evaluate(&game_state);
 
@@ -177,7 +273,7 @@ impl<'a> MctsArena<'a> {
     }
 
     /// Selects the best child by UCT score
-    /// 1. Get parent's children
+    /// 1. Get parent's children which are not proven to lose
     /// 2. Select the best child using UCT score
     /// 3. Return the best child's index
     fn select(&self, parent: &MctsNode) -> usize {
@@ -185,14 +281,14 @@ impl<'a> MctsArena<'a> {
         // Values from Silver paper
         let epsilon = 0.25;
         let eta = 0.03;
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
 
         let mut uct = |child: &MctsNode| {
             // if it is a root node, apply Dirichlet noise
             if parent.parent == None && self.config.train {
                 let action_num = children.len();
                 let dirichlet = Dirichlet::new_with_size(eta, action_num).unwrap();
-                let noise_vector = dirichlet.sample(&mut rng);
+                let noise_vector = dirichlet.sample(&mut *rng);
                 // Root is at 0th index, other root children are in sequence
                 let noise = noise_vector[child.idx - 1];
 
@@ -212,6 +308,7 @@ impl<'a> MctsArena<'a> {
         // with max_by_key
         let best_child_idx = children
             .iter()
+            .filter(|&c| !matches!(self.arena[*c].proof, ProofStatus::Loss(_)))
             .max_by(|&a, &b| {
                 let uct_a = uct(&self.arena[*a]);
                 let uct_b = uct(&self.arena[*b]);
@@ -320,6 +417,35 @@ impl<'a> MctsArena<'a> {
                 break;
             }
         }
+
+        self.back_propagate_proof(node_idx);
+    }
+
+    /// Proves the ancestors of the proven node while it is possible
+    fn back_propagate_proof(&mut self, node_idx: usize) {
+        if !self.arena[node_idx].proof.is_proven() {
+            return;
+        }
+
+        let mut node_idx = node_idx;
+        while let Some(parent) = self.arena[node_idx].parent {
+            if self.arena[parent].proof.is_proven() {
+                break;
+            }
+
+            let proof = ProofStatus::from_children(
+                self.arena[parent]
+                    .children
+                    .iter()
+                    .map(|c| self.arena[*c].proof),
+            );
+            if !proof.is_proven() {
+                break;
+            }
+
+            self.arena[parent].proof = proof;
+            node_idx = parent;
+        }
     }
 
     pub fn evaluate_state(&self, state: &State, player_color: PlayerColor) -> ResTowerTensor {
@@ -370,6 +496,8 @@ pub struct MctsNode {
     pub is_expanded: bool,
     pub player_color: PlayerColor,
     pub probability: f64,
+    /// Proven value of the node for the player who moved into it
+    pub proof: ProofStatus,
 }
 
 impl MctsNode {
@@ -392,6 +520,7 @@ impl MctsNode {
             is_expanded: false,
             player_color,
             probability,
+            proof: ProofStatus::Unknown,
         }
     }
 
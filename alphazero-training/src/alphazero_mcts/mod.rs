@@ -2,11 +2,16 @@ pub mod mcts_arena;
 
 use std::{
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use onitama_game::{
-    ai::agent::Agent,
+    ai::{
+        agent::Agent,
+        analysis::{AnalysisLine, SearchControl},
+        mcts::SearchInfo,
+        registry::{format_duration, AgentSpec},
+    },
     game::{
         done_move::DoneMove, game_state::GameState, move_result::MoveResult,
         player_color::PlayerColor, state::State,
@@ -23,12 +28,18 @@ use crate::{
 
 use self::mcts_arena::MctsArena;
 
+/// The analysis reports the lines after this time of the search
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlphaZeroMctsConfig {
     pub search_time: Duration,
     pub exploration_c: f64,
     pub max_playouts: u32,
     pub train: bool,
+    /// Seed of the Dirichlet noise. The noise is the same for the same position, ply and seed
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl Default for AlphaZeroMctsConfig {
@@ -38,6 +49,7 @@ impl Default for AlphaZeroMctsConfig {
             exploration_c: 2f64.sqrt(),
             max_playouts: 5000,
             train: false,
+            seed: None,
         }
     }
 }
@@ -64,10 +76,12 @@ impl TrainingAlphaZeroMcts {
         &self,
         state: &State,
         curr_player_color: PlayerColor,
+        ply: usize,
     ) -> (DoneMove, Tensor) {
         let mut arena = MctsArena::new(
             state.clone(),
             curr_player_color,
+            ply,
             self.config.clone(),
             &self.model,
             self.options,
@@ -83,6 +97,8 @@ pub struct AlphaZeroMcts {
     pub config: AlphaZeroMctsConfig,
     pub model: Arc<Mutex<ConvResNet>>,
     pub options: Options,
+    /// File of the model if it was loaded from one, the agent specification needs it
+    pub model_path: Option<String>,
 }
 
 impl AlphaZeroMcts {
@@ -101,6 +117,82 @@ impl AlphaZeroMcts {
             config,
             model,
             options,
+            model_path: Some(model_path.to_owned()),
+        }
+    }
+
+    /// Searches the position without playing the move, e.g. to see if the result is proven
+    pub fn search(&self, game_state: &GameState) -> SearchInfo {
+        let model = self.model.lock().unwrap();
+        let mut arena = MctsArena::new(
+            game_state.state.clone(),
+            game_state.curr_player_color,
+            game_state.history.len(),
+            self.config.clone(),
+            &model,
+            self.options,
+            reward,
+        );
+
+        arena.search();
+        arena.search_info()
+    }
+
+    /// Searches the position and returns up to `lines` best root moves
+    pub fn analyze_lines(&self, game_state: &GameState, lines: usize) -> Vec<AnalysisLine> {
+        let model = self.model.lock().unwrap();
+        let mut arena = MctsArena::new(
+            game_state.state.clone(),
+            game_state.curr_player_color,
+            game_state.history.len(),
+            self.config.clone(),
+            &model,
+            self.options,
+            reward,
+        );
+
+        arena.search();
+        arena.analysis(lines)
+    }
+
+    /// Searches the position in the slices of `REPORT_INTERVAL` and reports the lines
+    /// after every slice. The search ends at its limits or at the stop or the deadline
+    pub fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        let model = self.model.lock().unwrap();
+        let mut arena = MctsArena::new(
+            game_state.state.clone(),
+            game_state.curr_player_color,
+            game_state.history.len(),
+            self.config.clone(),
+            &model,
+            self.options,
+            reward,
+        );
+        let end = control.end(self.config.search_time);
+
+        loop {
+            let remaining = match end {
+                Some(end) => end.saturating_duration_since(Instant::now()),
+                None => Duration::MAX,
+            };
+            // Every slice makes at least one playout, so there is a move to play
+            arena.config.search_time = remaining.min(REPORT_INTERVAL).max(Duration::from_millis(1));
+            arena.search();
+
+            let analysis = arena.analysis(lines);
+            on_lines(&analysis);
+            let is_finished = arena.playouts >= arena.config.max_playouts
+                || arena.arena[0].proof.is_proven()
+                || remaining <= REPORT_INTERVAL;
+            if is_finished || control.is_stopped() {
+                return analysis;
+            }
         }
     }
 }
@@ -113,7 +205,7 @@ impl Serialize for AlphaZeroMcts {
         let mut state = serializer.serialize_struct("AlphaZeroMcts", 3)?;
         state.serialize_field("config", &self.config)?;
         state.serialize_field("model", &self.model.lock().unwrap().id)?;
-        state.serialize_field("options", "TODO: most likely FLOAT_CUDA")?;
+        state.serialize_field("options", &self.options)?;
 
         state.end()
     }
@@ -125,6 +217,7 @@ impl Agent for AlphaZeroMcts {
         let mut arena = MctsArena::new(
             game_state.state.clone(),
             game_state.curr_player_color,
+            game_state.history.len(),
             self.config.clone(),
             &model,
             self.options,
@@ -158,4 +251,37 @@ impl Agent for AlphaZeroMcts {
             + self.config.train as u64
             + self.model.lock().unwrap().id.parse::<u64>().unwrap()
     }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        if self.config.train {
+            return None;
+        }
+
+        let model_path = self.model_path.clone()?;
+        let blocks = self.model.lock().unwrap().net_config.resnet_block_amnt;
+        let spec = AgentSpec::new("alphazero")
+            .with("model", model_path)
+            .with("blocks", blocks)
+            .with("time", format_duration(self.config.search_time))
+            .with("c", self.config.exploration_c)
+            .with("playouts", self.config.max_playouts);
+        Some(match self.config.seed {
+            Some(seed) => spec.with("seed", seed),
+            None => spec,
+        })
+    }
+
+    fn analyze(&self, game_state: &GameState, lines: usize) -> Vec<AnalysisLine> {
+        self.analyze_lines(game_state, lines)
+    }
+
+    fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        AlphaZeroMcts::analyze_until(self, game_state, lines, control, on_lines)
+    }
 }
@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+use onitama_game::{
+    ai::alpha_beta::AlphaBeta,
+    game::{deck::Deck, game_state::GameState},
+};
+
+/// Measures how deep the AlphaBeta gets with different amount of threads
+/// during the same search time
+fn main() {
+    let position_amnt = 20;
+    let thread_values = [1, 2, 4, 8];
+
+    // Same positions are used for every thread amount
+    let positions = (0..position_amnt)
+        .map(|_| GameState::with_deck(Deck::default()))
+        .collect::<Vec<_>>();
+
+    for threads in thread_values {
+        let alphabeta = AlphaBeta {
+            max_depth: 64,
+            search_time: Duration::from_millis(500),
+            threads,
+            ..Default::default()
+        };
+
+        let mut depth_sum = 0u64;
+        let mut nodes_sum = 0u64;
+
+        let now = Instant::now();
+        for game_state in positions.iter() {
+            let info = alphabeta.search(game_state);
+            depth_sum += info.depth as u64;
+            nodes_sum += info.positions;
+        }
+        let elapsed = now.elapsed();
+
+        println!(
+            "Threads: {} -> average depth: {:3.2}, average nodes: {}, nodes per second: {:.0}",
+            threads,
+            depth_sum as f64 / position_amnt as f64,
+            nodes_sum / position_amnt as u64,
+            nodes_sum as f64 / elapsed.as_secs_f64(),
+        );
+    }
+}
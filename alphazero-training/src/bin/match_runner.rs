@@ -0,0 +1,173 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use alphazero_training::registry::registry;
+use onitama_game::{
+    ai::registry::{parse_duration, AgentSpec},
+    match_runner::{
+        rating::Ratings, score::format_elo, sprt::SprtConfig, suite::load_suite, MatchConfig,
+        MatchGame, MatchProgress, MatchRunner,
+    },
+};
+
+const USAGE: &str = "Usage: match_runner [--games N] [--concurrency N] [--tc TIME] \
+[--timeout TIME] [--max-plies N] [--repetitions N] [--suite FILE] [--seed SEED] \
+[--records DIR] [--sprt ELO0,ELO1] [--alpha A] [--beta B] [--trinomial] AGENT AGENT [AGENT...]
+
+Plays the games of every pairing of the agents, each opening is played twice with the colors swapped.
+--tc replaces the search time of every agent, a move longer than --timeout loses the game
+(--tc plus 1s by default). The opening suite has an opening per line: a deck written as
+`tiger,dragon frog,rabbit horse` (two red cards, two blue cards and the neutral one) or a position,
+optionally followed by moves, e.g. `tiger,dragon frog,rabbit horse tiger:c1c3`.
+Without the suite every pair of games gets a random deck, see opening_suite for balanced ones.
+--sprt tests the Elo difference of the first agent to the second one and stops the match
+as soon as ELO0 or ELO1 is accepted. The pairs of games are counted unless --trinomial is given";
+
+struct Args {
+    specs: Vec<AgentSpec>,
+    config: MatchConfig,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        specs: vec![],
+        config: MatchConfig::default(),
+    };
+    let mut timeout = None;
+    let mut sprt = None;
+    let mut sprt_config = SprtConfig::default();
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--games" => args.config.games = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--concurrency" => {
+                args.config.concurrency = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--tc" => args.config.move_time = Some(parse_duration(&value()?)?),
+            "--timeout" => timeout = Some(parse_duration(&value()?)?),
+            "--max-plies" => {
+                args.config.adjudication.max_plies =
+                    value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--repetitions" => {
+                args.config.adjudication.repetitions =
+                    value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--suite" => {
+                let path = value()?;
+                args.config.openings = load_suite(&path).map_err(|e| format!("{}: {}", path, e))?
+            }
+            "--seed" => args.config.seed = Some(value()?.parse().map_err(|e| format!("{}", e))?),
+            "--records" => args.config.record_dir = Some(PathBuf::from(value()?)),
+            "--sprt" => sprt = Some(parse_elo_bounds(&value()?)?),
+            "--alpha" => sprt_config.alpha = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--beta" => sprt_config.beta = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--trinomial" => sprt_config.pentanomial = false,
+            "--help" | "-h" => return Err(format!("{}\n\n{}", USAGE, registry().help())),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => args
+                .specs
+                .push(arg.parse().map_err(|e| format!("{}: {}", arg, e))?),
+        }
+    }
+
+    if args.specs.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+    args.config.adjudication.time_limit = timeout.or_else(|| {
+        args.config
+            .move_time
+            .map(|move_time| move_time + Duration::from_secs(1))
+    });
+
+    args.config.sprt = sprt.map(|(elo0, elo1)| SprtConfig {
+        elo0,
+        elo1,
+        ..sprt_config
+    });
+
+    Ok(args)
+}
+
+/// Bounds of the SPRT written as `0,20`
+fn parse_elo_bounds(value: &str) -> Result<(f64, f64), String> {
+    let parse = |elo: &str| {
+        elo.trim()
+            .parse::<f64>()
+            .map_err(|e| format!("{}: {}", value, e))
+    };
+    match value.split_once(',') {
+        Some((elo0, elo1)) => Ok((parse(elo0)?, parse(elo1)?)),
+        None => Err(format!(
+            "SPRT bounds must be written as ELO0,ELO1: {}",
+            value
+        )),
+    }
+}
+
+fn print_game(game: &MatchGame, progress: &MatchProgress) {
+    let table = &progress.table;
+    println!(
+        "Game {} ({} vs {}): {} {:?}",
+        game.number,
+        game.red,
+        game.blue,
+        game.result(),
+        game.termination
+    );
+
+    let [red, blue] = game.players;
+    let (agent, opponent) = (red.min(blue), red.max(blue));
+    let score = table.score(agent, opponent);
+    println!(
+        "Score of {} vs {}: {}  {}  {} games",
+        table.agents[agent],
+        table.agents[opponent],
+        score,
+        format_elo(score.elo()),
+        score.games()
+    );
+    if let Some(sprt) = &progress.sprt {
+        println!("{}", sprt);
+    }
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    // Random is the zero of the ratings if it plays, otherwise the first agent
+    let anchor = args
+        .specs
+        .iter()
+        .position(|spec| spec.kind == "random")
+        .unwrap_or(0);
+    let runner = match MatchRunner::new(registry(), args.specs, args.config) {
+        Ok(runner) => runner,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let now = Instant::now();
+    let progress = runner
+        .run(print_game)
+        .expect("Cannot create the directory of the game records");
+
+    println!("\nFinished in {:?}\n", now.elapsed());
+    print!("{}", progress.table);
+    println!("\n{}", Ratings::estimate(&progress.table, anchor, 0.));
+    if let Some(sprt) = progress.sprt {
+        println!("\n{}", sprt);
+    }
+}
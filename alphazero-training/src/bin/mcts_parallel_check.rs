@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use onitama_game::{
+    ai::{
+        agent::Agent,
+        mcts::{Mcts, ParallelMode},
+    },
+    game::{deck::Deck, game_state::GameState, move_result::MoveResult, player_color::PlayerColor},
+};
+
+pub fn play(agent: Box<dyn Agent>, opponent: Box<dyn Agent>, game_amnt: u32) -> u32 {
+    let mut agents = [agent, opponent];
+    let mut agent_color = PlayerColor::Red;
+    let mut wins = 0;
+
+    for _ in 0..game_amnt {
+        let deck = Deck::default();
+        let mut state = GameState::with_deck(deck);
+        let mut progress = MoveResult::InProgress;
+        let mut max_plies = 150;
+
+        while !progress.is_win() && max_plies > 0 {
+            let (done_move, _) = agents[state.curr_agent_idx].generate_move(&state);
+            progress = state.progress(done_move);
+            max_plies -= 1;
+        }
+
+        wins += match (progress, agent_color) {
+            (MoveResult::BlueWin, PlayerColor::Blue) | (MoveResult::RedWin, PlayerColor::Red) => 1,
+            _ => 0,
+        };
+
+        agent_color.switch();
+        agents.swap(0, 1);
+    }
+
+    wins
+}
+
+fn mcts(threads: usize, parallel_mode: ParallelMode) -> Mcts {
+    Mcts {
+        search_time: Duration::from_millis(200),
+        max_playouts: u32::MAX,
+        threads,
+        parallel_mode,
+        ..Default::default()
+    }
+}
+
+/// Compares the parallel MCTS with the single threaded one during the same search time:
+/// the amount of playouts per second and the winrate against the single threaded search
+fn main() {
+    let position_amnt = 20;
+    let game_amnt = 50;
+    let thread_values = [1, 2, 4, 8];
+    let modes = [ParallelMode::Tree, ParallelMode::Root];
+
+    let positions = (0..position_amnt)
+        .map(|_| GameState::with_deck(Deck::default()))
+        .collect::<Vec<_>>();
+
+    for mode in modes {
+        for threads in thread_values {
+            let agent = mcts(threads, mode);
+
+            // The searched tree is saved in the agent, so the playouts can be read from it
+            let mut playouts = 0u64;
+            for game_state in positions.iter() {
+                agent.generate_move(game_state);
+                let tree = agent.tree.lock().unwrap();
+                playouts += tree.as_ref().map(|t| t.playouts as u64).unwrap_or(0);
+            }
+            let seconds = agent.search_time.as_secs_f64() * position_amnt as f64;
+
+            let wins = play(
+                Box::new(mcts(threads, mode)),
+                Box::new(mcts(1, ParallelMode::Tree)),
+                game_amnt,
+            );
+
+            println!(
+                "{:?} parallel, threads: {} -> playouts per second: {:.0}, winrate against 1 thread: {:3.2}",
+                mode,
+                threads,
+                playouts as f64 / seconds,
+                wins as f64 / game_amnt as f64,
+            );
+        }
+    }
+}
@@ -1,8 +1,16 @@
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use onitama_game::{
-    ai::{agent::Agent, mcts::Mcts},
-    game::{deck::Deck, game_state::GameState, move_result::MoveResult, player_color::PlayerColor},
+    ai::{agent::Agent, registry::AgentRegistry},
+    game::{deck::Deck, move_result::MoveResult, player_color::PlayerColor},
+    match_runner::{play_game, suite::Opening, Adjudication, Termination},
+};
+
+/// Games which are too long are played again with a new deck
+const ADJUDICATION: Adjudication = Adjudication {
+    max_plies: 150,
+    repetitions: 0,
+    time_limit: None,
 };
 
 pub fn play(agent: Box<dyn Agent>, opponent: Box<dyn Agent>, game_amnt: u32) -> u32 {
@@ -12,28 +20,20 @@ pub fn play(agent: Box<dyn Agent>, opponent: Box<dyn Agent>, game_amnt: u32) ->
     let mut game = 0;
 
     while game < game_amnt {
-        let deck = Deck::default();
-        let mut state = GameState::with_deck(deck);
-        let mut progress = MoveResult::InProgress;
-
-        let mut max_plies = 150;
-
-        while !progress.is_win() {
-            let (done_move, _) = agents[state.curr_agent_idx].generate_move(&state);
-
-            progress = state.progress(done_move);
-
-            if max_plies < 0 {
-                println!("Game has become infinite!");
-                game -= 1;
-                break;
-            }
-
-            max_plies -= 1;
+        let (record, termination) = play_game(
+            [agents[0].as_ref(), agents[1].as_ref()],
+            &Opening::from(Deck::default()),
+            &ADJUDICATION,
+        );
+        if termination == Termination::MaxPlies {
+            println!("Game has become infinite!");
+            agent_color.switch();
+            agents.swap(0, 1);
+            continue;
         }
 
         // Gather statistics
-        wins += match (progress, agent_color) {
+        wins += match (record.result, agent_color) {
             (MoveResult::BlueWin, PlayerColor::Blue) | (MoveResult::RedWin, PlayerColor::Red) => 1,
             _ => 0,
         };
@@ -46,26 +46,23 @@ pub fn play(agent: Box<dyn Agent>, opponent: Box<dyn Agent>, game_amnt: u32) ->
     wins
 }
 
+/// MCTS with the given parameters and the rest of the search fixed
+fn mcts(params: &str) -> Box<dyn Agent> {
+    AgentRegistry::default()
+        .build(&format!("mcts:time=400ms,playouts=1600,{}", params))
+        .expect("MCTS specification must be valid")
+}
+
 pub fn play_with_c_value() {
     let game_amnt = 100;
     let c_values = [0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0];
 
     for i in 0..c_values.len() {
-        let mcts1: Box<dyn Agent> = Box::new(Mcts {
-            search_time: Duration::from_millis(400),
-            min_node_visits: 1,
-            exploration_c: c_values[i],
-            max_playouts: 1600,
-        });
+        let mcts1 = mcts(&format!("visits=1,c={}", c_values[i]));
 
         let mut handles = vec![];
         for k in (i + 1)..c_values.len() {
-            let mcts2: Box<dyn Agent> = Box::new(Mcts {
-                search_time: Duration::from_millis(400),
-                min_node_visits: 1,
-                exploration_c: c_values[k],
-                max_playouts: 1600,
-            });
+            let mcts2 = mcts(&format!("visits=1,c={}", c_values[k]));
 
             let clone = mcts1.clone();
             let handle = std::thread::spawn(move || play(clone, mcts2, game_amnt));
@@ -91,21 +88,11 @@ pub fn play_with_n_value() {
     let n_values = [0, 1, 2, 3, 4, 5, 6, 7, 8];
 
     for i in 0..n_values.len() {
-        let mcts1: Box<dyn Agent> = Box::new(Mcts {
-            search_time: Duration::from_millis(400),
-            min_node_visits: n_values[i],
-            exploration_c: 1.,
-            max_playouts: 1600,
-        });
+        let mcts1 = mcts(&format!("visits={},c=1", n_values[i]));
 
         let mut handles = vec![];
         for k in (i + 1)..n_values.len() {
-            let mcts2: Box<dyn Agent> = Box::new(Mcts {
-                search_time: Duration::from_millis(400),
-                min_node_visits: n_values[k],
-                exploration_c: 1.,
-                max_playouts: 1600,
-            });
+            let mcts2 = mcts(&format!("visits={},c=1", n_values[k]));
 
             let clone = mcts1.clone();
             let handle = std::thread::spawn(move || play(clone, mcts2, game_amnt));
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use onitama_game::{
+    ai::{
+        agent::Agent,
+        mcts::{rave::RaveSchedule, Mcts},
+    },
+    game::{deck::Deck, game_state::GameState, move_result::MoveResult, player_color::PlayerColor},
+};
+
+pub fn play(agent: Box<dyn Agent>, opponent: Box<dyn Agent>, game_amnt: u32) -> u32 {
+    let mut agents = [agent, opponent];
+    let mut agent_color = PlayerColor::Red;
+    let mut wins = 0;
+
+    for _ in 0..game_amnt {
+        let deck = Deck::default();
+        let mut state = GameState::with_deck(deck);
+        let mut progress = MoveResult::InProgress;
+        let mut max_plies = 150;
+
+        while !progress.is_win() && max_plies > 0 {
+            let (done_move, _) = agents[state.curr_agent_idx].generate_move(&state);
+            progress = state.progress(done_move);
+            max_plies -= 1;
+        }
+
+        wins += match (progress, agent_color) {
+            (MoveResult::BlueWin, PlayerColor::Blue) | (MoveResult::RedWin, PlayerColor::Red) => 1,
+            _ => 0,
+        };
+
+        agent_color.switch();
+        agents.swap(0, 1);
+    }
+
+    wins
+}
+
+/// Plays the RAVE with different schedules against plain UCT
+/// with the same amount of playouts per move
+fn main() {
+    let game_amnt = 100;
+    let playout_values = [400, 1600, 5000];
+    let schedules = [
+        RaveSchedule::Equivalence(100.),
+        RaveSchedule::Equivalence(1000.),
+        RaveSchedule::MinimumMse(0.1),
+        RaveSchedule::MinimumMse(0.5),
+    ];
+
+    for max_playouts in playout_values {
+        let mcts = |rave| Mcts {
+            // Only the amount of playouts must limit the search
+            search_time: Duration::from_secs(60),
+            max_playouts,
+            rave,
+            ..Default::default()
+        };
+
+        let handles = schedules
+            .into_iter()
+            .map(|schedule| {
+                let rave: Box<dyn Agent> = Box::new(mcts(Some(schedule)));
+                let uct: Box<dyn Agent> = Box::new(mcts(None));
+                std::thread::spawn(move || (schedule, play(rave, uct, game_amnt)))
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            let (schedule, wins) = handle.join().unwrap();
+            println!(
+                "Playouts: {}, RAVE with {:?} vs UCT -> winrate: {:3.2}",
+                max_playouts,
+                schedule,
+                wins as f64 / game_amnt as f64,
+            );
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use onitama_game::{
+    ai::{
+        agent::Agent,
+        mcts::{rollout::Rollout, Mcts},
+    },
+    game::{deck::Deck, game_state::GameState, move_result::MoveResult, player_color::PlayerColor},
+};
+
+pub fn play(agent: Box<dyn Agent>, opponent: Box<dyn Agent>, game_amnt: u32) -> u32 {
+    let mut agents = [agent, opponent];
+    let mut agent_color = PlayerColor::Red;
+    let mut wins = 0;
+
+    for _ in 0..game_amnt {
+        let deck = Deck::default();
+        let mut state = GameState::with_deck(deck);
+        let mut progress = MoveResult::InProgress;
+        let mut max_plies = 150;
+
+        while !progress.is_win() && max_plies > 0 {
+            let (done_move, _) = agents[state.curr_agent_idx].generate_move(&state);
+            progress = state.progress(done_move);
+            max_plies -= 1;
+        }
+
+        wins += match (progress, agent_color) {
+            (MoveResult::BlueWin, PlayerColor::Blue) | (MoveResult::RedWin, PlayerColor::Red) => 1,
+            _ => 0,
+        };
+
+        agent_color.switch();
+        agents.swap(0, 1);
+    }
+
+    wins
+}
+
+/// Plays the rollout policies against the uniformly random rollouts
+/// with the same search time
+fn main() {
+    let game_amnt = 100;
+    let policies = [
+        (Rollout::WinFirst, None),
+        (Rollout::AvoidLoss, None),
+        (Rollout::CaptureBiased { epsilon: 0.3 }, None),
+        (Rollout::EvaluationGuided { epsilon: 0.3 }, None),
+        (Rollout::Uniform, Some(20)),
+        (Rollout::AvoidLoss, Some(20)),
+    ];
+
+    let mcts = |rollout, max_rollout_plies| Mcts {
+        search_time: Duration::from_millis(200),
+        max_playouts: u32::MAX,
+        rollout,
+        max_rollout_plies,
+        ..Default::default()
+    };
+
+    let handles = policies
+        .into_iter()
+        .map(|(rollout, max_plies)| {
+            let agent: Box<dyn Agent> = Box::new(mcts(rollout, max_plies));
+            let uniform: Box<dyn Agent> = Box::new(mcts(Rollout::Uniform, None));
+            std::thread::spawn(move || (rollout, max_plies, play(agent, uniform, game_amnt)))
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        let (rollout, max_plies, wins) = handle.join().unwrap();
+        println!(
+            "{:?} with ply limit {:?} vs Uniform -> winrate: {:3.2}",
+            rollout,
+            max_plies,
+            wins as f64 / game_amnt as f64,
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use alphazero_training::{
+    alphazero_mcts::{AlphaZeroMcts, AlphaZeroMctsConfig},
+    common::Options,
+    net::ConvResNetConfig,
+    nn_evaluation::{NnAlphaBeta, NnEvaluation},
+};
+use onitama_game::{
+    ai::agent::Agent,
+    game::{deck::Deck, game_state::GameState, move_result::MoveResult, player_color::PlayerColor},
+};
+use tch::{kind, nn::VarStore, Device};
+
+pub fn play(agent: Box<dyn Agent>, opponent: Box<dyn Agent>, game_amnt: u32) -> u32 {
+    let mut agents = [agent, opponent];
+    let mut agent_color = PlayerColor::Red;
+    let mut wins = 0;
+
+    for game in 0..game_amnt {
+        let deck = Deck::from_seed(game as u64);
+        let mut state = GameState::with_deck(deck);
+        let mut progress = MoveResult::InProgress;
+        let mut max_plies = 150;
+
+        while !progress.is_win() && max_plies > 0 {
+            let (done_move, _) = agents[state.curr_agent_idx].generate_move(&state);
+            progress = state.progress(done_move);
+            max_plies -= 1;
+        }
+
+        wins += match (progress, agent_color) {
+            (MoveResult::BlueWin, PlayerColor::Blue) | (MoveResult::RedWin, PlayerColor::Red) => 1,
+            _ => 0,
+        };
+
+        agent_color.switch();
+        agents.swap(0, 1);
+    }
+
+    wins
+}
+
+/// Plays the AlphaBeta search with the network evaluation against
+/// the PUCT search with the same network and the same search time
+fn main() {
+    let game_amnt = 50;
+    let options = Options::new(kind::FLOAT_CPU);
+
+    let mut vs = VarStore::new(Device::Cpu);
+    let alphazero = AlphaZeroMcts::from_model_file(
+        &mut vs,
+        "../models/model_5e-3.ot",
+        AlphaZeroMctsConfig::default(),
+        ConvResNetConfig::default(),
+        options,
+    );
+    let evaluation = NnEvaluation::new(alphazero.model.clone(), options);
+
+    for search_time in [100, 400, 1000] {
+        let search_time = Duration::from_millis(search_time);
+
+        let nn_alpha_beta = Box::new(NnAlphaBeta {
+            max_depth: 15,
+            search_time,
+            threads: 1,
+            ..NnAlphaBeta::with_evaluator(evaluation.clone())
+        });
+        let puct = Box::new(AlphaZeroMcts {
+            config: AlphaZeroMctsConfig {
+                search_time,
+                max_playouts: u32::MAX,
+                ..Default::default()
+            },
+            ..alphazero.clone()
+        });
+
+        let wins = play(nn_alpha_beta, puct, game_amnt);
+        println!(
+            "NN AlphaBeta vs AlphaZero MCTS with {:?} -> winrate: {:3.2}",
+            search_time,
+            wins as f64 / game_amnt as f64
+        );
+    }
+}
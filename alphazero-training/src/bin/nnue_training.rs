@@ -0,0 +1,192 @@
+use std::{path::PathBuf, time::Duration};
+
+use alphazero_training::{
+    nnue_trainer::{collect_samples, train, NnueTrainConfig},
+    texel::self_play,
+};
+use onitama_game::{
+    ai::{
+        agent::Agent,
+        alpha_beta::{weighted_evaluation::WeightedEvaluation, AlphaBeta},
+        nnue::NnueEvaluation,
+    },
+    game::{
+        deck::Deck, game_record::GameRecord, game_state::GameState, move_result::MoveResult,
+        player_color::PlayerColor,
+    },
+};
+
+const USAGE: &str = "Usage: nnue_training [--output FILE] [--self-play GAMES] [--seed SEED] \
+[--epochs N] [--learning-rate LR] [--lambda L] [--search-depth D] [--check-games N] [DIR...]
+
+Reads game records and GUI move histories from the directories (./saves by default),
+labels the positions with the AlphaBeta search and writes the NNUE network
+which can be loaded by NnueEvaluation::from_file";
+
+struct Args {
+    output: PathBuf,
+    self_play_games: usize,
+    check_games: usize,
+    seed: Option<u64>,
+    dirs: Vec<PathBuf>,
+    config: NnueTrainConfig,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        output: PathBuf::from("nnue.bin"),
+        self_play_games: 0,
+        check_games: 0,
+        seed: None,
+        dirs: vec![],
+        config: NnueTrainConfig::default(),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--output" => args.output = PathBuf::from(value()?),
+            "--self-play" => {
+                args.self_play_games = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--check-games" => args.check_games = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--seed" => args.seed = Some(value()?.parse().map_err(|e| format!("{}", e))?),
+            "--epochs" => args.config.epochs = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--learning-rate" => {
+                args.config.learning_rate = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--lambda" => args.config.lambda = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--search-depth" => {
+                args.config.search_depth = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--help" | "-h" => return Err(USAGE.to_owned()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => args.dirs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if args.dirs.is_empty() && args.self_play_games == 0 {
+        args.dirs.push(PathBuf::from("./saves"));
+    }
+
+    Ok(args)
+}
+
+/// Plays AlphaBeta with the network against AlphaBeta with the weighted evaluation
+/// at the same depth and returns the score of the network
+fn check(evaluation: NnueEvaluation, game_amnt: usize, seed: u64) -> f64 {
+    let nnue: Box<dyn Agent> = Box::new(AlphaBeta {
+        max_depth: 5,
+        search_time: Duration::from_secs(60),
+        ..AlphaBeta::with_evaluator(evaluation)
+    });
+    let weighted: Box<dyn Agent> = Box::new(AlphaBeta {
+        max_depth: 5,
+        search_time: Duration::from_secs(60),
+        ..AlphaBeta::with_evaluator(WeightedEvaluation::default())
+    });
+
+    let mut agents = [nnue, weighted];
+    let mut agent_color = PlayerColor::Red;
+    let mut score = 0.;
+
+    for game in 0..game_amnt {
+        // Every deck is played twice, so both agents play both sides
+        let mut game_state = GameState::with_deck(Deck::from_seed(seed + (game / 2) as u64));
+        let mut progress = MoveResult::InProgress;
+        let mut max_plies = 150;
+
+        while !progress.is_win() && max_plies > 0 {
+            let (done_move, _) = agents[game_state.curr_agent_idx].generate_move(&game_state);
+            progress = game_state.progress(done_move);
+            max_plies -= 1;
+        }
+
+        score += match (progress, agent_color) {
+            (MoveResult::BlueWin, PlayerColor::Blue) | (MoveResult::RedWin, PlayerColor::Red) => 1.,
+            (MoveResult::InProgress, _) => 0.5,
+            _ => 0.,
+        };
+
+        agent_color.switch();
+        agents.swap(0, 1);
+    }
+
+    score / game_amnt as f64
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut config = args.config;
+    config.seed = seed;
+
+    let mut records = vec![];
+    for dir in args.dirs.iter() {
+        match GameRecord::load_dir(dir) {
+            Ok(loaded) => {
+                println!("Loaded {} games from {}", loaded.len(), dir.display());
+                records.extend(loaded);
+            }
+            Err(e) => eprintln!("Cannot read {}: {}", dir.display(), e),
+        }
+    }
+
+    if args.self_play_games > 0 {
+        println!(
+            "Playing {} self-play games with seed {}...",
+            args.self_play_games, seed
+        );
+        records.extend(self_play(
+            args.self_play_games,
+            &WeightedEvaluation::default(),
+            6,
+            150,
+            seed,
+        ));
+    }
+
+    let samples = collect_samples(&records, &config);
+    if samples.is_empty() {
+        eprintln!("There are no positions from finished games to train on");
+        std::process::exit(1);
+    }
+    println!(
+        "Training on {} positions labelled by the depth {} search...",
+        samples.len(),
+        config.search_depth
+    );
+
+    let report = train(&samples, &config);
+
+    println!("Loss before: {:.6}", report.loss_before);
+    println!("Loss after: {:.6}", report.loss_after);
+    println!(
+        "Loss of the quantized network: {:.6}",
+        report.quantized_loss
+    );
+
+    report
+        .network
+        .save(&args.output)
+        .expect("Cannot save the network");
+    println!("Network is saved to {}", args.output.display());
+
+    if args.check_games > 0 {
+        let evaluation =
+            NnueEvaluation::from_file(&args.output).expect("Cannot load the saved network");
+        let score = check(evaluation, args.check_games, seed);
+        println!(
+            "NNUE vs weighted evaluation at depth 4 -> score: {:3.2}",
+            score
+        );
+    }
+}
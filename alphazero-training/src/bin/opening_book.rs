@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+use alphazero_training::texel::self_play;
+use onitama_game::{
+    ai::{
+        alpha_beta::weighted_evaluation::WeightedEvaluation,
+        opening_book::{BookConfig, OpeningBook},
+    },
+    game::game_record::GameRecord,
+};
+
+const USAGE: &str = "Usage: opening_book [--output FILE] [--max-ply N] [--min-games N] \
+[--self-play GAMES] [--seed SEED] [DIR...]
+
+Reads game records and GUI move histories from the directories and their subdirectories
+(./saves with the tournament folders by default) and writes the opening book
+which is played by the book agent, e.g. book:file=opening_book.bin,agent=alphabeta:depth=8";
+
+struct Args {
+    output: PathBuf,
+    self_play_games: usize,
+    seed: Option<u64>,
+    dirs: Vec<PathBuf>,
+    config: BookConfig,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        output: PathBuf::from("opening_book.bin"),
+        self_play_games: 0,
+        seed: None,
+        dirs: vec![],
+        config: BookConfig::default(),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--output" => args.output = PathBuf::from(value()?),
+            "--max-ply" => args.config.max_ply = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--min-games" => {
+                args.config.min_games = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--self-play" => {
+                args.self_play_games = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--seed" => args.seed = Some(value()?.parse().map_err(|e| format!("{}", e))?),
+            "--help" | "-h" => return Err(USAGE.to_owned()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => args.dirs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if args.dirs.is_empty() && args.self_play_games == 0 {
+        args.dirs.push(PathBuf::from("./saves"));
+    }
+
+    Ok(args)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    let mut records = vec![];
+    for dir in args.dirs.iter() {
+        match GameRecord::load_dir(dir) {
+            Ok(loaded) => {
+                println!("Loaded {} games from {}", loaded.len(), dir.display());
+                records.extend(loaded);
+            }
+            Err(e) => eprintln!("Cannot read {}: {}", dir.display(), e),
+        }
+    }
+
+    if args.self_play_games > 0 {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        println!(
+            "Playing {} self-play games with seed {}...",
+            args.self_play_games, seed
+        );
+        // A couple of random plies give different lines, bad ones get low weights from the results
+        records.extend(self_play(
+            args.self_play_games,
+            &WeightedEvaluation::default(),
+            2,
+            150,
+            seed,
+        ));
+    }
+
+    let book = OpeningBook::build(&records, args.config);
+    if book.is_empty() {
+        eprintln!("There are no moves to put into the book");
+        std::process::exit(1);
+    }
+
+    book.save(&args.output)
+        .expect("Cannot save the opening book");
+    println!(
+        "Opening book with {} positions is saved to {}",
+        book.len(),
+        args.output.display()
+    );
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashSet, path::PathBuf, time::Duration};
+
+use alphazero_training::registry::registry;
+use onitama_game::{
+    ai::registry::{parse_duration, AgentSpec},
+    common::seeded_rng,
+    game::deck::Deck,
+    match_runner::{
+        suite::{balanced, measure_balance, save_suite, Opening},
+        MatchConfig, MatchRunner,
+    },
+};
+
+const USAGE: &str = "Usage: opening_suite [--candidates N] [--pairs N] [--size N] [--tolerance X] \
+[--concurrency N] [--tc TIME] [--max-plies N] [--seed SEED] [--output FILE] [AGENT [AGENT]]
+
+Plays random decks with the colors swapped and writes the most balanced ones as an opening suite
+for match_runner, tournament and EvaluatorConfig::suite. Every candidate deck is played --pairs times
+by the two agents with both colors, one agent plays against itself. A deck is kept if the red score
+differs from 0.5 by at most --tolerance. A deterministic agent plays the same game every time,
+so more pairs need agents with randomness, e.g. mcts";
+
+const DEFAULT_AGENT: &str = "mcts:time=200ms";
+
+struct Args {
+    specs: Vec<AgentSpec>,
+    candidates: usize,
+    pairs: usize,
+    size: usize,
+    tolerance: f64,
+    output: PathBuf,
+    config: MatchConfig,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        specs: vec![],
+        candidates: 100,
+        pairs: 2,
+        size: 50,
+        tolerance: 0.25,
+        output: PathBuf::from("suite.txt"),
+        config: MatchConfig::default(),
+    };
+    args.config.adjudication.max_plies = 150;
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--candidates" => args.candidates = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--pairs" => args.pairs = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--size" => args.size = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--tolerance" => args.tolerance = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--concurrency" => {
+                args.config.concurrency = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--tc" => args.config.move_time = Some(parse_duration(&value()?)?),
+            "--max-plies" => {
+                args.config.adjudication.max_plies =
+                    value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--seed" => args.config.seed = Some(value()?.parse().map_err(|e| format!("{}", e))?),
+            "--output" => args.output = PathBuf::from(value()?),
+            "--help" | "-h" => return Err(format!("{}\n\n{}", USAGE, registry().help())),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => args
+                .specs
+                .push(arg.parse().map_err(|e| format!("{}: {}", arg, e))?),
+        }
+    }
+
+    match args.specs.len() {
+        0 => {
+            let spec: AgentSpec = DEFAULT_AGENT.parse().expect("Default agent is valid");
+            args.specs = vec![spec.clone(), spec];
+        }
+        1 => args.specs.push(args.specs[0].clone()),
+        2 => (),
+        _ => return Err(USAGE.to_owned()),
+    }
+    args.config.adjudication.time_limit = args
+        .config
+        .move_time
+        .map(|move_time| move_time + Duration::from_secs(1));
+
+    Ok(args)
+}
+
+/// Different random decks, a deck with the same cards in the same hands is not repeated
+fn candidates(amount: usize, seed: Option<u64>) -> Vec<Opening> {
+    let mut rng = seeded_rng(seed, 0);
+    let mut seen = HashSet::new();
+    let mut openings = vec![];
+
+    while openings.len() < amount && seen.len() < amount * 100 {
+        let deck = Deck::random(&mut rng);
+        let mut hands = deck.cards.iter().map(|card| card.index).collect::<Vec<_>>();
+        hands[..2].sort();
+        hands[2..4].sort();
+        if seen.insert(hands) {
+            openings.push(Opening::from(deck));
+        }
+    }
+
+    openings
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    let openings = candidates(args.candidates, args.config.seed);
+    let amount = openings.len();
+    let runner = match MatchRunner::new(registry(), args.specs, args.config) {
+        Ok(runner) => runner,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let agents = runner.agents();
+
+    let balances = measure_balance(&runner, openings, args.pairs, |measured| {
+        if measured % 10 == 0 || measured == amount {
+            println!("Measured {}/{} decks", measured, amount);
+        }
+    })
+    .expect("Games are not saved");
+
+    let suite = balanced(balances, args.size, args.tolerance);
+    let lines = suite
+        .iter()
+        .map(|balance| {
+            let comment = format!("red {}", balance.red);
+            (balance.opening.clone(), comment)
+        })
+        .collect::<Vec<_>>();
+    if let Err(e) = save_suite(&args.output, &lines) {
+        eprintln!("Cannot save the suite to {}: {}", args.output.display(), e);
+        std::process::exit(1);
+    }
+
+    println!(
+        "Saved {} of {} decks to {}, measured by {} vs {}",
+        suite.len(),
+        amount,
+        args.output.display(),
+        agents[0],
+        agents[1]
+    );
+}
@@ -0,0 +1,90 @@
+use onitama_game::{
+    ai::{registry::AgentRegistry, skill_level::SkillLevel},
+    game::deck::Deck,
+    match_runner::{
+        rating::Ratings, score::ScoreTable, suite::Opening, tournament::GameResult, MatchConfig,
+        MatchRunner, ScheduledGame,
+    },
+};
+
+/// Games of every pairing, the agents take turns to play red
+const GAME_AMNT: usize = 40;
+const SEED: u64 = 42;
+const MAX_PLIES: usize = 150;
+
+/// Plays every skill level against the fixed anchors and prints
+/// the estimated Elo rating of each level with Random at 0.
+/// The anchors also play each other, so their ratings are fitted together with the levels
+fn main() {
+    // Random player is the zero of the scale
+    let anchors = [
+        format!("random:seed={}", SEED),
+        "alphabeta:depth=2,time=60s".to_owned(),
+        "alphabeta:depth=4,time=60s".to_owned(),
+        "alphabeta:depth=6,time=60s".to_owned(),
+    ];
+    let levels =
+        SkillLevel::all().map(|level| format!("skill:level={},seed={}", level.level(), SEED));
+    let specs = anchors
+        .iter()
+        .cloned()
+        .chain(levels)
+        .map(|spec| spec.parse().expect("Calibration agents must be valid"))
+        .collect::<Vec<_>>();
+
+    let mut pairs = vec![];
+    for i in 0..anchors.len() {
+        for j in (i + 1)..anchors.len() {
+            pairs.push((i, j));
+        }
+    }
+    for i in anchors.len()..specs.len() {
+        for j in 0..anchors.len() {
+            pairs.push((i, j));
+        }
+    }
+
+    let mut config = MatchConfig::default();
+    config.adjudication.max_plies = MAX_PLIES;
+    config.concurrency = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let runner = MatchRunner::new(AgentRegistry::default(), specs, config)
+        .expect("Calibration agents must be valid");
+
+    let mut games = vec![];
+    for &(i, j) in pairs.iter() {
+        for game in 0..GAME_AMNT {
+            let (red, blue) = if game & 1 == 0 { (i, j) } else { (j, i) };
+            games.push(ScheduledGame {
+                number: games.len() + 1,
+                red,
+                blue,
+                opening: Opening::from(Deck::from_seed(SEED + game as u64)),
+            });
+        }
+    }
+
+    let mut table = ScoreTable::new(runner.agents());
+    runner
+        .play_games(games, |game| {
+            let result = GameResult::from(game);
+            table.add(result.red, result.blue, result.points);
+            true
+        })
+        .expect("Games are not saved");
+
+    for &(i, j) in pairs.iter() {
+        let score = table.score(i, j);
+        println!(
+            "{} vs {} -> score: {:4.1}/{}",
+            table.agents[i],
+            table.agents[j],
+            score.ratio() * score.games() as f64,
+            score.games()
+        );
+    }
+
+    println!();
+    print!("{}", Ratings::estimate(&table, 0, 0.));
+}
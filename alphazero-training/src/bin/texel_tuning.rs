@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use alphazero_training::texel::{collect_samples, self_play, tune, TexelConfig};
+use onitama_game::{
+    ai::alpha_beta::weighted_evaluation::WeightedEvaluation, game::game_record::GameRecord,
+};
+
+const USAGE: &str = "Usage: texel_tuning [--weights FILE] [--output FILE] [--self-play GAMES] \
+[--seed SEED] [--epochs N] [--learning-rate LR] [DIR...]
+
+Reads game records and GUI move histories from the directories (./saves by default)
+and writes the tuned evaluation weights which can be loaded by WeightedEvaluation::from_file";
+
+struct Args {
+    weights: Option<PathBuf>,
+    output: PathBuf,
+    self_play_games: usize,
+    seed: Option<u64>,
+    dirs: Vec<PathBuf>,
+    config: TexelConfig,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        weights: None,
+        output: PathBuf::from("tuned_weights.json"),
+        self_play_games: 0,
+        seed: None,
+        dirs: vec![],
+        config: TexelConfig::default(),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--weights" => args.weights = Some(PathBuf::from(value()?)),
+            "--output" => args.output = PathBuf::from(value()?),
+            "--self-play" => {
+                args.self_play_games = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--seed" => args.seed = Some(value()?.parse().map_err(|e| format!("{}", e))?),
+            "--epochs" => args.config.epochs = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--learning-rate" => {
+                args.config.learning_rate = value()?.parse().map_err(|e| format!("{}", e))?
+            }
+            "--help" | "-h" => return Err(USAGE.to_owned()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => args.dirs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if args.dirs.is_empty() && args.self_play_games == 0 {
+        args.dirs.push(PathBuf::from("./saves"));
+    }
+
+    Ok(args)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    let initial = match &args.weights {
+        Some(path) => WeightedEvaluation::from_file(path).expect("Cannot load the weights"),
+        None => WeightedEvaluation::default(),
+    };
+
+    let mut records = vec![];
+    for dir in args.dirs.iter() {
+        match GameRecord::load_dir(dir) {
+            Ok(loaded) => {
+                println!("Loaded {} games from {}", loaded.len(), dir.display());
+                records.extend(loaded);
+            }
+            Err(e) => eprintln!("Cannot read {}: {}", dir.display(), e),
+        }
+    }
+
+    if args.self_play_games > 0 {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        println!(
+            "Playing {} self-play games with seed {}...",
+            args.self_play_games, seed
+        );
+        records.extend(self_play(args.self_play_games, &initial, 6, 150, seed));
+    }
+
+    let samples = collect_samples(&records, args.config.skip_plies);
+    if samples.is_empty() {
+        eprintln!("There are no positions from finished games to tune on");
+        std::process::exit(1);
+    }
+    println!("Tuning on {} positions...", samples.len());
+
+    let report = tune(&samples, &initial, &args.config);
+
+    println!("K: {:.6}", report.k);
+    println!("Loss before: {:.6}", report.loss_before);
+    println!("Loss after: {:.6}", report.loss_after);
+    println!(
+        "Loss dropped by {:.6} ({:.2}%)",
+        report.loss_before - report.loss_after,
+        (report.loss_before - report.loss_after) / report.loss_before * 100.
+    );
+
+    report
+        .evaluation
+        .save(&args.output)
+        .expect("Cannot save the tuned weights");
+    println!("Tuned weights are saved to {}", args.output.display());
+}
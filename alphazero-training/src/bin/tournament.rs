@@ -1,193 +1,152 @@
-use std::time::{Duration, Instant};
-
-use alphazero_training::{
-    alphazero_mcts::{AlphaZeroMcts, AlphaZeroMctsConfig},
-    common::Options,
-    elo_rating::EloRating,
-    net::ConvResNetConfig,
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
 };
+
+use alphazero_training::registry::registry;
 use onitama_game::{
-    ai::{agent::Agent, alpha_beta::AlphaBeta, mcts::Mcts, random::Random},
-    game::{
-        card::CARD_NAMES, deck::Deck, game_state::GameState, move_result::MoveResult,
-        player_color::PlayerColor,
+    ai::registry::{parse_duration, AgentSpec},
+    match_runner::{
+        rating::Ratings,
+        suite::load_suite,
+        tournament::{Tournament, TournamentConfig, TournamentState},
+        MatchGame,
     },
 };
-use tch::{kind, nn::VarStore, Device};
-
-fn play(
-    agent: &Box<dyn Agent>,
-    opponent: &Box<dyn Agent>,
-    ra: &mut f64,
-    rb: &mut f64,
-    game_amnt: usize,
-    decks: &Vec<Deck>,
-) {
-    let mut agents = [agent, opponent];
-    let mut agent_color = PlayerColor::Red;
-    let mut wins = 0;
-    let mut game = 0;
-
-    let now = Instant::now();
-    while game < game_amnt {
-        let deck = decks[game].clone();
-        let mut state = GameState::with_deck(deck);
-        let mut progress = MoveResult::InProgress;
-
-        let mut max_plies = 150;
 
-        while !progress.is_win() {
-            let (done_move, _) = agents[state.curr_agent_idx].generate_move(&state);
-
-            progress = state.progress(done_move);
+const USAGE: &str =
+    "Usage: tournament [--format FORMAT] [--games N] [--concurrency N] [--tc TIME] \
+[--timeout TIME] [--max-plies N] [--repetitions N] [--suite FILE] [--seed SEED] [--records DIR] \
+[--state FILE] [AGENT...]
+
+FORMAT is round-robin (default), gauntlet where the first agent plays the rest, or swiss:ROUNDS.
+--games is the amount of games of every pairing, each opening is played twice with the colors swapped.
+--state saves the tournament after every game, an interrupted tournament is resumed from the file.
+The other options are the same as of match_runner. Without the agents the default ones play";
+
+/// Agents of the tournament if none are given in the arguments
+const DEFAULT_AGENTS: [&str; 4] = [
+    "alphabeta:depth=8,time=1s",
+    "mcts:time=1s,visits=0,c=1,playouts=5000",
+    "alphazero:model=../models/model_5e-3.ot,time=1s,c=1,playouts=5000",
+    "random",
+];
+
+struct Args {
+    specs: Vec<AgentSpec>,
+    config: TournamentConfig,
+}
 
-            if max_plies < 0 {
-                println!("Game has become infinite!");
-                game -= 1;
-                break;
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        specs: vec![],
+        config: TournamentConfig {
+            games: 100,
+            ..Default::default()
+        },
+    };
+    // Games which are too long are draws
+    args.config.adjudication.max_plies = 150;
+    let mut timeout = None;
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--format" => args.config.format = value()?.parse()?,
+            "--games" => args.config.games = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--concurrency" => {
+                args.config.concurrency = value()?.parse().map_err(|e| format!("{}", e))?
             }
-
-            max_plies -= 1;
-        }
-
-        // Gather statistics
-        match (progress, agent_color) {
-            (MoveResult::BlueWin, PlayerColor::Blue) | (MoveResult::RedWin, PlayerColor::Red) => {
-                wins += 1;
-                (*ra, *rb) = EloRating::elo_change(*ra, *rb, true);
+            "--tc" => args.config.move_time = Some(parse_duration(&value()?)?),
+            "--timeout" => timeout = Some(parse_duration(&value()?)?),
+            "--max-plies" => {
+                args.config.adjudication.max_plies =
+                    value()?.parse().map_err(|e| format!("{}", e))?
             }
-            (MoveResult::BlueWin, PlayerColor::Red) | (MoveResult::RedWin, PlayerColor::Blue) => {
-                (*ra, *rb) = EloRating::elo_change(*ra, *rb, false);
+            "--repetitions" => {
+                args.config.adjudication.repetitions =
+                    value()?.parse().map_err(|e| format!("{}", e))?
             }
-            _ => {}
-        };
-
-        agent_color.switch();
-        agents.swap(0, 1);
-        game += 1;
+            "--suite" => {
+                let path = value()?;
+                args.config.openings = load_suite(&path).map_err(|e| format!("{}: {}", path, e))?
+            }
+            "--seed" => args.config.seed = Some(value()?.parse().map_err(|e| format!("{}", e))?),
+            "--records" => args.config.record_dir = Some(PathBuf::from(value()?)),
+            "--state" => args.config.state_file = Some(PathBuf::from(value()?)),
+            "--help" | "-h" => return Err(format!("{}\n\n{}", USAGE, registry().help())),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => args
+                .specs
+                .push(arg.parse().map_err(|e| format!("{}: {}", arg, e))?),
+        }
     }
 
-    println!(
-        "{} ({:5.2}) vs {} ({:5.2}) -> winrate: {:3.2}",
-        agent.name(),
-        ra,
-        opponent.name(),
-        rb,
-        (wins as f64 / game_amnt as f64),
-    );
-    println!("Elapsed: {:?}\n", now.elapsed());
-}
-
-const GAME_AMNT: usize = 100;
-
-pub fn pit() {
-    let mut alphabeta_rating = 800.;
-    let mut mcts_rating = 800.;
-    let mut alphazero_rating = 800.;
-    let mut random_rating = 800.;
-
-    let mut decks = Vec::with_capacity(GAME_AMNT);
-    for _ in 0..GAME_AMNT {
-        decks.push(Deck::default());
+    if args.specs.is_empty() {
+        args.specs = DEFAULT_AGENTS
+            .iter()
+            .map(|spec| spec.parse().expect("Default agents are valid"))
+            .collect();
     }
-    println!("Using decks: ");
-    for i in 0..decks.len() {
-        println!(
-            "{}. Deck: {:?}, Color: {:?}",
-            i + 1,
-            decks[i]
-                .cards
-                .iter()
-                .map(|c| CARD_NAMES[c.index])
-                .collect::<Vec<_>>(),
-            decks[i].neutral_card().player_color
-        );
-    }
-
-    let alphabeta: Box<dyn Agent> = Box::new(AlphaBeta {
-        max_depth: 8,
-        search_time: Duration::from_secs(1),
+    args.config.adjudication.time_limit = timeout.or_else(|| {
+        args.config
+            .move_time
+            .map(|move_time| move_time + Duration::from_secs(1))
     });
 
-    let mcts: Box<dyn Agent> = Box::new(Mcts {
-        search_time: Duration::from_secs(1),
-        min_node_visits: 0,
-        exploration_c: 1.0,
-        max_playouts: 5000,
-    });
-
-    let mut vs = VarStore::new(Device::Cpu);
-    let net_config = ConvResNetConfig::default();
-    let alphazero: Box<dyn Agent> = Box::new(AlphaZeroMcts::from_model_file(
-        &mut vs,
-        "../models/model_5e-3.ot",
-        AlphaZeroMctsConfig {
-            search_time: Duration::from_secs(1),
-            exploration_c: 1.0,
-            max_playouts: 5000,
-            train: false,
-        },
-        net_config,
-        Options::new(kind::FLOAT_CPU),
-    ));
-
-    let random: Box<dyn Agent> = Box::new(Random);
-
-    play(
-        &alphabeta,
-        &mcts,
-        &mut alphabeta_rating,
-        &mut mcts_rating,
-        GAME_AMNT,
-        &decks,
-    );
-
-    play(
-        &alphabeta,
-        &alphazero,
-        &mut alphabeta_rating,
-        &mut alphazero_rating,
-        GAME_AMNT,
-        &decks,
-    );
-
-    play(
-        &alphabeta,
-        &random,
-        &mut alphabeta_rating,
-        &mut random_rating,
-        GAME_AMNT,
-        &decks,
-    );
-
-    play(
-        &mcts,
-        &alphazero,
-        &mut mcts_rating,
-        &mut alphazero_rating,
-        GAME_AMNT,
-        &decks,
-    );
-
-    play(
-        &mcts,
-        &random,
-        &mut mcts_rating,
-        &mut random_rating,
-        GAME_AMNT,
-        &decks,
-    );
+    Ok(args)
+}
 
-    play(
-        &alphazero,
-        &random,
-        &mut alphazero_rating,
-        &mut random_rating,
-        GAME_AMNT,
-        &decks,
+fn print_game(game: &MatchGame, state: &TournamentState) {
+    println!(
+        "Game {}/{} ({} vs {}): {} {:?}",
+        game.number,
+        state.scheduled().len(),
+        game.red,
+        game.blue,
+        game.result(),
+        game.termination
     );
 }
 
 fn main() {
-    pit();
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    // Random is the zero of the ratings if it plays, otherwise the first agent
+    let anchor = args
+        .specs
+        .iter()
+        .position(|spec| spec.kind == "random")
+        .unwrap_or(0);
+    let mut tournament = match Tournament::new(registry(), args.specs, args.config) {
+        Ok(tournament) => tournament,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let finished = tournament.state().results.len();
+    if finished > 0 {
+        println!("Resuming the tournament after {} games", finished);
+    }
+
+    let now = Instant::now();
+    let state = match tournament.run(print_game) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("\nFinished in {:?}\n", now.elapsed());
+    print!("{}", state);
+    println!("\n{}", Ratings::estimate(&state.table(), anchor, 0.));
 }
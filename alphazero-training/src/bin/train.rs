@@ -7,6 +7,7 @@ use alphazero_training::{
     train::{train, TrainConfig},
 };
 use chrono::Local;
+use onitama_game::match_runner::sprt::SprtConfig;
 use tracing_subscriber::{filter, prelude::*, util::SubscriberInitExt, Layer};
 
 fn main() {
@@ -53,6 +54,7 @@ fn main() {
             max_playouts: 400,
             exploration_c: 5.,
             train: true,
+            ..Default::default()
         },
         model_config: ConvResNetConfig {
             hidden_channels: 64,
@@ -72,6 +74,12 @@ fn main() {
         evaluator_config: EvaluatorConfig {
             winrate_percent: 0.55,
             game_amnt: 20,
+            // Most fights against the best model are decided by the SPRT earlier
+            sprt_game_amnt: 100,
+            sprt: Some(SprtConfig {
+                elo1: 50.,
+                ..Default::default()
+            }),
             ..Default::default()
         },
         ..Default::default()
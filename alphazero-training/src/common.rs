@@ -3,6 +3,7 @@ use onitama_game::{
     game::{player_color::PlayerColor, state::State},
 };
 
+use serde::{ser::SerializeStruct, Serialize};
 use tch::{IndexOp, Tensor};
 
 #[derive(Debug, Clone, Copy)]
@@ -22,6 +23,19 @@ impl Options {
     }
 }
 
+/// Kind and device are written by their names, e.g. `Float` and `Cpu`
+impl Serialize for Options {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("Options", 2)?;
+        state.serialize_field("kind", &format!("{:?}", self.kind))?;
+        state.serialize_field("device", &format!("{:?}", self.device))?;
+        state.end()
+    }
+}
+
 // first approach
 pub fn create_tensor_from_state(
     state: &State,
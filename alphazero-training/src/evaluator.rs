@@ -5,8 +5,13 @@ use std::{
 };
 
 use onitama_game::{
-    ai::{agent::Agent, alpha_beta::AlphaBeta, mcts::Mcts, random::Random},
-    game::{deck::Deck, game_state::GameState, move_result::MoveResult, player_color::PlayerColor},
+    ai::{agent::Agent, registry::AgentSpec},
+    game::{deck::Deck, move_result::MoveResult, player_color::PlayerColor},
+    match_runner::{
+        score::Score,
+        sprt::{Sprt, SprtConfig, SprtDecision},
+        suite::Opening,
+    },
 };
 use serde::{Deserialize, Serialize};
 use tch::nn;
@@ -17,8 +22,12 @@ use crate::{
     common::Options,
     elo_rating::{EloRating, PlayerRating},
     net::{ConvResNet, ConvResNetConfig},
+    registry::registry,
 };
 
+/// Creates the agent of one game with the seed of the game
+pub type AgentFactory = Box<dyn Fn(Option<u64>) -> Box<dyn Agent> + Send>;
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct WinLoseDraws {
     pub wins: u64,
@@ -26,6 +35,16 @@ pub struct WinLoseDraws {
     pub draws: u64,
 }
 
+impl WinLoseDraws {
+    pub fn score(&self) -> Score {
+        Score {
+            wins: self.wins as usize,
+            draws: self.draws as usize,
+            losses: self.loses as usize,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct RatingChange {
     pub before_a: f64,
@@ -44,6 +63,9 @@ pub struct FightStatistics {
     pub rating_a: f64,
     pub rating_b: f64,
     pub rating_change_history: Vec<RatingChange>,
+    /// Test of the agent against the opponent if the fight has used it
+    #[serde(default)]
+    pub sprt: Option<Sprt>,
 }
 
 impl FightStatistics {
@@ -122,7 +144,23 @@ pub struct EvaluatorConfig {
     pub winrate_percent: f64,
     pub game_amnt: u64,
     pub deck: Option<Deck>,
+    /// Opening suite of the fights, every opening is played twice with the colors swapped.
+    /// It replaces `deck`, see `onitama_game::match_runner::suite`
+    pub suite: Vec<Opening>,
     pub max_plies: i64,
+    /// Seed of the random decks and the agents. Every game gets the seed increased by the game number,
+    /// the opponents which set their own seed keep it
+    pub seed: Option<u64>,
+    /// Opponents of the new model written as the agent specifications, see `registry`
+    pub random_opponent: String,
+    pub mcts_opponent: String,
+    pub alphabeta_opponent: String,
+    /// Test of the new model against the best one. It decides instead of `winrate_percent`
+    /// and stops the fight as soon as a hypothesis is accepted, so `sprt_game_amnt` is the maximum.
+    /// If the test is not finished after `sprt_game_amnt` games, the winrate decides
+    pub sprt: Option<SprtConfig>,
+    /// Games of the fight against the best model with the test, the other fights play `game_amnt`
+    pub sprt_game_amnt: u64,
 }
 
 impl Default for EvaluatorConfig {
@@ -131,7 +169,14 @@ impl Default for EvaluatorConfig {
             winrate_percent: 0.55,
             game_amnt: 20,
             deck: None,
+            suite: vec![],
             max_plies: 150,
+            seed: None,
+            random_opponent: "random".to_owned(),
+            mcts_opponent: "mcts:time=400ms,c=1.41,playouts=400,visits=5".to_owned(),
+            alphabeta_opponent: "alphabeta:depth=4,time=400ms".to_owned(),
+            sprt: None,
+            sprt_game_amnt: 100,
         }
     }
 }
@@ -169,19 +214,22 @@ impl<'a> Evaluator<'a> {
     pub fn pit(&mut self) -> (PitStatistics, bool) {
         // Apply handles to start threads working in parallel
         let self_fight_handle = self.fight_against_best(&self.ratings[0]);
-        let random_fight_handle = self.fight_against_random(&self.ratings[1]);
-        let mcts_fight_handle = self.fight_against_mcts(&self.ratings[2]);
-        let alphabeta_fight_handle = self.fight_against_alphabeta(&self.ratings[3]);
+        let random_fight_handle =
+            self.fight_against(&self.config.random_opponent, &self.ratings[1]);
+        let mcts_fight_handle = self.fight_against(&self.config.mcts_opponent, &self.ratings[2]);
+        let alphabeta_fight_handle =
+            self.fight_against(&self.config.alphabeta_opponent, &self.ratings[3]);
 
         let self_fight = self_fight_handle.join().unwrap();
         let random_fight = random_fight_handle.join().unwrap();
         let mcts_fight = mcts_fight_handle.join().unwrap();
         let alphabeta_fight = alphabeta_fight_handle.join().unwrap();
 
-        let mut is_best = false;
-        if self_fight.winrate > self.config.winrate_percent {
-            is_best = true;
-        }
+        let is_best = match self_fight.sprt.as_ref().map(Sprt::decision) {
+            Some(SprtDecision::AcceptH1) => true,
+            Some(SprtDecision::AcceptH0) => false,
+            _ => self_fight.winrate > self.config.winrate_percent,
+        };
         let statistics = PitStatistics {
             self_fight,
             random_fight,
@@ -215,6 +263,7 @@ impl<'a> Evaluator<'a> {
             config: mcts_config.clone(),
             model: training_model,
             options: self.options,
+            model_path: None,
         };
 
         let mut best_vs = nn::VarStore::new(self.options.device);
@@ -231,50 +280,33 @@ impl<'a> Evaluator<'a> {
             config: mcts_config.clone(),
             model: best_model,
             options: self.options,
+            model_path: None,
         };
 
-        let config = self.config.clone();
-        let ra = *ratings[0].rating;
-        let rb = *ratings[1].rating;
-        std::thread::spawn(move || fight(config, Box::new(train_mcts), Box::new(best_mcts), ra, rb))
-    }
-
-    pub fn fight_against_random(
-        &self,
-        ratings: &'a [PlayerRating; 2],
-    ) -> JoinHandle<FightStatistics> {
-        let mcts_config = AlphaZeroMctsConfig {
-            search_time: Duration::from_millis(400),
-            max_playouts: 400,
-            train: false,
-            ..Default::default()
+        let config = match self.config.sprt {
+            Some(_) => EvaluatorConfig {
+                game_amnt: self.config.sprt_game_amnt,
+                ..self.config.clone()
+            },
+            None => self.config.clone(),
         };
-
-        let mut train_vs = nn::VarStore::new(self.options.device);
-        if let Err(e) = train_vs.copy(self.new_nn_vs) {
-            error!("Was not able to copy VarStore: {}", e);
-        }
-        let model = Arc::new(Mutex::new(ConvResNet::new(
-            &train_vs.root(),
-            self.net_config.clone(),
-            self.options,
-        )));
-        let mcts = AlphaZeroMcts {
-            config: mcts_config.clone(),
-            model,
-            options: self.options,
-        };
-
-        let random = Random;
-
-        let config = self.config.clone();
         let ra = *ratings[0].rating;
         let rb = *ratings[1].rating;
-        std::thread::spawn(move || fight(config, Box::new(mcts), Box::new(random), ra, rb))
+        std::thread::spawn(move || {
+            fight(
+                config,
+                seeded_mcts(train_mcts),
+                seeded_mcts(best_mcts),
+                ra,
+                rb,
+            )
+        })
     }
 
-    pub fn fight_against_alphabeta(
+    /// Plays the new model against the agent of the specification, see `registry`
+    pub fn fight_against(
         &self,
+        opponent: &str,
         ratings: &'a [PlayerRating; 2],
     ) -> JoinHandle<FightStatistics> {
         let mcts_config = AlphaZeroMctsConfig {
@@ -288,7 +320,6 @@ impl<'a> Evaluator<'a> {
         if let Err(e) = train_vs.copy(self.new_nn_vs) {
             error!("Was not able to copy VarStore: {}", e);
         }
-
         let model = Arc::new(Mutex::new(ConvResNet::new(
             &train_vs.root(),
             self.net_config.clone(),
@@ -298,85 +329,89 @@ impl<'a> Evaluator<'a> {
             config: mcts_config.clone(),
             model,
             options: self.options,
+            model_path: None,
         };
 
-        let alphabeta = AlphaBeta {
-            max_depth: 4,
-            search_time: Duration::from_millis(400),
+        let registry = registry();
+        let spec = opponent
+            .parse::<AgentSpec>()
+            .and_then(|spec| registry.build_spec(&spec).map(|_| spec))
+            .unwrap_or_else(|e| panic!("Invalid opponent {}: {}", opponent, e));
+        let opponent: AgentFactory = Box::new(move |seed| {
+            registry
+                .build_spec(&registry.seeded(&spec, seed))
+                .expect("Opponent is checked before the fight")
+        });
+
+        // The other agents only measure the progress, they do not decide anything
+        let config = EvaluatorConfig {
+            sprt: None,
+            ..self.config.clone()
         };
-
-        let config = self.config.clone();
         let ra = *ratings[0].rating;
         let rb = *ratings[1].rating;
-        std::thread::spawn(move || fight(config, Box::new(mcts), Box::new(alphabeta), ra, rb))
+        std::thread::spawn(move || fight(config, seeded_mcts(mcts), opponent, ra, rb))
     }
+}
 
-    pub fn fight_against_mcts(
-        &self,
-        ratings: &'a [PlayerRating; 2],
-    ) -> JoinHandle<FightStatistics> {
-        let mcts_config = AlphaZeroMctsConfig {
-            search_time: Duration::from_millis(400),
-            max_playouts: 400,
-            train: false,
-            ..Default::default()
-        };
-
-        let mut train_vs = nn::VarStore::new(self.options.device);
-        if let Err(e) = train_vs.copy(self.new_nn_vs) {
-            error!("Was not able to copy VarStore: {}", e);
-        }
-
-        let model = Arc::new(Mutex::new(ConvResNet::new(
-            &train_vs.root(),
-            self.net_config.clone(),
-            self.options,
-        )));
-        let a0_mcts = AlphaZeroMcts {
-            config: mcts_config.clone(),
-            model,
-            options: self.options,
-        };
-
-        let mcts = Mcts {
-            search_time: Duration::from_millis(400),
-            min_node_visits: 5,
-            exploration_c: 1.41,
-            max_playouts: 400,
-        };
+/// Every game gets a copy of the agent with the seed of the game, the model is shared
+fn seeded_mcts(mcts: AlphaZeroMcts) -> AgentFactory {
+    Box::new(move |seed| {
+        let mut mcts = mcts.clone();
+        mcts.config.seed = seed;
+        Box::new(mcts)
+    })
+}
 
-        let config = self.config.clone();
-        let ra = *ratings[0].rating;
-        let rb = *ratings[1].rating;
-        std::thread::spawn(move || fight(config, Box::new(a0_mcts), Box::new(mcts), ra, rb))
+/// Opening of the game from the suite, the fixed deck or the deck of the game seed
+fn game_opening(config: &EvaluatorConfig, game: u64, seed: Option<u64>) -> Opening {
+    if !config.suite.is_empty() {
+        return config.suite[(game / 2) as usize % config.suite.len()].clone();
     }
+    Opening::from(match (&config.deck, seed) {
+        (Some(deck), _) => deck.clone(),
+        (None, Some(seed)) => Deck::from_seed(seed),
+        (None, None) => Deck::default(),
+    })
 }
 
 pub fn fight(
     config: EvaluatorConfig,
-    agent: Box<dyn Agent>,
-    opponent: Box<dyn Agent>,
+    agent: AgentFactory,
+    opponent: AgentFactory,
     agent_rating: f64,
     opponent_rating: f64,
 ) -> FightStatistics {
-    let mut agents = [agent, opponent];
+    let mut factories = [agent, opponent];
     let mut agent_color = PlayerColor::Red;
     let mut statistics = FightStatistics::new(agent_rating, opponent_rating);
-
-    for _ in 0..config.game_amnt {
-        let deck: Deck;
-        if let None = config.deck {
-            deck = Deck::default();
-        } else {
-            deck = config.deck.clone().unwrap_or_default();
+    let mut sprt = config.sprt.map(Sprt::new);
+    let mut opening = Opening::from(Deck::default());
+    let mut first_points = 0.;
+
+    for game in 0..config.game_amnt {
+        let seed = config.seed.map(|seed| seed.wrapping_add(game));
+        let agents = [factories[0](seed), factories[1](seed)];
+
+        // With the test or the suite both games of a pair have the same opening
+        let paired = (sprt.is_some() || !config.suite.is_empty()) && game & 1 == 1;
+        if !paired {
+            opening = game_opening(&config, game, seed);
         }
-        let mut state = GameState::with_deck(deck);
+        let mut state = opening.game_state();
         let mut progress = MoveResult::InProgress;
 
         let mut max_plies = config.max_plies;
 
         while !progress.is_win() {
-            let (done_move, _) = agents[state.curr_agent_idx].generate_move(&state);
+            let done_move = match agents[state.curr_agent_idx].try_generate_move(&state) {
+                Ok((done_move, _)) => done_move,
+                Err(e) => {
+                    println!("{:?} forfeits the game: {}", state.curr_player_color, e);
+                    progress = MoveResult::forfeit(state.curr_player_color);
+                    break;
+                }
+            };
 
             progress = state.progress(done_move);
 
@@ -391,9 +426,48 @@ pub fn fight(
         // Gather statistics
         statistics.update(progress, agent_color);
 
+        if let Some(sprt) = sprt.as_mut() {
+            let points = match (progress, agent_color) {
+                (MoveResult::RedWin, PlayerColor::Red)
+                | (MoveResult::BlueWin, PlayerColor::Blue) => 1.,
+                (MoveResult::RedWin, PlayerColor::Blue)
+                | (MoveResult::BlueWin, PlayerColor::Red) => 0.,
+                _ => 0.5,
+            };
+            sprt.add_game(points);
+            if paired {
+                sprt.add_pair(first_points, points);
+            } else {
+                first_points = points;
+            }
+
+            if sprt.decision() != SprtDecision::Continue {
+                break;
+            }
+        }
+
         agent_color.switch();
-        agents.swap(0, 1);
+        factories.swap(0, 1);
     }
 
+    statistics.sprt = sprt;
     statistics
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_games_have_different_decks() {
+        let config = EvaluatorConfig {
+            seed: Some(3),
+            ..Default::default()
+        };
+        let deck = |game: u64| game_opening(&config, game, Some(3 + game)).deck;
+
+        assert_eq!(deck(0), deck(0));
+        assert_ne!(deck(0), deck(1));
+        assert_ne!(deck(0), deck(2));
+    }
+}
@@ -3,5 +3,9 @@ pub mod common;
 pub mod elo_rating;
 pub mod evaluator;
 pub mod net;
+pub mod nn_evaluation;
+pub mod nnue_trainer;
+pub mod registry;
 pub mod stats;
+pub mod texel;
 pub mod train;
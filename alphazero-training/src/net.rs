@@ -95,6 +95,7 @@ pub struct ConvResNet {
     pub value_head: nn::SequentialT,
     pub options: Options,
     pub id: String,
+    pub net_config: ConvResNetConfig,
 }
 
 impl ConvResNet {
@@ -105,13 +106,14 @@ impl ConvResNet {
         );
         let policy_head = Self::build_policy_head(path, &net_config, options);
         let value_head = Self::build_value_head(path, &net_config);
-        let model = Self::build_model(path, net_config);
+        let model = Self::build_model(path, net_config.clone());
         Self {
             model,
             policy_head,
             value_head,
             options,
             id,
+            net_config,
         }
     }
 
@@ -231,6 +233,16 @@ impl ConvResNet {
         }
     }
 
+    /// Inference on the batch of positions with the size of (Batch, Channels, 5, 5)
+    pub fn forward_batch(&self, xs: &Tensor) -> ResTowerTensor {
+        let y = self.model.forward_t(xs, false);
+
+        ResTowerTensor {
+            policy: self.policy_head.forward_t(&y, false).reshape(&[-1, 2, 25]),
+            value: self.value_head.forward_t(&y, false),
+        }
+    }
+
     pub fn alphaloss(&self, v: &Tensor, p: &Tensor, pi: &Tensor, z: &Tensor) -> (Tensor, Tensor) {
         let diff = z.to_device(self.options.device) - v;
         let value_loss = (&diff * &diff).mean(self.options.kind);
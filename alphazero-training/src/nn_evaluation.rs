@@ -0,0 +1,172 @@
+use std::sync::{Arc, Mutex};
+
+use onitama_game::{
+    ai::alpha_beta::{evaluation::Evaluator, weighted_evaluation::WIN_SCORE, AlphaBeta},
+    game::{done_move::DoneMove, move_result::MoveResult, player_color::PlayerColor, state::State},
+};
+use serde::{ser::SerializeStruct, Serialize};
+use tch::{nn, IndexOp, Tensor};
+
+use crate::{
+    common::{create_tensor_from_state, Options},
+    net::{ConvResNet, ConvResNetConfig},
+};
+
+/// Value of the network from -1 to 1 is scaled to the score of the search.
+/// It stays far from the win score, so the proven wins are always preferred
+const VALUE_SCALE: f64 = 1000.;
+
+/// AlphaBeta search where the leaves are evaluated by the value head of the network
+/// and the moves are ordered by the policy head
+pub type NnAlphaBeta = AlphaBeta<NnEvaluation>;
+
+#[derive(Debug, Clone)]
+pub struct NnEvaluation {
+    pub model: Arc<Mutex<ConvResNet>>,
+    pub options: Options,
+    /// File of the model if it was loaded from one, the agent specification needs it
+    pub model_path: Option<String>,
+}
+
+impl NnEvaluation {
+    /// The model can be shared with `AlphaZeroMcts`
+    pub fn new(model: Arc<Mutex<ConvResNet>>, options: Options) -> Self {
+        Self {
+            model,
+            options,
+            model_path: None,
+        }
+    }
+
+    pub fn from_model_file(
+        vs: &mut nn::VarStore,
+        model_path: &str,
+        net_config: ConvResNetConfig,
+        options: Options,
+    ) -> Self {
+        let model = Arc::new(Mutex::new(ConvResNet::new(&vs.root(), net_config, options)));
+        if let Err(e) = vs.load(model_path) {
+            eprintln!("An error occurred while loading the file: {}", e);
+        }
+        Self {
+            model,
+            options,
+            model_path: Some(model_path.to_owned()),
+        }
+    }
+
+    /// Value is given for the player who has to make a move, the search needs it for the red player
+    fn to_score(value: f64, player_color: PlayerColor) -> i32 {
+        let sign = match player_color {
+            PlayerColor::Red => 1.,
+            PlayerColor::Blue => -1.,
+        };
+        (sign * value * VALUE_SCALE) as i32
+    }
+
+    fn win_score(move_result: &Option<MoveResult>) -> Option<i32> {
+        match move_result {
+            Some(MoveResult::RedWin) => Some(WIN_SCORE),
+            Some(MoveResult::BlueWin) => Some(-WIN_SCORE),
+            _ => None,
+        }
+    }
+}
+
+impl Evaluator for NnEvaluation {
+    type Accumulator = ();
+
+    fn evaluate(
+        &self,
+        state: &State,
+        player_color: PlayerColor,
+        move_result: &Option<MoveResult>,
+    ) -> i32 {
+        self.evaluate_batch(&[(state.clone(), player_color, *move_result)])[0]
+    }
+
+    fn prefers_batches(&self) -> bool {
+        true
+    }
+
+    /// Finished games get the win score, the rest go through the network in one batch
+    fn evaluate_batch(&self, positions: &[(State, PlayerColor, Option<MoveResult>)]) -> Vec<i32> {
+        let mut scores = positions
+            .iter()
+            .map(|(_, _, move_result)| Self::win_score(move_result))
+            .collect::<Vec<_>>();
+
+        let tensors = positions
+            .iter()
+            .zip(scores.iter())
+            .filter(|(_, score)| score.is_none())
+            .map(|((state, player_color, _), _)| {
+                create_tensor_from_state(state, *player_color, self.options.to_tuple())
+            })
+            .collect::<Vec<_>>();
+
+        if !tensors.is_empty() {
+            let model = self.model.lock().unwrap();
+            let results = tch::no_grad(|| model.forward_batch(&Tensor::stack(&tensors, 0)));
+            let mut values = Vec::<f64>::from(results.value.reshape(&[-1])).into_iter();
+
+            for (score, (_, player_color, _)) in scores.iter_mut().zip(positions.iter()) {
+                if score.is_none() {
+                    *score = Some(Self::to_score(values.next().unwrap(), *player_color));
+                }
+            }
+        }
+
+        scores.into_iter().map(|score| score.unwrap()).collect()
+    }
+
+    /// Moves with the highest policy probability go first
+    fn order_moves(&self, state: &State, player_color: PlayerColor, moves: &mut [DoneMove]) {
+        let t = create_tensor_from_state(state, player_color, self.options.to_tuple());
+        let results = {
+            let model = self.model.lock().unwrap();
+            tch::no_grad(|| model.forward_batch(&t.unsqueeze(0)))
+        };
+
+        // Squeeze batch dimension that should be [1], so [1, 2, 25] -> [2, 25]
+        let policy = results.policy.squeeze_dim(0);
+        let card_policy = [
+            Vec::<f64>::from(policy.i((0, ..))),
+            Vec::<f64>::from(policy.i((1, ..))),
+        ];
+
+        let probability = |done_move: &DoneMove| {
+            let card = match done_move.used_card_idx {
+                0 | 2 => 0,
+                1 | 3 => 1,
+                idx => panic!("Incorrect card index {} was somehow used", idx),
+            };
+            card_policy[card][done_move.mov.to as usize]
+        };
+
+        moves.sort_by(|a, b| probability(b).total_cmp(&probability(a)));
+    }
+
+    fn spec_params(&self) -> Option<Vec<(&'static str, String)>> {
+        let model_path = self.model_path.clone()?;
+        let blocks = self.model.lock().unwrap().net_config.resnet_block_amnt;
+        Some(vec![
+            ("eval", "nn".to_owned()),
+            ("model", model_path),
+            ("blocks", blocks.to_string()),
+        ])
+    }
+}
+
+impl Serialize for NnEvaluation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("NnEvaluation", 2)?;
+        state.serialize_field("model", &self.model.lock().unwrap().id)?;
+        state.serialize_field("options", &self.options)?;
+
+        state.end()
+    }
+}
@@ -0,0 +1,425 @@
+use std::time::Duration;
+
+use onitama_game::{
+    ai::{
+        alpha_beta::{weighted_evaluation::WeightedEvaluation, AlphaBeta},
+        nnue::{
+            features::{active_features, FEATURE_AMOUNT},
+            NnueNetwork, HIDDEN_SIZE, MAX_OUTPUT_BIAS, MAX_WEIGHT, QA, QB,
+        },
+    },
+    game::{game_record::GameRecord, game_state::GameState, player_color::PlayerColor},
+};
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+
+/// Output weights are kept in the range which fits into i8 after the quantization
+const MAX_OUTPUT_WEIGHT: f32 = i8::MAX as f32 / QB as f32;
+/// Feature weights are kept in the range where the accumulator of the engine does not overflow
+const MAX_FEATURE_WEIGHT: f32 = MAX_WEIGHT as f32 / QA as f32;
+const MAX_OUTPUT_BIAS_WEIGHT: f32 = MAX_OUTPUT_BIAS as f32 / (QA * QB) as f32;
+
+/// Position from the perspective of the player to move with its labels
+#[derive(Debug, Clone)]
+pub struct NnueSample {
+    /// Features of the player to move and of the enemy
+    pub features: [Vec<usize>; 2],
+    /// Score of the search for the player to move
+    pub score: f64,
+    /// Result of the game for the player to move: 1 is a win, 0 is a loss
+    pub result: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct NnueTrainConfig {
+    pub epochs: usize,
+    pub learning_rate: f32,
+    pub batch_size: usize,
+    /// Weight of the search score in the target, the rest is the game result
+    pub lambda: f64,
+    /// Depth of the search which labels the positions
+    pub search_depth: u8,
+    /// Score of the search which corresponds to the logit of 1
+    pub score_scale: f64,
+    /// Opening positions are mostly the same and say nothing about the result
+    pub skip_plies: usize,
+    pub seed: u64,
+}
+
+impl Default for NnueTrainConfig {
+    fn default() -> Self {
+        Self {
+            epochs: 30,
+            learning_rate: 1e-3,
+            batch_size: 256,
+            lambda: 0.7,
+            search_depth: 3,
+            score_scale: 40.,
+            skip_plies: 2,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NnueReport {
+    pub loss_before: f64,
+    pub loss_after: f64,
+    /// Loss of the network with the integer weights which the engine uses
+    pub quantized_loss: f64,
+    pub network: NnueNetwork,
+}
+
+/// Takes every position of finished games except the opening and the final position
+/// and labels it with the score of the AlphaBeta search with the weighted evaluation.
+/// Positions are searched by all available threads
+pub fn collect_samples(records: &[GameRecord], config: &NnueTrainConfig) -> Vec<NnueSample> {
+    let mut positions = vec![];
+    for record in records {
+        let winner = match record.winner() {
+            Some(winner) => winner,
+            None => continue,
+        };
+        let record_positions = match record.positions() {
+            Ok(positions) => positions,
+            Err(_) => continue,
+        };
+
+        positions.extend(
+            record_positions
+                .into_iter()
+                .skip(config.skip_plies)
+                .filter(|p| p.done_move.is_some())
+                .map(|p| (p, winner)),
+        );
+    }
+
+    let agent = AlphaBeta {
+        // Maximal depth is not searched
+        max_depth: config.search_depth + 1,
+        search_time: Duration::from_secs(60),
+        ..AlphaBeta::with_evaluator(WeightedEvaluation::default())
+    };
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let chunk_size = positions.len() / threads + 1;
+
+    std::thread::scope(|s| {
+        let handles = positions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let agent = &agent;
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(position, winner)| {
+                            let player_color = position.player_color;
+                            let game_state = GameState {
+                                state: position.state.clone(),
+                                history: vec![],
+                                curr_agent_idx: player_color as usize,
+                                curr_player_color: player_color,
+                            };
+                            let score = agent.search(&game_state).best_score as f64;
+
+                            NnueSample {
+                                features: [
+                                    active_features(&position.state, player_color),
+                                    active_features(&position.state, player_color.enemy()),
+                                ],
+                                score: match player_color {
+                                    PlayerColor::Red => score,
+                                    PlayerColor::Blue => -score,
+                                },
+                                result: if *winner == player_color { 1. } else { 0. },
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+#[inline]
+fn sigmoid(x: f64) -> f64 {
+    1. / (1. + (-x).exp())
+}
+
+/// Network with the float weights for the training.
+/// Activations are clipped to [0, 1] which corresponds to [0, QA] of the quantized network
+#[derive(Debug, Clone)]
+pub struct FloatNetwork {
+    pub feature_weights: Vec<[f32; HIDDEN_SIZE]>,
+    pub feature_bias: [f32; HIDDEN_SIZE],
+    pub output_weights: [f32; 2 * HIDDEN_SIZE],
+    pub output_bias: f32,
+}
+
+/// Accumulators of both perspectives, the player to move goes first
+type Hidden = [[f32; HIDDEN_SIZE]; 2];
+
+impl FloatNetwork {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut network = Self {
+            feature_weights: vec![[0.; HIDDEN_SIZE]; FEATURE_AMOUNT],
+            feature_bias: [0.; HIDDEN_SIZE],
+            output_weights: [0.; 2 * HIDDEN_SIZE],
+            output_bias: 0.,
+        };
+
+        for w in network.feature_weights.iter_mut().flatten() {
+            *w = rng.gen_range(-0.1..0.1);
+        }
+        // Positive bias keeps most neurons active at the start
+        for w in network.feature_bias.iter_mut() {
+            *w = rng.gen_range(0.2..0.6);
+        }
+        for w in network.output_weights.iter_mut() {
+            *w = rng.gen_range(-0.2..0.2);
+        }
+
+        network
+    }
+
+    fn hidden(&self, sample: &NnueSample) -> Hidden {
+        let mut hidden = [self.feature_bias; 2];
+        for (values, features) in hidden.iter_mut().zip(sample.features.iter()) {
+            for feature in features {
+                for (v, w) in values.iter_mut().zip(self.feature_weights[*feature].iter()) {
+                    *v += w;
+                }
+            }
+        }
+        hidden
+    }
+
+    /// Logit of the win probability for the player to move
+    fn output(&self, hidden: &Hidden) -> f32 {
+        self.output_bias
+            + hidden
+                .iter()
+                .flatten()
+                .zip(self.output_weights.iter())
+                .map(|(v, w)| v.clamp(0., 1.) * w)
+                .sum::<f32>()
+    }
+
+    pub fn forward(&self, sample: &NnueSample) -> f32 {
+        self.output(&self.hidden(sample))
+    }
+
+    /// Rounds the weights to the integers of the engine
+    pub fn quantize(&self) -> NnueNetwork {
+        let mut network = NnueNetwork::zeroed();
+        let quantize_i16 = |w: f32| ((w * QA as f32).round() as i16).clamp(-MAX_WEIGHT, MAX_WEIGHT);
+
+        for (row, float_row) in network
+            .feature_weights
+            .iter_mut()
+            .zip(self.feature_weights.iter())
+        {
+            for (w, float_w) in row.iter_mut().zip(float_row.iter()) {
+                *w = quantize_i16(*float_w);
+            }
+        }
+        for (w, float_w) in network
+            .feature_bias
+            .iter_mut()
+            .zip(self.feature_bias.iter())
+        {
+            *w = quantize_i16(*float_w);
+        }
+        for (w, float_w) in network
+            .output_weights
+            .iter_mut()
+            .zip(self.output_weights.iter())
+        {
+            *w = (float_w * QB as f32).round() as i8;
+        }
+        network.output_bias = ((self.output_bias * (QA * QB) as f32).round() as i32)
+            .clamp(-MAX_OUTPUT_BIAS, MAX_OUTPUT_BIAS);
+
+        network
+    }
+}
+
+/// Target probability of the win for the player to move
+fn target(sample: &NnueSample, config: &NnueTrainConfig) -> f64 {
+    config.lambda * sigmoid(sample.score / config.score_scale)
+        + (1. - config.lambda) * sample.result
+}
+
+/// Mean logistic loss between the predicted win probability and the target
+pub fn loss(
+    samples: &[NnueSample],
+    config: &NnueTrainConfig,
+    logit: impl Fn(&NnueSample) -> f64,
+) -> f64 {
+    // Keeps the logarithm finite
+    let eps = 1e-12;
+
+    let sum: f64 = samples
+        .iter()
+        .map(|s| {
+            let t = target(s, config);
+            let p = sigmoid(logit(s)).clamp(eps, 1. - eps);
+            -(t * p.ln() + (1. - t) * (1. - p).ln())
+        })
+        .sum();
+
+    sum / samples.len() as f64
+}
+
+/// Adam state of one group of the parameters
+struct Adam {
+    m: Vec<f32>,
+    v: Vec<f32>,
+}
+
+impl Adam {
+    fn new(size: usize) -> Self {
+        Self {
+            m: vec![0.; size],
+            v: vec![0.; size],
+        }
+    }
+
+    fn step<'a>(
+        &mut self,
+        params: impl Iterator<Item = &'a mut f32>,
+        gradient: &[f32],
+        learning_rate: f32,
+        step: i32,
+        limit: f32,
+    ) {
+        let (beta1, beta2, eps) = (0.9f32, 0.999f32, 1e-8f32);
+        for (i, p) in params.enumerate() {
+            let g = gradient[i];
+            self.m[i] = beta1 * self.m[i] + (1. - beta1) * g;
+            self.v[i] = beta2 * self.v[i] + (1. - beta2) * g * g;
+            let m_hat = self.m[i] / (1. - beta1.powi(step));
+            let v_hat = self.v[i] / (1. - beta2.powi(step));
+            *p = (*p - learning_rate * m_hat / (v_hat.sqrt() + eps)).clamp(-limit, limit);
+        }
+    }
+}
+
+/// Trains the network with Adam on the mini-batches of the shuffled samples
+pub fn train(samples: &[NnueSample], config: &NnueTrainConfig) -> NnueReport {
+    let mut network = FloatNetwork::new(config.seed);
+    let mut rng = SmallRng::seed_from_u64(config.seed);
+    let loss_before = loss(samples, config, |s| network.forward(s) as f64);
+
+    let mut feature_adam = Adam::new(FEATURE_AMOUNT * HIDDEN_SIZE);
+    let mut bias_adam = Adam::new(HIDDEN_SIZE);
+    let mut output_adam = Adam::new(2 * HIDDEN_SIZE);
+    let mut output_bias_adam = Adam::new(1);
+
+    let mut order = (0..samples.len()).collect::<Vec<_>>();
+    let mut step = 0;
+
+    for _ in 0..config.epochs {
+        order.shuffle(&mut rng);
+
+        for batch in order.chunks(config.batch_size) {
+            let mut feature_gradient = vec![0f32; FEATURE_AMOUNT * HIDDEN_SIZE];
+            let mut bias_gradient = vec![0f32; HIDDEN_SIZE];
+            let mut output_gradient = vec![0f32; 2 * HIDDEN_SIZE + 1];
+            let scale = 1. / batch.len() as f32;
+
+            for &idx in batch {
+                let sample = &samples[idx];
+                let hidden = network.hidden(sample);
+                let p = sigmoid(network.output(&hidden) as f64);
+                // Derivative of the logistic loss over the logit
+                let error = (p - target(sample, config)) as f32 * scale;
+
+                for (perspective, features) in sample.features.iter().enumerate() {
+                    for j in 0..HIDDEN_SIZE {
+                        let value = hidden[perspective][j];
+                        let w = perspective * HIDDEN_SIZE + j;
+                        output_gradient[w] += error * value.clamp(0., 1.);
+
+                        // Clipped activation does not pass the gradient
+                        if value <= 0. || value >= 1. {
+                            continue;
+                        }
+                        let g = error * network.output_weights[w];
+                        bias_gradient[j] += g;
+                        for feature in features {
+                            feature_gradient[feature * HIDDEN_SIZE + j] += g;
+                        }
+                    }
+                }
+                output_gradient[2 * HIDDEN_SIZE] += error;
+            }
+
+            step += 1;
+            let lr = config.learning_rate;
+            feature_adam.step(
+                network.feature_weights.iter_mut().flatten(),
+                &feature_gradient,
+                lr,
+                step,
+                MAX_FEATURE_WEIGHT,
+            );
+            bias_adam.step(
+                network.feature_bias.iter_mut(),
+                &bias_gradient,
+                lr,
+                step,
+                MAX_FEATURE_WEIGHT,
+            );
+            output_adam.step(
+                network.output_weights.iter_mut(),
+                &output_gradient[..2 * HIDDEN_SIZE],
+                lr,
+                step,
+                MAX_OUTPUT_WEIGHT,
+            );
+            output_bias_adam.step(
+                std::iter::once(&mut network.output_bias),
+                &output_gradient[2 * HIDDEN_SIZE..],
+                lr,
+                step,
+                MAX_OUTPUT_BIAS_WEIGHT,
+            );
+        }
+    }
+
+    let loss_after = loss(samples, config, |s| network.forward(s) as f64);
+    let quantized = network.quantize();
+    let quantized_loss = loss(samples, config, |s| quantized_logit(&quantized, s));
+
+    NnueReport {
+        loss_before,
+        loss_after,
+        quantized_loss,
+        network: quantized,
+    }
+}
+
+/// Logit of the quantized network computed the same way as in the engine.
+/// The bounded weights keep the sums in the range of the i16 accumulator
+fn quantized_logit(network: &NnueNetwork, sample: &NnueSample) -> f64 {
+    let mut sum = network.output_bias as i64;
+    for (perspective, features) in sample.features.iter().enumerate() {
+        for j in 0..HIDDEN_SIZE {
+            let value = features
+                .iter()
+                .map(|f| network.feature_weights[*f][j] as i64)
+                .sum::<i64>()
+                + network.feature_bias[j] as i64;
+            sum += value.clamp(0, QA as i64)
+                * network.output_weights[perspective * HIDDEN_SIZE + j] as i64;
+        }
+    }
+    sum as f64 / (QA * QB) as f64
+}
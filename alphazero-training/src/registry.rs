@@ -0,0 +1,105 @@
+use std::sync::{Arc, Mutex};
+
+use onitama_game::ai::{
+    agent::Agent,
+    registry::{
+        alpha_beta_agent, alpha_beta_params, build_alpha_beta, AgentKind, AgentRegistry, AgentSpec,
+        ParamInfo, SpecError,
+    },
+};
+use tch::{kind, nn, Device};
+
+use crate::{
+    alphazero_mcts::{AlphaZeroMcts, AlphaZeroMctsConfig},
+    common::Options,
+    net::{ConvResNet, ConvResNetConfig},
+    nn_evaluation::NnEvaluation,
+};
+
+/// Registry with the agents of `onitama_game` and the agents which use the network:
+/// `alphazero` and `alphabeta` with `eval=nn`
+pub fn registry() -> AgentRegistry {
+    let mut registry = AgentRegistry::default();
+
+    registry.register(AgentKind {
+        name: "alphazero",
+        description: "MCTS guided by the policy and the value of the network",
+        params: vec![
+            ParamInfo::new("model", "none", "File of the network weights"),
+            ParamInfo::new("blocks", "5", "Residual blocks of the network"),
+            ParamInfo::new("time", "400ms", "Search time"),
+            ParamInfo::new("c", "1.41", "Exploration constant"),
+            ParamInfo::new("playouts", "5000", "Maximal amount of playouts"),
+            ParamInfo::new("seed", "none", "Seed of the Dirichlet noise"),
+        ],
+        build: build_alphazero,
+    });
+
+    let mut params = alpha_beta_params();
+    for param in params.iter_mut().filter(|p| p.name == "eval") {
+        param.description = "Evaluation: simple, weighted, nnue or nn";
+    }
+    params.push(ParamInfo::new(
+        "model",
+        "none",
+        "Network of the nn evaluation",
+    ));
+    params.push(ParamInfo::new(
+        "blocks",
+        "5",
+        "Residual blocks of the network",
+    ));
+    registry.register(AgentKind {
+        name: "alphabeta",
+        description: "AlphaBeta search with iterative deepening",
+        params,
+        build: |spec| match spec.get("eval") {
+            Some("nn") => {
+                let (model, model_path) = load_model(spec)?;
+                let evaluation = NnEvaluation {
+                    model_path: Some(model_path),
+                    ..NnEvaluation::new(model, Options::new(kind::FLOAT_CPU))
+                };
+                alpha_beta_agent(spec, evaluation)
+            }
+            _ => build_alpha_beta(spec),
+        },
+    });
+
+    registry
+}
+
+/// Loads the network of the `model` parameter to the CPU
+fn load_model(spec: &AgentSpec) -> Result<(Arc<Mutex<ConvResNet>>, String), SpecError> {
+    let model_path = spec.require("model")?;
+    let net_config = ConvResNetConfig {
+        resnet_block_amnt: spec
+            .parse_or("blocks", ConvResNetConfig::default().resnet_block_amnt)?,
+        ..Default::default()
+    };
+
+    let mut vs = nn::VarStore::new(Device::Cpu);
+    let model = ConvResNet::new(&vs.root(), net_config, Options::new(kind::FLOAT_CPU));
+    vs.load(model_path)
+        .map_err(|e| SpecError::Build(format!("{}: {}", model_path, e)))?;
+
+    Ok((Arc::new(Mutex::new(model)), model_path.to_owned()))
+}
+
+fn build_alphazero(spec: &AgentSpec) -> Result<Box<dyn Agent>, SpecError> {
+    let (model, model_path) = load_model(spec)?;
+    let default = AlphaZeroMctsConfig::default();
+
+    Ok(Box::new(AlphaZeroMcts {
+        config: AlphaZeroMctsConfig {
+            search_time: spec.duration_or("time", default.search_time)?,
+            exploration_c: spec.parse_or("c", default.exploration_c)?,
+            max_playouts: spec.parse_or("playouts", default.max_playouts)?,
+            train: false,
+            seed: spec.parse_opt("seed")?,
+        },
+        model,
+        options: Options::new(kind::FLOAT_CPU),
+        model_path: Some(model_path),
+    }))
+}
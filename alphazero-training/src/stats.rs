@@ -1,9 +1,13 @@
 use std::{fs, path::PathBuf};
 
+use onitama_game::match_runner::{rating::Ratings, score::ScoreTable};
 use serde::{Deserialize, Serialize};
 
 use crate::evaluator::PitStatistics;
 
+/// Agents of the ratings before the evaluated models, the random one is the anchor
+const OPPONENTS: [&str; 4] = ["random", "mcts", "alphabeta", "initial model"];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GamesPlayed {
     pub games_amnt: usize,
@@ -19,6 +23,9 @@ pub struct Stats {
     pub was_best_change: Vec<bool>,
     pub fight_statistics: Vec<PitStatistics>,
     pub games_played: Vec<GamesPlayed>,
+    /// Maximum-likelihood ratings of every evaluated model from all the fights
+    #[serde(default)]
+    pub ratings: Option<Ratings>,
     pub dir: PathBuf,
 }
 
@@ -38,6 +45,7 @@ impl Stats {
             was_best_change: vec![],
             fight_statistics: vec![],
             games_played: vec![],
+            ratings: None,
             dir,
         }
     }
@@ -59,6 +67,29 @@ impl Stats {
     pub fn push_fight(&mut self, was_best_change: bool, fight_statistics: PitStatistics) {
         self.was_best_change.push(was_best_change);
         self.fight_statistics.push(fight_statistics);
+        self.ratings = Some(self.estimate_ratings());
+    }
+
+    /// Ratings of the models evaluated so far, named by the evaluation, where `Random` is 0.
+    /// Every model has played the best model at its time and the fixed opponents
+    pub fn estimate_ratings(&self) -> Ratings {
+        let mut agents = OPPONENTS.map(String::from).to_vec();
+        agents.extend((1..=self.fight_statistics.len()).map(|i| format!("model {}", i)));
+        let mut table = ScoreTable::new(agents);
+
+        let mut best = OPPONENTS.len() - 1;
+        for (i, fight) in self.fight_statistics.iter().enumerate() {
+            let model = OPPONENTS.len() + i;
+            table.add_score(model, best, fight.self_fight.general.score());
+            table.add_score(model, 0, fight.random_fight.general.score());
+            table.add_score(model, 1, fight.mcts_fight.general.score());
+            table.add_score(model, 2, fight.alphabeta_fight.general.score());
+            if self.was_best_change[i] {
+                best = model;
+            }
+        }
+
+        Ratings::estimate(&table, 0, 0.)
     }
 
     pub fn get_filename(&self) -> String {
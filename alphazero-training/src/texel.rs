@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use onitama_game::{
+    ai::{
+        agent::Agent,
+        alpha_beta::{
+            weighted_evaluation::{WeightedEvaluation, WEIGHT_AMOUNT},
+            AlphaBeta,
+        },
+    },
+    game::{
+        deck::Deck, done_move::DoneMove, game_record::GameRecord, game_state::GameState,
+        move_result::MoveResult, player_color::PlayerColor,
+    },
+};
+use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
+
+/// Position features together with the game result from the red player perspective:
+/// 1 is a red win and 0 is a blue win
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub features: [f64; WEIGHT_AMOUNT],
+    pub result: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TexelConfig {
+    pub epochs: usize,
+    pub learning_rate: f64,
+    /// Opening positions are mostly the same and say nothing about the result
+    pub skip_plies: usize,
+}
+
+impl Default for TexelConfig {
+    fn default() -> Self {
+        Self {
+            epochs: 2000,
+            learning_rate: 0.1,
+            skip_plies: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TexelReport {
+    /// Scaling constant of the sigmoid which fits the initial weights the best
+    pub k: f64,
+    pub loss_before: f64,
+    pub loss_after: f64,
+    pub evaluation: WeightedEvaluation,
+}
+
+/// Takes every position of finished games except the opening and the final position
+pub fn collect_samples(records: &[GameRecord], skip_plies: usize) -> Vec<Sample> {
+    let mut samples = vec![];
+
+    for record in records {
+        let result = match record.winner() {
+            Some(PlayerColor::Red) => 1.,
+            Some(PlayerColor::Blue) => 0.,
+            None => continue,
+        };
+
+        let positions = match record.positions() {
+            Ok(positions) => positions,
+            Err(_) => continue,
+        };
+
+        for position in positions
+            .iter()
+            .skip(skip_plies)
+            .filter(|p| p.done_move.is_some())
+        {
+            let features = WeightedEvaluation::features(&position.state);
+            samples.push(Sample {
+                features: features.map(|f| f as f64),
+                result,
+            });
+        }
+    }
+
+    samples
+}
+
+#[inline]
+fn sigmoid(x: f64) -> f64 {
+    1. / (1. + (-x).exp())
+}
+
+#[inline]
+fn dot(features: &[f64; WEIGHT_AMOUNT], weights: &[f64; WEIGHT_AMOUNT]) -> f64 {
+    features
+        .iter()
+        .zip(weights.iter())
+        .map(|(f, w)| f * w)
+        .sum()
+}
+
+/// Mean logistic loss between the predicted win probability and the game result
+pub fn loss(samples: &[Sample], weights: &[f64; WEIGHT_AMOUNT], k: f64) -> f64 {
+    // Keeps the logarithm finite
+    let eps = 1e-12;
+
+    let sum: f64 = samples
+        .iter()
+        .map(|s| {
+            let p = sigmoid(k * dot(&s.features, weights)).clamp(eps, 1. - eps);
+            -(s.result * p.ln() + (1. - s.result) * (1. - p).ln())
+        })
+        .sum();
+
+    sum / samples.len() as f64
+}
+
+/// Golden section search of K in the logarithmic scale
+pub fn fit_k(samples: &[Sample], weights: &[f64; WEIGHT_AMOUNT]) -> f64 {
+    let ratio = (5f64.sqrt() - 1.) / 2.;
+    let (mut low, mut high) = (1e-5f64.ln(), 1f64.ln());
+
+    for _ in 0..50 {
+        let a = high - ratio * (high - low);
+        let b = low + ratio * (high - low);
+
+        if loss(samples, weights, a.exp()) < loss(samples, weights, b.exp()) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+
+    ((low + high) / 2.).exp()
+}
+
+/// Fits the weights with Adam by minimizing the logistic loss.
+/// K is fitted once for the initial weights and stays fixed afterwards
+pub fn tune(samples: &[Sample], initial: &WeightedEvaluation, config: &TexelConfig) -> TexelReport {
+    let (beta1, beta2, eps) = (0.9, 0.999, 1e-8);
+
+    let mut weights = initial.weights().map(|w| w as f64);
+    let k = fit_k(samples, &weights);
+    let loss_before = loss(samples, &weights, k);
+
+    let mut m = [0.; WEIGHT_AMOUNT];
+    let mut v = [0.; WEIGHT_AMOUNT];
+
+    for epoch in 1..=config.epochs {
+        let mut gradient = [0.; WEIGHT_AMOUNT];
+        for sample in samples {
+            let error = sigmoid(k * dot(&sample.features, &weights)) - sample.result;
+            for (g, f) in gradient.iter_mut().zip(sample.features.iter()) {
+                *g += k * error * f;
+            }
+        }
+
+        for i in 0..WEIGHT_AMOUNT {
+            let g = gradient[i] / samples.len() as f64;
+            m[i] = beta1 * m[i] + (1. - beta1) * g;
+            v[i] = beta2 * v[i] + (1. - beta2) * g * g;
+            let m_hat = m[i] / (1. - beta1.powi(epoch as i32));
+            let v_hat = v[i] / (1. - beta2.powi(epoch as i32));
+            weights[i] -= config.learning_rate * m_hat / (v_hat.sqrt() + eps);
+        }
+    }
+
+    // Evaluation works with integers, so the loss is measured for the rounded weights
+    let rounded = weights.map(|w| w.round() as i32);
+    let loss_after = loss(samples, &rounded.map(|w| w as f64), k);
+
+    TexelReport {
+        k,
+        loss_before,
+        loss_after,
+        evaluation: WeightedEvaluation::from_weights(&rounded),
+    }
+}
+
+/// Plays games between two AlphaBeta agents with the given evaluation.
+/// First plies are random to get different games.
+/// Every game gets the seed increased by the game number, which is saved in the record
+pub fn self_play(
+    game_amnt: usize,
+    evaluation: &WeightedEvaluation,
+    random_plies: usize,
+    max_plies: usize,
+    seed: u64,
+) -> Vec<GameRecord> {
+    let agent = AlphaBeta {
+        max_depth: 4,
+        search_time: Duration::from_millis(100),
+        ..AlphaBeta::with_evaluator(evaluation.clone())
+    };
+    let mut records = Vec::with_capacity(game_amnt);
+
+    for game in 0..game_amnt {
+        let game_seed = seed.wrapping_add(game as u64);
+        let mut rng = SmallRng::seed_from_u64(game_seed);
+        let deck = Deck::random(&mut rng);
+        let mut game_state = GameState::with_deck(deck.clone());
+        let mut record = GameRecord::new(deck);
+        record.seed = Some(game_seed);
+        let mut move_result = MoveResult::InProgress;
+        let mut ply = 0;
+
+        while !move_result.is_win() && ply < max_plies {
+            let done_move = if ply < random_plies {
+                let moves = game_state
+                    .state
+                    .generate_all_legal_moves(game_state.curr_player_color);
+                let (card_idx, mov) = *moves
+                    .choose(&mut rng)
+                    .expect("There must be at least one legal move");
+                DoneMove {
+                    mov,
+                    used_card_idx: card_idx,
+                }
+            } else {
+                agent.generate_move(&game_state).0
+            };
+
+            let card = *game_state.state.deck.get_card(done_move.used_card_idx);
+            move_result = game_state.progress(done_move);
+            record.push(&card, done_move.mov, move_result);
+            ply += 1;
+        }
+
+        records.push(record);
+    }
+
+    records
+}
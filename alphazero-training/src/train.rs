@@ -4,10 +4,11 @@ use std::{
 };
 
 use chrono::Local;
+use onitama_game::common::seeded_rng;
 use onitama_game::game::{
     deck::Deck, move_result::MoveResult, player_color::PlayerColor, state::State,
 };
-use rand::{seq::IteratorRandom, thread_rng};
+use rand::seq::IteratorRandom;
 use tch::{
     kind,
     nn::{self, OptimizerConfig},
@@ -32,17 +33,26 @@ pub struct SelfPlayData {
     pub player_color: PlayerColor,
 }
 
+/// Every game gets the seed increased by the game number to choose the random deck and the noise
 pub fn self_play(
-    mcts: TrainingAlphaZeroMcts,
+    mut mcts: TrainingAlphaZeroMcts,
     options: Options,
     deck: Option<Deck>,
     config: &TrainConfig,
+    seed: Option<u64>,
 ) -> Vec<SelfPlayData> {
     let mut play_buffer = vec![];
 
-    for _ in 0..config.self_play_game_amnt {
+    for game in 0..config.self_play_game_amnt {
+        let game_seed = seed.map(|seed| seed.wrapping_add(game as u64));
+        if game_seed.is_some() {
+            mcts.config.seed = game_seed;
+        }
+
         let mut state = if let Some(deck) = deck.clone() {
             State::with_deck(deck)
+        } else if let Some(seed) = game_seed {
+            State::with_deck(Deck::from_seed(seed))
         } else {
             State::new()
         };
@@ -53,7 +63,7 @@ pub fn self_play(
         let mut play_history = vec![];
 
         while !progress.is_win() {
-            let (mov, priors) = mcts.generate_move_tensor(&state, player_color);
+            let (mov, priors) = mcts.generate_move_tensor(&state, player_color, play_history.len());
 
             let state_tensor = create_tensor_from_state(&state, player_color, options.to_tuple());
 
@@ -115,6 +125,8 @@ pub struct TrainConfig {
     pub max_plies: isize,
     pub deck: Option<Deck>,
     pub evaluator_config: EvaluatorConfig,
+    /// Seed of the self-play decks, the Dirichlet noise and the batch sampling
+    pub seed: Option<u64>,
 }
 
 impl Default for TrainConfig {
@@ -136,6 +148,7 @@ impl Default for TrainConfig {
                 exploration_c: 2.,
                 max_playouts: 400,
                 train: true,
+                ..Default::default()
             },
             iterations: 10,
             training_epochs: 10,
@@ -149,6 +162,7 @@ impl Default for TrainConfig {
             max_plies: 150,
             deck: None,
             evaluator_config: EvaluatorConfig::default(),
+            seed: None,
         }
     }
 }
@@ -206,7 +220,7 @@ pub fn train(config: TrainConfig) -> anyhow::Result<()> {
 
     info!("[*] Starting self play");
 
-    let mut rng = thread_rng();
+    let mut rng = seeded_rng(config.seed, 0);
 
     for iter in 1..config.iterations + 1 {
         info!("[*] Iteration {} has started.", iter);
@@ -218,7 +232,7 @@ pub fn train(config: TrainConfig) -> anyhow::Result<()> {
         std::thread::scope(|s| {
             let mut handles = vec![];
 
-            for _ in 0..config.thread_amnt {
+            for thread in 0..config.thread_amnt {
                 let mut best_vs_copy = nn::VarStore::new(device);
 
                 let best_model_copy =
@@ -234,7 +248,14 @@ pub fn train(config: TrainConfig) -> anyhow::Result<()> {
                     options,
                 };
 
-                let handle = s.spawn(|| self_play(mcts, options, config.deck.clone(), &config));
+                // Every thread plays its own games
+                let game = ((iter - 1) * config.thread_amnt + thread) * config.self_play_game_amnt;
+                let seed = config.seed.map(|seed| seed.wrapping_add(game as u64));
+
+                let deck = config.deck.clone();
+                let config = &config;
+
+                let handle = s.spawn(move || self_play(mcts, options, deck, config, seed));
                 handles.push(handle);
             }
 
@@ -376,9 +397,20 @@ pub fn train(config: TrainConfig) -> anyhow::Result<()> {
                 end, fight_statistics.self_fight.winrate,
                 fight_statistics.self_fight.rating_a
             );
+            if let Some(sprt) = &fight_statistics.self_fight.sprt {
+                info!("[*] {}", sprt);
+            }
 
             update_ratings(&mut ratings, &fight_statistics);
             loss_stats.push_fight(should_change_best, fight_statistics);
+            let model = format!("model {}", loss_stats.fight_statistics.len());
+            if let Some(rating) = loss_stats.ratings.as_ref().and_then(|r| r.get(&model)) {
+                info!(
+                    "[*] Maximum-likelihood rating of the new model: {:.1} +/- {:.1}",
+                    rating.elo,
+                    rating.margin.unwrap_or(f64::INFINITY)
+                );
+            }
 
             if should_change_best {
                 info!("[*] New model is better. Changing...");
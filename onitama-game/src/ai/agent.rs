@@ -1,19 +1,125 @@
-use std::hash::Hasher;
+use std::{fmt, hash::Hasher};
 
 use erased_serde::serialize_trait_object;
 
 use crate::game::{done_move::DoneMove, game_state::GameState};
 
+use super::{
+    analysis::{AnalysisLine, SearchControl},
+    registry::AgentSpec,
+};
+
+/// Reason why the agent could not make a move. The player of such agent forfeits the game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentError {
+    /// Agent has not answered in time
+    Timeout,
+    /// Agent cannot play anymore, e.g. its process has exited
+    Crashed(String),
+    /// Agent has answered with something which is not a legal move
+    InvalidMove(String),
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentError::Timeout => write!(f, "Agent has not made a move in time"),
+            AgentError::Crashed(reason) => write!(f, "Agent has crashed: {}", reason),
+            AgentError::InvalidMove(answer) => {
+                write!(f, "Agent has made an invalid move: {}", answer)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AgentError {}
+
 pub trait Agent: Send + erased_serde::Serialize {
     /// Returns best move and a score for it
     fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64);
 
+    /// Same as `generate_move`, but the agents which can fail, e.g. the external engines,
+    /// return the error instead of panicking, so the game can be scored as a forfeit
+    fn try_generate_move(&self, game_state: &GameState) -> Result<(DoneMove, f64), AgentError> {
+        Ok(self.generate_move(game_state))
+    }
+
     fn name(&self) -> &'static str;
 
     // To clone the agent, it requires quite awful construction: https://stackoverflow.com/a/69891769
     fn clone_dyn(&self) -> Box<dyn Agent>;
 
     fn id(&self) -> u64;
+
+    /// Specification which creates the same agent with the `AgentRegistry`.
+    /// Agents which cannot be written as a specification return None
+    fn spec(&self) -> Option<AgentSpec> {
+        None
+    }
+
+    /// Analyzes the position and returns up to `lines` best root moves ordered from the best one.
+    /// Agents without a search return an empty list
+    fn analyze(&self, _game_state: &GameState, _lines: usize) -> Vec<AnalysisLine> {
+        vec![]
+    }
+
+    /// Analysis which reports the lines found so far with `on_lines`, e.g. after every depth,
+    /// and ends early at the stop or the deadline of `control`. Returns the last reported lines.
+    /// Agents without a gradual search report the lines of `analyze` once
+    fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        _control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        let analysis = self.analyze(game_state, lines);
+        if !analysis.is_empty() {
+            on_lines(&analysis);
+        }
+        analysis
+    }
+}
+
+/// Boxed agent can be wrapped by the generic agents, e.g. by the agent of the opening book
+impl Agent for Box<dyn Agent> {
+    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
+        (**self).generate_move(game_state)
+    }
+
+    fn try_generate_move(&self, game_state: &GameState) -> Result<(DoneMove, f64), AgentError> {
+        (**self).try_generate_move(game_state)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Agent> {
+        (**self).clone_dyn()
+    }
+
+    fn id(&self) -> u64 {
+        (**self).id()
+    }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        (**self).spec()
+    }
+
+    fn analyze(&self, game_state: &GameState, lines: usize) -> Vec<AnalysisLine> {
+        (**self).analyze(game_state, lines)
+    }
+
+    fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        (**self).analyze_until(game_state, lines, control, on_lines)
+    }
 }
 
 impl Clone for Box<dyn Agent> {
@@ -1,6 +1,9 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     common::{count_bits, get_bit, get_msb},
     game::{
+        done_move::DoneMove,
         move_result::MoveResult,
         player_color::PlayerColor,
         r#move::Move,
@@ -9,7 +12,7 @@ use crate::{
 };
 
 // PST is taken from: https://github.com/maxbennedich/onitama/blob/master/src/main/java/onitama/ai/evaluation/PieceSquareTables.java#L10
-const PIECE_SQUARE_TABLE: [i32; 25] = [
+pub(crate) const PIECE_SQUARE_TABLE: [i32; 25] = [
     0, 4, 8, 4, 0, //
     4, 8, 12, 8, 4, //
     8, 12, 16, 12, 8, //
@@ -17,11 +20,77 @@ const PIECE_SQUARE_TABLE: [i32; 25] = [
     0, 4, 8, 4, 0,
 ];
 
-#[derive(Debug)]
+/// Evaluates the position from the perspective of the red player.
+/// Positive score is good for red, negative is good for blue
+pub trait Evaluator: std::fmt::Debug + Clone + Send + Sync {
+    /// State of the evaluation which every search thread updates with the moves,
+    /// so the evaluation does not start from scratch in every position.
+    /// Evaluators which do not need it use `()`
+    type Accumulator: Default + Send;
+
+    fn evaluate(
+        &self,
+        state: &State,
+        player_color: PlayerColor,
+        move_result: &Option<MoveResult>,
+    ) -> i32;
+
+    /// Whether the leaves of one node should be evaluated with `evaluate_batch`.
+    /// It is worth it for the expensive evaluators, e.g. neural networks,
+    /// although the batch is evaluated fully before any cutoff
+    fn prefers_batches(&self) -> bool {
+        false
+    }
+
+    /// Evaluates the positions after every move of one node
+    fn evaluate_batch(&self, positions: &[(State, PlayerColor, Option<MoveResult>)]) -> Vec<i32> {
+        positions
+            .iter()
+            .map(|(state, player_color, move_result)| {
+                self.evaluate(state, *player_color, move_result)
+            })
+            .collect()
+    }
+
+    /// Sorts the moves of the position so the most promising are searched first.
+    /// Moves keep the order of the generation by default
+    fn order_moves(&self, _state: &State, _player_color: PlayerColor, _moves: &mut [DoneMove]) {}
+
+    /// Computes the accumulator for the root of the search
+    fn refresh(&self, _accumulator: &mut Self::Accumulator, _state: &State) {}
+
+    /// Updates the accumulator after the move from the position `before` to the position `after`
+    fn make_move(&self, _accumulator: &mut Self::Accumulator, _before: &State, _after: &State) {}
+
+    /// Restores the accumulator of the position before the last move
+    fn unmake_move(&self, _accumulator: &mut Self::Accumulator) {}
+
+    /// Evaluates the position which the accumulator follows
+    fn evaluate_incremental(
+        &self,
+        _accumulator: &Self::Accumulator,
+        state: &State,
+        player_color: PlayerColor,
+        move_result: &Option<MoveResult>,
+    ) -> i32 {
+        self.evaluate(state, player_color, move_result)
+    }
+
+    /// Parameters of the `alphabeta` agent specification which select this evaluation.
+    /// Evaluations which cannot be selected by the specification return None
+    fn spec_params(&self) -> Option<Vec<(&'static str, String)>> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Evaluation;
 
-impl Evaluation {
-    pub fn evaluate(
+impl Evaluator for Evaluation {
+    type Accumulator = ();
+
+    fn evaluate(
+        &self,
         state: &State,
         player_color: PlayerColor,
         move_result: &Option<MoveResult>,
@@ -89,7 +158,13 @@ impl Evaluation {
             + (my_piece_square - enemy_piece_square))
     }
 
-    fn distance(from: u32, to: u32) -> i32 {
+    fn spec_params(&self) -> Option<Vec<(&'static str, String)>> {
+        Some(vec![("eval", "simple".to_owned())])
+    }
+}
+
+impl Evaluation {
+    pub(crate) fn distance(from: u32, to: u32) -> i32 {
         let (from_x, from_y) = Move::convert_to_2d(from);
         let (to_x, to_y) = Move::convert_to_2d(to);
         Self::euclidean_distance(from_x, from_y, to_x, to_y)
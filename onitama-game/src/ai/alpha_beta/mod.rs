@@ -1,6 +1,16 @@
 pub mod evaluation;
-
-use std::time::{Duration, Instant};
+pub mod transposition_table;
+pub mod weighted_evaluation;
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -8,31 +18,129 @@ use crate::game::{
     done_move::DoneMove, game_state::GameState, move_result::MoveResult, player_color::PlayerColor,
 };
 
-use self::evaluation::Evaluation;
+use self::{
+    evaluation::{Evaluation, Evaluator},
+    transposition_table::{Bound, TableEntry, TableMove, TranspositionTable},
+};
 
-use super::agent::Agent;
+use super::{
+    agent::Agent,
+    analysis::{AnalysisLine, SearchControl},
+    registry::{format_duration, AgentSpec},
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AlphaBeta {
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlphaBeta<E = Evaluation> {
     pub max_depth: u8,
     pub search_time: Duration,
+    /// Amount of threads for the Lazy SMP search.
+    /// With one thread the search is deterministic
+    pub threads: usize,
+    pub evaluator: E,
+    /// The table of the last search, it is cleared and reused by the next one.
+    /// Every clone starts without the table, so the clones playing other games do not wait for it
+    #[serde(skip)]
+    pub table: Arc<Mutex<Option<TranspositionTable>>>,
 }
 
-impl Default for AlphaBeta {
-    fn default() -> Self {
+impl<E: Clone> Clone for AlphaBeta<E> {
+    fn clone(&self) -> Self {
         Self {
-            max_depth: 6,
-            search_time: Duration::from_secs(1),
+            max_depth: self.max_depth,
+            search_time: self.search_time,
+            threads: self.threads,
+            evaluator: self.evaluator.clone(),
+            table: Arc::default(),
         }
     }
 }
 
+impl Default for AlphaBeta {
+    fn default() -> Self {
+        Self::with_evaluator(Evaluation)
+    }
+}
+
 struct CalculationResult {
     best_move: Option<DoneMove>,
     best_score: i32,
 }
 
-impl AlphaBeta {
+/// Information about the finished search
+#[derive(Debug, Clone, Copy)]
+pub struct SearchInfo {
+    pub best_move: DoneMove,
+    pub best_score: i32,
+    /// The last fully searched depth of the main thread
+    pub depth: u8,
+    /// Positions analyzed by all threads
+    pub positions: u64,
+}
+
+/// The clock is read once in this amount of the stop checks, it is a power of two
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
+
+/// Data shared by the search of one thread
+struct SearchContext<'a, A> {
+    table: &'a TranspositionTable,
+    /// Helper threads and the analysis are stopped with this flag,
+    /// main thread of `search` always finishes its depth
+    stop: Option<&'a AtomicBool>,
+    /// The search is stopped at this time
+    deadline: Option<Instant>,
+    checks: u32,
+    /// The search was stopped, its result is not reliable
+    is_aborted: bool,
+    /// Root moves which are not searched. Used by the analysis to find the next best moves
+    excluded: &'a [DoneMove],
+    positions: u64,
+    /// Incremental state of the evaluation which follows the moves of the thread
+    accumulator: A,
+}
+
+impl<'a, A> SearchContext<'a, A> {
+    #[inline]
+    fn is_stopped(&mut self) -> bool {
+        if !self.is_aborted {
+            self.checks = self.checks.wrapping_add(1);
+            let is_late = self.checks & (DEADLINE_CHECK_INTERVAL - 1) == 0
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+            self.is_aborted = is_late || self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed));
+        }
+        self.is_aborted
+    }
+}
+
+impl<E: Evaluator> AlphaBeta<E> {
+    /// Creates the agent with default search parameters and the given evaluation
+    pub fn with_evaluator(evaluator: E) -> Self {
+        Self {
+            max_depth: 6,
+            search_time: Duration::from_secs(1),
+            threads: 1,
+            evaluator,
+            table: Arc::default(),
+        }
+    }
+
+    /// Empty table for the search. The table of the last search is taken,
+    /// a new one is allocated if there is none or another search is using it
+    fn take_table(&self) -> TranspositionTable {
+        match self.table.lock().unwrap().take() {
+            Some(mut table) => {
+                table.clear();
+                table
+            }
+            None => TranspositionTable::default(),
+        }
+    }
+
+    fn save_table(&self, table: TranspositionTable) {
+        *self.table.lock().unwrap() = Some(table);
+    }
+
     fn alpha_beta(
         &self,
         depth: u8,
@@ -41,9 +149,9 @@ impl AlphaBeta {
         mut beta: i32,
         game_state: &mut GameState,
         move_result: Option<MoveResult>,
-        positions: &mut i32,
+        ctx: &mut SearchContext<E::Accumulator>,
     ) -> CalculationResult {
-        *positions += 1;
+        ctx.positions += 1;
         let player_color = game_state.curr_player_color;
 
         if depth == max_depth
@@ -52,77 +160,166 @@ impl AlphaBeta {
         {
             return CalculationResult {
                 best_move: None,
-                best_score: Evaluation::evaluate(&game_state.state, player_color, &move_result),
+                best_score: self.evaluator.evaluate_incremental(
+                    &ctx.accumulator,
+                    &game_state.state,
+                    player_color,
+                    &move_result,
+                ),
             };
         }
 
-        let cards = game_state.state.deck.get_player_cards_idx(player_color);
+        let hash = game_state.state.zobrist_hash(player_color);
+        let remaining_depth = max_depth - depth;
+        let (alpha_orig, beta_orig) = (alpha, beta);
+
+        let table_entry = ctx.table.probe(hash);
+        if let Some(entry) = table_entry {
+            // Root must always be searched to produce a move
+            if depth > 0 && entry.depth >= remaining_depth {
+                let is_cut = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => entry.score >= beta,
+                    Bound::Upper => entry.score <= alpha,
+                };
+
+                if is_cut {
+                    return CalculationResult {
+                        best_move: entry
+                            .best_move
+                            .and_then(|m| m.done_move(&game_state.state, player_color)),
+                        best_score: entry.score,
+                    };
+                }
+            }
+        }
+
+        let mut allowed_moves = game_state
+            .state
+            .generate_all_legal_moves(player_color)
+            .into_iter()
+            .map(|(card_idx, mov)| DoneMove {
+                mov,
+                used_card_idx: card_idx,
+            })
+            .collect::<Vec<_>>();
+        if depth == 0 && !ctx.excluded.is_empty() {
+            allowed_moves.retain(|m| !ctx.excluded.contains(m));
+        }
+        // Children are the leaves, so they can be evaluated at once.
+        // All of them are evaluated before any cutoff, so their order does not matter
+        let is_batch = depth + 1 == max_depth && self.evaluator.prefers_batches();
+        if !is_batch {
+            self.evaluator
+                .order_moves(&game_state.state, player_color, &mut allowed_moves);
+        }
+
+        // Best move from the previous search goes first
+        let table_move = table_entry
+            .and_then(|e| e.best_move)
+            .and_then(|m| m.done_move(&game_state.state, player_color));
+        if let Some(table_move) = table_move {
+            if let Some(pos) = allowed_moves.iter().position(|m| *m == table_move) {
+                allowed_moves[..=pos].rotate_right(1);
+            }
+        }
 
         let mut best_score;
         if player_color == PlayerColor::Red {
-            best_score = std::i32::MIN;
+            best_score = i32::MIN;
         } else {
-            best_score = std::i32::MAX;
+            best_score = i32::MAX;
         }
 
         let mut best_move = None;
 
-        for card_idx in cards {
-            let allowed_moves = game_state
-                .state
-                .generate_legal_moves_card_idx(player_color, card_idx);
+        let leaf_scores = if is_batch {
+            Some(self.evaluate_leaves(&allowed_moves, game_state, ctx))
+        } else {
+            None
+        };
+
+        for (i, done_move) in allowed_moves.into_iter().enumerate() {
+            let score = match &leaf_scores {
+                Some(scores) => scores[i],
+                None => {
+                    let result = self.make_move(game_state, done_move, ctx);
+
+                    // go deeper the tree
+                    let calc_result = self.alpha_beta(
+                        depth + 1,
+                        max_depth,
+                        alpha,
+                        beta,
+                        game_state,
+                        Some(result),
+                        ctx,
+                    );
+
+                    // Undo all made moves
+                    self.unmake_move(game_state, ctx);
+
+                    calc_result.best_score
+                }
+            };
 
-            'br: for mov in allowed_moves.iter() {
-                let done_move = DoneMove {
-                    mov: *mov,
-                    used_card_idx: card_idx,
+            // The result of an aborted search is not reliable
+            if ctx.is_stopped() {
+                return CalculationResult {
+                    best_move: None,
+                    best_score: 0,
                 };
-                let result = game_state.progress(done_move);
-
-                // go deeper the tree
-                let calc_result = self.alpha_beta(
-                    depth + 1,
-                    max_depth,
-                    alpha,
-                    beta,
-                    game_state,
-                    Some(result),
-                    positions,
-                );
+            }
 
-                let score = calc_result.best_score;
+            if player_color == PlayerColor::Red {
+                if score > best_score {
+                    best_score = score;
+                    best_move = Some(done_move);
+                }
 
-                // Undo all made moves
-                game_state.undo();
+                if score >= beta {
+                    break;
+                }
 
-                if player_color == PlayerColor::Red {
-                    if score > best_score {
-                        best_score = score;
-                        best_move = Some(done_move);
-                    }
+                alpha = std::cmp::max(alpha, score);
+            } else {
+                if score < best_score {
+                    best_score = score;
+                    best_move = Some(done_move);
+                }
 
-                    if score >= beta {
-                        break 'br;
-                    }
+                if score <= alpha {
+                    break;
+                }
 
-                    alpha = std::cmp::max(alpha, score);
-                } else {
-                    if score < best_score {
-                        best_score = score;
-                        best_move = Some(done_move);
-                    }
+                beta = std::cmp::min(beta, score);
+            }
 
-                    if score <= alpha {
-                        break 'br;
-                    }
+            if alpha >= beta {
+                break;
+            }
+        }
 
-                    beta = std::cmp::min(beta, score);
-                }
+        // The root without some moves has a wrong score for the other searches
+        let is_partial_root = depth == 0 && !ctx.excluded.is_empty();
+        if best_move.is_some() && !is_partial_root {
+            let bound = if best_score <= alpha_orig {
+                Bound::Upper
+            } else if best_score >= beta_orig {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
 
-                if alpha >= beta {
-                    break 'br;
-                }
-            }
+            ctx.table.store(
+                hash,
+                TableEntry {
+                    score: best_score,
+                    depth: remaining_depth,
+                    bound,
+                    best_move: best_move.map(|m| TableMove::new(m, &game_state.state)),
+                },
+            );
         }
 
         CalculationResult {
@@ -130,13 +327,115 @@ impl AlphaBeta {
             best_score,
         }
     }
-}
 
-impl Agent for AlphaBeta {
-    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
-        let mut positions = 0;
+    /// Makes the move and updates the accumulator of the evaluation
+    fn make_move(
+        &self,
+        game_state: &mut GameState,
+        done_move: DoneMove,
+        ctx: &mut SearchContext<E::Accumulator>,
+    ) -> MoveResult {
+        let result = game_state.progress(done_move);
+        let before = game_state
+            .history
+            .last()
+            .expect("The position before the move must be saved");
+        self.evaluator
+            .make_move(&mut ctx.accumulator, before, &game_state.state);
+        result
+    }
+
+    fn unmake_move(&self, game_state: &mut GameState, ctx: &mut SearchContext<E::Accumulator>) {
+        game_state.undo();
+        self.evaluator.unmake_move(&mut ctx.accumulator);
+    }
+
+    /// Creates the context of one thread for the search from the position
+    fn context<'a>(
+        &self,
+        table: &'a TranspositionTable,
+        stop: Option<&'a AtomicBool>,
+        excluded: &'a [DoneMove],
+        game_state: &GameState,
+    ) -> SearchContext<'a, E::Accumulator> {
+        let mut ctx = SearchContext {
+            table,
+            stop,
+            deadline: None,
+            checks: 0,
+            is_aborted: false,
+            excluded,
+            positions: 0,
+            accumulator: E::Accumulator::default(),
+        };
+        self.evaluator
+            .refresh(&mut ctx.accumulator, &game_state.state);
+        ctx
+    }
+
+    /// Evaluates the positions after every move with one batch
+    fn evaluate_leaves(
+        &self,
+        moves: &[DoneMove],
+        game_state: &mut GameState,
+        ctx: &mut SearchContext<E::Accumulator>,
+    ) -> Vec<i32> {
+        ctx.positions += moves.len() as u64;
+
+        let positions = moves
+            .iter()
+            .map(|done_move| {
+                let result = game_state.progress(*done_move);
+                let position = (
+                    game_state.state.clone(),
+                    game_state.curr_player_color,
+                    Some(result),
+                );
+                game_state.undo();
+                position
+            })
+            .collect::<Vec<_>>();
+
+        self.evaluator.evaluate_batch(&positions)
+    }
+
+    /// Iterative deepening search of the helper thread.
+    /// Odd helpers start one ply deeper, so the threads do not search the same depth all the time
+    fn helper_search(
+        &self,
+        helper_idx: usize,
+        game_state: &GameState,
+        table: &TranspositionTable,
+        stop: &AtomicBool,
+    ) -> u64 {
+        let mut game_state = game_state.clone();
+        let mut ctx = self.context(table, Some(stop), &[], &game_state);
+
+        let mut depth = 1 + (helper_idx % 2) as u8;
+        while !stop.load(Ordering::Relaxed) && depth < self.max_depth {
+            self.alpha_beta(
+                0,
+                depth,
+                i32::MIN,
+                i32::MAX,
+                &mut game_state,
+                None,
+                &mut ctx,
+            );
+            depth += 1;
+        }
+
+        ctx.positions
+    }
 
+    /// Iterative deepening search of the main thread. Its result is the result of the whole search
+    fn main_search(
+        &self,
+        game_state: &GameState,
+        table: &TranspositionTable,
+    ) -> (CalculationResult, u8, u64) {
         let mut game_state = game_state.clone();
+        let mut ctx = self.context(table, None, &[], &game_state);
         let mut result = None;
 
         let mut depth = 1;
@@ -146,27 +445,247 @@ impl Agent for AlphaBeta {
             result = Some(self.alpha_beta(
                 0,
                 depth,
-                std::i32::MIN,
-                std::i32::MAX,
+                i32::MIN,
+                i32::MAX,
                 &mut game_state,
                 None,
-                &mut positions,
+                &mut ctx,
             ));
             depth += 1;
         }
 
-        // println!("Last depth {}", depth);
-        // println!("Analyzed {} positions", positions);
         // We must receive some kind of result
-        let result = result.unwrap();
-        // println!("Best score is {}", result.best_score);
+        (result.unwrap(), depth - 1, ctx.positions)
+    }
 
-        (
-            result
+    /// Searches the best move using Lazy SMP.
+    /// Helper threads share the transposition table with the main thread,
+    /// so the main thread can reuse their results and reach a deeper depth
+    pub fn search(&self, game_state: &GameState) -> SearchInfo {
+        let table = self.take_table();
+        let stop = AtomicBool::new(false);
+
+        let (result, depth, positions) = std::thread::scope(|s| {
+            let handles = (1..self.threads)
+                .map(|helper_idx| {
+                    let (table, stop) = (&table, &stop);
+                    s.spawn(move || self.helper_search(helper_idx, game_state, table, stop))
+                })
+                .collect::<Vec<_>>();
+
+            let (result, depth, mut positions) = self.main_search(game_state, &table);
+
+            stop.store(true, Ordering::Relaxed);
+            for handle in handles {
+                positions += handle.join().unwrap();
+            }
+
+            (result, depth, positions)
+        });
+        self.save_table(table);
+
+        SearchInfo {
+            best_move: result
                 .best_move
                 .expect("AlphaBeta agent must produce a move!"),
-            result.best_score as f64,
-        )
+            best_score: result.best_score,
+            depth,
+            positions,
+        }
+    }
+
+    /// Finds the best root moves within `search_time`, see `analyze_until`
+    pub fn analyze_lines(&self, game_state: &GameState, lines: usize) -> Vec<AnalysisLine> {
+        let stop = AtomicBool::new(false);
+        self.analyze_until(game_state, lines, &SearchControl::new(&stop), &mut |_| ())
+    }
+
+    /// Iterative deepening of all lines together. At every depth the best root moves are found
+    /// one by one: every next search excludes the moves found before. The lines are reported
+    /// after every finished depth, the depth which is interrupted by the end of `search_time`
+    /// or by the control is thrown away. The helper threads fill the shared transposition table
+    pub fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        let table = self.take_table();
+        let helpers_stop = AtomicBool::new(false);
+        let end = control.end(self.search_time);
+
+        let analysis = std::thread::scope(|s| {
+            let handles = (1..self.threads)
+                .map(|helper_idx| {
+                    let (table, stop) = (&table, &helpers_stop);
+                    s.spawn(move || self.helper_search(helper_idx, game_state, table, stop))
+                })
+                .collect::<Vec<_>>();
+
+            let analysis =
+                self.deepen_lines(game_state, lines, &table, control.stop, end, on_lines);
+
+            helpers_stop.store(true, Ordering::Relaxed);
+            for handle in handles {
+                handle.join().unwrap();
+            }
+
+            analysis
+        });
+        self.save_table(table);
+        analysis
+    }
+
+    fn deepen_lines(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        table: &TranspositionTable,
+        stop: &AtomicBool,
+        end: Option<Instant>,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        let sign = match game_state.curr_player_color {
+            PlayerColor::Red => 1.,
+            PlayerColor::Blue => -1.,
+        };
+        let mut analysis = vec![];
+
+        for depth in 1..self.max_depth {
+            let mut found = vec![];
+            let mut excluded = vec![];
+            let mut is_aborted = false;
+
+            while found.len() < lines {
+                let mut searched = game_state.clone();
+                let mut ctx = self.context(table, None, &excluded, &searched);
+                // The first depth always finishes, so there is a move to play
+                if depth > 1 {
+                    ctx.stop = Some(stop);
+                    ctx.deadline = end;
+                }
+                let result =
+                    self.alpha_beta(0, depth, i32::MIN, i32::MAX, &mut searched, None, &mut ctx);
+                is_aborted = ctx.is_aborted;
+
+                let best_move = match result.best_move {
+                    Some(best_move) if !is_aborted => best_move,
+                    _ => break,
+                };
+                found.push(AnalysisLine {
+                    done_move: best_move,
+                    score: sign * result.best_score as f64,
+                    visits: 0,
+                    depth,
+                    pv: self.principal_variation(game_state, best_move, depth, table),
+                });
+                excluded.push(best_move);
+            }
+
+            if is_aborted || found.is_empty() {
+                break;
+            }
+            analysis = found;
+            on_lines(&analysis);
+
+            let is_late = end.is_some_and(|end| Instant::now() >= end);
+            if is_late || stop.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+
+        analysis
+    }
+
+    /// Follows the best moves saved in the table after the root move
+    fn principal_variation(
+        &self,
+        game_state: &GameState,
+        first_move: DoneMove,
+        depth: u8,
+        table: &TranspositionTable,
+    ) -> Vec<DoneMove> {
+        let mut game_state = game_state.clone();
+        let mut pv = vec![first_move];
+        let mut result = game_state.progress(first_move);
+
+        while !result.is_win() && pv.len() < depth as usize {
+            let player_color = game_state.curr_player_color;
+            let hash = game_state.state.zobrist_hash(player_color);
+            let table_move = match table.probe(hash).and_then(|e| e.best_move) {
+                Some(table_move) => table_move,
+                None => break,
+            };
+
+            // The entry could be overwritten by another position with the same index.
+            // The card is compared because the same position can have it in another slot
+            let legal_move = game_state
+                .state
+                .generate_all_legal_moves(player_color)
+                .into_iter()
+                .find(|(card_idx, mov)| {
+                    game_state.state.deck.get_card(*card_idx).index == table_move.card
+                        && *mov == table_move.mov
+                });
+            let done_move = match legal_move {
+                Some((card_idx, mov)) => DoneMove {
+                    mov,
+                    used_card_idx: card_idx,
+                },
+                None => break,
+            };
+
+            result = game_state.progress(done_move);
+            pv.push(done_move);
+        }
+
+        pv
+    }
+
+    /// Scores every legal move with the search of the given depth, the move itself is the first ply.
+    /// Scores are from the red player perspective like the evaluation
+    pub fn score_moves(&self, game_state: &GameState, depth: u8) -> Vec<(DoneMove, i32)> {
+        let table = self.take_table();
+        let mut ctx = self.context(&table, None, &[], game_state);
+        let mut game_state = game_state.clone();
+        let depth = depth.max(1);
+
+        let scores = game_state
+            .state
+            .generate_all_legal_moves(game_state.curr_player_color)
+            .into_iter()
+            .map(|(card_idx, mov)| {
+                let done_move = DoneMove {
+                    mov,
+                    used_card_idx: card_idx,
+                };
+                let result = self.make_move(&mut game_state, done_move, &mut ctx);
+                let score = self
+                    .alpha_beta(
+                        1,
+                        depth,
+                        i32::MIN,
+                        i32::MAX,
+                        &mut game_state,
+                        Some(result),
+                        &mut ctx,
+                    )
+                    .best_score;
+                self.unmake_move(&mut game_state, &mut ctx);
+
+                (done_move, score)
+            })
+            .collect();
+        self.save_table(table);
+        scores
+    }
+}
+
+impl<E: Evaluator + Serialize + 'static> Agent for AlphaBeta<E> {
+    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
+        let info = self.search(game_state);
+        (info.best_move, info.best_score as f64)
     }
 
     fn name(&self) -> &'static str {
@@ -178,6 +697,207 @@ impl Agent for AlphaBeta {
     }
 
     fn id(&self) -> u64 {
-        self.search_time.as_nanos() as u64 + self.max_depth as u64
+        let mut hasher = DefaultHasher::new();
+        self.max_depth.hash(&mut hasher);
+        self.search_time.hash(&mut hasher);
+        self.threads.hash(&mut hasher);
+        self.evaluator.spec_params().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        let mut spec = AgentSpec::new("alphabeta")
+            .with("depth", self.max_depth)
+            .with("time", format_duration(self.search_time))
+            .with("threads", self.threads);
+        for (key, value) in self.evaluator.spec_params()? {
+            spec.set(key, value);
+        }
+        Some(spec)
+    }
+
+    fn analyze(&self, game_state: &GameState, lines: usize) -> Vec<AnalysisLine> {
+        self.analyze_lines(game_state, lines)
+    }
+
+    fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        AlphaBeta::analyze_until(self, game_state, lines, control, on_lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::from_2d_to_bitboard,
+        game::{
+            card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+            deck::Deck,
+            piece::PieceKind,
+            r#move::Move,
+            state::State,
+        },
+    };
+
+    use super::{
+        weighted_evaluation::{WeightedEvaluation, WIN_SCORE},
+        *,
+    };
+
+    fn alpha_beta(threads: usize) -> AlphaBeta {
+        AlphaBeta {
+            max_depth: 6,
+            search_time: Duration::from_secs(60),
+            threads,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_single_thread_is_deterministic() {
+        let game_state = GameState::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let alpha_beta = alpha_beta(1);
+
+        let first = alpha_beta.search(&game_state);
+        let second = alpha_beta.search(&game_state);
+
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.best_score, second.best_score);
+        assert_eq!(first.positions, second.positions);
+        assert_eq!(first.depth, 5);
+
+        // The table is kept for the next search, the clones allocate their own
+        assert!(alpha_beta.table.lock().unwrap().is_some());
+        assert!(alpha_beta.clone().table.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_threads_find_king_capture() {
+        // Same position as in MCTS test: Blue must eat the Red king with the Dragon card
+        let mut game_state = GameState::with_deck(Deck::new([RABBIT, FROG, TIGER, DRAGON, HORSE]));
+        game_state.state.kings[PlayerColor::Red as usize] = from_2d_to_bitboard((1, 3));
+        game_state.curr_player_color = PlayerColor::Blue;
+        game_state.curr_agent_idx = 1;
+
+        let expected = DoneMove {
+            mov: Move {
+                from: 1,
+                to: 8,
+                piece: PieceKind::Pawn,
+            },
+            used_card_idx: 3,
+        };
+
+        for threads in [1, 4] {
+            let info = alpha_beta(threads).search(&game_state);
+            assert_eq!(info.best_move, expected);
+            assert_eq!(info.best_score, -10000);
+        }
+    }
+
+    #[test]
+    fn test_weighted_evaluation_finds_king_capture() {
+        let mut game_state = GameState::with_deck(Deck::new([RABBIT, FROG, TIGER, DRAGON, HORSE]));
+        game_state.state.kings[PlayerColor::Red as usize] = from_2d_to_bitboard((1, 3));
+        game_state.curr_player_color = PlayerColor::Blue;
+        game_state.curr_agent_idx = 1;
+
+        let alpha_beta = AlphaBeta {
+            max_depth: 4,
+            search_time: Duration::from_secs(60),
+            ..AlphaBeta::with_evaluator(WeightedEvaluation::default())
+        };
+        let info = alpha_beta.search(&game_state);
+
+        assert_eq!(info.best_move.mov.to, 8);
+        assert_eq!(info.best_score, -WIN_SCORE);
+    }
+
+    #[test]
+    fn test_analysis_finds_distinct_ordered_lines() {
+        let game_state = GameState::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let alpha_beta = AlphaBeta {
+            max_depth: 5,
+            search_time: Duration::from_secs(60),
+            ..Default::default()
+        };
+
+        let lines = alpha_beta.analyze_lines(&game_state, 3);
+        let info = alpha_beta.search(&game_state);
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].done_move, info.best_move);
+        assert_eq!(lines[0].score, info.best_score as f64);
+        for (i, line) in lines.iter().enumerate() {
+            assert_eq!(line.pv[0], line.done_move);
+            assert!(line.pv.len() <= line.depth as usize);
+            assert!(lines[..i].iter().all(|l| l.done_move != line.done_move));
+        }
+        assert!(lines.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+
+    #[test]
+    fn test_analysis_stops_after_all_moves() {
+        let mut game_state = GameState::with_deck(Deck::new([RABBIT, FROG, TIGER, DRAGON, HORSE]));
+        game_state.state.kings[PlayerColor::Red as usize] = from_2d_to_bitboard((1, 3));
+        game_state.curr_player_color = PlayerColor::Blue;
+        game_state.curr_agent_idx = 1;
+
+        let moves = game_state
+            .state
+            .generate_all_legal_moves(PlayerColor::Blue)
+            .len();
+        let lines = alpha_beta(1).analyze_lines(&game_state, moves + 5);
+
+        assert_eq!(lines.len(), moves);
+        // Score is from the perspective of Blue who captures the king
+        assert_eq!(lines[0].score, 10000.);
+        assert_eq!(lines[0].pv.len(), 1);
+    }
+
+    /// Default evaluation which asks for the batches of leaves
+    #[derive(Debug, Clone)]
+    struct BatchEvaluation;
+
+    impl Evaluator for BatchEvaluation {
+        type Accumulator = ();
+
+        fn evaluate(
+            &self,
+            state: &State,
+            player_color: PlayerColor,
+            move_result: &Option<MoveResult>,
+        ) -> i32 {
+            Evaluation.evaluate(state, player_color, move_result)
+        }
+
+        fn prefers_batches(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_batched_leaves_give_same_result() {
+        let game_state = GameState::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let batched = AlphaBeta {
+            max_depth: 5,
+            search_time: Duration::from_secs(60),
+            ..AlphaBeta::with_evaluator(BatchEvaluation)
+        };
+        let plain = AlphaBeta {
+            max_depth: 5,
+            search_time: Duration::from_secs(60),
+            ..Default::default()
+        };
+
+        let batched = batched.search(&game_state);
+        let plain = plain.search(&game_state);
+        assert_eq!(batched.best_move, plain.best_move);
+        assert_eq!(batched.best_score, plain.best_score);
     }
 }
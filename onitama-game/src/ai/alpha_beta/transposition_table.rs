@@ -0,0 +1,287 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::game::{
+    done_move::DoneMove, piece::PieceKind, player_color::PlayerColor, r#move::Move, state::State,
+};
+
+/// Amount of entries in the table. Must be a power of two.
+/// Each entry takes 16 bytes, so the table takes 16 MB
+pub const TABLE_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// Score is exact
+    Exact,
+    /// Score is at least the saved value (beta cut-off happened)
+    Lower,
+    /// Score is at most the saved value (alpha cut-off happened)
+    Upper,
+}
+
+/// Move saved with the card instead of its deck slot. The hash does not depend
+/// on the order of the cards in the hand, so the same card can be in another slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableMove {
+    pub mov: Move,
+    /// Index of the card in the original cards
+    pub card: usize,
+}
+
+impl TableMove {
+    pub fn new(done_move: DoneMove, state: &State) -> Self {
+        Self {
+            mov: done_move.mov,
+            card: state.deck.get_card(done_move.used_card_idx).index,
+        }
+    }
+
+    /// Move with the slot of the card in the hand of the player, None if the player does not have it
+    pub fn done_move(&self, state: &State, player_color: PlayerColor) -> Option<DoneMove> {
+        state
+            .deck
+            .get_player_cards_idx(player_color)
+            .into_iter()
+            .find(|&idx| state.deck.get_card(idx).index == self.card)
+            .map(|idx| DoneMove {
+                mov: self.mov,
+                used_card_idx: idx,
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry {
+    pub score: i32,
+    /// Remaining depth which was searched from the position
+    pub depth: u8,
+    pub bound: Bound,
+    pub best_move: Option<TableMove>,
+}
+
+impl TableEntry {
+    /// Packs the entry into 64 bits:
+    /// score(32) | depth(8) | bound(2) | unused(6) | has move(1) | card(4) | piece(1) | from(5) | to(5)
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0u64,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+
+        let mov = match self.best_move {
+            Some(table_move) => {
+                let piece = match table_move.mov.piece {
+                    PieceKind::Pawn => 0u64,
+                    PieceKind::King => 1,
+                };
+                1 << 15
+                    | (table_move.card as u64 & 0b1111) << 11
+                    | piece << 10
+                    | (table_move.mov.from as u64 & 0b11111) << 5
+                    | table_move.mov.to as u64 & 0b11111
+            }
+            None => 0,
+        };
+
+        (self.score as u32 as u64) << 32 | (self.depth as u64) << 24 | bound << 22 | mov
+    }
+
+    fn unpack(data: u64) -> Self {
+        let bound = match (data >> 22) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+
+        let best_move = if (data >> 15) & 1 == 1 {
+            let piece = if (data >> 10) & 1 == 1 {
+                PieceKind::King
+            } else {
+                PieceKind::Pawn
+            };
+            Some(TableMove {
+                mov: Move {
+                    from: ((data >> 5) & 0b11111) as u32,
+                    to: (data & 0b11111) as u32,
+                    piece,
+                },
+                card: ((data >> 11) & 0b1111) as usize,
+            })
+        } else {
+            None
+        };
+
+        Self {
+            score: (data >> 32) as u32 as i32,
+            depth: ((data >> 24) & 0xFF) as u8,
+            bound,
+            best_move,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    /// Zobrist hash XORed with the data, so that a torn write
+    /// from another thread is detected during the probe
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Transposition table which can be shared between the search threads without locks.
+/// Uses the "lockless hashing" idea: https://craftychess.com/hyatt/hashing.html
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    mask: usize,
+}
+
+impl TranspositionTable {
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "Table size must be a power of two");
+        let slots = (0..size).map(|_| Slot::default()).collect();
+        Self {
+            slots,
+            mask: size - 1,
+        }
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TableEntry> {
+        let slot = &self.slots[hash as usize & self.mask];
+        let key = slot.key.load(Ordering::Relaxed);
+        let data = slot.data.load(Ordering::Relaxed);
+
+        // Empty slots have zero data and will not match a real hash
+        if key ^ data == hash && data != 0 {
+            Some(TableEntry::unpack(data))
+        } else {
+            None
+        }
+    }
+
+    /// Removes all entries, so the next search does not depend on the previous ones
+    pub fn clear(&mut self) {
+        self.slots.fill_with(Slot::default);
+    }
+
+    /// Saves the entry. A deeper search of the same position is never replaced by a shallower one
+    pub fn store(&self, hash: u64, entry: TableEntry) {
+        let slot = &self.slots[hash as usize & self.mask];
+        let key = slot.key.load(Ordering::Relaxed);
+        let data = slot.data.load(Ordering::Relaxed);
+
+        if key ^ data == hash && TableEntry::unpack(data).depth > entry.depth {
+            return;
+        }
+
+        let data = entry.pack();
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("size", &self.slots.len())
+            .finish()
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(TABLE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+        deck::Deck,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_table_move_keeps_card_in_other_slot() {
+        let state = State::with_deck(Deck::new([DRAGON, RABBIT, TIGER, HORSE, FROG]));
+        let swapped = State::with_deck(Deck::new([RABBIT, DRAGON, TIGER, HORSE, FROG]));
+        assert_eq!(
+            state.zobrist_hash(PlayerColor::Red),
+            swapped.zobrist_hash(PlayerColor::Red)
+        );
+
+        let (card_idx, mov) = state.generate_all_legal_moves(PlayerColor::Red)[0];
+        let table_move = TableMove::new(
+            DoneMove {
+                mov,
+                used_card_idx: card_idx,
+            },
+            &state,
+        );
+        let done_move = table_move
+            .done_move(&swapped, PlayerColor::Red)
+            .expect("Red player has the card");
+
+        assert_eq!(done_move.mov, mov);
+        assert_ne!(done_move.used_card_idx, card_idx);
+        assert_eq!(
+            swapped.deck.get_card(done_move.used_card_idx).index,
+            state.deck.get_card(card_idx).index
+        );
+        assert_eq!(table_move.done_move(&state, PlayerColor::Blue), None);
+    }
+
+    #[test]
+    fn test_pack_unpack_entry() {
+        let entry = TableEntry {
+            score: -10042,
+            depth: 7,
+            bound: Bound::Upper,
+            best_move: Some(TableMove {
+                mov: Move {
+                    from: 22,
+                    to: 2,
+                    piece: PieceKind::King,
+                },
+                card: 15,
+            }),
+        };
+        assert_eq!(TableEntry::unpack(entry.pack()), entry);
+
+        let entry = TableEntry {
+            score: 15,
+            depth: 0,
+            bound: Bound::Exact,
+            best_move: None,
+        };
+        assert_eq!(TableEntry::unpack(entry.pack()), entry);
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let table = TranspositionTable::new(1 << 4);
+        let entry = TableEntry {
+            score: 20,
+            depth: 3,
+            bound: Bound::Lower,
+            best_move: None,
+        };
+        table.store(0xDEAD_BEEF, entry);
+
+        assert_eq!(table.probe(0xDEAD_BEEF), Some(entry));
+        // Same slot, but another position
+        assert_eq!(table.probe(0xDEAD_BEEF + (1 << 4)), None);
+
+        // Shallower search must not replace the deeper one
+        table.store(0xDEAD_BEEF, TableEntry { depth: 1, ..entry });
+        assert_eq!(table.probe(0xDEAD_BEEF), Some(entry));
+
+        let mut table = table;
+        table.clear();
+        assert_eq!(table.probe(0xDEAD_BEEF), None);
+    }
+}
@@ -0,0 +1,332 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::get_bit,
+    game::{
+        move_result::MoveResult,
+        player_color::PlayerColor,
+        state::{State, BLUE_TEMPLE, RED_TEMPLE},
+    },
+};
+
+use super::evaluation::{Evaluation, Evaluator, PIECE_SQUARE_TABLE};
+
+pub const WIN_SCORE: i32 = 10000;
+
+/// Amount of weights: pawn, temple distance, king proximity and two piece-square tables
+pub const WEIGHT_AMOUNT: usize = 53;
+const PAWN_SQUARE_OFFSET: usize = 3;
+const KING_SQUARE_OFFSET: usize = PAWN_SQUARE_OFFSET + 25;
+
+/// Evaluation which is a weighted sum of the position features.
+/// All weights can be loaded from a JSON file, missing fields take the default values.
+/// Only JSON is supported, the tuner writes the weights in it.
+///
+/// Piece-square tables are written from the red player perspective,
+/// i.e. the first row is the row of the blue temple. They are mirrored for the blue player
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeightedEvaluation {
+    /// Value of one pawn
+    pub pawn: i32,
+    /// Reward for every step the king is closer to the enemy temple than the enemy king to ours
+    pub temple_distance: i32,
+    /// Reward for every step the pieces are closer to the enemy king than the enemy pieces to our king
+    pub king_proximity: i32,
+    pub pawn_square_table: [i32; 25],
+    pub king_square_table: [i32; 25],
+    /// File of the weights to know which ones were playing
+    #[serde(skip)]
+    pub path: Option<String>,
+}
+
+impl Default for WeightedEvaluation {
+    fn default() -> Self {
+        Self {
+            pawn: 10,
+            temple_distance: 1,
+            king_proximity: 1,
+            pawn_square_table: PIECE_SQUARE_TABLE,
+            king_square_table: [0; 25],
+            path: None,
+        }
+    }
+}
+
+impl WeightedEvaluation {
+    /// Reads the weights from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let content = fs::read_to_string(&path)?;
+        Ok(Self {
+            path: Some(path.as_ref().display().to_string()),
+            ..serde_json::from_str(&content)?
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Weights in the same order as the `features`
+    pub fn weights(&self) -> [i32; WEIGHT_AMOUNT] {
+        let mut weights = [0; WEIGHT_AMOUNT];
+        weights[0] = self.pawn;
+        weights[1] = self.temple_distance;
+        weights[2] = self.king_proximity;
+        weights[PAWN_SQUARE_OFFSET..KING_SQUARE_OFFSET].copy_from_slice(&self.pawn_square_table);
+        weights[KING_SQUARE_OFFSET..].copy_from_slice(&self.king_square_table);
+        weights
+    }
+
+    pub fn from_weights(weights: &[i32; WEIGHT_AMOUNT]) -> Self {
+        let mut evaluation = Self {
+            pawn: weights[0],
+            temple_distance: weights[1],
+            king_proximity: weights[2],
+            pawn_square_table: [0; 25],
+            king_square_table: [0; 25],
+            path: None,
+        };
+        evaluation
+            .pawn_square_table
+            .copy_from_slice(&weights[PAWN_SQUARE_OFFSET..KING_SQUARE_OFFSET]);
+        evaluation
+            .king_square_table
+            .copy_from_slice(&weights[KING_SQUARE_OFFSET..]);
+        evaluation
+    }
+
+    /// Features of the position from the red player perspective.
+    /// Evaluation of a not finished game is a dot product of the features and the weights
+    pub fn features(state: &State) -> [i32; WEIGHT_AMOUNT] {
+        let mut features = [0; WEIGHT_AMOUNT];
+        Self::add_side_features(&mut features, state, PlayerColor::Red, 1);
+        Self::add_side_features(&mut features, state, PlayerColor::Blue, -1);
+        features
+    }
+
+    fn add_side_features(
+        features: &mut [i32; WEIGHT_AMOUNT],
+        state: &State,
+        color: PlayerColor,
+        sign: i32,
+    ) {
+        let enemy_temple = match color {
+            PlayerColor::Red => BLUE_TEMPLE,
+            PlayerColor::Blue => RED_TEMPLE,
+        };
+
+        let king_pos = state.kings[color as usize].leading_zeros();
+        let enemy_king_pos = state.kings[color.enemy() as usize].leading_zeros();
+        let pawns = state.pawns[color as usize];
+
+        let mut distance_to_enemy_king = Evaluation::distance(king_pos, enemy_king_pos);
+
+        for n in 0..25 {
+            if get_bit(pawns, n) == 1 {
+                features[0] += sign;
+                features[PAWN_SQUARE_OFFSET + Self::table_idx(n, color)] += sign;
+                distance_to_enemy_king += Evaluation::distance(n as u32, enemy_king_pos);
+            }
+        }
+
+        features[1] -= sign * Evaluation::distance(king_pos, enemy_temple as u32);
+        features[2] -= sign * distance_to_enemy_king;
+        features[KING_SQUARE_OFFSET + Self::table_idx(king_pos as usize, color)] += sign;
+    }
+
+    /// Score of one side, which does not take the enemy pieces into account
+    /// except the enemy king position
+    fn side_score(&self, state: &State, color: PlayerColor) -> i32 {
+        let enemy_color = color.enemy();
+        let enemy_temple = match color {
+            PlayerColor::Red => BLUE_TEMPLE,
+            PlayerColor::Blue => RED_TEMPLE,
+        };
+
+        // Board position n is stored in the bit 31 - n
+        let king_pos = state.kings[color as usize].leading_zeros();
+        let enemy_king_pos = state.kings[enemy_color as usize].leading_zeros();
+        let pawns = state.pawns[color as usize];
+
+        let mut pawn_amount = 0;
+        let mut pawn_squares = 0;
+        let mut distance_to_enemy_king = Evaluation::distance(king_pos, enemy_king_pos);
+
+        for n in 0..25 {
+            if get_bit(pawns, n) == 1 {
+                pawn_amount += 1;
+                pawn_squares += self.pawn_square_table[Self::table_idx(n, color)];
+                distance_to_enemy_king += Evaluation::distance(n as u32, enemy_king_pos);
+            }
+        }
+
+        self.pawn * pawn_amount
+            + pawn_squares
+            + self.king_square_table[Self::table_idx(king_pos as usize, color)]
+            - self.temple_distance * Evaluation::distance(king_pos, enemy_temple as u32)
+            - self.king_proximity * distance_to_enemy_king
+    }
+
+    #[inline]
+    fn table_idx(pos: usize, color: PlayerColor) -> usize {
+        match color {
+            PlayerColor::Red => pos,
+            PlayerColor::Blue => 24 - pos,
+        }
+    }
+}
+
+impl Evaluator for WeightedEvaluation {
+    type Accumulator = ();
+
+    fn evaluate(
+        &self,
+        state: &State,
+        _player_color: PlayerColor,
+        move_result: &Option<MoveResult>,
+    ) -> i32 {
+        // The side without the king has lost even if the move result does not say it
+        let is_captured = |color: PlayerColor| state.kings[color as usize] == 0;
+        match move_result {
+            Some(MoveResult::RedWin) => WIN_SCORE,
+            Some(MoveResult::BlueWin) => -WIN_SCORE,
+            _ if is_captured(PlayerColor::Blue) => WIN_SCORE,
+            _ if is_captured(PlayerColor::Red) => -WIN_SCORE,
+            _ => {
+                self.side_score(state, PlayerColor::Red) - self.side_score(state, PlayerColor::Blue)
+            }
+        }
+    }
+
+    /// Weights are written as their file, the tuned weights which are not saved cannot be written
+    fn spec_params(&self) -> Option<Vec<(&'static str, String)>> {
+        let mut params = vec![("eval", "weighted".to_owned())];
+        match &self.path {
+            Some(path) => params.push(("file", path.clone())),
+            None if *self != Self::default() => return None,
+            None => (),
+        }
+        Some(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+        deck::Deck,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_start_position_is_equal() {
+        let state = State::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let evaluation = WeightedEvaluation::default();
+
+        assert_eq!(evaluation.evaluate(&state, PlayerColor::Red, &None), 0);
+        assert_eq!(evaluation.evaluate(&state, PlayerColor::Blue, &None), 0);
+    }
+
+    #[test]
+    fn test_pawn_weight_changes_score() {
+        let mut state = State::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        // Remove one blue pawn
+        state.pawns[PlayerColor::Blue as usize] &= !(1 << 31);
+
+        let evaluation = WeightedEvaluation {
+            pawn: 100,
+            temple_distance: 0,
+            king_proximity: 0,
+            pawn_square_table: [0; 25],
+            king_square_table: [0; 25],
+            path: None,
+        };
+
+        assert_eq!(evaluation.evaluate(&state, PlayerColor::Red, &None), 100);
+        assert_eq!(
+            evaluation.evaluate(&state, PlayerColor::Red, &Some(MoveResult::BlueWin)),
+            -WIN_SCORE
+        );
+    }
+
+    #[test]
+    fn test_side_without_king_loses() {
+        let mut state = State::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let evaluation = WeightedEvaluation::default();
+
+        state.kings[PlayerColor::Blue as usize] = 0;
+        assert_eq!(
+            evaluation.evaluate(&state, PlayerColor::Red, &None),
+            WIN_SCORE
+        );
+
+        state.kings[PlayerColor::Blue as usize] = state.kings[PlayerColor::Red as usize];
+        state.kings[PlayerColor::Red as usize] = 0;
+        assert_eq!(
+            evaluation.evaluate(&state, PlayerColor::Blue, &None),
+            -WIN_SCORE
+        );
+    }
+
+    #[test]
+    fn test_features_match_evaluation() {
+        let mut state = State::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        state.pawns[PlayerColor::Blue as usize] &= !(1 << 31);
+        state.kings[PlayerColor::Red as usize] = 1 << (31 - 12);
+
+        let mut evaluation = WeightedEvaluation::default();
+        evaluation.king_square_table[12] = 7;
+        evaluation.king_square_table[2] = -3;
+
+        let weights = evaluation.weights();
+        assert_eq!(WeightedEvaluation::from_weights(&weights), evaluation);
+
+        let dot = WeightedEvaluation::features(&state)
+            .iter()
+            .zip(weights.iter())
+            .map(|(f, w)| f * w)
+            .sum::<i32>();
+        assert_eq!(dot, evaluation.evaluate(&state, PlayerColor::Red, &None));
+    }
+
+    #[test]
+    fn test_spec_names_weights_file() {
+        let path =
+            std::env::temp_dir().join(format!("onitama_weights_{}.json", std::process::id()));
+        let evaluation = WeightedEvaluation {
+            pawn: 42,
+            ..Default::default()
+        };
+        assert_eq!(evaluation.spec_params(), None);
+
+        evaluation.save(&path).unwrap();
+        let loaded = WeightedEvaluation::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.pawn, 42);
+        assert_eq!(
+            loaded.spec_params(),
+            Some(vec![
+                ("eval", "weighted".to_owned()),
+                ("file", path.display().to_string())
+            ])
+        );
+    }
+
+    #[test]
+    fn test_missing_weights_are_default() {
+        let evaluation: WeightedEvaluation = serde_json::from_str(r#"{ "pawn": 42 }"#).unwrap();
+
+        assert_eq!(
+            evaluation,
+            WeightedEvaluation {
+                pawn: 42,
+                ..Default::default()
+            }
+        );
+    }
+}
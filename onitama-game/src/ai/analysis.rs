@@ -0,0 +1,53 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::game::done_move::DoneMove;
+
+/// One of the best root moves found by the analysis of the agent
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisLine {
+    pub done_move: DoneMove,
+    /// Score of the move for the player to move. It is the search score for AlphaBeta
+    /// and the winrate for MCTS. `generate_move` of MCTS returns the same value,
+    /// while AlphaBeta returns its score from the red player perspective
+    pub score: f64,
+    /// Visits of the move in the tree. Always zero for AlphaBeta
+    pub visits: u32,
+    /// Searched depth. Always zero for MCTS
+    pub depth: u8,
+    /// Expected continuation which starts with the move itself
+    pub pv: Vec<DoneMove>,
+}
+
+/// Ends the analysis before the limits of the agent, e.g. at the `stop` of the engine protocol
+#[derive(Debug, Clone, Copy)]
+pub struct SearchControl<'a> {
+    /// The analysis ends as soon as it is set
+    pub stop: &'a AtomicBool,
+    /// End of the whole analysis, all lines are searched before it
+    pub deadline: Option<Instant>,
+}
+
+impl<'a> SearchControl<'a> {
+    pub fn new(stop: &'a AtomicBool) -> Self {
+        Self {
+            stop,
+            deadline: None,
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// The earlier of the deadline and the end of the given search time from now
+    pub fn end(&self, search_time: Duration) -> Option<Instant> {
+        let end = Instant::now().checked_add(search_time);
+        match (end, self.deadline) {
+            (Some(end), Some(deadline)) => Some(end.min(deadline)),
+            (end, deadline) => end.or(deadline),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command as Process, Stdio},
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    game::{
+        done_move::DoneMove, game_state::GameState, notation::parse_move, player_color::PlayerColor,
+    },
+    protocol::{Command, Response, SearchLimits, StartPosition},
+};
+
+use super::{
+    agent::{Agent, AgentError},
+    registry::{format_duration, AgentSpec},
+};
+
+/// Agent which plays with another program through the engine protocol, see `protocol`.
+/// The program is started with the first move and lives until the agent is dropped.
+/// If the engine crashes, answers too late or makes an illegal move, the move fails
+/// and the next move starts the program again
+#[derive(Serialize)]
+pub struct ExternalEngine {
+    /// Program of the engine
+    pub command: String,
+    pub args: Vec<String>,
+    /// Agent kind or specification which is sent with `setoption name Agent`
+    pub agent: Option<String>,
+    pub search_time: Duration,
+    pub max_depth: Option<u8>,
+    pub max_nodes: Option<u32>,
+    /// Time over the search time after which the engine loses the game.
+    /// It also limits the handshake
+    pub timeout: Duration,
+    #[serde(skip)]
+    process: Mutex<Option<EngineProcess>>,
+}
+
+impl ExternalEngine {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            args: vec![],
+            agent: None,
+            search_time: Duration::from_secs(1),
+            max_depth: None,
+            max_nodes: None,
+            timeout: Duration::from_secs(5),
+            process: Mutex::new(None),
+        }
+    }
+
+    /// Starts the program and waits until it has accepted the agent
+    fn start(&self) -> Result<EngineProcess, AgentError> {
+        let mut engine = EngineProcess::spawn(&self.command, &self.args)?;
+        let deadline = Instant::now() + self.timeout;
+
+        engine.send(&Command::Onitama)?;
+        while engine.receive(deadline)? != Response::OnitamaOk {}
+
+        if let Some(agent) = &self.agent {
+            engine.send(&Command::SetOption {
+                name: "Agent".to_owned(),
+                value: agent.clone(),
+            })?;
+        }
+        engine.synchronize(deadline)?;
+
+        Ok(engine)
+    }
+
+    fn play(
+        &self,
+        engine: &mut EngineProcess,
+        game_state: &GameState,
+    ) -> Result<(DoneMove, f64), AgentError> {
+        let deadline = Instant::now() + self.timeout;
+        engine.send(&Command::Position {
+            start: StartPosition::Position(game_state.state.clone(), game_state.curr_player_color),
+            moves: vec![],
+        })?;
+        engine.synchronize(deadline)?;
+
+        engine.send(&Command::Go(SearchLimits {
+            time: Some(self.search_time),
+            depth: self.max_depth,
+            nodes: self.max_nodes,
+        }))?;
+        let deadline = Instant::now() + self.search_time + self.timeout;
+
+        let mut score = 0.;
+        loop {
+            match engine.receive(deadline)? {
+                Response::Info(info) if info.multipv == 1 => score = info.score,
+                Response::BestMove(Some(notation)) => {
+                    let done_move =
+                        parse_move(&game_state.state, game_state.curr_player_color, &notation)
+                            .map_err(|_| AgentError::InvalidMove(notation))?;
+                    // The engine scores for the player to move, the agents score for the red player
+                    let sign = match game_state.curr_player_color {
+                        PlayerColor::Red => 1.,
+                        PlayerColor::Blue => -1.,
+                    };
+                    return Ok((done_move, sign * score));
+                }
+                Response::BestMove(None) => return Err(AgentError::InvalidMove("none".to_owned())),
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Clone for ExternalEngine {
+    /// The clone starts its own program
+    fn clone(&self) -> Self {
+        Self {
+            command: self.command.clone(),
+            args: self.args.clone(),
+            agent: self.agent.clone(),
+            search_time: self.search_time,
+            max_depth: self.max_depth,
+            max_nodes: self.max_nodes,
+            timeout: self.timeout,
+            process: Mutex::new(None),
+        }
+    }
+}
+
+impl Agent for ExternalEngine {
+    /// Returns the move and the score of the engine from the red player perspective.
+    /// Panics if the engine fails, `try_generate_move` returns the error instead
+    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
+        match self.try_generate_move(game_state) {
+            Ok(result) => result,
+            Err(e) => panic!("Engine {} cannot make a move: {}", self.command, e),
+        }
+    }
+
+    fn try_generate_move(&self, game_state: &GameState) -> Result<(DoneMove, f64), AgentError> {
+        let mut process = self.process.lock().unwrap();
+        if process.is_none() {
+            *process = Some(self.start()?);
+        }
+
+        let engine = process.as_mut().expect("Engine has been started");
+        let result = self.play(engine, game_state);
+        if result.is_err() {
+            // The engine is in an unknown state, so the next move starts it again
+            *process = None;
+        }
+        result
+    }
+
+    fn name(&self) -> &'static str {
+        "External engine"
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Agent> {
+        Box::new(self.clone())
+    }
+
+    fn id(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.command.hash(&mut hasher);
+        self.args.hash(&mut hasher);
+        self.agent.hash(&mut hasher);
+        self.search_time.hash(&mut hasher);
+        self.max_depth.hash(&mut hasher);
+        self.max_nodes.hash(&mut hasher);
+        self.timeout.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        let mut spec = AgentSpec::new("engine").with("cmd", &self.command);
+        if !self.args.is_empty() {
+            spec.set("args", self.args.join(" "));
+        }
+        if let Some(agent) = &self.agent {
+            // Commas separate the parameters of the specification itself
+            spec.set("agent", agent.replace(',', ";"));
+        }
+        spec.set("time", format_duration(self.search_time));
+        if let Some(depth) = self.max_depth {
+            spec.set("depth", depth);
+        }
+        if let Some(nodes) = self.max_nodes {
+            spec.set("nodes", nodes);
+        }
+        spec.set("timeout", format_duration(self.timeout));
+        Some(spec)
+    }
+}
+
+/// Running program of the engine. Its output is read by a separate thread,
+/// so the answers can be awaited with a timeout
+struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl EngineProcess {
+    fn spawn(command: &str, args: &[String]) -> Result<Self, AgentError> {
+        let mut child = Process::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| AgentError::Crashed(format!("cannot start {}: {}", command, e)))?;
+
+        let stdin = child.stdin.take().expect("Stdin of the engine is piped");
+        let stdout = child.stdout.take().expect("Stdout of the engine is piped");
+        let (lines_tx, lines) = channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if lines_tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    fn send(&mut self, command: &Command) -> Result<(), AgentError> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| AgentError::Crashed(e.to_string()))
+    }
+
+    /// Waits for the next answer of the engine. The lines which are not answers are skipped
+    fn receive(&mut self, deadline: Instant) -> Result<Response, AgentError> {
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(timeout) {
+                Ok(line) => {
+                    if let Ok(response) = line.parse() {
+                        return Ok(response);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => return Err(AgentError::Timeout),
+                Err(RecvTimeoutError::Disconnected) => {
+                    let reason = match self.child.try_wait() {
+                        Ok(Some(status)) => format!("engine has exited with {}", status),
+                        _ => "engine has closed its output".to_owned(),
+                    };
+                    return Err(AgentError::Crashed(reason));
+                }
+            }
+        }
+    }
+
+    /// Waits until the engine has executed the previous commands.
+    /// An error message of the engine means that it has rejected one of them
+    fn synchronize(&mut self, deadline: Instant) -> Result<(), AgentError> {
+        self.send(&Command::IsReady)?;
+        loop {
+            match self.receive(deadline)? {
+                Response::ReadyOk => return Ok(()),
+                Response::Message(message) => {
+                    return Err(AgentError::Crashed(format!(
+                        "engine has rejected the command: {}",
+                        message
+                    )))
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        self.send(&Command::Quit).ok();
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
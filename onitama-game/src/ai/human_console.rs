@@ -10,7 +10,7 @@ use crate::{
     },
 };
 
-use super::agent::Agent;
+use super::{agent::Agent, registry::AgentSpec};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct HumanConsole;
@@ -152,4 +152,8 @@ impl Agent for HumanConsole {
     fn id(&self) -> u64 {
         "human".parse::<u64>().unwrap()
     }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        Some(AgentSpec::new("human"))
+    }
 }
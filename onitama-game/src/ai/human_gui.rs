@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::game::{done_move::DoneMove, game_state::GameState};
 
-use super::agent::Agent;
+use super::{agent::Agent, registry::AgentSpec};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct HumanGui;
@@ -24,4 +24,8 @@ impl Agent for HumanGui {
     fn id(&self) -> u64 {
         todo!()
     }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        Some(AgentSpec::new("human"))
+    }
 }
@@ -1,10 +1,23 @@
-use std::time::{Duration, Instant};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 
-use rand::Rng;
+use super::{
+    rave::{amaf_key, PlayedMoves, RaveSchedule},
+    rollout::{evaluation_reward, Rollout, RolloutPolicy},
+    solver::ProofStatus,
+    SearchInfo,
+};
 
-use crate::game::{
-    card::CARD_NAMES, deck::Deck, done_move::DoneMove, move_result::MoveResult,
-    player_color::PlayerColor, r#move::Move, state::State,
+use crate::{
+    ai::analysis::AnalysisLine,
+    game::{
+        card::CARD_NAMES, deck::Deck, done_move::DoneMove, move_result::MoveResult,
+        player_color::PlayerColor, r#move::Move, state::State,
+    },
 };
 
 #[derive(Clone)]
@@ -21,6 +34,15 @@ pub struct MctsArena {
     pub exploration_c: f32,
     pub playouts: u32,
     pub max_playouts: u32,
+    /// Blends the UCT value with the AMAF value of the move if set
+    pub rave: Option<RaveSchedule>,
+    pub rollout_policy: Arc<dyn RolloutPolicy>,
+    /// The simulation is stopped after this amount of plies
+    /// and the position is scored by the evaluation
+    pub max_rollout_plies: Option<u32>,
+    /// Generator for the simulations. The search is reproducible with the same seed
+    /// and the playouts limit if it is single threaded
+    pub rng: SmallRng,
 }
 
 impl MctsArena {
@@ -47,28 +69,91 @@ impl MctsArena {
             exploration_c,
             playouts: 0,
             max_playouts,
+            rave: None,
+            rollout_policy: Arc::new(Rollout::Uniform),
+            max_rollout_plies: None,
+            rng: SmallRng::from_entropy(),
         }
     }
 
     /// Seach the best move
-    /// 1. Make the playouts until time is up
+    /// 1. Make the playouts until time is up or the root is proven
     /// 2. Select the best node which was visited the most
     pub fn search(&mut self) -> (DoneMove, f64) {
         let now = Instant::now();
 
-        while now.elapsed() < self.search_time && self.playouts < self.max_playouts {
+        while now.elapsed() < self.search_time
+            && self.playouts < self.max_playouts
+            && !self.arena[0].proof.is_proven()
+        {
             self.playout();
             self.playouts += 1;
         }
 
-        let children = &self.arena[0].children;
+        self.best_move()
+    }
 
-        // Did not see any major difference between number of visits
-        // and the winrate
-        let best_child_idx = children
-            .iter()
-            .max_by_key(|&c| self.arena[*c].visits)
-            .expect("Must find the best child");
+    /// Tree parallel search. Threads share the tree and only the simulation
+    /// is done without the lock. Virtual loss makes the threads choose different paths
+    pub fn search_parallel(&mut self, threads: usize) -> (DoneMove, f64) {
+        let now = Instant::now();
+        let search_time = self.search_time;
+        let rave = self.rave;
+        let rollout_policy = self.rollout_policy.clone();
+        let rollout_policy = rollout_policy.as_ref();
+        let max_rollout_plies = self.max_rollout_plies;
+        let seeds = (0..threads).map(|_| self.rng.gen()).collect::<Vec<u64>>();
+        let shared = &Mutex::new(&mut *self);
+
+        std::thread::scope(|s| {
+            for seed in seeds {
+                s.spawn(move || {
+                    let mut rng = SmallRng::seed_from_u64(seed);
+                    loop {
+                        let (node_idx, game_state, reward_color, proven) = {
+                            let mut arena = shared.lock().unwrap();
+                            if now.elapsed() >= search_time
+                                || arena.playouts >= arena.max_playouts
+                                || arena.arena[0].proof.is_proven()
+                            {
+                                break;
+                            }
+                            arena.playouts += 1;
+                            let (node_idx, game_state, reward_color) = arena.select_leaf(true);
+                            let proven = arena.arena[node_idx].proof.reward();
+                            (node_idx, game_state, reward_color, proven)
+                        };
+
+                        let mut played = rave.map(|_| PlayedMoves::default());
+                        let reward = match proven {
+                            Some(reward) => reward,
+                            None => Self::simulate(
+                                game_state,
+                                reward_color,
+                                rollout_policy,
+                                max_rollout_plies,
+                                played.as_mut(),
+                                &mut rng,
+                            ),
+                        };
+
+                        let mut arena = shared.lock().unwrap();
+                        arena.revert_virtual_loss(node_idx);
+                        arena.back_propagate(node_idx, reward);
+                        if let Some(played) = played {
+                            arena.update_amaf(node_idx, reward, played);
+                        }
+                    }
+                });
+            }
+        });
+
+        self.best_move()
+    }
+
+    /// The most visited child of the root. Proven wins and losses go before the visits
+    pub fn best_move(&self) -> (DoneMove, f64) {
+        let best_child_idx = self.best_child();
 
         // let best_child_idx = children
         //     .iter()
@@ -76,22 +161,119 @@ impl MctsArena {
         //     .expect("Must find the best child");
 
         (
-            self.arena[*best_child_idx]
+            self.arena[best_child_idx]
                 .mov
                 .expect("A child node must have a move"),
-            self.arena[*best_child_idx].winrate as f64,
+            self.arena[best_child_idx].winrate as f64,
         )
     }
 
+    pub fn search_info(&self) -> SearchInfo {
+        let (best_move, value) = self.best_move();
+        SearchInfo {
+            best_move,
+            value,
+            playouts: self.playouts,
+            proof: self.arena[self.best_child()].proof,
+        }
+    }
+
+    fn best_child(&self) -> usize {
+        self.best_child_of(0).expect("Must find the best child")
+    }
+
+    fn best_child_of(&self, node_idx: usize) -> Option<usize> {
+        // Did not see any major difference between number of visits
+        // and the winrate
+        self.arena[node_idx]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&c| self.arena[c].proof.best_move_key(self.arena[c].visits))
+    }
+
+    /// The best root children with their statistics, the first one is the move of the search.
+    /// The continuation follows the best children while they are visited
+    pub fn analysis(&self, lines: usize) -> Vec<AnalysisLine> {
+        // The stable sort keeps the last of the equal children first like `max_by_key`
+        let mut children = self.arena[0].children.clone();
+        children.reverse();
+        children.sort_by_key(|&c| {
+            std::cmp::Reverse(self.arena[c].proof.best_move_key(self.arena[c].visits))
+        });
+
+        children
+            .into_iter()
+            .take(lines)
+            .map(|child| {
+                let node = &self.arena[child];
+                let mut pv = vec![node.mov.expect("A child node must have a move")];
+
+                let mut node_idx = child;
+                while let Some(next) = self.best_child_of(node_idx) {
+                    let next = &self.arena[next];
+                    if next.visits == 0 && !next.proof.is_proven() {
+                        break;
+                    }
+                    pv.push(next.mov.expect("A child node must have a move"));
+                    node_idx = next.idx;
+                }
+
+                AnalysisLine {
+                    done_move: pv[0],
+                    score: node.winrate as f64,
+                    visits: node.visits,
+                    depth: 0,
+                    pv,
+                }
+            })
+            .collect()
+    }
+
     /// Make a playout to find the best node
     pub fn playout(&mut self) {
+        let (node_idx, game_state, reward_color) = self.select_leaf(false);
+        let mut played = self.rave.map(|_| PlayedMoves::default());
+        let reward = match self.arena[node_idx].proof.reward() {
+            Some(reward) => reward,
+            None => Self::simulate(
+                game_state,
+                reward_color,
+                self.rollout_policy.as_ref(),
+                self.max_rollout_plies,
+                played.as_mut(),
+                &mut self.rng,
+            ),
+        };
+        self.back_propagate(node_idx, reward);
+        if let Some(played) = played {
+            self.update_amaf(node_idx, reward, played);
+        }
+    }
+
+    /// Selects the node for the simulation and expands it if needed.
+    /// Returns the node, its position and the color whose reward is computed.
+    /// With the virtual loss every node on the path counts as lost until the result is back
+    fn select_leaf(&mut self, virtual_loss: bool) -> (usize, MctsState, PlayerColor) {
         let mut game_state = self.game_state.clone();
         let mut node_idx = 0; // root node
 
-        // 1. Select the best node and make a move with it
-        while self.arena[node_idx].is_expanded && !self.arena[node_idx].is_terminal {
+        if virtual_loss {
+            self.arena[node_idx].virtual_loss += 1;
+        }
+
+        // 1. Select the best node and make a move with it.
+        // Proven nodes are not searched further, their value is known
+        while self.arena[node_idx].is_expanded
+            && !self.arena[node_idx].is_terminal
+            && !self.arena[node_idx].proof.is_proven()
+        {
             node_idx = self.select(&self.arena[node_idx]);
 
+            if virtual_loss {
+                self.arena[node_idx].virtual_loss += 1;
+            }
+
             if let Some(mov) = self.arena[node_idx].mov {
                 // during a move phase we must not see a root node
                 // therefore unwrap is appropriate
@@ -108,44 +290,55 @@ impl MctsArena {
 
                 if move_result.is_win() {
                     self.arena[node_idx].is_terminal = true;
+                    self.arena[node_idx].proof = ProofStatus::Win(0);
                 }
             }
         }
 
-        // 2. Expand if node is capable of it
+        // 2. Expand if node is capable of it.
+        // The root is expanded at once, so there is a move even after a few playouts
         if !self.arena[node_idx].is_expanded
             && !self.arena[node_idx].is_terminal
-            && self.arena[node_idx].visits > self.min_node_visits
+            && (node_idx == 0 || self.arena[node_idx].visits > self.min_node_visits)
         {
             self.expand(node_idx, &game_state);
         }
 
-        // 3. Simulate the game till the end
+        // 3. Simulation will be done by the caller
         // Reward color is dependent on the parent color, because
         // the child is the next move from the parent
         let parent = self.arena[node_idx].parent.unwrap_or(0);
-        let reward = self.simulate(game_state, self.arena[parent].player_color);
-
-        // 4. Back propagate the result back to the root
-        self.back_propagate(node_idx, reward);
+        (node_idx, game_state, self.arena[parent].player_color)
     }
 
     /// Selects the best child by UCT score
-    /// 1. Get parent's children
-    /// 2. Select the best child using UCT score
+    /// 1. Get parent's children which are not proven to lose
+    /// 2. Select the best child using UCT score blended with the AMAF value if RAVE is used
     /// 3. Return the best child's index
     fn select(&self, parent: &MctsNode) -> usize {
         let children = &parent.children;
 
+        let parent_visits = (parent.visits + parent.virtual_loss) as f32;
         let uct = |child: &MctsNode| {
-            child.winrate
-                + self.exploration_c * ((parent.visits as f32).ln() / child.visits as f32).sqrt()
+            let visits = (child.visits + child.virtual_loss) as f32;
+            let mut winrate = if child.virtual_loss == 0 {
+                child.winrate
+            } else {
+                (child.reward - child.virtual_loss as f32) / visits
+            };
+            if let (Some(rave), true) = (self.rave, child.amaf_visits > 0) {
+                let amaf_visits = child.amaf_visits as f32;
+                let beta = rave.beta(visits, amaf_visits);
+                winrate = (1. - beta) * winrate + beta * child.amaf_reward / amaf_visits;
+            }
+            winrate + self.exploration_c * (parent_visits.ln() / visits).sqrt()
         };
 
         // Need to use max by, since it is not possible to compare floats
         // with max_by_key
         let best_child_idx = children
             .iter()
+            .filter(|&c| !matches!(self.arena[*c].proof, ProofStatus::Loss(_)))
             .max_by(|&a, &b| {
                 let child_a = &self.arena[*a];
                 let child_b = &self.arena[*b];
@@ -178,7 +371,12 @@ impl MctsArena {
                 used_card_idx: mov.0,
             };
             let idx = self.size();
-            let child = MctsNode::new(Some(parent), idx, Some(done_move), player_color.enemy());
+            let mut child = MctsNode::new(Some(parent), idx, Some(done_move), player_color.enemy());
+            child.amaf_key = amaf_key(
+                player_color,
+                cloned_state.state.deck.get_card(mov.0),
+                &mov.1,
+            );
 
             self.arena.push(child);
             self.arena[parent].children.push(idx);
@@ -186,19 +384,32 @@ impl MctsArena {
         self.arena[parent].is_expanded = true;
     }
 
-    /// Simulate the game using random moves
-    pub fn simulate(&self, mut mcts_state: MctsState, reward_color: PlayerColor) -> f32 {
+    /// Simulate the game using the moves of the rollout policy.
+    /// If the game is not finished after `max_plies`, the position is scored by the evaluation.
+    /// The moves are saved to `played` for the AMAF statistics
+    pub fn simulate(
+        mut mcts_state: MctsState,
+        reward_color: PlayerColor,
+        rollout_policy: &dyn RolloutPolicy,
+        max_plies: Option<u32>,
+        mut played: Option<&mut PlayedMoves>,
+        rng: &mut SmallRng,
+    ) -> f32 {
         let mut move_result = mcts_state.state.current_state();
 
         if move_result.is_win() {
-            return self.reward(move_result, reward_color);
+            return Self::reward(move_result, reward_color);
         }
 
-        let mut rng = rand::thread_rng();
-
         // let mut capture_reward = 0.;
+        let mut ply = 0;
 
         while !move_result.is_win() {
+            if max_plies.is_some_and(|max_plies| ply >= max_plies) {
+                return evaluation_reward(&mcts_state.state, reward_color);
+            }
+            ply += 1;
+
             // generate legal moves for the enemy
             let moves = mcts_state
                 .state
@@ -220,7 +431,17 @@ impl MctsArena {
                 continue;
             }
 
-            let (used_card_idx, mov) = moves[rng.gen_range(0..moves.len())];
+            let (used_card_idx, mov) = moves[rollout_policy.choose_move(
+                &mcts_state.state,
+                mcts_state.player_color,
+                &moves,
+                rng,
+            )];
+
+            if let Some(played) = played.as_mut() {
+                let card = mcts_state.state.deck.get_card(used_card_idx);
+                played.insert(amaf_key(mcts_state.player_color, card, &mov));
+            }
 
             move_result = mcts_state
                 .state
@@ -237,10 +458,10 @@ impl MctsArena {
             mcts_state.player_color.switch();
         }
 
-        self.reward(move_result, reward_color) // + capture_reward
+        Self::reward(move_result, reward_color) // + capture_reward
     }
 
-    pub fn reward(&self, move_result: MoveResult, reward_color: PlayerColor) -> f32 {
+    pub fn reward(move_result: MoveResult, reward_color: PlayerColor) -> f32 {
         match (reward_color, move_result) {
             (PlayerColor::Red, MoveResult::RedWin) => 1.,
             (PlayerColor::Red, MoveResult::BlueWin) => -1.,
@@ -261,6 +482,161 @@ impl MctsArena {
                 break;
             }
         }
+
+        self.back_propagate_proof(node_idx);
+    }
+
+    /// Proves the ancestors of the proven node while it is possible
+    fn back_propagate_proof(&mut self, node_idx: usize) {
+        if !self.arena[node_idx].proof.is_proven() {
+            return;
+        }
+
+        let mut node_idx = node_idx;
+        while let Some(parent) = self.arena[node_idx].parent {
+            if self.arena[parent].proof.is_proven() {
+                break;
+            }
+
+            let proof = ProofStatus::from_children(
+                self.arena[parent]
+                    .children
+                    .iter()
+                    .map(|c| self.arena[*c].proof),
+            );
+            if !proof.is_proven() {
+                break;
+            }
+
+            self.arena[parent].proof = proof;
+            node_idx = parent;
+        }
+    }
+
+    /// Updates the AMAF statistics: every child of the node on the path gets the reward
+    /// if its move was played later by the same player in the tree or in the simulation
+    pub fn update_amaf(&mut self, node_idx: usize, mut reward: f32, mut played: PlayedMoves) {
+        let mut node_idx = node_idx;
+        loop {
+            // The reward is for the player who moved into the node,
+            // children moves are made by the enemy
+            for i in 0..self.arena[node_idx].children.len() {
+                let child = self.arena[node_idx].children[i];
+                if played.contains(self.arena[child].amaf_key) {
+                    self.arena[child].update_amaf(-reward);
+                }
+            }
+
+            match self.arena[node_idx].parent {
+                Some(parent) => {
+                    played.insert(self.arena[node_idx].amaf_key);
+                    node_idx = parent;
+                    reward = -reward;
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Moves the root to the node with the given position to continue the search from it.
+    /// The position must be at most two plies deeper than the current root,
+    /// i.e. after our move and the enemy reply.
+    /// Returns false if the position is not found in the tree
+    pub fn advance_root(&mut self, state: &State, player_color: PlayerColor) -> bool {
+        let new_root = match self.find_node(state, player_color) {
+            Some(idx) => idx,
+            None => return false,
+        };
+
+        self.reroot(new_root);
+        self.game_state = MctsState {
+            state: state.clone(),
+            player_color,
+        };
+        self.playouts = 0;
+
+        true
+    }
+
+    /// Finds the node with the position among the root, its children and grandchildren
+    fn find_node(&self, state: &State, player_color: PlayerColor) -> Option<usize> {
+        let mut layer = vec![(0, self.game_state.state.clone())];
+
+        for depth in 0..=2 {
+            let mut next_layer = vec![];
+
+            for (node_idx, node_state) in layer {
+                let node = &self.arena[node_idx];
+                if node.player_color == player_color && node_state == *state {
+                    return Some(node_idx);
+                }
+
+                if depth == 2 {
+                    continue;
+                }
+
+                for child in node.children.iter() {
+                    let mov = self.arena[*child]
+                        .mov
+                        .expect("A child node must have a move");
+                    let mut child_state = node_state.clone();
+                    // a move makes a parent color
+                    child_state.make_move(&mov.mov, node.player_color, mov.used_card_idx);
+                    next_layer.push((*child, child_state));
+                }
+            }
+
+            layer = next_layer;
+        }
+
+        None
+    }
+
+    /// Makes the node a new root and compacts the arena,
+    /// so only the subtree of the new root is left
+    fn reroot(&mut self, new_root: usize) {
+        // Old indexes of the kept nodes in the breadth first order
+        let mut kept = vec![new_root];
+        let mut new_indexes = vec![usize::MAX; self.size()];
+        new_indexes[new_root] = 0;
+
+        let mut i = 0;
+        while i < kept.len() {
+            for child in self.arena[kept[i]].children.iter() {
+                new_indexes[*child] = kept.len();
+                kept.push(*child);
+            }
+            i += 1;
+        }
+
+        let mut old_arena = std::mem::take(&mut self.arena);
+        self.arena = kept
+            .iter()
+            .map(|old_idx| {
+                let mut node = std::mem::replace(
+                    &mut old_arena[*old_idx],
+                    MctsNode::new(None, 0, None, PlayerColor::Red),
+                );
+                node.idx = new_indexes[*old_idx];
+                node.parent = node.parent.map(|parent| new_indexes[parent]);
+                for child in node.children.iter_mut() {
+                    *child = new_indexes[*child];
+                }
+                node
+            })
+            .collect();
+
+        self.arena[0].parent = None;
+        self.arena[0].mov = None;
+    }
+
+    /// Removes the virtual loss from the path to the root
+    fn revert_virtual_loss(&mut self, node_idx: usize) {
+        let mut node_idx = Some(node_idx);
+        while let Some(idx) = node_idx {
+            self.arena[idx].virtual_loss -= 1;
+            node_idx = self.arena[idx].parent;
+        }
     }
 
     #[inline]
@@ -304,6 +680,15 @@ pub struct MctsNode {
     pub winrate: f32,
     pub is_terminal: bool,
     pub is_expanded: bool,
+    /// Amount of threads which are simulating the game through this node
+    pub virtual_loss: u32,
+    /// Identifies the move of the node independently from the position
+    pub amaf_key: u16,
+    /// Number of simulations where the move was played after the parent
+    pub amaf_visits: u32,
+    pub amaf_reward: f32,
+    /// Proven value of the node for the player who moved into it
+    pub proof: ProofStatus,
     /// Player color who has last moved the piece
     pub player_color: PlayerColor,
 }
@@ -325,6 +710,11 @@ impl MctsNode {
             winrate: 0.,
             is_terminal: false,
             is_expanded: false,
+            virtual_loss: 0,
+            amaf_key: 0,
+            amaf_visits: 0,
+            amaf_reward: 0.,
+            proof: ProofStatus::Unknown,
             player_color,
         }
     }
@@ -335,6 +725,11 @@ impl MctsNode {
         self.winrate = self.reward as f32 / self.visits as f32;
     }
 
+    pub fn update_amaf(&mut self, reward: f32) {
+        self.amaf_visits += 1;
+        self.amaf_reward += reward;
+    }
+
     pub fn to_string(&self, deck: &Deck) -> String {
         let mut mov = "None".to_string();
 
@@ -456,6 +851,64 @@ mod tests {
         assert_eq!("\n".to_string() + &result, expected);
     }
 
+    #[test]
+    fn test_advance_root_keeps_subtree() {
+        let mut arena = arena();
+        arena.max_playouts = 3000;
+        arena.search();
+
+        // Take the most visited reply to the most visited move
+        let most_visited = |arena: &MctsArena, idx: usize| {
+            *arena.arena[idx]
+                .children
+                .iter()
+                .max_by_key(|&c| arena.arena[*c].visits)
+                .unwrap()
+        };
+        let child = most_visited(&arena, 0);
+        let grandchild = most_visited(&arena, child);
+        let visits = arena.arena[grandchild].visits;
+        let grandchild_children = arena.arena[grandchild].children.len();
+
+        let mut state = arena.game_state.state.clone();
+        let mov = arena.arena[child].mov.unwrap();
+        state.make_move(&mov.mov, PlayerColor::Red, mov.used_card_idx);
+        let mov = arena.arena[grandchild].mov.unwrap();
+        state.make_move(&mov.mov, PlayerColor::Blue, mov.used_card_idx);
+
+        let size = arena.size();
+        assert!(arena.advance_root(&state, PlayerColor::Red));
+
+        assert!(arena.size() < size);
+        assert_eq!(arena.arena[0].visits, visits);
+        assert_eq!(arena.arena[0].children.len(), grandchild_children);
+        assert_eq!(arena.arena[0].parent, None);
+        assert_eq!(arena.playouts, 0);
+        for (idx, node) in arena.arena.iter().enumerate() {
+            assert_eq!(node.idx, idx);
+            for child in node.children.iter() {
+                assert_eq!(arena.arena[*child].parent, Some(idx));
+            }
+        }
+
+        // Continue the search from the new root
+        let (mov, _) = arena.search();
+        let legal_moves = state.generate_all_legal_moves(PlayerColor::Red);
+        assert!(legal_moves.contains(&(mov.used_card_idx, mov.mov)));
+    }
+
+    #[test]
+    fn test_advance_root_unknown_position() {
+        let mut arena = arena();
+        arena.max_playouts = 100;
+        arena.search();
+
+        let mut state = arena.game_state.state.clone();
+        state.pawns[PlayerColor::Blue as usize] = 0;
+
+        assert!(!arena.advance_root(&state, PlayerColor::Red));
+    }
+
     #[test]
     fn test_best_move_win() {
         // Theoritically using the same time, we should get the same results
@@ -483,6 +936,91 @@ mod tests {
         assert_eq!(mov, expected);
     }
 
+    #[test]
+    fn test_best_move_win_tree_parallel() {
+        let mut deck = deck();
+        deck.cards.swap(0, 3);
+        let search_time = Duration::from_millis(1000);
+        let mut state = State::with_deck(deck);
+        state.kings[PlayerColor::Red as usize] = from_2d_to_bitboard((1, 3));
+
+        let mut arena = MctsArena::new(state, search_time, PlayerColor::Blue, 5, 2f32.sqrt(), 5000);
+
+        let mov = arena.search_parallel(4).0;
+
+        let expected = DoneMove {
+            mov: Move {
+                from: 1,
+                to: 8,
+                piece: PieceKind::Pawn,
+            },
+            used_card_idx: 3,
+        };
+        assert_eq!(mov, expected);
+        // The search stops as soon as the win is proven
+        assert_eq!(arena.search_info().proof, ProofStatus::Win(0));
+        assert!(arena.playouts < 5000);
+        // Every thread must have removed its virtual loss
+        assert!(arena.arena.iter().all(|n| n.virtual_loss == 0));
+    }
+
+    #[test]
+    fn test_solver_proves_win() {
+        let mut deck = deck();
+        deck.cards.swap(0, 3);
+        let mut state = State::with_deck(deck);
+        state.kings[PlayerColor::Red as usize] = from_2d_to_bitboard((1, 3));
+
+        let mut arena = MctsArena::new(
+            state,
+            Duration::from_secs(10),
+            PlayerColor::Blue,
+            5,
+            2f32.sqrt(),
+            5000,
+        );
+
+        arena.search();
+
+        let info = arena.search_info();
+        assert_eq!(info.proof, ProofStatus::Win(0));
+        assert_eq!(info.best_move.mov.to, 8);
+        // Root is lost for the red player who has made the previous move
+        assert_eq!(arena.arena[0].proof, ProofStatus::Loss(1));
+        assert!(arena.playouts < 5000);
+    }
+
+    #[test]
+    fn test_best_move_win_rave() {
+        let mut deck = deck();
+        deck.cards.swap(0, 3);
+        let search_time = Duration::from_millis(1000);
+        let mut state = State::with_deck(deck);
+        state.kings[PlayerColor::Red as usize] = from_2d_to_bitboard((1, 3));
+
+        let mut arena = MctsArena::new(state, search_time, PlayerColor::Blue, 5, 2f32.sqrt(), 5000);
+        arena.rave = Some(RaveSchedule::default());
+
+        let mov = arena.search().0;
+
+        let expected = DoneMove {
+            mov: Move {
+                from: 1,
+                to: 8,
+                piece: PieceKind::Pawn,
+            },
+            used_card_idx: 3,
+        };
+        assert_eq!(mov, expected);
+
+        // The moves are also rated by the simulations which did not start with them
+        let root = &arena.arena[0];
+        assert!(root
+            .children
+            .iter()
+            .any(|c| arena.arena[*c].amaf_visits > arena.arena[*c].visits));
+    }
+
     #[test]
     fn test_no_way_to_hide_for_blue() {
         // Theoritically using the same time, we should get the same results
@@ -519,6 +1057,36 @@ mod tests {
         assert_eq!(mov, expected);
     }
 
+    #[test]
+    fn test_solver_proves_losing_moves() {
+        // Same position as above: every blue move except Rabbit e5-c5 loses the king
+        let deck = Deck::new([OX, MONKEY, RABBIT, HORSE, DRAGON]);
+        let mut state = State::with_deck(deck);
+        state.kings[PlayerColor::Blue as usize] = from_2d_to_bitboard((0, 4));
+        state.pawns[PlayerColor::Blue as usize] = 0;
+        state.pawns[PlayerColor::Red as usize] =
+            from_2d_to_bitboard((0, 3)) | from_2d_to_bitboard((1, 4));
+
+        let mut arena = MctsArena::new(
+            state,
+            Duration::from_secs(10),
+            PlayerColor::Blue,
+            0,
+            2.0,
+            5000,
+        );
+        arena.search();
+
+        let root = &arena.arena[0];
+        for child in root.children.iter().map(|c| &arena.arena[*c]) {
+            if child.mov.unwrap().mov.to == 2 {
+                assert!(!matches!(child.proof, ProofStatus::Loss(_)));
+            } else {
+                assert_eq!(child.proof, ProofStatus::Loss(1));
+            }
+        }
+    }
+
     #[test]
     fn test_worst_case_capture_blue() {
         // Theoritically using the same time, we should get the same results
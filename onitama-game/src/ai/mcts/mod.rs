@@ -1,21 +1,86 @@
 pub mod mcts_arena;
+pub mod rave;
+pub mod rollout;
+pub mod solver;
 
-use std::time::Duration;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::game::{done_move::DoneMove, game_state::GameState};
+use rand::rngs::SmallRng;
 
-use self::mcts_arena::MctsArena;
+use crate::{
+    common::seeded_rng,
+    game::{done_move::DoneMove, game_state::GameState},
+};
 
-use super::agent::Agent;
+use self::{mcts_arena::MctsArena, rave::RaveSchedule, rollout::Rollout, solver::ProofStatus};
 
-#[derive(Clone, Serialize, Deserialize)]
+use super::{
+    agent::Agent,
+    analysis::{AnalysisLine, SearchControl},
+    registry::{format_duration, format_rave, format_rollout, AgentSpec},
+};
+
+/// The analysis reports the lines after this time of the search
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Result of the MCTS search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchInfo {
+    pub best_move: DoneMove,
+    /// Value of the best move for the player who made the search
+    pub value: f64,
+    pub playouts: u32,
+    /// Proof status of the best move for the player who made the search
+    pub proof: ProofStatus,
+}
+
+/// The way the search is split between the threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ParallelMode {
+    /// All threads search in the same tree using the virtual loss
+    #[default]
+    Tree,
+    /// Every thread builds its own tree and the visits of the root children are summed up
+    Root,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Mcts {
     pub search_time: Duration,
     pub min_node_visits: u32,
     pub exploration_c: f32,
     pub max_playouts: u32,
+    /// Continue the search from the tree of the previous move.
+    /// Disabling it makes every move independent from the previous ones
+    #[serde(default = "default_reuse_tree")]
+    pub reuse_tree: bool,
+    #[serde(default = "default_threads")]
+    pub threads: usize,
+    #[serde(default)]
+    pub parallel_mode: ParallelMode,
+    /// Shares the values of the same moves between the subtrees (RAVE).
+    /// Plain UCT is used if it is not set
+    #[serde(default)]
+    pub rave: Option<RaveSchedule>,
+    #[serde(default)]
+    pub rollout: Rollout,
+    /// Rollouts longer than this are stopped and scored by the evaluation
+    #[serde(default)]
+    pub max_rollout_plies: Option<u32>,
+    /// Makes the search reproducible if it is limited by the playouts and single threaded
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// The tree of the last search. Every clone starts with its own empty tree,
+    /// so the clones playing other games do not take the trees of each other
+    #[serde(skip)]
+    pub tree: Arc<Mutex<Option<MctsArena>>>,
 }
 
 impl Default for Mcts {
@@ -25,12 +90,37 @@ impl Default for Mcts {
             exploration_c: 2f32.sqrt(),
             min_node_visits: 5,
             max_playouts: 5000,
+            reuse_tree: true,
+            threads: default_threads(),
+            parallel_mode: ParallelMode::default(),
+            rave: None,
+            rollout: Rollout::default(),
+            max_rollout_plies: None,
+            seed: None,
+            tree: Default::default(),
         }
     }
 }
 
-impl Agent for Mcts {
-    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
+impl Clone for Mcts {
+    fn clone(&self) -> Self {
+        Self {
+            tree: Arc::new(Mutex::new(None)),
+            ..*self
+        }
+    }
+}
+
+fn default_threads() -> usize {
+    1
+}
+
+fn default_reuse_tree() -> bool {
+    true
+}
+
+impl Mcts {
+    fn new_arena(&self, game_state: &GameState) -> MctsArena {
         let mut arena = MctsArena::new(
             game_state.state.clone(),
             self.search_time,
@@ -39,11 +129,191 @@ impl Agent for Mcts {
             self.exploration_c,
             self.max_playouts,
         );
-        let mov = arena.search();
+        arena.rave = self.rave;
+        arena.rollout_policy = Arc::new(self.rollout);
+        arena.max_rollout_plies = self.max_rollout_plies;
+        arena
+    }
+
+    /// Takes the saved tree if it contains the current position.
+    /// The tree is taken out of the mutex, so it is not locked during the search
+    fn take_arena(&self, game_state: &GameState) -> MctsArena {
+        let saved = self.tree.lock().unwrap().take();
+
+        if let Some(mut arena) = saved {
+            if arena.advance_root(&game_state.state, game_state.curr_player_color) {
+                return arena;
+            }
+        }
+
+        self.new_arena(game_state)
+    }
+
+    /// The random generator depends only on the seed and the position,
+    /// so the search does not depend on the moves searched before.
+    /// Every thread of the root parallel search gets its own generator
+    fn position_rng(&self, game_state: &GameState, thread: u64) -> SmallRng {
+        seeded_rng(
+            self.seed.map(|seed| seed.wrapping_add(thread)),
+            game_state.state.zobrist_hash(game_state.curr_player_color),
+        )
+    }
+
+    /// Searches the position and saves the tree for the next move if it is reused
+    pub fn search(&self, game_state: &GameState) -> SearchInfo {
+        let arena = self.search_tree(game_state);
+        let info = arena.search_info();
+        self.save_tree(arena);
+        info
+    }
+
+    /// Searches the position and returns up to `lines` best root moves
+    pub fn analyze_lines(&self, game_state: &GameState, lines: usize) -> Vec<AnalysisLine> {
+        let arena = self.search_tree(game_state);
+        let analysis = arena.analysis(lines);
+        self.save_tree(arena);
+        analysis
+    }
+
+    /// Searches the position in the slices of `REPORT_INTERVAL` and reports the lines
+    /// after every slice. The search ends at its limits or at the stop or the deadline
+    pub fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        let mut arena = self.prepare_arena(game_state);
+        let end = control.end(self.search_time);
+
+        let analysis = loop {
+            let remaining = match end {
+                Some(end) => end.saturating_duration_since(Instant::now()),
+                None => Duration::MAX,
+            };
+            // Every slice makes at least one playout, so there is a move to play
+            arena.search_time = remaining.min(REPORT_INTERVAL).max(Duration::from_millis(1));
+            self.run_search(&mut arena, game_state);
+
+            let analysis = arena.analysis(lines);
+            on_lines(&analysis);
+            let is_finished = arena.playouts >= arena.max_playouts
+                || arena.arena[0].proof.is_proven()
+                || remaining <= REPORT_INTERVAL;
+            if is_finished || control.is_stopped() {
+                break analysis;
+            }
+        };
+
+        arena.search_time = self.search_time;
+        self.save_tree(arena);
+        analysis
+    }
+
+    fn search_tree(&self, game_state: &GameState) -> MctsArena {
+        let mut arena = self.prepare_arena(game_state);
+        self.run_search(&mut arena, game_state);
+        arena
+    }
+
+    /// The saved or a new tree of the position
+    fn prepare_arena(&self, game_state: &GameState) -> MctsArena {
+        let mut arena = if self.reuse_tree {
+            self.take_arena(game_state)
+        } else {
+            self.new_arena(game_state)
+        };
+        arena.rng = self.position_rng(game_state, 0);
+        arena
+    }
+
+    /// Makes the playouts within `search_time` of the arena
+    fn run_search(&self, arena: &mut MctsArena, game_state: &GameState) {
+        match (self.threads, self.parallel_mode) {
+            (0 | 1, _) => {
+                arena.search();
+            }
+            (threads, ParallelMode::Tree) => {
+                arena.search_parallel(threads);
+            }
+            (_, ParallelMode::Root) => self.search_root_parallel(arena, game_state),
+        }
 
         // println!("Tree: {}", arena.debug_tree());
         // println!("Playouts: {}", arena.playouts);
-        mov
+    }
+
+    fn save_tree(&self, arena: MctsArena) {
+        if self.reuse_tree {
+            *self.tree.lock().unwrap() = Some(arena);
+        }
+    }
+
+    /// Every helper thread searches its own tree. The visits and rewards of the root
+    /// children are merged into the main tree, so the most visited move is chosen from all trees
+    fn search_root_parallel(&self, arena: &mut MctsArena, game_state: &GameState) {
+        let search_time = arena.search_time;
+        let helpers = std::thread::scope(|s| {
+            let handles = (1..self.threads)
+                .map(|thread| {
+                    s.spawn(move || {
+                        let mut helper = self.new_arena(game_state);
+                        helper.search_time = search_time;
+                        helper.rng = self.position_rng(game_state, thread as u64);
+                        helper.search();
+                        helper
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            arena.search();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("Helper search must not panic"))
+                .collect::<Vec<_>>()
+        });
+
+        for helper in helpers.iter() {
+            arena.playouts += helper.playouts;
+            for &helper_child in helper.arena[0].children.iter() {
+                let helper_child = &helper.arena[helper_child];
+                // DoneMove has no hash, but the root has only a few children
+                let child = arena.arena[0]
+                    .children
+                    .iter()
+                    .copied()
+                    .find(|&c| arena.arena[c].mov == helper_child.mov);
+
+                if let Some(child) = child {
+                    let node = &mut arena.arena[child];
+                    node.visits += helper_child.visits;
+                    node.reward += helper_child.reward;
+                    if node.visits > 0 {
+                        node.winrate = node.reward / node.visits as f32;
+                    }
+                    if !node.proof.is_proven() {
+                        node.proof = helper_child.proof;
+                    }
+                }
+            }
+        }
+
+        // Helpers could prove the children which the main tree has not
+        if !arena.arena[0].proof.is_proven() {
+            let root = &arena.arena[0];
+            let proof =
+                ProofStatus::from_children(root.children.iter().map(|c| arena.arena[*c].proof));
+            arena.arena[0].proof = proof;
+        }
+    }
+}
+
+impl Agent for Mcts {
+    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
+        let info = self.search(game_state);
+        (info.best_move, info.value)
     }
 
     fn name(&self) -> &'static str {
@@ -55,9 +325,149 @@ impl Agent for Mcts {
     }
 
     fn id(&self) -> u64 {
-        self.search_time.as_nanos() as u64
-            + self.exploration_c as u64
-            + self.max_playouts as u64
-            + self.min_node_visits as u64
+        let mut hasher = DefaultHasher::new();
+        self.search_time.hash(&mut hasher);
+        self.exploration_c.to_bits().hash(&mut hasher);
+        self.max_playouts.hash(&mut hasher);
+        self.min_node_visits.hash(&mut hasher);
+        self.reuse_tree.hash(&mut hasher);
+        self.threads.hash(&mut hasher);
+        (self.parallel_mode as u8).hash(&mut hasher);
+        // The schedule and the rollout are hashed in their notation, because they contain floats
+        self.rave.as_ref().map(format_rave).hash(&mut hasher);
+        format_rollout(&self.rollout).hash(&mut hasher);
+        self.max_rollout_plies.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        let parallel_mode = match self.parallel_mode {
+            ParallelMode::Tree => "tree",
+            ParallelMode::Root => "root",
+        };
+        let mut spec = AgentSpec::new("mcts")
+            .with("time", format_duration(self.search_time))
+            .with("c", self.exploration_c)
+            .with("playouts", self.max_playouts)
+            .with("visits", self.min_node_visits)
+            .with("reuse", self.reuse_tree)
+            .with("threads", self.threads)
+            .with("parallel", parallel_mode);
+        if let Some(rave) = &self.rave {
+            spec.set("rave", format_rave(rave));
+        }
+        if self.rollout != Rollout::default() {
+            spec.set("rollout", format_rollout(&self.rollout));
+        }
+        if let Some(plies) = self.max_rollout_plies {
+            spec.set("rollout_plies", plies);
+        }
+        if let Some(seed) = self.seed {
+            spec.set("seed", seed);
+        }
+        Some(spec)
+    }
+
+    fn analyze(&self, game_state: &GameState, lines: usize) -> Vec<AnalysisLine> {
+        self.analyze_lines(game_state, lines)
+    }
+
+    fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        Mcts::analyze_until(self, game_state, lines, control, on_lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ai::random::Random,
+        game::{deck::Deck, game_record::GameRecord, move_result::MoveResult},
+    };
+
+    use super::*;
+
+    fn play_seeded_game(seed: u64) -> GameRecord {
+        let mcts = Mcts {
+            // Only the playouts must limit the search to make it reproducible
+            search_time: Duration::from_secs(60),
+            max_playouts: 300,
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let random = Random { seed: Some(seed) };
+        let agents: [&dyn Agent; 2] = [&mcts, &random];
+
+        let deck = Deck::from_seed(seed);
+        let mut game_state = GameState::with_deck(deck.clone());
+        let mut record = GameRecord::new(deck);
+        record.seed = Some(seed);
+
+        let mut move_result = MoveResult::InProgress;
+        while !move_result.is_win() && record.moves.len() < 40 {
+            let (done_move, _) = agents[game_state.curr_agent_idx].generate_move(&game_state);
+            let card = *game_state.state.deck.get_card(done_move.used_card_idx);
+            move_result = game_state.progress(done_move);
+            record.push(&card, done_move.mov, move_result);
+        }
+
+        record
+    }
+
+    #[test]
+    fn test_analysis_starts_with_best_move() {
+        let mcts = Mcts {
+            search_time: Duration::from_secs(60),
+            max_playouts: 500,
+            reuse_tree: false,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let game_state = GameState::with_deck(Deck::from_seed(7));
+
+        let lines = mcts.analyze_lines(&game_state, 4);
+        let info = mcts.search(&game_state);
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].done_move, info.best_move);
+        assert_eq!(lines[0].score, info.value);
+        for line in lines.iter() {
+            assert_eq!(line.pv[0], line.done_move);
+        }
+        assert!(lines.windows(2).all(|w| w[0].visits >= w[1].visits));
+    }
+
+    #[test]
+    fn test_clones_have_own_trees() {
+        let mcts = Mcts {
+            max_playouts: 100,
+            ..Default::default()
+        };
+        mcts.generate_move(&GameState::new());
+        assert!(mcts.tree.lock().unwrap().is_some());
+
+        let clone = mcts.clone();
+        assert!(!Arc::ptr_eq(&mcts.tree, &clone.tree));
+        assert!(clone.tree.lock().unwrap().is_none());
+
+        // Agents saved before the tree reuse are loaded with it
+        let mut saved = serde_json::to_value(&mcts).unwrap();
+        saved.as_object_mut().unwrap().remove("reuse_tree");
+        assert!(serde_json::from_value::<Mcts>(saved).unwrap().reuse_tree);
+    }
+
+    #[test]
+    fn test_seeded_games_are_equal() {
+        let first = play_seeded_game(42);
+        let second = play_seeded_game(42);
+
+        assert_eq!(first.deck, second.deck);
+        assert_eq!(first.moves, second.moves);
+        assert_eq!(first.result, second.result);
     }
 }
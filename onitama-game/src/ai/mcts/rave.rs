@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{card::Card, player_color::PlayerColor, r#move::Move};
+
+/// Amount of the different AMAF keys: player color, card, from and to squares
+pub const AMAF_KEY_AMOUNT: usize = 2 * 16 * 25 * 25;
+
+/// How much the AMAF value is trusted compared to the UCT value.
+/// The weight of the AMAF value goes to zero when the node gets more visits
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RaveSchedule {
+    /// Hand-selected schedule with the equivalence parameter k:
+    /// beta = sqrt(k / (3n + k)), so AMAF and UCT have the same weight after k visits
+    Equivalence(f32),
+    /// Minimum MSE schedule with the AMAF bias b:
+    /// beta = n' / (n + n' + 4b^2 * n * n'), where n' is the amount of AMAF visits
+    MinimumMse(f32),
+}
+
+impl Default for RaveSchedule {
+    fn default() -> Self {
+        Self::Equivalence(1000.)
+    }
+}
+
+impl RaveSchedule {
+    pub fn beta(&self, visits: f32, amaf_visits: f32) -> f32 {
+        match *self {
+            RaveSchedule::Equivalence(k) => (k / (3. * visits + k)).sqrt(),
+            RaveSchedule::MinimumMse(bias) => {
+                let denominator = visits + amaf_visits + 4. * bias * bias * visits * amaf_visits;
+                if denominator > 0. {
+                    amaf_visits / denominator
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+}
+
+/// The move is the same in every position if the same player
+/// moves with the same card from and to the same squares
+#[inline]
+pub fn amaf_key(player_color: PlayerColor, card: &Card, mov: &Move) -> u16 {
+    ((player_color as usize * 16 + card.index) * 625 + mov.from as usize * 25 + mov.to as usize)
+        as u16
+}
+
+/// Set of the moves which were played after the node
+#[derive(Debug, Clone)]
+pub struct PlayedMoves {
+    bits: Vec<u64>,
+}
+
+impl Default for PlayedMoves {
+    fn default() -> Self {
+        Self {
+            bits: vec![0; AMAF_KEY_AMOUNT.div_ceil(64)],
+        }
+    }
+}
+
+impl PlayedMoves {
+    #[inline]
+    pub fn insert(&mut self, key: u16) {
+        self.bits[key as usize / 64] |= 1 << (key % 64);
+    }
+
+    #[inline]
+    pub fn contains(&self, key: u16) -> bool {
+        self.bits[key as usize / 64] & (1 << (key % 64)) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        card::{DRAGON, ORIGINAL_CARDS, TIGER},
+        piece::PieceKind,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_amaf_keys_are_unique() {
+        let mut played = PlayedMoves::default();
+        let mut amount = 0;
+
+        for color in [PlayerColor::Red, PlayerColor::Blue] {
+            for card in ORIGINAL_CARDS.iter() {
+                for from in 0..25 {
+                    for to in 0..25 {
+                        let mov = Move {
+                            from,
+                            to,
+                            piece: PieceKind::Pawn,
+                        };
+                        let key = amaf_key(color, card, &mov);
+                        assert!(!played.contains(key));
+                        played.insert(key);
+                        amount += 1;
+                    }
+                }
+            }
+        }
+
+        assert_eq!(amount, AMAF_KEY_AMOUNT);
+
+        let mov = Move {
+            from: 22,
+            to: 17,
+            piece: PieceKind::King,
+        };
+        assert_ne!(
+            amaf_key(PlayerColor::Red, &DRAGON, &mov),
+            amaf_key(PlayerColor::Red, &TIGER, &mov)
+        );
+    }
+
+    #[test]
+    fn test_beta_decreases_with_visits() {
+        for schedule in [
+            RaveSchedule::Equivalence(100.),
+            RaveSchedule::MinimumMse(0.1),
+        ] {
+            let few = schedule.beta(10., 30.);
+            let many = schedule.beta(1000., 3000.);
+            assert!(few > many, "{:?}: {} <= {}", schedule, few, many);
+            assert!((0. ..=1.).contains(&few));
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt::Debug;
+
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::alpha_beta::evaluation::{Evaluation, Evaluator},
+    common::get_bit,
+    game::{
+        piece::PieceKind,
+        player_color::PlayerColor,
+        r#move::Move,
+        state::{State, BLUE_TEMPLE, RED_TEMPLE},
+    },
+};
+
+/// Evaluation score which is mapped to the reward of about 0.76 (tanh(1))
+/// when the rollout is stopped by the ply limit
+pub const EVALUATION_SCALE: f32 = 50.;
+
+/// Chooses the moves during the simulation phase of the MCTS
+pub trait RolloutPolicy: Debug + Send + Sync {
+    /// Returns the index of the chosen move in the `moves`.
+    /// The moves are never empty
+    fn choose_move(
+        &self,
+        state: &State,
+        player_color: PlayerColor,
+        moves: &[(usize, Move)],
+        rng: &mut dyn RngCore,
+    ) -> usize;
+}
+
+/// Built-in rollout policies
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Rollout {
+    /// Uniformly random moves
+    #[default]
+    Uniform,
+    /// Takes the winning move if there is one, otherwise random
+    WinFirst,
+    /// Takes the winning move and does not allow the enemy to win on the next move if possible
+    AvoidLoss,
+    /// Takes the winning move, otherwise captures a piece
+    /// and makes a random move with the probability of epsilon
+    CaptureBiased { epsilon: f32 },
+    /// Takes the winning move, otherwise makes the best move by the `Evaluation`
+    /// and a random move with the probability of epsilon
+    EvaluationGuided { epsilon: f32 },
+}
+
+impl RolloutPolicy for Rollout {
+    fn choose_move(
+        &self,
+        state: &State,
+        player_color: PlayerColor,
+        moves: &[(usize, Move)],
+        rng: &mut dyn RngCore,
+    ) -> usize {
+        if *self == Rollout::Uniform {
+            return rng.gen_range(0..moves.len());
+        }
+
+        if let Some(idx) = moves
+            .iter()
+            .position(|(_, mov)| is_winning_move(state, player_color, mov))
+        {
+            return idx;
+        }
+
+        match *self {
+            Rollout::Uniform | Rollout::WinFirst => rng.gen_range(0..moves.len()),
+            Rollout::AvoidLoss => {
+                let safe = (0..moves.len())
+                    .filter(|&i| !allows_win(state, player_color, moves[i]))
+                    .collect::<Vec<_>>();
+                if safe.is_empty() {
+                    rng.gen_range(0..moves.len())
+                } else {
+                    safe[rng.gen_range(0..safe.len())]
+                }
+            }
+            Rollout::CaptureBiased { epsilon } => {
+                let captures = (0..moves.len())
+                    .filter(|&i| is_capture(state, player_color, &moves[i].1))
+                    .collect::<Vec<_>>();
+                if captures.is_empty() || rng.gen::<f32>() < epsilon {
+                    rng.gen_range(0..moves.len())
+                } else {
+                    captures[rng.gen_range(0..captures.len())]
+                }
+            }
+            Rollout::EvaluationGuided { epsilon } => {
+                if rng.gen::<f32>() < epsilon {
+                    return rng.gen_range(0..moves.len());
+                }
+
+                let sign = match player_color {
+                    PlayerColor::Red => 1,
+                    PlayerColor::Blue => -1,
+                };
+                (0..moves.len())
+                    .max_by_key(|&i| {
+                        let (card_idx, mov) = moves[i];
+                        let mut state = state.clone();
+                        state.make_move(&mov, player_color, card_idx);
+                        sign * Evaluation.evaluate(&state, PlayerColor::Red, &None)
+                    })
+                    .expect("Moves must not be empty")
+            }
+        }
+    }
+}
+
+/// The move captures the enemy king or moves the king to the enemy temple
+#[inline]
+pub fn is_winning_move(state: &State, player_color: PlayerColor, mov: &Move) -> bool {
+    let enemy_temple = match player_color {
+        PlayerColor::Red => BLUE_TEMPLE,
+        PlayerColor::Blue => RED_TEMPLE,
+    };
+
+    get_bit(state.kings[player_color.enemy() as usize], mov.to as usize) == 1
+        || (mov.piece == PieceKind::King && mov.to as usize == enemy_temple)
+}
+
+#[inline]
+pub fn is_capture(state: &State, player_color: PlayerColor, mov: &Move) -> bool {
+    get_bit(state.pawns[player_color.enemy() as usize], mov.to as usize) == 1
+}
+
+/// The enemy can win right after the move
+fn allows_win(state: &State, player_color: PlayerColor, (card_idx, mov): (usize, Move)) -> bool {
+    if is_winning_move(state, player_color, &mov) {
+        return false;
+    }
+
+    let mut state = state.clone();
+    state.make_move(&mov, player_color, card_idx);
+
+    let enemy = player_color.enemy();
+    state
+        .generate_all_legal_moves(enemy)
+        .iter()
+        .any(|(_, mov)| is_winning_move(&state, enemy, mov))
+}
+
+/// Reward for the position where the rollout was stopped.
+/// The evaluation is squashed to (-1, 1), so it is never as good as a real win
+pub fn evaluation_reward(state: &State, reward_color: PlayerColor) -> f32 {
+    let score = Evaluation.evaluate(state, PlayerColor::Red, &None) as f32;
+    let reward = (score / EVALUATION_SCALE).tanh();
+    match reward_color {
+        PlayerColor::Red => reward,
+        PlayerColor::Blue => -reward,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use crate::game::{
+        card::{BOAR, CRAB, DRAGON, FROG, RABBIT},
+        deck::Deck,
+    };
+
+    use super::*;
+
+    fn state() -> State {
+        State::with_deck(Deck::new([DRAGON, FROG, RABBIT, CRAB, BOAR]))
+    }
+
+    /// Moves the piece of the color to the square and removes the enemy from it
+    fn place(state: &mut State, color: PlayerColor, piece: PieceKind, square: u32) {
+        let bit = 1 << (31 - square);
+        state.pawns[color.enemy() as usize] &= !bit;
+        state.kings[color.enemy() as usize] &= !bit;
+        state.pawns[color as usize] &= !bit;
+        match piece {
+            PieceKind::Pawn => state.pawns[color as usize] |= bit,
+            PieceKind::King => state.kings[color as usize] = bit,
+        }
+    }
+
+    #[test]
+    fn test_policies_take_the_win() {
+        // Blue king is put where the red pawn can capture it
+        let mut state = state();
+        let (_, target) = state.generate_all_legal_moves(PlayerColor::Red)[0];
+        place(&mut state, PlayerColor::Blue, PieceKind::King, target.to);
+
+        let moves = state.generate_all_legal_moves(PlayerColor::Red);
+        let policies = [
+            Rollout::WinFirst,
+            Rollout::AvoidLoss,
+            Rollout::CaptureBiased { epsilon: 1. },
+            Rollout::EvaluationGuided { epsilon: 1. },
+        ];
+
+        for policy in policies {
+            let idx = policy.choose_move(&state, PlayerColor::Red, &moves, &mut thread_rng());
+            assert!(
+                is_winning_move(&state, PlayerColor::Red, &moves[idx].1),
+                "{:?} has not taken the win",
+                policy
+            );
+        }
+    }
+
+    #[test]
+    fn test_avoid_loss() {
+        // Red king is put where a blue piece attacks it,
+        // so only some of the moves save the king
+        let found = state()
+            .generate_all_legal_moves(PlayerColor::Blue)
+            .iter()
+            .find_map(|(_, attack)| {
+                let mut state = state();
+                place(&mut state, PlayerColor::Red, PieceKind::King, attack.to);
+                let moves = state.generate_all_legal_moves(PlayerColor::Red);
+                let safe = moves
+                    .iter()
+                    .filter(|m| !allows_win(&state, PlayerColor::Red, **m))
+                    .count();
+                (safe > 0 && safe < moves.len()).then_some((state, moves))
+            });
+        let (state, moves) = found.expect("Must find a position with the threatened king");
+
+        for _ in 0..20 {
+            let idx =
+                Rollout::AvoidLoss.choose_move(&state, PlayerColor::Red, &moves, &mut thread_rng());
+            assert!(!allows_win(&state, PlayerColor::Red, moves[idx]));
+        }
+    }
+
+    #[test]
+    fn test_evaluation_reward() {
+        let mut state = state();
+        let before = evaluation_reward(&state, PlayerColor::Red);
+
+        // Red has one pawn more
+        state.pawns[PlayerColor::Blue as usize] &= state.pawns[PlayerColor::Blue as usize] - 1;
+        let red = evaluation_reward(&state, PlayerColor::Red);
+        let blue = evaluation_reward(&state, PlayerColor::Blue);
+        assert!(red > before && red < 1.);
+        assert_eq!(red, -blue);
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// Game theoretic value of the node for the player who has moved into it.
+/// The distance is the amount of plies until the end of the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ProofStatus {
+    #[default]
+    Unknown,
+    Win(u32),
+    Loss(u32),
+}
+
+impl ProofStatus {
+    #[inline]
+    pub fn is_proven(&self) -> bool {
+        *self != ProofStatus::Unknown
+    }
+
+    /// Exact reward of the proven node which is used instead of the simulation
+    #[inline]
+    pub fn reward(&self) -> Option<f32> {
+        match self {
+            ProofStatus::Unknown => None,
+            ProofStatus::Win(_) => Some(1.),
+            ProofStatus::Loss(_) => Some(-1.),
+        }
+    }
+
+    /// Proves the node by the statuses of all its children.
+    /// Children moves are made by the enemy of the player who has moved into the node, so
+    /// - one winning child makes the node lost
+    /// - the node is won only if all children are lost
+    pub fn from_children(children: impl IntoIterator<Item = ProofStatus>) -> ProofStatus {
+        let mut all_lost = true;
+        let mut has_children = false;
+        let mut longest_loss = 0;
+        let mut shortest_win = None;
+
+        for child in children {
+            has_children = true;
+            match child {
+                ProofStatus::Win(distance) => {
+                    shortest_win = Some(shortest_win.map_or(distance, |d: u32| d.min(distance)))
+                }
+                ProofStatus::Loss(distance) => longest_loss = longest_loss.max(distance),
+                ProofStatus::Unknown => all_lost = false,
+            }
+        }
+
+        match shortest_win {
+            Some(distance) => ProofStatus::Loss(distance + 1),
+            None if has_children && all_lost => ProofStatus::Win(longest_loss + 1),
+            None => ProofStatus::Unknown,
+        }
+    }
+
+    /// Key to choose the final move among the root children:
+    /// the fastest proven win, then the most visited unproven move,
+    /// then the loss which takes the enemy the longest
+    #[inline]
+    pub fn best_move_key(&self, visits: u32) -> (u8, i64) {
+        match *self {
+            ProofStatus::Win(distance) => (2, -(distance as i64)),
+            ProofStatus::Unknown => (1, visits as i64),
+            ProofStatus::Loss(distance) => (0, distance as i64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProofStatus::*;
+    use super::*;
+
+    #[test]
+    fn test_from_children() {
+        assert_eq!(ProofStatus::from_children([]), Unknown);
+        assert_eq!(ProofStatus::from_children([Unknown, Loss(2)]), Unknown);
+        assert_eq!(
+            ProofStatus::from_children([Unknown, Win(3), Win(0)]),
+            Loss(1)
+        );
+        assert_eq!(ProofStatus::from_children([Loss(1), Loss(3)]), Win(4));
+    }
+
+    #[test]
+    fn test_best_move_key() {
+        let mut children = [(Loss(5), 900), (Unknown, 100), (Win(3), 1), (Win(1), 0)];
+        children.sort_by_key(|(proof, visits)| std::cmp::Reverse(proof.best_move_key(*visits)));
+        assert_eq!(children.map(|c| c.0), [Win(1), Win(3), Unknown, Loss(5)]);
+    }
+}
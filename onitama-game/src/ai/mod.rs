@@ -1,6 +1,13 @@
 pub mod agent;
+pub mod analysis;
 pub mod alpha_beta;
+pub mod external_engine;
 pub mod human_console;
 pub mod human_gui;
 pub mod mcts;
+pub mod nnue;
+pub mod opening_book;
+pub mod proof_number;
 pub mod random;
+pub mod registry;
+pub mod skill_level;
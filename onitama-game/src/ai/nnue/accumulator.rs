@@ -0,0 +1,89 @@
+use crate::game::{player_color::PlayerColor, state::State};
+
+use super::{
+    features::{active_features, changed_features},
+    NnueNetwork, HIDDEN_SIZE,
+};
+
+/// Sums of the feature weights for both perspectives, indexed by the player color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accumulator {
+    pub values: [[i16; HIDDEN_SIZE]; 2],
+}
+
+impl Accumulator {
+    pub fn new(network: &NnueNetwork, state: &State) -> Self {
+        let mut accumulator = Self {
+            values: [network.feature_bias; 2],
+        };
+
+        for perspective in [PlayerColor::Red, PlayerColor::Blue] {
+            let values = &mut accumulator.values[perspective as usize];
+            for feature in active_features(state, perspective) {
+                add(values, &network.feature_weights[feature]);
+            }
+        }
+
+        accumulator
+    }
+
+    /// Applies the difference between the positions instead of summing all features again
+    pub fn update(&mut self, network: &NnueNetwork, before: &State, after: &State) {
+        for perspective in [PlayerColor::Red, PlayerColor::Blue] {
+            let values = &mut self.values[perspective as usize];
+            let (added, removed) = changed_features(before, after, perspective);
+
+            for feature in added {
+                add(values, &network.feature_weights[feature]);
+            }
+            for feature in removed {
+                subtract(values, &network.feature_weights[feature]);
+            }
+        }
+    }
+}
+
+// Plain loops over the fixed arrays are vectorized by the compiler
+#[inline]
+fn add(values: &mut [i16; HIDDEN_SIZE], weights: &[i16; HIDDEN_SIZE]) {
+    for (v, w) in values.iter_mut().zip(weights.iter()) {
+        *v = v.wrapping_add(*w);
+    }
+}
+
+#[inline]
+fn subtract(values: &mut [i16; HIDDEN_SIZE], weights: &[i16; HIDDEN_SIZE]) {
+    for (v, w) in values.iter_mut().zip(weights.iter()) {
+        *v = v.wrapping_sub(*w);
+    }
+}
+
+/// Accumulators of the positions on the current search path, the last one is the current position
+#[derive(Debug, Clone, Default)]
+pub struct AccumulatorStack {
+    stack: Vec<Accumulator>,
+}
+
+impl AccumulatorStack {
+    pub fn refresh(&mut self, network: &NnueNetwork, state: &State) {
+        self.stack.clear();
+        self.stack.push(Accumulator::new(network, state));
+    }
+
+    pub fn push(&mut self, network: &NnueNetwork, before: &State, after: &State) {
+        let mut accumulator = match self.stack.last() {
+            Some(accumulator) => *accumulator,
+            None => Accumulator::new(network, before),
+        };
+        accumulator.update(network, before, after);
+        self.stack.push(accumulator);
+    }
+
+    pub fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    pub fn current(&self) -> Option<&Accumulator> {
+        self.stack.last()
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{
+    common::get_bit,
+    game::{
+        piece::PieceKind,
+        player_color::PlayerColor,
+        state::State,
+        zobrist::{card_owner, NEUTRAL_OWNER},
+    },
+};
+
+const PIECE_FEATURES: usize = 2 * 2 * 25;
+const CARD_AMOUNT: usize = 16;
+
+/// Pieces of both players on every square and the cards in the hands of both players and on the side
+pub const FEATURE_AMOUNT: usize = PIECE_FEATURES + 3 * CARD_AMOUNT;
+
+/// Features are relative to the perspective: the own pieces go first and the board
+/// is rotated for the blue player, so both players see the board from their side
+#[inline]
+pub fn piece_feature(
+    perspective: PlayerColor,
+    color: PlayerColor,
+    kind: PieceKind,
+    square: usize,
+) -> usize {
+    let side = if color == perspective { 0 } else { 1 };
+    let square = match perspective {
+        PlayerColor::Red => square,
+        PlayerColor::Blue => 24 - square,
+    };
+    (side * 2 + kind as usize) * 25 + square
+}
+
+/// Card in the own hand, in the enemy hand or on the side
+#[inline]
+pub fn card_feature(perspective: PlayerColor, deck_slot: usize, card_index: usize) -> usize {
+    let owner = card_owner(deck_slot);
+    let group = if owner == NEUTRAL_OWNER {
+        2
+    } else if owner == perspective as usize {
+        0
+    } else {
+        1
+    };
+    PIECE_FEATURES + group * CARD_AMOUNT + card_index
+}
+
+/// Pieces of both players and the cards of the deck
+pub const MAX_ACTIVE_FEATURES: usize = 2 * 5 + 5;
+
+/// All features of the position from the perspective
+pub fn active_features(state: &State, perspective: PlayerColor) -> Vec<usize> {
+    let mut features = Vec::with_capacity(MAX_ACTIVE_FEATURES);
+
+    for color in [PlayerColor::Red, PlayerColor::Blue] {
+        for (kind, bitboard) in [
+            (PieceKind::Pawn, state.pawns[color as usize]),
+            (PieceKind::King, state.kings[color as usize]),
+        ] {
+            for square in 0..25 {
+                if get_bit(bitboard, square) == 1 {
+                    features.push(piece_feature(perspective, color, kind, square));
+                }
+            }
+        }
+    }
+
+    for (slot, card) in state.deck.cards.iter().enumerate() {
+        features.push(card_feature(perspective, slot, card.index));
+    }
+
+    features
+}
+
+/// Features which were added and removed by the move from the position `before` to `after`.
+/// Only the changed squares and deck slots are visited
+pub fn changed_features(
+    before: &State,
+    after: &State,
+    perspective: PlayerColor,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut added = Vec::with_capacity(4);
+    let mut removed = Vec::with_capacity(4);
+
+    for color in [PlayerColor::Red, PlayerColor::Blue] {
+        for (kind, old, new) in [
+            (
+                PieceKind::Pawn,
+                before.pawns[color as usize],
+                after.pawns[color as usize],
+            ),
+            (
+                PieceKind::King,
+                before.kings[color as usize],
+                after.kings[color as usize],
+            ),
+        ] {
+            let mut changed = old ^ new;
+            while changed != 0 {
+                // Square n is the bit 31 - n
+                let square = changed.leading_zeros() as usize;
+                changed &= !(1 << (31 - square));
+
+                let feature = piece_feature(perspective, color, kind, square);
+                if get_bit(new, square) == 1 {
+                    added.push(feature);
+                } else {
+                    removed.push(feature);
+                }
+            }
+        }
+    }
+
+    for (slot, (old, new)) in before
+        .deck
+        .cards
+        .iter()
+        .zip(after.deck.cards.iter())
+        .enumerate()
+    {
+        if old.index != new.index {
+            removed.push(card_feature(perspective, slot, old.index));
+            added.push(card_feature(perspective, slot, new.index));
+        }
+    }
+
+    (added, removed)
+}
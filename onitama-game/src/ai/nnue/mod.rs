@@ -0,0 +1,508 @@
+pub mod accumulator;
+pub mod features;
+
+use std::{fmt, fs, io, path::Path, sync::Arc};
+
+use serde::Serialize;
+
+use crate::{
+    common::ByteReader,
+    game::{move_result::MoveResult, player_color::PlayerColor, state::State},
+};
+
+use self::{
+    accumulator::{Accumulator, AccumulatorStack},
+    features::{FEATURE_AMOUNT, MAX_ACTIVE_FEATURES},
+};
+
+use super::alpha_beta::{evaluation::Evaluator, weighted_evaluation::WIN_SCORE};
+
+/// Size of the accumulator of one perspective
+pub const HIDDEN_SIZE: usize = 64;
+/// Scale of the feature weights. Activations are clipped to [0, QA], so they fit into i8
+pub const QA: i32 = 127;
+/// Scale of the output weights
+pub const QB: i32 = 64;
+/// Bound of the feature weights and the feature bias, so the bias with all active features
+/// fits into the i16 accumulator. The updates add and remove the features with wrapping,
+/// an overflow in the middle of the update is cancelled by the rest of it
+pub const MAX_WEIGHT: i16 = i16::MAX / (MAX_ACTIVE_FEATURES as i16 + 1);
+/// Bound of the output bias scaled by QA * QB, the logit 16 is already a sure win
+pub const MAX_OUTPUT_BIAS: i32 = 16 * QA * QB;
+/// Output of the network is a logit of the win probability, the search gets it multiplied by this scale.
+/// It stays far from the win score, so the proven wins are always preferred
+pub const SCORE_SCALE: i32 = 100;
+
+const MAGIC: &[u8; 4] = b"ONNU";
+const VERSION: u8 = 1;
+
+#[derive(Debug)]
+pub enum NnueError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    /// Network in the file has different sizes of the layers
+    ShapeMismatch {
+        features: usize,
+        hidden: usize,
+    },
+    Truncated,
+    /// Weights are out of the bounds, the accumulator could overflow
+    WeightOutOfRange,
+}
+
+impl fmt::Display for NnueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NnueError::Io(e) => write!(f, "Cannot read the network: {}", e),
+            NnueError::InvalidMagic => write!(f, "File is not an NNUE network"),
+            NnueError::UnsupportedVersion(v) => {
+                write!(f, "Network version {} is not supported", v)
+            }
+            NnueError::ShapeMismatch { features, hidden } => write!(
+                f,
+                "Network has {} features and {} hidden neurons instead of {} and {}",
+                features, hidden, FEATURE_AMOUNT, HIDDEN_SIZE
+            ),
+            NnueError::Truncated => write!(f, "Network file is truncated"),
+            NnueError::WeightOutOfRange => write!(f, "Network has weights out of the bounds"),
+        }
+    }
+}
+
+impl std::error::Error for NnueError {}
+
+impl From<io::Error> for NnueError {
+    fn from(e: io::Error) -> Self {
+        NnueError::Io(e)
+    }
+}
+
+/// Quantized network: sparse features -> accumulator of every perspective -> clipped ReLU -> output.
+/// The accumulator of the player to move goes first into the output layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NnueNetwork {
+    /// Weights of every feature scaled by QA, bounded by MAX_WEIGHT
+    pub feature_weights: Vec<[i16; HIDDEN_SIZE]>,
+    pub feature_bias: [i16; HIDDEN_SIZE],
+    /// Weights of both accumulators scaled by QB
+    pub output_weights: [i8; 2 * HIDDEN_SIZE],
+    /// Bias scaled by QA * QB, bounded by MAX_OUTPUT_BIAS
+    pub output_bias: i32,
+}
+
+impl NnueNetwork {
+    /// Network which evaluates every position as equal
+    pub fn zeroed() -> Self {
+        Self {
+            feature_weights: vec![[0; HIDDEN_SIZE]; FEATURE_AMOUNT],
+            feature_bias: [0; HIDDEN_SIZE],
+            output_weights: [0; 2 * HIDDEN_SIZE],
+            output_bias: 0,
+        }
+    }
+
+    /// Score of the position for the player to move
+    pub fn output(&self, accumulator: &Accumulator, player_color: PlayerColor) -> i32 {
+        let mut sum = self.output_bias;
+        let perspectives = [player_color, player_color.enemy()];
+
+        for (perspective, weights) in perspectives
+            .iter()
+            .zip(self.output_weights.chunks_exact(HIDDEN_SIZE))
+        {
+            sum += accumulator.values[*perspective as usize]
+                .iter()
+                .zip(weights.iter())
+                .map(|(v, w)| (*v as i32).clamp(0, QA) * *w as i32)
+                .sum::<i32>();
+        }
+
+        sum * SCORE_SCALE / (QA * QB)
+    }
+
+    /// Score of the position for the player to move without the incremental updates
+    pub fn evaluate(&self, state: &State, player_color: PlayerColor) -> i32 {
+        self.output(&Accumulator::new(self, state), player_color)
+    }
+
+    /// Binary format: magic "ONNU", version byte, amount of features and hidden neurons as u16,
+    /// then feature weights by features, feature bias, output weights and output bias.
+    /// All numbers are little-endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(FEATURE_AMOUNT as u16).to_le_bytes());
+        bytes.extend_from_slice(&(HIDDEN_SIZE as u16).to_le_bytes());
+
+        for weight in self
+            .feature_weights
+            .iter()
+            .chain([&self.feature_bias])
+            .flatten()
+        {
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+        bytes.extend(self.output_weights.iter().map(|w| *w as u8));
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NnueError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(NnueError::InvalidMagic);
+        }
+        let mut reader = ByteReader::new(&bytes[MAGIC.len()..]);
+
+        let version = reader.u8().ok_or(NnueError::Truncated)?;
+        if version != VERSION {
+            return Err(NnueError::UnsupportedVersion(version));
+        }
+
+        let features = reader.u16().ok_or(NnueError::Truncated)? as usize;
+        let hidden = reader.u16().ok_or(NnueError::Truncated)? as usize;
+        if features != FEATURE_AMOUNT || hidden != HIDDEN_SIZE {
+            return Err(NnueError::ShapeMismatch { features, hidden });
+        }
+
+        let size = (FEATURE_AMOUNT + 1) * HIDDEN_SIZE * 2 + 2 * HIDDEN_SIZE + 4;
+        if reader.remaining() < size {
+            return Err(NnueError::Truncated);
+        }
+
+        let mut network = Self::zeroed();
+        let mut read_i16 = || reader.u16().unwrap() as i16;
+        for row in network.feature_weights.iter_mut() {
+            row.iter_mut().for_each(|w| *w = read_i16());
+        }
+        network
+            .feature_bias
+            .iter_mut()
+            .for_each(|w| *w = read_i16());
+
+        for w in network.output_weights.iter_mut() {
+            *w = reader.u8().unwrap() as i8;
+        }
+        network.output_bias = reader.u32().unwrap() as i32;
+
+        let is_weight_in_range = |w: &i16| (-MAX_WEIGHT..=MAX_WEIGHT).contains(w);
+        if !network
+            .feature_weights
+            .iter()
+            .chain([&network.feature_bias])
+            .flatten()
+            .all(is_weight_in_range)
+            || !(-MAX_OUTPUT_BIAS..=MAX_OUTPUT_BIAS).contains(&network.output_bias)
+        {
+            return Err(NnueError::WeightOutOfRange);
+        }
+
+        Ok(network)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, NnueError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+/// Evaluation of AlphaBeta by the NNUE network. Accumulators are updated with every move of the search
+#[derive(Debug, Clone, Serialize)]
+pub struct NnueEvaluation {
+    #[serde(skip)]
+    pub network: Arc<NnueNetwork>,
+    /// File of the network to know which one was playing
+    pub path: Option<String>,
+}
+
+impl NnueEvaluation {
+    pub fn new(network: NnueNetwork) -> Self {
+        Self {
+            network: Arc::new(network),
+            path: None,
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, NnueError> {
+        Ok(Self {
+            network: Arc::new(NnueNetwork::from_file(&path)?),
+            path: Some(path.as_ref().display().to_string()),
+        })
+    }
+
+    fn win_score(move_result: &Option<MoveResult>) -> Option<i32> {
+        match move_result {
+            Some(MoveResult::RedWin) => Some(WIN_SCORE),
+            Some(MoveResult::BlueWin) => Some(-WIN_SCORE),
+            _ => None,
+        }
+    }
+
+    /// Network gives the score for the player to move, the search needs it for the red player
+    #[inline]
+    fn red_score(score: i32, player_color: PlayerColor) -> i32 {
+        match player_color {
+            PlayerColor::Red => score,
+            PlayerColor::Blue => -score,
+        }
+    }
+}
+
+impl Evaluator for NnueEvaluation {
+    type Accumulator = AccumulatorStack;
+
+    fn evaluate(
+        &self,
+        state: &State,
+        player_color: PlayerColor,
+        move_result: &Option<MoveResult>,
+    ) -> i32 {
+        Self::win_score(move_result).unwrap_or_else(|| {
+            Self::red_score(self.network.evaluate(state, player_color), player_color)
+        })
+    }
+
+    fn refresh(&self, accumulator: &mut Self::Accumulator, state: &State) {
+        accumulator.refresh(&self.network, state);
+    }
+
+    fn make_move(&self, accumulator: &mut Self::Accumulator, before: &State, after: &State) {
+        accumulator.push(&self.network, before, after);
+    }
+
+    fn unmake_move(&self, accumulator: &mut Self::Accumulator) {
+        accumulator.pop();
+    }
+
+    fn evaluate_incremental(
+        &self,
+        accumulator: &Self::Accumulator,
+        state: &State,
+        player_color: PlayerColor,
+        move_result: &Option<MoveResult>,
+    ) -> i32 {
+        if let Some(score) = Self::win_score(move_result) {
+            return score;
+        }
+
+        match accumulator.current() {
+            Some(current) => {
+                Self::red_score(self.network.output(current, player_color), player_color)
+            }
+            None => self.evaluate(state, player_color, move_result),
+        }
+    }
+
+    fn spec_params(&self) -> Option<Vec<(&'static str, String)>> {
+        let path = self.path.clone()?;
+        Some(vec![("eval", "nnue".to_owned()), ("file", path)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use crate::{
+        ai::alpha_beta::AlphaBeta,
+        common::from_2d_to_bitboard,
+        game::{
+            card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+            deck::Deck,
+            done_move::DoneMove,
+            game_state::GameState,
+        },
+    };
+
+    use super::*;
+
+    fn random_network(seed: u64) -> NnueNetwork {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut network = NnueNetwork::zeroed();
+        for row in network.feature_weights.iter_mut() {
+            row.iter_mut().for_each(|w| *w = rng.gen_range(-40..40));
+        }
+        network
+            .feature_bias
+            .iter_mut()
+            .for_each(|w| *w = rng.gen_range(0..60));
+        network
+            .output_weights
+            .iter_mut()
+            .for_each(|w| *w = rng.gen_range(-64..64));
+        network.output_bias = rng.gen_range(-1000..1000);
+        network
+    }
+
+    #[test]
+    fn test_incremental_updates_match_refresh() {
+        let network = random_network(1);
+        let mut rng = SmallRng::seed_from_u64(2);
+        let mut game_state = GameState::with_deck(Deck::from_seed(3));
+        let mut stack = AccumulatorStack::default();
+        stack.refresh(&network, &game_state.state);
+
+        for _ in 0..30 {
+            let moves = game_state
+                .state
+                .generate_all_legal_moves(game_state.curr_player_color);
+            if moves.is_empty() {
+                break;
+            }
+            let (card_idx, mov) = moves[rng.gen_range(0..moves.len())];
+            let result = game_state.progress(DoneMove {
+                mov,
+                used_card_idx: card_idx,
+            });
+            stack.push(
+                &network,
+                game_state.history.last().unwrap(),
+                &game_state.state,
+            );
+
+            assert_eq!(
+                stack.current(),
+                Some(&Accumulator::new(&network, &game_state.state))
+            );
+            if result.is_win() {
+                break;
+            }
+        }
+
+        // Going back restores the accumulators of the previous positions
+        stack.pop();
+        game_state.undo();
+        assert_eq!(
+            stack.current(),
+            Some(&Accumulator::new(&network, &game_state.state))
+        );
+    }
+
+    #[test]
+    fn test_perspectives_are_symmetric() {
+        let network = random_network(4);
+        let deck = Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]);
+        let state = State::with_deck(deck.clone());
+
+        // The same position where the players have swapped their pieces and cards
+        let mut mirrored = state.clone();
+        mirrored.pawns = [
+            state.pawns[1].reverse_bits() << 7,
+            state.pawns[0].reverse_bits() << 7,
+        ];
+        mirrored.kings = [
+            state.kings[1].reverse_bits() << 7,
+            state.kings[0].reverse_bits() << 7,
+        ];
+        mirrored.deck.cards = [
+            deck.cards[2],
+            deck.cards[3],
+            deck.cards[0],
+            deck.cards[1],
+            deck.cards[4],
+        ];
+
+        assert_eq!(
+            network.evaluate(&state, PlayerColor::Red),
+            network.evaluate(&mirrored, PlayerColor::Blue)
+        );
+    }
+
+    #[test]
+    fn test_network_file_format() {
+        let network = random_network(5);
+        let bytes = network.to_bytes();
+        assert_eq!(&bytes[..4], b"ONNU");
+        assert_eq!(NnueNetwork::from_bytes(&bytes).unwrap(), network);
+
+        assert!(matches!(
+            NnueNetwork::from_bytes(b"ONBK"),
+            Err(NnueError::InvalidMagic)
+        ));
+        assert!(matches!(
+            NnueNetwork::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(NnueError::Truncated)
+        ));
+
+        let mut other_shape = bytes.clone();
+        other_shape[7] = 32;
+        assert!(matches!(
+            NnueNetwork::from_bytes(&other_shape),
+            Err(NnueError::ShapeMismatch { hidden: 32, .. })
+        ));
+
+        // The first feature weight goes after the header
+        let mut overflowing = bytes.clone();
+        overflowing[9..11].copy_from_slice(&(MAX_WEIGHT + 1).to_le_bytes());
+        assert!(matches!(
+            NnueNetwork::from_bytes(&overflowing),
+            Err(NnueError::WeightOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn test_bounded_weights_do_not_overflow() {
+        let mut network = NnueNetwork::zeroed();
+        for row in network.feature_weights.iter_mut() {
+            row.fill(MAX_WEIGHT);
+        }
+        network.feature_bias.fill(MAX_WEIGHT);
+
+        let game_state = GameState::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let accumulator = Accumulator::new(&network, &game_state.state);
+        let expected = MAX_WEIGHT * (MAX_ACTIVE_FEATURES as i16 + 1);
+        assert!(accumulator.values.iter().flatten().all(|v| *v == expected));
+    }
+
+    #[test]
+    fn test_search_with_incremental_evaluation() {
+        let evaluation = NnueEvaluation::new(random_network(6));
+        let mut game_state = GameState::with_deck(Deck::new([RABBIT, FROG, TIGER, DRAGON, HORSE]));
+
+        // Accumulators of the search give the same result as the full evaluation
+        let incremental = AlphaBeta {
+            max_depth: 4,
+            search_time: Duration::from_secs(60),
+            ..AlphaBeta::with_evaluator(evaluation.clone())
+        };
+        let full = AlphaBeta {
+            max_depth: 4,
+            search_time: Duration::from_secs(60),
+            ..AlphaBeta::with_evaluator(FullEvaluation(evaluation))
+        };
+        let info = incremental.search(&game_state);
+        assert_eq!(info.best_score, full.search(&game_state).best_score);
+
+        // Blue must eat the Red king with the Dragon card whatever the network says
+        game_state.state.kings[PlayerColor::Red as usize] = from_2d_to_bitboard((1, 3));
+        game_state.curr_player_color = PlayerColor::Blue;
+        game_state.curr_agent_idx = 1;
+        let info = incremental.search(&game_state);
+        assert_eq!(info.best_move.mov.to, 8);
+        assert_eq!(info.best_score, -WIN_SCORE);
+    }
+
+    /// NNUE evaluation without the accumulators
+    #[derive(Debug, Clone)]
+    struct FullEvaluation(NnueEvaluation);
+
+    impl Evaluator for FullEvaluation {
+        type Accumulator = ();
+
+        fn evaluate(
+            &self,
+            state: &State,
+            player_color: PlayerColor,
+            move_result: &Option<MoveResult>,
+        ) -> i32 {
+            self.0.evaluate(state, player_color, move_result)
+        }
+    }
+}
@@ -0,0 +1,555 @@
+use std::{collections::HashMap, fmt, fs, io, path::Path, sync::Arc};
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::{
+    common::{seeded_rng, ByteReader},
+    game::{
+        done_move::DoneMove,
+        game_record::{GameRecord, RecordedMove},
+        game_state::GameState,
+        player_color::PlayerColor,
+        r#move::Move,
+        state::State,
+    },
+};
+
+use super::{
+    agent::Agent,
+    analysis::{AnalysisLine, SearchControl},
+    registry::AgentSpec,
+};
+
+const MAGIC: &[u8; 4] = b"ONBK";
+const VERSION: u8 = 1;
+/// Hash and the amount of moves
+const POSITION_SIZE: usize = 8 + 1;
+/// Card, from, to, games and score
+const MOVE_SIZE: usize = 3 + 4 + 4;
+
+#[derive(Debug)]
+pub enum BookError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    /// File ends in the middle of an entry
+    Truncated,
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::Io(e) => write!(f, "Cannot read the opening book: {}", e),
+            BookError::InvalidMagic => write!(f, "File is not an opening book"),
+            BookError::UnsupportedVersion(v) => {
+                write!(f, "Opening book version {} is not supported", v)
+            }
+            BookError::Truncated => write!(f, "Opening book file is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+impl From<io::Error> for BookError {
+    fn from(e: io::Error) -> Self {
+        BookError::Io(e)
+    }
+}
+
+/// Move from the book together with its statistics.
+/// The move is stored with the card index, so it does not depend on the order of the cards in a hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    /// Index of the used card, see `Card::index`
+    pub card: u8,
+    pub from: u8,
+    pub to: u8,
+    /// How many times the move was played
+    pub games: u32,
+    /// Sum of the results for the player who made the move.
+    /// Win gives 2 points, unfinished game 1 point and loss 0 points
+    pub score: u32,
+}
+
+impl BookMove {
+    /// Frequency of the move multiplied by its smoothed score rate.
+    /// Moves which were played often and won often have the biggest weight
+    pub fn weight(&self) -> f64 {
+        let games = self.games as f64;
+        games * (self.score as f64 + 1.) / (2. * games + 2.)
+    }
+
+    /// Average result for the player who made the move from -1 to 1
+    pub fn value(&self) -> f64 {
+        self.score as f64 / self.games as f64 - 1.
+    }
+
+    /// The piece is not stored, it is the one on the starting square
+    fn find_move(&self, game_state: &GameState) -> Option<DoneMove> {
+        let from = self.from as u32;
+        let piece = game_state
+            .state
+            .get_piece_type_at_pos(Move::convert_to_2d(from))?;
+        let recorded_move = RecordedMove {
+            card: self.card as usize,
+            mov: Move {
+                from,
+                to: self.to as u32,
+                piece,
+            },
+        };
+        GameRecord::find_move(game_state, &recorded_move)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BookConfig {
+    /// Only the moves of the first plies get into the book
+    pub max_ply: usize,
+    /// Moves which were played fewer times are removed
+    pub min_games: u32,
+}
+
+impl Default for BookConfig {
+    fn default() -> Self {
+        Self {
+            max_ply: 10,
+            min_games: 1,
+        }
+    }
+}
+
+/// Opening moves keyed by the Zobrist hash of the position and the player to move
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpeningBook {
+    positions: HashMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    /// Builds the book from the first moves of the games.
+    /// Records which cannot be replayed are skipped
+    pub fn build(records: &[GameRecord], config: BookConfig) -> Self {
+        let mut book = Self::default();
+
+        for record in records {
+            let positions = match record.positions() {
+                Ok(positions) => positions,
+                Err(_) => continue,
+            };
+            let winner = record.winner();
+
+            for position in positions.iter().take(config.max_ply) {
+                let done_move = match position.done_move {
+                    Some(done_move) => done_move,
+                    None => break,
+                };
+                let score = match winner {
+                    Some(color) if color == position.player_color => 2,
+                    Some(_) => 0,
+                    None => 1,
+                };
+                let card = position.state.deck.get_card(done_move.used_card_idx).index as u8;
+                book.add(
+                    position.state.zobrist_hash(position.player_color),
+                    card,
+                    done_move,
+                    score,
+                );
+            }
+        }
+
+        for moves in book.positions.values_mut() {
+            moves.retain(|m| m.games >= config.min_games);
+            moves.sort_by_key(|m| std::cmp::Reverse(m.games));
+        }
+        book.positions.retain(|_, moves| !moves.is_empty());
+
+        book
+    }
+
+    fn add(&mut self, hash: u64, card: u8, done_move: DoneMove, score: u32) {
+        let moves = self.positions.entry(hash).or_default();
+        let from = done_move.mov.from as u8;
+        let to = done_move.mov.to as u8;
+
+        match moves
+            .iter_mut()
+            .find(|m| m.card == card && m.from == from && m.to == to)
+        {
+            Some(book_move) => {
+                book_move.games += 1;
+                book_move.score += score;
+            }
+            None => moves.push(BookMove {
+                card,
+                from,
+                to,
+                games: 1,
+                score,
+            }),
+        }
+    }
+
+    /// Amount of positions in the book
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn moves(&self, state: &State, player_color: PlayerColor) -> Option<&[BookMove]> {
+        self.positions
+            .get(&state.zobrist_hash(player_color))
+            .map(|moves| moves.as_slice())
+    }
+
+    /// Book moves which are legal in the position.
+    /// Hash collisions with other positions are filtered out by the legality check
+    pub fn probe(&self, game_state: &GameState) -> Vec<(DoneMove, BookMove)> {
+        self.moves(&game_state.state, game_state.curr_player_color)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|book_move| {
+                book_move
+                    .find_move(game_state)
+                    .map(|done_move| (done_move, *book_move))
+            })
+            .collect()
+    }
+
+    /// Binary format: magic "ONBK", version byte and the amount of positions.
+    /// Every position is a hash with the amount of moves followed by the moves.
+    /// All numbers are little-endian, positions are sorted by the hash
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut hashes = self.positions.keys().copied().collect::<Vec<_>>();
+        hashes.sort_unstable();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(hashes.len() as u32).to_le_bytes());
+
+        for hash in hashes {
+            // Only 255 moves of a position fit into the file, the rarest are dropped.
+            // The moves are already sorted from the most played one
+            let moves = &self.positions[&hash];
+            let moves = &moves[..moves.len().min(u8::MAX as usize)];
+
+            bytes.extend_from_slice(&hash.to_le_bytes());
+            bytes.push(moves.len() as u8);
+            for m in moves {
+                bytes.extend_from_slice(&[m.card, m.from, m.to]);
+                bytes.extend_from_slice(&m.games.to_le_bytes());
+                bytes.extend_from_slice(&m.score.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(BookError::InvalidMagic);
+        }
+        let mut reader = ByteReader::new(&bytes[MAGIC.len()..]);
+
+        let version = reader.u8().ok_or(BookError::Truncated)?;
+        if version != VERSION {
+            return Err(BookError::UnsupportedVersion(version));
+        }
+
+        let amount = reader.u32().ok_or(BookError::Truncated)? as usize;
+        // Every position takes some bytes, so a broken header cannot allocate too much
+        let mut positions = HashMap::with_capacity(amount.min(bytes.len() / POSITION_SIZE));
+
+        for _ in 0..amount {
+            let hash = reader.u64().ok_or(BookError::Truncated)?;
+            let move_amount = reader.u8().ok_or(BookError::Truncated)? as usize;
+            let mut moves = Vec::with_capacity(move_amount);
+
+            for _ in 0..move_amount {
+                let squares = reader.take(MOVE_SIZE - 8).ok_or(BookError::Truncated)?;
+                moves.push(BookMove {
+                    card: squares[0],
+                    from: squares[1],
+                    to: squares[2],
+                    games: reader.u32().ok_or(BookError::Truncated)?,
+                    score: reader.u32().ok_or(BookError::Truncated)?,
+                });
+            }
+
+            positions.insert(hash, moves);
+        }
+
+        Ok(Self { positions })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, BookError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+/// Plays the moves from the opening book while the position is in the book,
+/// then the inner agent makes the moves
+#[derive(Clone, Serialize)]
+pub struct BookAgent<A: Agent + Clone + Serialize> {
+    #[serde(skip)]
+    pub book: Arc<OpeningBook>,
+    /// File of the book, the agent has a specification only when it is known
+    #[serde(skip)]
+    pub path: Option<String>,
+    pub inner: A,
+    /// Book moves are chosen with the probability proportional to weight^(1 / variety).
+    /// Zero variety always plays the move with the biggest weight,
+    /// one follows the weights and bigger values make the choice closer to uniform
+    pub variety: f64,
+    /// With a seed the agent always chooses the same book move in the same position
+    pub seed: Option<u64>,
+}
+
+impl<A: Agent + Clone + Serialize> BookAgent<A> {
+    pub fn new(book: Arc<OpeningBook>, inner: A) -> Self {
+        Self {
+            book,
+            path: None,
+            inner,
+            variety: 1.,
+            seed: None,
+        }
+    }
+
+    pub fn from_file(path: &str, inner: A) -> Result<Self, BookError> {
+        Ok(Self {
+            path: Some(path.to_owned()),
+            ..Self::new(Arc::new(OpeningBook::from_file(path)?), inner)
+        })
+    }
+
+    /// Returns the book move or nothing if the position is out of the book
+    pub fn book_move(&self, game_state: &GameState) -> Option<(DoneMove, f64)> {
+        let candidates = self.book.probe(game_state);
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let best_idx = (0..candidates.len())
+            .max_by(|&a, &b| {
+                candidates[a]
+                    .1
+                    .weight()
+                    .total_cmp(&candidates[b].1.weight())
+            })
+            .unwrap();
+
+        let idx = if self.variety <= 0. {
+            best_idx
+        } else {
+            let mut rng = seeded_rng(
+                self.seed,
+                game_state.state.zobrist_hash(game_state.curr_player_color),
+            );
+            let weights = candidates
+                .iter()
+                .map(|(_, book_move)| book_move.weight().powf(1. / self.variety))
+                .collect::<Vec<_>>();
+
+            let mut target = rng.gen::<f64>() * weights.iter().sum::<f64>();
+            weights
+                .iter()
+                .position(|w| {
+                    target -= w;
+                    target <= 0.
+                })
+                .unwrap_or(best_idx)
+        };
+
+        let (done_move, book_move) = candidates[idx];
+        Some((done_move, book_move.value()))
+    }
+}
+
+impl<A: Agent + Clone + Serialize + 'static> Agent for BookAgent<A> {
+    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
+        self.book_move(game_state)
+            .unwrap_or_else(|| self.inner.generate_move(game_state))
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Agent> {
+        Box::new(self.clone())
+    }
+
+    fn id(&self) -> u64 {
+        self.inner.id()
+    }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        // Commas separate the parameters of the specification itself
+        let inner = self.inner.spec()?.to_string().replace(',', ";");
+        let mut spec = AgentSpec::new("book")
+            .with("file", self.path.as_ref()?)
+            .with("agent", inner)
+            .with("variety", self.variety);
+        if let Some(seed) = self.seed {
+            spec.set("seed", seed);
+        }
+        Some(spec)
+    }
+
+    fn analyze(&self, game_state: &GameState, lines: usize) -> Vec<AnalysisLine> {
+        self.inner.analyze(game_state, lines)
+    }
+
+    fn analyze_until(
+        &self,
+        game_state: &GameState,
+        lines: usize,
+        control: &SearchControl,
+        on_lines: &mut dyn FnMut(&[AnalysisLine]),
+    ) -> Vec<AnalysisLine> {
+        self.inner
+            .analyze_until(game_state, lines, control, on_lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ai::{random::Random, registry::AgentRegistry},
+        game::{
+            card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+            deck::Deck,
+            move_result::MoveResult,
+        },
+    };
+
+    use super::*;
+
+    /// Plays the moves with the given indices in the list of legal moves
+    fn play_game(choices: &[usize], result: MoveResult) -> GameRecord {
+        let deck = Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]);
+        let mut game_state = GameState::with_deck(deck.clone());
+        let mut record = GameRecord::new(deck);
+
+        for &choice in choices {
+            let moves = game_state
+                .state
+                .generate_all_legal_moves(game_state.curr_player_color);
+            let (card_idx, mov) = moves[choice];
+            let card = *game_state.state.deck.get_card(card_idx);
+            game_state.progress(DoneMove {
+                mov,
+                used_card_idx: card_idx,
+            });
+            record.push(&card, mov, MoveResult::InProgress);
+        }
+        record.result = result;
+
+        record
+    }
+
+    fn book() -> OpeningBook {
+        let records = [
+            play_game(&[0, 0, 0], MoveResult::RedWin),
+            play_game(&[0, 1, 0], MoveResult::RedWin),
+            play_game(&[1, 0], MoveResult::BlueWin),
+        ];
+        OpeningBook::build(&records, BookConfig::default())
+    }
+
+    #[test]
+    fn test_book_statistics() {
+        let book = book();
+        let game_state = GameState::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+
+        let moves = book.probe(&game_state);
+        assert_eq!(moves.len(), 2);
+        assert_eq!((moves[0].1.games, moves[0].1.score), (2, 4));
+        assert_eq!((moves[1].1.games, moves[1].1.score), (1, 0));
+        assert!(moves[0].1.weight() > moves[1].1.weight());
+
+        // Start position, two positions after the first ply and two after the second one
+        assert_eq!(book.len(), 5);
+
+        let pruned = OpeningBook::build(
+            &[play_game(&[0, 0], MoveResult::RedWin)],
+            BookConfig {
+                max_ply: 1,
+                min_games: 1,
+            },
+        );
+        assert_eq!(pruned.len(), 1);
+    }
+
+    #[test]
+    fn test_book_binary_format() {
+        let book = book();
+        let bytes = book.to_bytes();
+        assert_eq!(&bytes[..4], b"ONBK");
+        assert_eq!(OpeningBook::from_bytes(&bytes).unwrap(), book);
+
+        assert!(matches!(
+            OpeningBook::from_bytes(b"JSON"),
+            Err(BookError::InvalidMagic)
+        ));
+        assert!(matches!(
+            OpeningBook::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BookError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_book_agent() {
+        let records = [play_game(&[3], MoveResult::RedWin)];
+        let book = Arc::new(OpeningBook::build(&records, BookConfig::default()));
+        let agent = BookAgent {
+            variety: 0.,
+            ..BookAgent::new(book, Random { seed: Some(1) })
+        };
+
+        let mut game_state = GameState::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let expected = game_state
+            .state
+            .generate_all_legal_moves(game_state.curr_player_color)[3];
+        let (done_move, value) = agent.generate_move(&game_state);
+        assert_eq!((done_move.used_card_idx, done_move.mov), expected);
+        assert_eq!(value, 1.);
+
+        // Out of the book the inner agent plays
+        game_state.progress(done_move);
+        assert!(agent.book_move(&game_state).is_none());
+        assert_eq!(
+            agent.generate_move(&game_state).0,
+            agent.inner.generate_move(&game_state).0
+        );
+    }
+
+    #[test]
+    fn test_book_agent_spec() {
+        let path = std::env::temp_dir().join(format!("onitama_book_{}.bin", std::process::id()));
+        book().save(&path).unwrap();
+        let spec = format!(
+            "book:file={},agent=alphabeta:depth=4;time=400ms;threads=1;eval=simple,variety=0.5,seed=3",
+            path.display()
+        );
+
+        let agent = AgentRegistry::default().build(&spec);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(agent.unwrap().spec(), Some(spec.parse().unwrap()));
+
+        // Without the file the agent cannot be written as a specification
+        let agent = BookAgent::new(Arc::new(book()), Random { seed: Some(1) });
+        assert_eq!(agent.spec(), None);
+    }
+}
@@ -0,0 +1,514 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{done_move::DoneMove, game_state::GameState, player_color::PlayerColor};
+
+use super::{
+    agent::Agent,
+    alpha_beta::{weighted_evaluation::WIN_SCORE, AlphaBeta},
+    registry::{format_duration, AgentSpec},
+};
+
+/// Proof or disproof number of a solved node
+const INFINITY: u32 = u32::MAX;
+
+/// Result of the proof-number search for the player to move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofResult {
+    /// The player to move forces the win. The line contains the moves of both players,
+    /// the defender chooses the moves which delay the loss the longest
+    Win(Vec<DoneMove>),
+    /// The player to move cannot force the win within the plies limit
+    NoWin,
+    /// The nodes limit is reached before the position is solved
+    Unproven,
+}
+
+/// Information about the finished proof-number search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofInfo {
+    pub result: ProofResult,
+    /// Nodes created by the search
+    pub nodes: usize,
+    /// Nodes which were solved by the transposition table instead of the search
+    pub transpositions: usize,
+}
+
+/// Proof-number search which proves or disproves the forced win of the player to move.
+/// The player to move is the attacker, the enemy is the defender.
+/// The solved positions are kept in the transposition table, so the same position
+/// reached by other moves is not searched again
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ProofNumberSearch {
+    /// The search is stopped after this amount of nodes
+    pub max_nodes: usize,
+    /// Only the wins within this amount of plies are searched, moves of both players are counted.
+    /// It also makes the repeated positions harmless, because every line ends
+    pub max_plies: u16,
+}
+
+impl Default for ProofNumberSearch {
+    fn default() -> Self {
+        Self {
+            max_nodes: 200_000,
+            max_plies: 15,
+        }
+    }
+}
+
+/// Solved position in the transposition table
+struct SolvedEntry {
+    /// The attacker wins, otherwise the win is disproven
+    is_proven: bool,
+    /// Plies which were left when the position was solved. The proof holds with more plies
+    /// and the disproof holds with less plies
+    plies_left: u16,
+    /// Winning line from the proven position
+    line: Vec<DoneMove>,
+}
+
+/// Transposition table of the solved positions, the key is the Zobrist hash
+type SolvedTable = HashMap<u64, SolvedEntry>;
+
+struct ProofNode {
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// A move which the node represents
+    mov: Option<DoneMove>,
+    /// Minimal amount of leaves which must be proven to prove the node
+    proof: u32,
+    /// Minimal amount of leaves which must be disproven to disprove the node
+    disproof: u32,
+    /// Plies from the root. The attacker moves from the even plies
+    ply: u16,
+    is_expanded: bool,
+    /// Zobrist hash of the position with the player to move
+    hash: u64,
+    /// Winning line of the node proven by the transposition table
+    known_line: Vec<DoneMove>,
+}
+
+impl ProofNode {
+    fn new(parent: Option<usize>, mov: Option<DoneMove>, ply: u16, hash: u64) -> Self {
+        Self {
+            parent,
+            children: vec![],
+            mov,
+            proof: 1,
+            disproof: 1,
+            ply,
+            is_expanded: false,
+            hash,
+            known_line: vec![],
+        }
+    }
+
+    #[inline]
+    fn is_attacker_node(&self) -> bool {
+        self.ply & 1 == 0
+    }
+
+    #[inline]
+    fn is_solved(&self) -> bool {
+        self.proof == 0 || self.disproof == 0
+    }
+}
+
+impl ProofNumberSearch {
+    pub fn solve(&self, game_state: &GameState) -> ProofInfo {
+        let mut game_state = game_state.clone();
+        let hash = game_state.state.zobrist_hash(game_state.curr_player_color);
+        let mut tree = vec![ProofNode::new(None, None, 0, hash)];
+        let mut table = SolvedTable::new();
+        let mut transpositions = 0;
+
+        while !tree[0].is_solved() && tree.len() < self.max_nodes {
+            let leaf = Self::select_most_proving(&tree, &mut game_state);
+            transpositions += self.expand(&mut tree, leaf, &mut game_state, &table);
+            for _ in 0..tree[leaf].ply {
+                game_state.undo();
+            }
+            self.update_ancestors(&mut tree, leaf, &mut table);
+        }
+
+        let result = if tree[0].proof == 0 {
+            ProofResult::Win(Self::win_line(&tree, 0))
+        } else if tree[0].disproof == 0 {
+            ProofResult::NoWin
+        } else {
+            ProofResult::Unproven
+        };
+
+        ProofInfo {
+            result,
+            nodes: tree.len(),
+            transpositions,
+        }
+    }
+
+    /// Goes down to the leaf which proves or disproves the root with the least effort.
+    /// The moves to the leaf are made in the game state
+    fn select_most_proving(tree: &[ProofNode], game_state: &mut GameState) -> usize {
+        let mut node_idx = 0;
+
+        while tree[node_idx].is_expanded {
+            let node = &tree[node_idx];
+            node_idx = *node
+                .children
+                .iter()
+                .filter(|&&c| !tree[c].is_solved())
+                .min_by_key(|&&c| {
+                    if node.is_attacker_node() {
+                        tree[c].proof
+                    } else {
+                        tree[c].disproof
+                    }
+                })
+                .expect("Unsolved node must have an unsolved child");
+
+            game_state.progress(tree[node_idx].mov.expect("A child node must have a move"));
+        }
+
+        node_idx
+    }
+
+    /// Creates the children of the node. Returns the amount of the children
+    /// which are solved by the transposition table
+    fn expand(
+        &self,
+        tree: &mut Vec<ProofNode>,
+        node_idx: usize,
+        game_state: &mut GameState,
+        table: &SolvedTable,
+    ) -> usize {
+        let ply = tree[node_idx].ply + 1;
+        let plies_left = self.max_plies.saturating_sub(ply);
+        let mut transpositions = 0;
+        let is_attacker_move = tree[node_idx].is_attacker_node();
+        let player_color = game_state.curr_player_color;
+
+        let moves = game_state.state.generate_all_legal_moves(player_color);
+        // The game cannot go on without moves, so the win cannot be proven
+        if moves.is_empty() {
+            tree[node_idx].proof = INFINITY;
+            tree[node_idx].disproof = 0;
+        }
+
+        for (card_idx, mov) in moves {
+            let done_move = DoneMove {
+                mov,
+                used_card_idx: card_idx,
+            };
+            let result = game_state.progress(done_move);
+            let hash = game_state.state.zobrist_hash(game_state.curr_player_color);
+            game_state.undo();
+
+            let mut child = ProofNode::new(Some(node_idx), Some(done_move), ply, hash);
+            let solved = table.get(&hash).filter(|entry| {
+                if entry.is_proven {
+                    entry.plies_left <= plies_left
+                } else {
+                    entry.plies_left >= plies_left
+                }
+            });
+            if result.is_win() && is_attacker_move {
+                child.proof = 0;
+                child.disproof = INFINITY;
+                child.is_expanded = true;
+            } else if result.is_win() || ply >= self.max_plies {
+                // The defender has won or the line is too long
+                child.proof = INFINITY;
+                child.disproof = 0;
+                child.is_expanded = true;
+            } else if let Some(entry) = solved {
+                (child.proof, child.disproof) = if entry.is_proven {
+                    (0, INFINITY)
+                } else {
+                    (INFINITY, 0)
+                };
+                child.known_line = entry.line.clone();
+                child.is_expanded = true;
+                transpositions += 1;
+            }
+
+            let child_idx = tree.len();
+            tree.push(child);
+            tree[node_idx].children.push(child_idx);
+        }
+
+        tree[node_idx].is_expanded = true;
+        transpositions
+    }
+
+    /// Updates the numbers of the node and its ancestors.
+    /// The nodes which become solved are saved in the transposition table
+    fn update_ancestors(&self, tree: &mut [ProofNode], node_idx: usize, table: &mut SolvedTable) {
+        let mut node_idx = Some(node_idx);
+
+        while let Some(idx) = node_idx {
+            let node = &tree[idx];
+            let was_solved = node.is_solved() && !node.children.is_empty();
+            if !node.children.is_empty() {
+                let proofs = node.children.iter().map(|&c| tree[c].proof);
+                let disproofs = node.children.iter().map(|&c| tree[c].disproof);
+
+                // The attacker needs one winning move, the defender must lose after every move
+                let (proof, disproof) = if node.is_attacker_node() {
+                    (
+                        proofs.min().unwrap(),
+                        disproofs.fold(0, u32::saturating_add),
+                    )
+                } else {
+                    (
+                        proofs.fold(0, u32::saturating_add),
+                        disproofs.min().unwrap(),
+                    )
+                };
+
+                tree[idx].proof = proof;
+                tree[idx].disproof = disproof;
+            }
+
+            let node = &tree[idx];
+            if node.is_solved() && !was_solved {
+                let is_proven = node.proof == 0;
+                let line = if is_proven {
+                    Self::win_line(tree, idx)
+                } else {
+                    vec![]
+                };
+                let entry = SolvedEntry {
+                    is_proven,
+                    plies_left: self.max_plies.saturating_sub(node.ply),
+                    line,
+                };
+                table.insert(node.hash, entry);
+            }
+
+            node_idx = tree[idx].parent;
+        }
+    }
+
+    /// Moves after the proven node: the attacker takes the fastest win
+    /// and the defender takes the slowest loss
+    fn win_line(tree: &[ProofNode], node_idx: usize) -> Vec<DoneMove> {
+        let node = &tree[node_idx];
+        if node.children.is_empty() {
+            return node.known_line.clone();
+        }
+        let lines = node
+            .children
+            .iter()
+            .filter(|&&c| tree[c].proof == 0)
+            .map(|&c| {
+                let mut line = vec![tree[c].mov.expect("A child node must have a move")];
+                line.extend(Self::win_line(tree, c));
+                line
+            });
+
+        let line = if node.is_attacker_node() {
+            lines.min_by_key(|l| l.len())
+        } else {
+            lines.max_by_key(|l| l.len())
+        };
+        line.unwrap_or_default()
+    }
+}
+
+/// Plays the proven winning move if there is one, otherwise the move of the AlphaBeta search
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SolverAgent {
+    pub search: ProofNumberSearch,
+    pub fallback: AlphaBeta,
+}
+
+impl Agent for SolverAgent {
+    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
+        match self.search.solve(game_state).result {
+            ProofResult::Win(line) => {
+                // The score is from the red player perspective like the score of AlphaBeta
+                let score = match game_state.curr_player_color {
+                    PlayerColor::Red => WIN_SCORE,
+                    PlayerColor::Blue => -WIN_SCORE,
+                };
+                (line[0], score as f64)
+            }
+            ProofResult::NoWin | ProofResult::Unproven => self.fallback.generate_move(game_state),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Proof-number solver"
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Agent> {
+        Box::new(self.clone())
+    }
+
+    fn id(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.search.max_nodes.hash(&mut hasher);
+        self.search.max_plies.hash(&mut hasher);
+        self.fallback.id().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        Some(
+            AgentSpec::new("solver")
+                .with("nodes", self.search.max_nodes)
+                .with("plies", self.search.max_plies)
+                .with("depth", self.fallback.max_depth)
+                .with("time", format_duration(self.fallback.search_time))
+                .with("threads", self.fallback.threads),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::{
+        common::{from_2d_to_bitboard, seeded_rng},
+        game::{
+            card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+            deck::Deck,
+            move_result::MoveResult,
+        },
+    };
+
+    use super::*;
+
+    /// Checks that the line is made of legal moves and the attacker wins with the last one
+    fn assert_winning_line(game_state: &GameState, line: &[DoneMove]) {
+        let mut game_state = game_state.clone();
+        let attacker = game_state.curr_player_color;
+        let mut result = MoveResult::InProgress;
+
+        for (i, done_move) in line.iter().enumerate() {
+            assert!(!result.is_win(), "The game has ended before move {}", i);
+            let player_color = game_state.curr_player_color;
+            assert!(game_state
+                .state
+                .generate_all_legal_moves(player_color)
+                .contains(&(done_move.used_card_idx, done_move.mov)));
+            result = game_state.progress(*done_move);
+        }
+
+        let expected = match attacker {
+            PlayerColor::Red => MoveResult::RedWin,
+            PlayerColor::Blue => MoveResult::BlueWin,
+        };
+        assert_eq!(result, expected);
+    }
+
+    fn king_capture_position() -> GameState {
+        // Same position as in AlphaBeta test: Blue eats the Red king with the Dragon card
+        let mut game_state = GameState::with_deck(Deck::new([RABBIT, FROG, TIGER, DRAGON, HORSE]));
+        game_state.state.kings[PlayerColor::Red as usize] = from_2d_to_bitboard((1, 3));
+        game_state.curr_player_color = PlayerColor::Blue;
+        game_state.curr_agent_idx = 1;
+        game_state
+    }
+
+    #[test]
+    fn test_proves_king_capture() {
+        let game_state = king_capture_position();
+        let info = ProofNumberSearch::default().solve(&game_state);
+
+        match info.result {
+            ProofResult::Win(line) => {
+                assert_eq!(line.len(), 1);
+                assert_eq!(line[0].mov.to, 8);
+                assert_winning_line(&game_state, &line);
+            }
+            result => panic!("The win must be proven, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_disproves_win_without_plies() {
+        let game_state = GameState::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let search = ProofNumberSearch {
+            max_plies: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(search.solve(&game_state).result, ProofResult::NoWin);
+    }
+
+    #[test]
+    fn test_nodes_limit_leaves_position_unproven() {
+        let game_state = GameState::with_deck(Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]));
+        let search = ProofNumberSearch {
+            max_nodes: 100,
+            max_plies: 30,
+        };
+
+        let info = search.solve(&game_state);
+        assert_eq!(info.result, ProofResult::Unproven);
+        assert!(info.nodes >= 100);
+    }
+
+    #[test]
+    fn test_proven_lines_are_wins() {
+        // Random positions in the middle of the game, some of them have the forced wins
+        let search = ProofNumberSearch {
+            max_nodes: 20_000,
+            max_plies: 5,
+        };
+        let mut proven = 0;
+        let mut transpositions = 0;
+
+        for seed in 0..20 {
+            let mut game_state = GameState::with_deck(Deck::from_seed(seed));
+            let mut rng = seeded_rng(Some(seed), 0);
+            for _ in 0..12 {
+                let moves = game_state
+                    .state
+                    .generate_all_legal_moves(game_state.curr_player_color);
+                if moves.is_empty() {
+                    break;
+                }
+                let (card_idx, mov) = moves[rng.gen_range(0..moves.len())];
+                let result = game_state.progress(DoneMove {
+                    mov,
+                    used_card_idx: card_idx,
+                });
+                if result.is_win() {
+                    game_state.undo();
+                    break;
+                }
+            }
+
+            let info = search.solve(&game_state);
+            transpositions += info.transpositions;
+            if let ProofResult::Win(line) = info.result {
+                assert!(line.len() % 2 == 1);
+                assert!(line.len() <= 5);
+                assert_winning_line(&game_state, &line);
+                proven += 1;
+            }
+        }
+
+        assert!(proven > 0, "Some of the positions must have the forced win");
+        assert!(
+            transpositions > 0,
+            "Some of the positions must be transposed"
+        );
+    }
+
+    #[test]
+    fn test_solver_agent_plays_winning_move() {
+        let game_state = king_capture_position();
+        let (done_move, score) = SolverAgent::default().generate_move(&game_state);
+
+        assert_eq!(done_move.mov.to, 8);
+        assert_eq!(score, -WIN_SCORE as f64);
+    }
+}
@@ -1,18 +1,25 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::game::{done_move::DoneMove, game_state::GameState, r#move::Move};
+use crate::{
+    common::seeded_rng,
+    game::{done_move::DoneMove, game_state::GameState, r#move::Move},
+};
 
-use super::agent::Agent;
+use super::{agent::Agent, registry::AgentSpec};
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct Random;
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Random {
+    /// With a seed the agent always makes the same move in the same position
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
 
 impl Agent for Random {
     fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
         let player_color = game_state.curr_player_color;
         let state = &game_state.state;
-        let mut rng = thread_rng();
+        let mut rng = seeded_rng(self.seed, state.zobrist_hash(player_color));
         let cards = state.deck.get_player_cards(player_color);
         let card_idx = rng.gen_range(0..2);
         let card = cards[card_idx];
@@ -53,4 +60,12 @@ impl Agent for Random {
     fn id(&self) -> u64 {
         "random".parse::<u64>().unwrap()
     }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        let spec = AgentSpec::new("random");
+        Some(match self.seed {
+            Some(seed) => spec.with("seed", seed),
+            None => spec,
+        })
+    }
 }
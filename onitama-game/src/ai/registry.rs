@@ -0,0 +1,842 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use serde::Serialize;
+
+use super::{
+    agent::Agent,
+    alpha_beta::{
+        evaluation::{Evaluation, Evaluator},
+        weighted_evaluation::WeightedEvaluation,
+        AlphaBeta,
+    },
+    external_engine::ExternalEngine,
+    human_console::HumanConsole,
+    mcts::{rave::RaveSchedule, rollout::Rollout, Mcts, ParallelMode},
+    nnue::NnueEvaluation,
+    opening_book::BookAgent,
+    proof_number::{ProofNumberSearch, SolverAgent},
+    random::Random,
+    skill_level::{SkillLevel, SkilledAgent},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecError {
+    Empty,
+    /// Parameter is not written as `key=value`
+    InvalidParam(String),
+    UnknownKind(String),
+    UnknownParam {
+        kind: String,
+        param: String,
+    },
+    MissingParam {
+        kind: String,
+        param: String,
+    },
+    InvalidValue {
+        param: String,
+        value: String,
+        reason: String,
+    },
+    /// Agent cannot be created, e.g. its model file cannot be loaded
+    Build(String),
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecError::Empty => write!(f, "Agent specification is empty"),
+            SpecError::InvalidParam(param) => {
+                write!(f, "Parameter '{}' must be written as key=value", param)
+            }
+            SpecError::UnknownKind(kind) => write!(f, "Unknown agent kind '{}'", kind),
+            SpecError::UnknownParam { kind, param } => {
+                write!(f, "Agent '{}' does not have parameter '{}'", kind, param)
+            }
+            SpecError::MissingParam { kind, param } => {
+                write!(f, "Agent '{}' requires parameter '{}'", kind, param)
+            }
+            SpecError::InvalidValue {
+                param,
+                value,
+                reason,
+            } => write!(
+                f,
+                "Invalid value '{}' of parameter '{}': {}",
+                value, param, reason
+            ),
+            SpecError::Build(message) => write!(f, "Cannot create the agent: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+/// Agent written as `kind:key=value,key=value`, e.g. `alphabeta:depth=8,time=1s`.
+/// Parameters which are not given take the default values of the agent kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentSpec {
+    pub kind: String,
+    /// Parameters in the written order
+    pub params: Vec<(String, String)>,
+}
+
+impl AgentSpec {
+    pub fn new(kind: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            params: vec![],
+        }
+    }
+
+    /// Adds the parameter or replaces its value
+    pub fn with(mut self, key: &str, value: impl ToString) -> Self {
+        self.set(key, value);
+        self
+    }
+
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        let value = value.to_string();
+        match self.params.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.params.push((key.to_owned(), value)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Value of the parameter or the default value if it is not given
+    pub fn parse_or<T>(&self, key: &str, default: T) -> Result<T, SpecError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        Ok(self.parse_opt(key)?.unwrap_or(default))
+    }
+
+    pub fn parse_opt<T>(&self, key: &str) -> Result<Option<T>, SpecError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e: T::Err| invalid_value(key, value, e))
+            })
+            .transpose()
+    }
+
+    pub fn duration_or(&self, key: &str, default: Duration) -> Result<Duration, SpecError> {
+        match self.get(key) {
+            Some(value) => parse_duration(value).map_err(|e| invalid_value(key, value, e)),
+            None => Ok(default),
+        }
+    }
+
+    /// Parameter which must be given
+    pub fn require(&self, key: &str) -> Result<&str, SpecError> {
+        self.get(key).ok_or_else(|| SpecError::MissingParam {
+            kind: self.kind.clone(),
+            param: key.to_owned(),
+        })
+    }
+}
+
+impl FromStr for AgentSpec {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, params) = match s.trim().split_once(':') {
+            Some((kind, params)) => (kind.trim(), params),
+            None => (s.trim(), ""),
+        };
+        if kind.is_empty() {
+            return Err(SpecError::Empty);
+        }
+
+        let mut spec = AgentSpec::new(kind.to_lowercase());
+        for param in params.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match param.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() => {
+                    spec.set(&key.trim().to_lowercase(), value.trim())
+                }
+                _ => return Err(SpecError::InvalidParam(param.to_owned())),
+            }
+        }
+
+        Ok(spec)
+    }
+}
+
+impl fmt::Display for AgentSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        for (i, (key, value)) in self.params.iter().enumerate() {
+            let separator = if i == 0 { ':' } else { ',' };
+            write!(f, "{}{}={}", separator, key, value)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid_value(param: &str, value: &str, reason: impl fmt::Display) -> SpecError {
+    SpecError::InvalidValue {
+        param: param.to_owned(),
+        value: value.to_owned(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Parses the durations like `400ms`, `1s`, `1.5s` or `2m`
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit_nanos) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 1e6)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1e9)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 6e10)
+    } else {
+        return Err("time must end with the unit: ms, s or m".to_owned());
+    };
+
+    let number = number.trim().parse::<f64>().map_err(|e| e.to_string())?;
+    if !number.is_finite() || number < 0. {
+        return Err("time must be a positive number".to_owned());
+    }
+    // Rounded to nanoseconds, so 0.3s is exactly 300ms
+    Ok(Duration::from_nanos((number * unit_nanos).round() as u64))
+}
+
+/// Writes the duration in the shortest form which `parse_duration` reads back
+pub fn format_duration(duration: Duration) -> String {
+    if duration.subsec_nanos() == 0 {
+        format!("{}s", duration.as_secs())
+    } else if duration.subsec_nanos() == duration.subsec_millis() * 1_000_000 {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{}s", duration.as_secs_f64())
+    }
+}
+
+/// Parameter of the agent kind with its default value for the help
+#[derive(Debug, Clone, Copy)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub default: &'static str,
+    pub description: &'static str,
+}
+
+impl ParamInfo {
+    pub const fn new(name: &'static str, default: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            default,
+            description,
+        }
+    }
+}
+
+pub type BuildAgent = fn(&AgentSpec) -> Result<Box<dyn Agent>, SpecError>;
+
+/// One kind of the agents which the registry can create
+#[derive(Clone)]
+pub struct AgentKind {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: Vec<ParamInfo>,
+    pub build: BuildAgent,
+}
+
+/// Creates the agents from their specifications.
+/// The default registry knows all agents of this crate, other crates register their own kinds
+#[derive(Clone)]
+pub struct AgentRegistry {
+    kinds: Vec<AgentKind>,
+}
+
+impl AgentRegistry {
+    pub fn empty() -> Self {
+        Self { kinds: vec![] }
+    }
+
+    /// Adds the kind or replaces the kind with the same name
+    pub fn register(&mut self, kind: AgentKind) {
+        match self.kinds.iter_mut().find(|k| k.name == kind.name) {
+            Some(existing) => *existing = kind,
+            None => self.kinds.push(kind),
+        }
+    }
+
+    /// Removes the kind, e.g. the one which must not be created by the untrusted users
+    pub fn unregister(&mut self, name: &str) {
+        self.kinds.retain(|kind| kind.name != name);
+    }
+
+    pub fn kinds(&self) -> &[AgentKind] {
+        &self.kinds
+    }
+
+    pub fn kind(&self, name: &str) -> Option<&AgentKind> {
+        self.kinds.iter().find(|k| k.name == name)
+    }
+
+    pub fn build(&self, spec: &str) -> Result<Box<dyn Agent>, SpecError> {
+        self.build_spec(&spec.parse()?)
+    }
+
+    pub fn build_spec(&self, spec: &AgentSpec) -> Result<Box<dyn Agent>, SpecError> {
+        let kind = self
+            .kind(&spec.kind)
+            .ok_or_else(|| SpecError::UnknownKind(spec.kind.clone()))?;
+
+        if let Some((param, _)) = spec
+            .params
+            .iter()
+            .find(|(key, _)| !kind.params.iter().any(|p| p.name == key))
+        {
+            return Err(SpecError::UnknownParam {
+                kind: spec.kind.clone(),
+                param: param.clone(),
+            });
+        }
+
+        (kind.build)(spec)
+    }
+
+    /// Gives the seed to the agent whose kind has the parameter and whose specification does not set it.
+    /// The game loops pass the seed of every game, so the games can be replayed
+    pub fn seeded(&self, spec: &AgentSpec, seed: Option<u64>) -> AgentSpec {
+        let has_seed = self
+            .kind(&spec.kind)
+            .is_some_and(|kind| kind.params.iter().any(|p| p.name == "seed"));
+        match seed {
+            Some(seed) if has_seed && spec.get("seed").is_none() => spec.clone().with("seed", seed),
+            _ => spec.clone(),
+        }
+    }
+
+    /// Description of all agent kinds and their parameters for the command line help
+    pub fn help(&self) -> String {
+        let mut help = String::from("Agents are written as kind:key=value,key=value\n");
+        for kind in self.kinds.iter() {
+            help += &format!("\n{:<12}{}\n", kind.name, kind.description);
+            for param in kind.params.iter() {
+                let name = format!("{}={}", param.name, param.default);
+                help += &format!("    {:<20}{}\n", name, param.description);
+            }
+        }
+        help
+    }
+}
+
+impl Default for AgentRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register(AgentKind {
+            name: "random",
+            description: "Makes random legal moves",
+            params: vec![ParamInfo::new(
+                "seed",
+                "none",
+                "Makes the moves reproducible",
+            )],
+            build: |spec| {
+                Ok(Box::new(Random {
+                    seed: spec.parse_opt("seed")?,
+                }))
+            },
+        });
+
+        registry.register(AgentKind {
+            name: "human",
+            description: "Reads the moves from the console",
+            params: vec![],
+            build: |_| Ok(Box::new(HumanConsole)),
+        });
+
+        registry.register(AgentKind {
+            name: "alphabeta",
+            description: "AlphaBeta search with iterative deepening",
+            params: alpha_beta_params(),
+            build: build_alpha_beta,
+        });
+
+        registry.register(AgentKind {
+            name: "mcts",
+            description: "Monte Carlo tree search with random rollouts",
+            params: vec![
+                ParamInfo::new("time", "1s", "Search time"),
+                ParamInfo::new("c", "1.41", "Exploration constant"),
+                ParamInfo::new("playouts", "5000", "Maximal amount of playouts"),
+                ParamInfo::new("visits", "5", "Visits of a node before it is expanded"),
+                ParamInfo::new(
+                    "reuse",
+                    "true",
+                    "Continue from the tree of the previous move",
+                ),
+                ParamInfo::new("threads", "1", "Search threads"),
+                ParamInfo::new("parallel", "tree", "Parallelization: tree or root"),
+                ParamInfo::new("rave", "none", "RAVE schedule: eq:<k> or mse:<bias>"),
+                ParamInfo::new(
+                    "rollout",
+                    "uniform",
+                    "uniform, winfirst, avoidloss, capture:<eps> or eval:<eps>",
+                ),
+                ParamInfo::new(
+                    "rollout_plies",
+                    "none",
+                    "Rollouts longer than this are evaluated",
+                ),
+                ParamInfo::new(
+                    "seed",
+                    "none",
+                    "Makes the single thread search reproducible",
+                ),
+            ],
+            build: build_mcts,
+        });
+
+        registry.register(AgentKind {
+            name: "solver",
+            description: "Proof-number search of the forced wins, AlphaBeta plays the other moves",
+            params: vec![
+                ParamInfo::new("nodes", "200000", "Maximal amount of nodes of the proof"),
+                ParamInfo::new("plies", "15", "Maximal length of the proven win"),
+                ParamInfo::new(
+                    "depth",
+                    "6",
+                    "Maximal depth of AlphaBeta, it is not searched",
+                ),
+                ParamInfo::new("time", "1s", "Search time of AlphaBeta"),
+                ParamInfo::new("threads", "1", "Threads of AlphaBeta"),
+            ],
+            build: |spec| {
+                let default = ProofNumberSearch::default();
+                Ok(Box::new(SolverAgent {
+                    search: ProofNumberSearch {
+                        max_nodes: spec.parse_or("nodes", default.max_nodes)?,
+                        max_plies: spec.parse_or("plies", default.max_plies)?,
+                    },
+                    fallback: alpha_beta_from_spec(spec, Evaluation)?,
+                }))
+            },
+        });
+
+        registry.register(AgentKind {
+            name: "engine",
+            description: "External program which speaks the engine protocol",
+            params: vec![
+                ParamInfo::new("cmd", "none", "Program of the engine"),
+                ParamInfo::new(
+                    "args",
+                    "none",
+                    "Arguments of the program separated by spaces",
+                ),
+                ParamInfo::new(
+                    "agent",
+                    "none",
+                    "Agent of the engine, its parameters are separated by ;",
+                ),
+                ParamInfo::new("time", "1s", "Search time of a move"),
+                ParamInfo::new("depth", "none", "Search depth of a move"),
+                ParamInfo::new("nodes", "none", "Playouts or nodes of a move"),
+                ParamInfo::new(
+                    "timeout",
+                    "5s",
+                    "Time over the search time after which the engine loses",
+                ),
+            ],
+            build: |spec| {
+                let mut engine = ExternalEngine::new(spec.require("cmd")?);
+                if let Some(args) = spec.get("args") {
+                    engine.args = args.split_whitespace().map(str::to_owned).collect();
+                }
+                engine.agent = spec.get("agent").map(|agent| agent.replace(';', ","));
+                engine.search_time = spec.duration_or("time", engine.search_time)?;
+                engine.max_depth = spec.parse_opt("depth")?;
+                engine.max_nodes = spec.parse_opt("nodes")?;
+                engine.timeout = spec.duration_or("timeout", engine.timeout)?;
+                Ok(Box::new(engine))
+            },
+        });
+
+        registry.register(AgentKind {
+            name: "skill",
+            description: "Agent of the skill level from 1 to 10 for the human players",
+            params: vec![
+                ParamInfo::new("level", "5", "Level from 1 to 10"),
+                ParamInfo::new("seed", "none", "Makes the moves reproducible"),
+            ],
+            build: |spec| {
+                let level = spec.parse_or("level", SkillLevel::default().level())?;
+                if !(SkillLevel::MIN..=SkillLevel::MAX).contains(&level) {
+                    return Err(invalid_value(
+                        "level",
+                        &level.to_string(),
+                        format!("level goes from {} to {}", SkillLevel::MIN, SkillLevel::MAX),
+                    ));
+                }
+                Ok(Box::new(SkilledAgent {
+                    level: SkillLevel::new(level),
+                    seed: spec.parse_opt("seed")?,
+                }))
+            },
+        });
+
+        registry.register(AgentKind {
+            name: "book",
+            description: "Plays the moves of the opening book, then the inner agent",
+            params: vec![
+                ParamInfo::new("file", "none", "Opening book file"),
+                ParamInfo::new(
+                    "agent",
+                    "alphabeta",
+                    "Inner agent of the built-in kinds, its parameters are separated by ;",
+                ),
+                ParamInfo::new(
+                    "variety",
+                    "1",
+                    "0 plays the best book move, bigger is more varied",
+                ),
+                ParamInfo::new("seed", "none", "Makes the book moves reproducible"),
+            ],
+            build: |spec| {
+                let inner = spec.get("agent").unwrap_or("alphabeta").replace(';', ",");
+                let inner = AgentRegistry::default().build(&inner)?;
+                let file = spec.require("file")?;
+                let agent = BookAgent::from_file(file, inner)
+                    .map_err(|e| SpecError::Build(format!("{}: {}", file, e)))?;
+                Ok(Box::new(BookAgent {
+                    variety: spec.parse_or("variety", agent.variety)?,
+                    seed: spec.parse_opt("seed")?,
+                    ..agent
+                }))
+            },
+        });
+
+        registry
+    }
+}
+
+/// Parameters of the AlphaBeta agent, so the crates with other evaluations can extend them
+pub fn alpha_beta_params() -> Vec<ParamInfo> {
+    vec![
+        ParamInfo::new("depth", "6", "Maximal search depth, it is not searched"),
+        ParamInfo::new("time", "1s", "Search time"),
+        ParamInfo::new("threads", "1", "Threads of the Lazy SMP search"),
+        ParamInfo::new("eval", "simple", "Evaluation: simple, weighted or nnue"),
+        ParamInfo::new(
+            "file",
+            "none",
+            "JSON weights of the weighted evaluation or the NNUE network",
+        ),
+    ]
+}
+
+/// Creates AlphaBeta with the search parameters of the specification and the given evaluation
+pub fn alpha_beta_agent<E>(spec: &AgentSpec, evaluator: E) -> Result<Box<dyn Agent>, SpecError>
+where
+    E: Evaluator + Serialize + 'static,
+{
+    Ok(Box::new(alpha_beta_from_spec(spec, evaluator)?))
+}
+
+fn alpha_beta_from_spec<E>(spec: &AgentSpec, evaluator: E) -> Result<AlphaBeta<E>, SpecError>
+where
+    E: Evaluator,
+{
+    let default = AlphaBeta::default();
+    let max_depth = spec.parse_or("depth", default.max_depth)?;
+    // The maximal depth is exclusive, so at least one ply must be searched
+    if max_depth < 2 {
+        return Err(invalid_value(
+            "depth",
+            &max_depth.to_string(),
+            "depth must be at least 2",
+        ));
+    }
+
+    Ok(AlphaBeta {
+        max_depth,
+        search_time: spec.duration_or("time", default.search_time)?,
+        threads: spec.parse_or("threads", default.threads)?.max(1),
+        ..AlphaBeta::with_evaluator(evaluator)
+    })
+}
+
+pub fn build_alpha_beta(spec: &AgentSpec) -> Result<Box<dyn Agent>, SpecError> {
+    match spec.get("eval").unwrap_or("simple") {
+        "simple" => alpha_beta_agent(spec, Evaluation),
+        "weighted" => {
+            let evaluation = match spec.get("file") {
+                Some(path) => WeightedEvaluation::from_file(path)
+                    .map_err(|e| SpecError::Build(format!("{}: {}", path, e)))?,
+                None => WeightedEvaluation::default(),
+            };
+            alpha_beta_agent(spec, evaluation)
+        }
+        "nnue" => {
+            let path = spec.require("file")?;
+            let evaluation = NnueEvaluation::from_file(path)
+                .map_err(|e| SpecError::Build(format!("{}: {}", path, e)))?;
+            alpha_beta_agent(spec, evaluation)
+        }
+        eval => Err(invalid_value(
+            "eval",
+            eval,
+            "evaluation must be simple, weighted or nnue",
+        )),
+    }
+}
+
+fn build_mcts(spec: &AgentSpec) -> Result<Box<dyn Agent>, SpecError> {
+    let default = Mcts::default();
+    let parallel_mode = match spec.get("parallel") {
+        None | Some("tree") => ParallelMode::Tree,
+        Some("root") => ParallelMode::Root,
+        Some(value) => return Err(invalid_value("parallel", value, "expected tree or root")),
+    };
+    let rave = match spec.get("rave") {
+        None | Some("none") => None,
+        Some(value) => Some(parse_rave(value).map_err(|e| invalid_value("rave", value, e))?),
+    };
+    let rollout = match spec.get("rollout") {
+        Some(value) => parse_rollout(value).map_err(|e| invalid_value("rollout", value, e))?,
+        None => Rollout::default(),
+    };
+
+    Ok(Box::new(Mcts {
+        search_time: spec.duration_or("time", default.search_time)?,
+        exploration_c: spec.parse_or("c", default.exploration_c)?,
+        max_playouts: spec.parse_or("playouts", default.max_playouts)?,
+        min_node_visits: spec.parse_or("visits", default.min_node_visits)?,
+        reuse_tree: spec.parse_or("reuse", default.reuse_tree)?,
+        threads: spec.parse_or("threads", default.threads)?.max(1),
+        parallel_mode,
+        rave,
+        rollout,
+        max_rollout_plies: spec.parse_opt("rollout_plies")?,
+        seed: spec.parse_opt("seed")?,
+        ..default
+    }))
+}
+
+/// Splits `name:number` into the name and the parsed number
+fn named_number(value: &str) -> Result<(&str, Option<f32>), String> {
+    match value.split_once(':') {
+        Some((name, number)) => Ok((
+            name,
+            Some(number.parse::<f32>().map_err(|e| e.to_string())?),
+        )),
+        None => Ok((value, None)),
+    }
+}
+
+fn parse_rave(value: &str) -> Result<RaveSchedule, String> {
+    match named_number(value)? {
+        ("eq", Some(k)) => Ok(RaveSchedule::Equivalence(k)),
+        ("mse", Some(bias)) => Ok(RaveSchedule::MinimumMse(bias)),
+        _ => Err("expected eq:<k> or mse:<bias>".to_owned()),
+    }
+}
+
+pub(crate) fn format_rave(rave: &RaveSchedule) -> String {
+    match rave {
+        RaveSchedule::Equivalence(k) => format!("eq:{}", k),
+        RaveSchedule::MinimumMse(bias) => format!("mse:{}", bias),
+    }
+}
+
+fn parse_rollout(value: &str) -> Result<Rollout, String> {
+    match named_number(value)? {
+        ("uniform", None) => Ok(Rollout::Uniform),
+        ("winfirst", None) => Ok(Rollout::WinFirst),
+        ("avoidloss", None) => Ok(Rollout::AvoidLoss),
+        ("capture", Some(epsilon)) => Ok(Rollout::CaptureBiased { epsilon }),
+        ("eval", Some(epsilon)) => Ok(Rollout::EvaluationGuided { epsilon }),
+        _ => Err("expected uniform, winfirst, avoidloss, capture:<eps> or eval:<eps>".to_owned()),
+    }
+}
+
+pub(crate) fn format_rollout(rollout: &Rollout) -> String {
+    match rollout {
+        Rollout::Uniform => "uniform".to_owned(),
+        Rollout::WinFirst => "winfirst".to_owned(),
+        Rollout::AvoidLoss => "avoidloss".to_owned(),
+        Rollout::CaptureBiased { epsilon } => format!("capture:{}", epsilon),
+        Rollout::EvaluationGuided { epsilon } => format!("eval:{}", epsilon),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_parsing() {
+        let spec: AgentSpec = " AlphaBeta: depth=8 , time=1s ".parse().unwrap();
+        assert_eq!(spec.kind, "alphabeta");
+        assert_eq!(spec.get("depth"), Some("8"));
+        assert_eq!(spec.get("time"), Some("1s"));
+        assert_eq!(spec.to_string(), "alphabeta:depth=8,time=1s");
+
+        assert_eq!("random".parse::<AgentSpec>().unwrap().params.len(), 0);
+        assert_eq!("".parse::<AgentSpec>(), Err(SpecError::Empty));
+        assert_eq!(
+            "mcts:time".parse::<AgentSpec>(),
+            Err(SpecError::InvalidParam("time".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(parse_duration("400ms"), Ok(Duration::from_millis(400)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("0.3s"), Ok(Duration::from_millis(300)));
+        assert!(parse_duration("400").is_err());
+
+        for duration in [
+            Duration::from_secs(3),
+            Duration::from_millis(400),
+            Duration::from_micros(1500),
+        ] {
+            assert_eq!(parse_duration(&format_duration(duration)), Ok(duration));
+        }
+    }
+
+    #[test]
+    fn test_agents_are_written_back_to_their_specs() {
+        let registry = AgentRegistry::default();
+        for spec in [
+            "random:seed=3",
+            "human",
+            "alphabeta:depth=8,time=1s,threads=2,eval=simple",
+            "alphabeta:depth=4,time=400ms,threads=1,eval=weighted",
+            "mcts:time=400ms,c=1.4,playouts=1600,visits=5,reuse=true,threads=1,parallel=root,\
+            rave=eq:1000,rollout=capture:0.1,rollout_plies=20,seed=7",
+            "skill:level=3",
+            "solver:nodes=5000,plies=9,depth=4,time=400ms,threads=1",
+            "engine:cmd=./onitama_engine,args=--quiet,agent=mcts:time=1s;threads=2,time=400ms,\
+            depth=5,nodes=1000,timeout=2s",
+        ] {
+            let agent = registry.build(spec).unwrap();
+            let written = agent.spec().expect("Built-in agents have specifications");
+            assert_eq!(written, spec.parse::<AgentSpec>().unwrap());
+            assert_eq!(registry.build_spec(&written).unwrap().spec(), Some(written));
+        }
+    }
+
+    #[test]
+    fn test_engine_limits_change_id() {
+        let registry = AgentRegistry::default();
+        let id = |spec: &str| registry.build(spec).unwrap().id();
+
+        let engine = id("engine:cmd=./onitama_engine,time=1s");
+        assert_eq!(engine, id("engine:cmd=./onitama_engine,time=1s"));
+        assert_ne!(engine, id("engine:cmd=./onitama_engine,time=2s"));
+        assert_ne!(engine, id("engine:cmd=./onitama_engine,time=1s,depth=5"));
+        assert_ne!(engine, id("engine:cmd=./onitama_engine,time=1s,nodes=1000"));
+    }
+
+    #[test]
+    fn test_configs_have_different_ids() {
+        let registry = AgentRegistry::default();
+        let id = |spec: &str| registry.build(spec).unwrap().id();
+
+        // The sums of these fields are the same
+        assert_ne!(id("alphabeta:depth=6,threads=2"), id("alphabeta:depth=7"));
+        assert_ne!(id("alphabeta"), id("alphabeta:eval=weighted"));
+        assert_ne!(id("mcts:playouts=5001"), id("mcts:visits=6"));
+        assert_ne!(id("mcts"), id("mcts:rave=eq:1000"));
+        assert_ne!(id("mcts:c=1.4"), id("mcts:c=1.5"));
+        assert_ne!(id("solver:nodes=200001"), id("solver:plies=16"));
+        assert_ne!(id("solver:depth=7"), id("solver:plies=16"));
+        assert_eq!(id("mcts:seed=1"), id("mcts:seed=2"));
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        let registry = AgentRegistry::default();
+        let error = |spec: &str| registry.build(spec).err().unwrap();
+
+        assert_eq!(
+            error("minimax"),
+            SpecError::UnknownKind("minimax".to_owned())
+        );
+        assert_eq!(
+            error("random:depth=2"),
+            SpecError::UnknownParam {
+                kind: "random".to_owned(),
+                param: "depth".to_owned()
+            }
+        );
+        assert!(matches!(
+            error("alphabeta:depth=1"),
+            SpecError::InvalidValue { .. }
+        ));
+        assert!(matches!(
+            error("mcts:time=fast"),
+            SpecError::InvalidValue { .. }
+        ));
+        assert!(matches!(
+            error("solver:depth=1"),
+            SpecError::InvalidValue { .. }
+        ));
+        assert_eq!(
+            error("engine:time=1s"),
+            SpecError::MissingParam {
+                kind: "engine".to_owned(),
+                param: "cmd".to_owned()
+            }
+        );
+        assert!(matches!(
+            error("skill:level=11"),
+            SpecError::InvalidValue { .. }
+        ));
+        assert_eq!(
+            error("alphabeta:eval=nnue"),
+            SpecError::MissingParam {
+                kind: "alphabeta".to_owned(),
+                param: "file".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn test_seeded_specs() {
+        let registry = AgentRegistry::default();
+        let seeded = |spec: &str| registry.seeded(&spec.parse().unwrap(), Some(5)).to_string();
+
+        assert_eq!(seeded("random"), "random:seed=5");
+        assert_eq!(seeded("mcts:seed=2"), "mcts:seed=2");
+        assert_eq!(seeded("alphabeta:depth=4"), "alphabeta:depth=4");
+        assert_eq!(
+            registry
+                .seeded(&"random".parse().unwrap(), None)
+                .to_string(),
+            "random"
+        );
+    }
+
+    #[test]
+    fn test_help_lists_all_kinds() {
+        let registry = AgentRegistry::default();
+        let help = registry.help();
+        for kind in registry.kinds() {
+            assert!(help.contains(kind.name));
+            for param in kind.params.iter() {
+                assert!(help.contains(&format!("{}={}", param.name, param.default)));
+            }
+        }
+    }
+}
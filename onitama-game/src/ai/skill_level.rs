@@ -0,0 +1,248 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::seeded_rng,
+    game::{done_move::DoneMove, game_state::GameState, player_color::PlayerColor},
+};
+
+use super::{agent::Agent, alpha_beta::AlphaBeta, registry::AgentSpec};
+
+/// How the agent of some level chooses the move
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SkillConfig {
+    /// Depth of the search which scores every root move
+    pub depth: u8,
+    /// Moves are sampled with the probability proportional to exp(score / temperature).
+    /// The score of one pawn is about 10, so a high temperature makes the moves close to random.
+    /// Zero temperature always chooses the best move
+    pub temperature: f64,
+    /// Chance to make a completely random move
+    pub blunder_chance: f64,
+}
+
+/// Configurations of the levels from the weakest to the strongest.
+/// `cargo run --release --bin skill_calibration` in alphazero-training measures their Elo ratings
+const LEVELS: [SkillConfig; 10] = [
+    SkillConfig {
+        depth: 1,
+        temperature: 40.,
+        blunder_chance: 0.3,
+    },
+    SkillConfig {
+        depth: 1,
+        temperature: 10.,
+        blunder_chance: 0.15,
+    },
+    SkillConfig {
+        depth: 2,
+        temperature: 10.,
+        blunder_chance: 0.15,
+    },
+    SkillConfig {
+        depth: 2,
+        temperature: 10.,
+        blunder_chance: 0.1,
+    },
+    SkillConfig {
+        depth: 3,
+        temperature: 8.,
+        blunder_chance: 0.07,
+    },
+    SkillConfig {
+        depth: 3,
+        temperature: 5.,
+        blunder_chance: 0.05,
+    },
+    SkillConfig {
+        depth: 4,
+        temperature: 3.,
+        blunder_chance: 0.03,
+    },
+    SkillConfig {
+        depth: 4,
+        temperature: 1.,
+        blunder_chance: 0.01,
+    },
+    SkillConfig {
+        depth: 5,
+        temperature: 0.,
+        blunder_chance: 0.,
+    },
+    SkillConfig {
+        depth: 6,
+        temperature: 0.,
+        blunder_chance: 0.,
+    },
+];
+
+/// Difficulty of the agent for the human players from 1 to 10
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SkillLevel(u8);
+
+impl SkillLevel {
+    pub const MIN: u8 = 1;
+    pub const MAX: u8 = 10;
+
+    /// The level is clamped to the allowed range
+    pub fn new(level: u8) -> Self {
+        Self(level.clamp(Self::MIN, Self::MAX))
+    }
+
+    #[inline]
+    pub fn level(&self) -> u8 {
+        self.0
+    }
+
+    pub fn config(&self) -> SkillConfig {
+        LEVELS[(self.0 - Self::MIN) as usize]
+    }
+
+    pub fn all() -> impl Iterator<Item = SkillLevel> {
+        (Self::MIN..=Self::MAX).map(SkillLevel)
+    }
+}
+
+impl Default for SkillLevel {
+    fn default() -> Self {
+        Self(5)
+    }
+}
+
+/// Agent which plays on the given level. Weaker levels search less deep,
+/// choose the moves by chance among the good ones and sometimes make random moves
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SkilledAgent {
+    pub level: SkillLevel,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl SkilledAgent {
+    pub fn new(level: SkillLevel) -> Self {
+        Self { level, seed: None }
+    }
+}
+
+impl Agent for SkilledAgent {
+    fn generate_move(&self, game_state: &GameState) -> (DoneMove, f64) {
+        let config = self.level.config();
+        let player_color = game_state.curr_player_color;
+        let mut rng = seeded_rng(self.seed, game_state.state.zobrist_hash(player_color));
+
+        let sign = match player_color {
+            PlayerColor::Red => 1.,
+            PlayerColor::Blue => -1.,
+        };
+        let scored = AlphaBeta::default()
+            .score_moves(game_state, config.depth)
+            .into_iter()
+            .map(|(mov, score)| (mov, sign * score as f64))
+            .collect::<Vec<_>>();
+        assert!(!scored.is_empty(), "Skilled agent must produce a move!");
+
+        let best_idx = (0..scored.len())
+            .max_by(|&a, &b| scored[a].1.total_cmp(&scored[b].1))
+            .expect("There must be at least one move");
+
+        let idx = if rng.gen::<f64>() < config.blunder_chance {
+            rng.gen_range(0..scored.len())
+        } else if config.temperature <= 0. {
+            best_idx
+        } else {
+            // Softmax sampling. The best score is subtracted, so the exponent does not overflow
+            let best_score = scored[best_idx].1;
+            let weights = scored
+                .iter()
+                .map(|(_, score)| ((score - best_score) / config.temperature).exp())
+                .collect::<Vec<_>>();
+
+            let mut target = rng.gen::<f64>() * weights.iter().sum::<f64>();
+            weights
+                .iter()
+                .position(|w| {
+                    target -= w;
+                    target <= 0.
+                })
+                .unwrap_or(best_idx)
+        };
+
+        (scored[idx].0, sign * scored[idx].1)
+    }
+
+    fn name(&self) -> &'static str {
+        "Skilled AI"
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Agent> {
+        Box::new(self.clone())
+    }
+
+    fn id(&self) -> u64 {
+        self.level.level() as u64
+    }
+
+    fn spec(&self) -> Option<AgentSpec> {
+        let spec = AgentSpec::new("skill").with("level", self.level.level());
+        Some(match self.seed {
+            Some(seed) => spec.with("seed", seed),
+            None => spec,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{deck::Deck, move_result::MoveResult};
+
+    use super::*;
+
+    #[test]
+    fn test_level_is_clamped() {
+        assert_eq!(SkillLevel::new(0).level(), SkillLevel::MIN);
+        assert_eq!(SkillLevel::new(42).level(), SkillLevel::MAX);
+        assert_eq!(SkillLevel::all().count(), 10);
+    }
+
+    #[test]
+    fn test_levels_get_stronger() {
+        let configs = SkillLevel::all().map(|l| l.config()).collect::<Vec<_>>();
+        for pair in configs.windows(2) {
+            assert!(pair[0].depth <= pair[1].depth);
+            assert!(pair[0].temperature >= pair[1].temperature);
+            assert!(pair[0].blunder_chance >= pair[1].blunder_chance);
+        }
+    }
+
+    #[test]
+    fn test_seeded_agent_is_reproducible() {
+        let game_state = GameState::with_deck(Deck::from_seed(3));
+        let agent = SkilledAgent {
+            level: SkillLevel::new(1),
+            seed: Some(11),
+        };
+
+        let first = agent.generate_move(&game_state);
+        let second = agent.generate_move(&game_state);
+        assert_eq!(first.0, second.0);
+    }
+
+    #[test]
+    fn test_best_level_takes_the_win() {
+        let mut game_state = GameState::with_deck(Deck::from_seed(5));
+        let player_color = game_state.curr_player_color;
+        let (_, mov) = game_state.state.generate_all_legal_moves(player_color)[0];
+
+        // Put the enemy king where the first legal move goes
+        let bit = 1 << (31 - mov.to);
+        let enemy = player_color.enemy() as usize;
+        game_state.state.pawns[enemy] &= !bit;
+        game_state.state.kings[enemy] = bit;
+
+        let agent = SkilledAgent::new(SkillLevel::new(SkillLevel::MAX));
+        let (done_move, _) = agent.generate_move(&game_state);
+        let result = game_state.progress(done_move);
+
+        assert!(matches!(result, MoveResult::RedWin | MoveResult::BlueWin));
+    }
+}
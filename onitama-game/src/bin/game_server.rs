@@ -0,0 +1,48 @@
+use std::{io, net::TcpListener, sync::Arc};
+
+use onitama_game::game_server::{server_registry, GameServer};
+
+const USAGE: &str = "Usage: game_server [--allow-origin ORIGIN] [ADDRESS]
+
+ADDRESS is 127.0.0.1:8080 by default. Only the pages of ORIGIN, e.g. http://localhost:3000,
+may call the API from the browser, without it only the same origin and the scripts can";
+
+/// HTTP server of the games, see `onitama_game::game_server` for the API
+fn main() -> io::Result<()> {
+    let mut address = "127.0.0.1:8080".to_owned();
+    let mut origin = None;
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--allow-origin" => match iter.next() {
+                Some(value) => origin = Some(value),
+                None => {
+                    eprintln!("Missing value for {}", arg);
+                    std::process::exit(1);
+                }
+            },
+            "--help" | "-h" => {
+                eprintln!("{}", USAGE);
+                std::process::exit(1);
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("Unknown option {}\n\n{}", arg, USAGE);
+                std::process::exit(1);
+            }
+            _ => address = arg,
+        }
+    }
+
+    let listener = TcpListener::bind(address)?;
+    println!(
+        "Game server is listening on http://{}",
+        listener.local_addr()?
+    );
+
+    let mut server = GameServer::new(server_registry());
+    if let Some(origin) = origin {
+        server = server.allow_origin(origin);
+    }
+    Arc::new(server).serve(listener)
+}
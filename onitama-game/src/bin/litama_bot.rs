@@ -0,0 +1,49 @@
+use onitama_game::{
+    ai::registry::AgentRegistry,
+    litama::{LitamaClient, LitamaPlayer},
+};
+
+const USAGE: &str = "Usage: litama_bot <url> <agent> [match id]\n\n\
+    Creates the match, or joins it if the id is given, and plays it with the agent.\n\
+    Only ws:// URLs are supported. A wss:// server needs a local TLS proxy, e.g.\n    \
+    socat TCP-LISTEN:5001,fork,reuseaddr OPENSSL:<host>:443\n\
+    and the bot connects to ws://127.0.0.1:5001";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.len() < 2 || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let registry = AgentRegistry::default();
+    let agent = match registry.build(&args[1]) {
+        Ok(agent) => agent,
+        Err(e) => {
+            eprintln!("{}: {}", args[1], e);
+            std::process::exit(1);
+        }
+    };
+    let username = agent.name().replace(' ', "_");
+
+    let result = LitamaClient::connect(&args[0]).and_then(|mut client| {
+        let token = match args.get(2) {
+            Some(match_id) => client.join(match_id, &username)?,
+            None => {
+                let token = client.create(&username)?;
+                println!("Created match {}", token.match_id);
+                token
+            }
+        };
+        println!("Playing {:?}", token.color);
+        LitamaPlayer::new(agent).play(&mut client, &token)
+    });
+
+    match result {
+        Ok(winner) => println!("{:?} has won", winner),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use std::io;
+
+use onitama_game::litama::server::LitamaServer;
+
+/// Local Litama server for the matches without the network
+fn main() -> io::Result<()> {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:5000".to_owned());
+    let server = LitamaServer::start(address, None)?;
+    println!("Litama server is listening on {}", server.url());
+
+    loop {
+        std::thread::park();
+    }
+}
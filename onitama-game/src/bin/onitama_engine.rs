@@ -0,0 +1,9 @@
+use std::io;
+
+use onitama_game::{ai::registry::AgentRegistry, protocol::engine::Engine};
+
+/// Engine of the text protocol, see `onitama_game::protocol` for the commands
+fn main() -> io::Result<()> {
+    let mut engine = Engine::new(AgentRegistry::default());
+    engine.run(io::stdin().lock(), io::stdout())
+}
@@ -0,0 +1,55 @@
+use std::io::{self, BufRead, Write};
+
+use onitama_game::{
+    game::{deck::Deck, done_move::DoneMove, game_state::GameState, notation::format_move},
+    protocol::{Command, Response, StartPosition},
+};
+
+/// Tiny engine for the tests of the external engine agent. It plays the first legal move,
+/// unless the argument makes it misbehave on `go`:
+/// `crash` exits, `hang` never answers and `illegal` answers with an illegal move
+fn main() -> io::Result<()> {
+    let mode = std::env::args().nth(1).unwrap_or_default();
+    let mut game_state = GameState::with_deck(Deck::from_seed(0));
+    let mut output = io::stdout();
+
+    for line in io::stdin().lock().lines() {
+        let response = match line?.parse::<Command>() {
+            Ok(Command::Onitama) => Response::OnitamaOk,
+            Ok(Command::IsReady) => Response::ReadyOk,
+            Ok(Command::Position {
+                start: StartPosition::Position(state, player_color),
+                ..
+            }) => {
+                game_state = GameState::from_state(state, player_color);
+                continue;
+            }
+            Ok(Command::Go(_)) => match mode.as_str() {
+                "crash" => std::process::exit(1),
+                "hang" => continue,
+                "illegal" => Response::BestMove(Some("tiger:a1a1".to_owned())),
+                _ => {
+                    let best_move = game_state
+                        .state
+                        .generate_all_legal_moves(game_state.curr_player_color)
+                        .first()
+                        .map(|&(card_idx, mov)| {
+                            let done_move = DoneMove {
+                                mov,
+                                used_card_idx: card_idx,
+                            };
+                            format_move(&game_state.state, &done_move)
+                        });
+                    Response::BestMove(best_move)
+                }
+            },
+            Ok(Command::Quit) => break,
+            Ok(_) => continue,
+            Err(e) => Response::Message(e.to_string()),
+        };
+        writeln!(output, "{}", response)?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use rand::{rngs::SmallRng, SeedableRng};
+
 #[inline]
 pub const fn get_bit(x: u32, n: usize) -> u32 {
     x >> (31 - n) & 1
@@ -74,6 +76,56 @@ pub fn get_bit_array<T: Copy + Default + From<u32>>(value: u32) -> [T; 25] {
     bit_arr
 }
 
+/// Random generator which gives the same numbers for the same seed and salt.
+/// The salt, e.g. a position hash, gives every position its own sequence.
+/// Without a seed the generator is seeded from the system entropy
+pub fn seeded_rng(seed: Option<u64>, salt: u64) -> SmallRng {
+    match seed {
+        Some(seed) => SmallRng::seed_from_u64(seed ^ salt),
+        None => SmallRng::from_entropy(),
+    }
+}
+
+/// Reads little-endian numbers from the binary files, e.g. the opening book.
+/// Every read returns nothing if the bytes are over
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub(crate) fn take(&mut self, amount: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos + amount)?;
+        self.pos += amount;
+        Some(slice)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Amount of bytes which are not read yet
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::common::{from_2d_to_bitboard, get_bit};
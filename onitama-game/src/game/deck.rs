@@ -1,6 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
-use rand::seq::SliceRandom;
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
 use crate::common::get_bit;
@@ -17,7 +17,7 @@ pub const BLUE_CARD1: usize = 2;
 pub const BLUE_CARD2: usize = 3;
 pub const NEUTRAL: usize = 4;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Deck {
     pub cards: [Card; 5],
 }
@@ -27,6 +27,22 @@ impl Deck {
         Deck { cards: deck }
     }
 
+    /// Random deck which is always the same for the same seed
+    pub fn from_seed(seed: u64) -> Self {
+        Self::random(&mut SmallRng::seed_from_u64(seed))
+    }
+
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let mut shuffled = ORIGINAL_CARDS;
+        shuffled.shuffle(rng);
+
+        Self {
+            cards: shuffled[0..5]
+                .try_into()
+                .expect("Deck must have 5 random cards"),
+        }
+    }
+
     #[inline]
     /// Return if the card should be mirrored. Mirroring is default to the blue player
     pub fn is_mirrored(&self, card: &Card) -> Option<bool> {
@@ -138,15 +154,7 @@ impl Deck {
 
 impl Default for Deck {
     fn default() -> Self {
-        let mut rng = rand::thread_rng();
-        let mut shuffled = ORIGINAL_CARDS.clone();
-        shuffled.shuffle(&mut rng);
-
-        Self {
-            cards: shuffled[0..5]
-                .try_into()
-                .expect("Deck must have 5 random cards"),
-        }
+        Self::random(&mut rand::thread_rng())
     }
 }
 
@@ -0,0 +1,396 @@
+use std::{ffi::OsStr, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    card::Card,
+    deck::Deck,
+    done_move::DoneMove,
+    game_state::GameState,
+    move_result::MoveResult,
+    notation::parse_position,
+    player_color::PlayerColor,
+    r#move::{Move, NotationError},
+    state::State,
+};
+
+#[derive(Debug)]
+pub enum RecordError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// The move with the given ply cannot be made in the game
+    IllegalMove(usize),
+    Empty,
+    /// The starting position cannot be read
+    Position(NotationError),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Io(e) => write!(f, "Cannot read the game record: {}", e),
+            RecordError::Parse(e) => write!(f, "Cannot parse the game record: {}", e),
+            RecordError::IllegalMove(ply) => write!(f, "Move at ply {} is illegal", ply),
+            RecordError::Empty => write!(f, "Game record does not have any moves"),
+            RecordError::Position(e) => write!(f, "Incorrect starting position: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<io::Error> for RecordError {
+    fn from(e: io::Error) -> Self {
+        RecordError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for RecordError {
+    fn from(e: serde_json::Error) -> Self {
+        RecordError::Parse(e)
+    }
+}
+
+/// Move which does not depend on the order of the cards in the player's hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedMove {
+    /// Index of the used card, see `Card::index`
+    pub card: usize,
+    pub mov: Move,
+}
+
+/// Position before the move together with the move made from it
+#[derive(Debug, Clone)]
+pub struct RecordedPosition {
+    pub state: State,
+    pub player_color: PlayerColor,
+    /// Is empty for the last position of the game
+    pub done_move: Option<DoneMove>,
+}
+
+/// Finished or unfinished game which can be replayed from the starting deck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub deck: Deck,
+    pub moves: Vec<RecordedMove>,
+    /// Either a win of some player or `InProgress` if the game was not finished
+    pub result: MoveResult,
+    /// Seed of the game loop and the agents if the game was played with one.
+    /// Replaying it with the same agents and playouts limits gives the same game
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Position in the notation if the game has not started from the starting position
+    /// of the deck, e.g. it was played from an opening suite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+}
+
+impl GameRecord {
+    pub fn new(deck: Deck) -> Self {
+        Self {
+            deck,
+            moves: vec![],
+            result: MoveResult::InProgress,
+            seed: None,
+            position: None,
+        }
+    }
+
+    /// Game before the first move
+    pub fn start(&self) -> Result<GameState, RecordError> {
+        match &self.position {
+            Some(position) => {
+                let (state, player_color) =
+                    parse_position(position).map_err(RecordError::Position)?;
+                Ok(GameState::from_state(state, player_color))
+            }
+            None => Ok(GameState::with_deck(self.deck.clone())),
+        }
+    }
+
+    /// Saves the move made with the card
+    pub fn push(&mut self, card: &Card, mov: Move, move_result: MoveResult) {
+        self.moves.push(RecordedMove {
+            card: card.index,
+            mov,
+        });
+
+        if move_result.is_win() {
+            self.result = move_result;
+        }
+    }
+
+    pub fn winner(&self) -> Option<PlayerColor> {
+        match self.result {
+            MoveResult::RedWin => Some(PlayerColor::Red),
+            MoveResult::BlueWin => Some(PlayerColor::Blue),
+            _ => None,
+        }
+    }
+
+    /// Replays the game and returns every position including the last one.
+    /// A move after the end of the game is illegal
+    pub fn positions(&self) -> Result<Vec<RecordedPosition>, RecordError> {
+        let mut game_state = self.start()?;
+        let mut positions = Vec::with_capacity(self.moves.len() + 1);
+        let mut is_over = false;
+
+        for (ply, recorded_move) in self.moves.iter().enumerate() {
+            let done_move = Self::find_move(&game_state, recorded_move)
+                .filter(|_| !is_over)
+                .ok_or(RecordError::IllegalMove(ply + 1))?;
+
+            positions.push(RecordedPosition {
+                state: game_state.state.clone(),
+                player_color: game_state.curr_player_color,
+                done_move: Some(done_move),
+            });
+
+            is_over = game_state.progress(done_move).is_win();
+        }
+
+        positions.push(RecordedPosition {
+            state: game_state.state,
+            player_color: game_state.curr_player_color,
+            done_move: None,
+        });
+
+        Ok(positions)
+    }
+
+    /// Finds the legal move which uses the recorded card
+    pub(crate) fn find_move(
+        game_state: &GameState,
+        recorded_move: &RecordedMove,
+    ) -> Option<DoneMove> {
+        game_state
+            .state
+            .generate_all_legal_moves(game_state.curr_player_color)
+            .into_iter()
+            .find(|(card_idx, mov)| {
+                *mov == recorded_move.mov
+                    && game_state.state.deck.get_card(*card_idx).index == recorded_move.card
+            })
+            .map(|(card_idx, mov)| DoneMove {
+                mov,
+                used_card_idx: card_idx,
+            })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// Parses either a game record or a move history saved by the GUI
+    pub fn from_json(json: &str) -> Result<Self, RecordError> {
+        match serde_json::from_str::<GameRecord>(json) {
+            Ok(record) => Ok(record),
+            Err(_) => Self::from_move_history(serde_json::from_str(json)?),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RecordError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Loads all game records from the directory and its subdirectories.
+    /// Files which are not game records, e.g. tournament results, are skipped
+    pub fn load_dir(path: impl AsRef<Path>) -> io::Result<Vec<Self>> {
+        let mut records = vec![];
+
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                records.extend(Self::load_dir(&path)?);
+            } else if path.extension() == Some(OsStr::new("json")) {
+                if let Ok(record) = Self::from_file(&path) {
+                    records.push(record);
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// GUI saves positions after each move, so the starting deck must be restored.
+    /// The neutral card before the first move is now in the hand of the first player,
+    /// the order of the cards in a hand does not matter
+    fn from_move_history(move_history: SavedMoveHistory) -> Result<Self, RecordError> {
+        let first = move_history.history.first().ok_or(RecordError::Empty)?;
+
+        let mut deck = first.state.deck.clone();
+        let hand = deck.get_player_cards_idx(first.player_color);
+        let neutral_idx = hand
+            .into_iter()
+            .find(|idx| deck.get_card(*idx).player_color == first.player_color)
+            .unwrap_or(hand[0]);
+        deck.rotate(neutral_idx);
+
+        let mut record = GameRecord::new(deck);
+        let mut game_state = GameState::with_deck(record.deck.clone());
+        let mut is_over = false;
+
+        for (ply, saved_move) in move_history.history.iter().enumerate() {
+            let recorded_move = RecordedMove {
+                card: saved_move.card.index,
+                mov: saved_move.done_move,
+            };
+
+            let done_move = match Self::find_move(&game_state, &recorded_move) {
+                Some(done_move)
+                    if !is_over && game_state.curr_player_color == saved_move.player_color =>
+                {
+                    done_move
+                }
+                _ => return Err(RecordError::IllegalMove(ply + 1)),
+            };
+
+            record.push(&saved_move.card, done_move.mov, saved_move.move_result);
+            is_over = game_state.progress(done_move).is_win();
+
+            if game_state.state.kings != saved_move.state.kings
+                || game_state.state.pawns != saved_move.state.pawns
+            {
+                return Err(RecordError::IllegalMove(ply + 1));
+            }
+        }
+
+        Ok(record)
+    }
+}
+
+/// Part of the GUI move history which is needed to restore the game
+#[derive(Deserialize)]
+struct SavedMoveHistory {
+    history: Vec<SavedMove>,
+}
+
+#[derive(Deserialize)]
+struct SavedMove {
+    /// Position after the move
+    state: State,
+    /// Player who made the move
+    player_color: PlayerColor,
+    done_move: Move,
+    card: Card,
+    move_result: MoveResult,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::game::card::{CRAB, DRAGON, FROG, HORSE, RABBIT, TIGER};
+
+    use super::*;
+
+    /// Position after the move, player who moved, used card, move and its result
+    type PlayedMove = (State, PlayerColor, Card, DoneMove, MoveResult);
+
+    fn play_game(plies: usize) -> (GameRecord, Vec<PlayedMove>) {
+        let deck = Deck::new([DRAGON, FROG, TIGER, RABBIT, HORSE]);
+        let mut game_state = GameState::with_deck(deck.clone());
+        let mut record = GameRecord::new(deck);
+        let mut played = vec![];
+
+        for _ in 0..plies {
+            let player_color = game_state.curr_player_color;
+            let (card_idx, mov) = game_state.state.generate_all_legal_moves(player_color)[0];
+            let done_move = DoneMove {
+                mov,
+                used_card_idx: card_idx,
+            };
+            let card = *game_state.state.deck.get_card(card_idx);
+
+            let move_result = game_state.progress(done_move);
+            record.push(&card, done_move.mov, move_result);
+            played.push((
+                game_state.state.clone(),
+                player_color,
+                card,
+                done_move,
+                move_result,
+            ));
+        }
+
+        (record, played)
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let (record, played) = play_game(4);
+        let record = GameRecord::from_json(&serde_json::to_string(&record).unwrap()).unwrap();
+        let positions = record.positions().unwrap();
+
+        assert_eq!(positions.len(), 5);
+        for (position, (_, player_color, _, done_move, _)) in positions.iter().zip(played.iter()) {
+            assert_eq!(position.player_color, *player_color);
+            assert_eq!(position.done_move, Some(*done_move));
+        }
+        assert_eq!(positions[4].state.pawns, played[3].0.pawns);
+        assert_eq!(record.winner(), None);
+
+        // Red pawn captures the blue king on the fifth ply
+        let (record, _) = play_game(5);
+        assert_eq!(record.winner(), Some(PlayerColor::Red));
+    }
+
+    fn move_history(played: &[PlayedMove]) -> String {
+        let history = played
+            .iter()
+            .map(|(state, player_color, card, done_move, move_result)| {
+                json!({
+                    "state": state,
+                    "player_color": player_color,
+                    "done_move": done_move.mov,
+                    "card": card,
+                    "evaluation": 0.,
+                    "ply": 1,
+                    "move_result": move_result,
+                })
+            })
+            .collect::<Vec<_>>();
+        let move_history = json!({
+            "red_player": { "max_depth": 6 },
+            "blue_player": null,
+            "history": history,
+        });
+        move_history.to_string()
+    }
+
+    #[test]
+    fn test_record_from_move_history() {
+        let (record, played) = play_game(5);
+
+        let restored = GameRecord::from_json(&move_history(&played)).unwrap();
+        assert_eq!(restored.moves, record.moves);
+        assert_eq!(
+            restored.positions().unwrap().last().unwrap().state.kings,
+            played[4].0.kings
+        );
+        assert_eq!(restored.winner(), Some(PlayerColor::Red));
+    }
+
+    #[test]
+    fn test_illegal_record() {
+        let (mut record, _) = play_game(2);
+        record.moves[1].card = CRAB.index;
+
+        assert!(matches!(
+            record.positions(),
+            Err(RecordError::IllegalMove(2))
+        ));
+
+        // Red wins on the fifth ply, so the game cannot go on
+        let (record, played) = play_game(6);
+        assert!(matches!(
+            record.positions(),
+            Err(RecordError::IllegalMove(6))
+        ));
+        assert!(matches!(
+            GameRecord::from_json(&move_history(&played)),
+            Err(RecordError::IllegalMove(6))
+        ));
+    }
+}
@@ -47,6 +47,16 @@ impl GameState {
         }
     }
 
+    /// Game which starts from the given position, e.g. the one read from the notation
+    pub fn from_state(state: State, player_color: PlayerColor) -> Self {
+        Self {
+            state,
+            history: vec![],
+            curr_agent_idx: player_color as usize,
+            curr_player_color: player_color,
+        }
+    }
+
     pub fn clear(&mut self) {
         self.state = match self.history.first() {
             Some(s) => s.clone(),
@@ -1,9 +1,12 @@
 pub mod card;
 pub mod deck;
 pub mod done_move;
+pub mod game_record;
 pub mod game_state;
 pub mod r#move;
 pub mod move_result;
+pub mod notation;
 pub mod piece;
 pub mod player_color;
 pub mod state;
+pub mod zobrist;
@@ -6,8 +6,8 @@ use crate::common::from_2d_to_1d;
 
 use super::piece::PieceKind;
 
-#[derive(Debug, Clone)]
-pub struct NotationError(String);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotationError(pub(crate) String);
 
 impl Error for NotationError {}
 
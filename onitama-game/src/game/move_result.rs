@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::player_color::PlayerColor;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MoveResult {
     Capture,
@@ -13,4 +15,12 @@ impl MoveResult {
     pub fn is_win(&self) -> bool {
         *self == MoveResult::RedWin || *self == MoveResult::BlueWin
     }
+
+    /// Result of the game where the player could not make a move, so the enemy wins
+    pub fn forfeit(player_color: PlayerColor) -> Self {
+        match player_color {
+            PlayerColor::Red => MoveResult::BlueWin,
+            PlayerColor::Blue => MoveResult::RedWin,
+        }
+    }
 }
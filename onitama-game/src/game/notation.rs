@@ -0,0 +1,304 @@
+//! Text notation of the moves and the positions.
+//!
+//! A move is the card name and the squares in the algebraic notation, e.g. `tiger:c1c3`.
+//! A position is the board from the 5th rank to the 1st one, the player to move and the cards:
+//! `bbBbb/5/5/5/rrRrr r tiger,dragon frog,rabbit horse`.
+//! Pawns are `r` and `b`, kings are `R` and `B`, digits count the empty squares.
+//! The cards go in the order of the deck: two red cards, two blue cards and the neutral one
+
+use crate::common::{get_bit, set_bit};
+
+use super::{
+    card::{Card, CARD_NAMES, ORIGINAL_CARDS},
+    deck::Deck,
+    done_move::DoneMove,
+    player_color::PlayerColor,
+    r#move::{Move, NotationError},
+    state::State,
+};
+
+pub fn card_name(card: &Card) -> String {
+    CARD_NAMES[card.index].to_lowercase()
+}
+
+/// Finds the card by its name, the case does not matter
+pub fn parse_card(name: &str) -> Result<Card, NotationError> {
+    CARD_NAMES
+        .iter()
+        .position(|card_name| card_name.eq_ignore_ascii_case(name))
+        .map(|idx| ORIGINAL_CARDS[idx])
+        .ok_or_else(|| NotationError(format!("Unknown card: {}", name)))
+}
+
+pub fn format_move(state: &State, done_move: &DoneMove) -> String {
+    format!(
+        "{}:{}{}",
+        card_name(state.deck.get_card(done_move.used_card_idx)),
+        Move::convert_idx_to_notation(done_move.mov.from),
+        Move::convert_idx_to_notation(done_move.mov.to)
+    )
+}
+
+/// Finds the legal move of the player which is written in the notation
+pub fn parse_move(
+    state: &State,
+    player_color: PlayerColor,
+    notation: &str,
+) -> Result<DoneMove, NotationError> {
+    let (card, squares) = notation.split_once(':').ok_or_else(|| {
+        NotationError(format!(
+            "Move must be written as card:fromto, e.g. tiger:c1c3: {}",
+            notation
+        ))
+    })?;
+    let card = parse_card(card)?;
+    if squares.len() != 4 || !squares.is_ascii() {
+        return Err(NotationError(format!(
+            "Incorrect squares of the move: {}",
+            squares
+        )));
+    }
+    let from = Move::convert_notation_to_idx(&squares[..2])?;
+    let to = Move::convert_notation_to_idx(&squares[2..])?;
+
+    state
+        .generate_all_legal_moves(player_color)
+        .into_iter()
+        .find(|(card_idx, mov)| {
+            state.deck.get_card(*card_idx).index == card.index && mov.from == from && mov.to == to
+        })
+        .map(|(card_idx, mov)| DoneMove {
+            mov,
+            used_card_idx: card_idx,
+        })
+        .ok_or_else(|| NotationError(format!("Move {} is illegal", notation)))
+}
+
+pub fn format_position(state: &State, player_color: PlayerColor) -> String {
+    let pieces = [
+        (state.pawns[PlayerColor::Red as usize], 'r'),
+        (state.kings[PlayerColor::Red as usize], 'R'),
+        (state.pawns[PlayerColor::Blue as usize], 'b'),
+        (state.kings[PlayerColor::Blue as usize], 'B'),
+    ];
+
+    let rows = (0..5)
+        .map(|row| {
+            let mut result = String::new();
+            let mut empty = 0;
+            for pos in row * 5..row * 5 + 5 {
+                match pieces.iter().find(|(bits, _)| get_bit(*bits, pos) == 1) {
+                    Some((_, piece)) => {
+                        if empty > 0 {
+                            result += &empty.to_string();
+                            empty = 0;
+                        }
+                        result.push(*piece);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                result += &empty.to_string();
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+
+    let player = match player_color {
+        PlayerColor::Red => 'r',
+        PlayerColor::Blue => 'b',
+    };
+
+    format!("{} {} {}", rows.join("/"), player, format_deck(&state.deck))
+}
+
+/// Cards of the deck as in the position: `tiger,dragon frog,rabbit horse`
+pub fn format_deck(deck: &Deck) -> String {
+    let cards = deck.cards.iter().map(card_name).collect::<Vec<_>>();
+    format!(
+        "{},{} {},{} {}",
+        cards[0], cards[1], cards[2], cards[3], cards[4]
+    )
+}
+
+/// Reads the cards of the deck written as in the position
+pub fn parse_deck(deck: &str) -> Result<Deck, NotationError> {
+    let groups = deck.split_whitespace().collect::<Vec<_>>();
+    if groups.len() != 3 {
+        return Err(NotationError(format!(
+            "Cards must be split as 2 red, 2 blue and 1 neutral: {}",
+            deck
+        )));
+    }
+
+    let mut cards = vec![];
+    for (group, amount) in groups.iter().zip([2, 2, 1]) {
+        let group = group
+            .split(',')
+            .map(parse_card)
+            .collect::<Result<Vec<_>, _>>()?;
+        if group.len() != amount {
+            return Err(NotationError(format!(
+                "Cards must be split as 2 red, 2 blue and 1 neutral: {}",
+                deck
+            )));
+        }
+        cards.extend(group);
+    }
+    if (1..cards.len()).any(|i| cards[..i].contains(&cards[i])) {
+        return Err(NotationError(String::from("Cards must be different")));
+    }
+
+    Ok(Deck::new(cards.try_into().expect("Deck must have 5 cards")))
+}
+
+/// Reads the position and the player to move
+pub fn parse_position(position: &str) -> Result<(State, PlayerColor), NotationError> {
+    let parts = position.split_whitespace().collect::<Vec<_>>();
+    if parts.len() != 5 {
+        return Err(NotationError(format!(
+            "Position must have the board, the player and 3 groups of cards: {}",
+            position
+        )));
+    }
+
+    let mut state = State::new();
+    state.kings = [0; 2];
+    state.pawns = [0; 2];
+
+    let rows = parts[0].split('/').collect::<Vec<_>>();
+    if rows.len() != 5 {
+        return Err(NotationError(format!(
+            "Board must have 5 rows: {}",
+            parts[0]
+        )));
+    }
+    for (row_idx, row) in rows.iter().enumerate() {
+        let mut col = 0;
+        for c in row.chars() {
+            let pos = row_idx * 5 + col;
+            match c {
+                '1'..='5' => col += c.to_digit(10).unwrap() as usize,
+                'r' | 'R' | 'b' | 'B' if col < 5 => {
+                    let color = if c.eq_ignore_ascii_case(&'r') {
+                        PlayerColor::Red
+                    } else {
+                        PlayerColor::Blue
+                    };
+                    if c.is_uppercase() {
+                        set_bit(&mut state.kings[color as usize], pos);
+                    } else {
+                        set_bit(&mut state.pawns[color as usize], pos);
+                    }
+                    col += 1;
+                }
+                _ => {
+                    return Err(NotationError(format!(
+                        "Incorrect row of the board: {}",
+                        row
+                    )))
+                }
+            }
+        }
+        if col != 5 {
+            return Err(NotationError(format!("Row must have 5 squares: {}", row)));
+        }
+    }
+    if state.kings.iter().any(|king| king.count_ones() != 1) {
+        return Err(NotationError(String::from(
+            "Every player must have one king",
+        )));
+    }
+
+    let player_color = match parts[1] {
+        "r" => PlayerColor::Red,
+        "b" => PlayerColor::Blue,
+        player => return Err(NotationError(format!("Player must be r or b: {}", player))),
+    };
+
+    state.deck = parse_deck(&parts[2..].join(" "))?;
+
+    Ok((state, player_color))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+        game_state::GameState,
+        piece::PieceKind,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_start_position() {
+        let deck = Deck::new([TIGER, DRAGON, FROG, RABBIT, HORSE]);
+        let state = State::with_deck(deck);
+        let position = format_position(&state, PlayerColor::Red);
+
+        assert_eq!(
+            position,
+            "bbBbb/5/5/5/rrRrr r tiger,dragon frog,rabbit horse"
+        );
+        assert_eq!(
+            parse_position(&position),
+            Ok((state.clone(), PlayerColor::Red))
+        );
+        assert_eq!(parse_deck(&format_deck(&state.deck)), Ok(state.deck));
+        assert!(parse_deck("tiger,dragon,frog rabbit horse").is_err());
+    }
+
+    #[test]
+    fn test_positions_are_written_back() {
+        let mut game_state = GameState::with_deck(Deck::from_seed(3));
+        for _ in 0..6 {
+            let (card_idx, mov) = game_state
+                .state
+                .generate_all_legal_moves(game_state.curr_player_color)[0];
+            game_state.progress(DoneMove {
+                mov,
+                used_card_idx: card_idx,
+            });
+
+            let position = format_position(&game_state.state, game_state.curr_player_color);
+            assert_eq!(
+                parse_position(&position),
+                Ok((game_state.state.clone(), game_state.curr_player_color))
+            );
+        }
+    }
+
+    #[test]
+    fn test_moves() {
+        let deck = Deck::new([TIGER, DRAGON, FROG, RABBIT, HORSE]);
+        let state = State::with_deck(deck);
+
+        let done_move = parse_move(&state, PlayerColor::Red, "Tiger:c1c3").unwrap();
+        assert_eq!(done_move.used_card_idx, 0);
+        assert_eq!(done_move.mov.piece, PieceKind::King);
+        assert_eq!(format_move(&state, &done_move), "tiger:c1c3");
+
+        assert!(parse_move(&state, PlayerColor::Red, "tiger:c1c2").is_err());
+        assert!(parse_move(&state, PlayerColor::Red, "frog:c5c3").is_err());
+        assert!(parse_move(&state, PlayerColor::Red, "c1c3").is_err());
+        assert!(parse_move(&state, PlayerColor::Red, "wolf:c1c3").is_err());
+    }
+
+    #[test]
+    fn test_invalid_positions() {
+        for position in [
+            "",
+            "bbBbb/5/5/5/rrRrr r tiger,dragon frog,rabbit",
+            "bbBbb/5/5/rrRrr r tiger,dragon frog,rabbit horse",
+            "bbBbb/6/5/5/rrRrr r tiger,dragon frog,rabbit horse",
+            "bbbbb/5/5/5/rrRrr r tiger,dragon frog,rabbit horse",
+            "bbBbb/5/5/5/rrRrr x tiger,dragon frog,rabbit horse",
+            "bbBbb/5/5/5/rrRrr r tiger,tiger frog,rabbit horse",
+            "bbBbb/5/5/5/rrRrr r tiger frog,rabbit,dragon horse",
+        ] {
+            assert!(parse_position(position).is_err(), "{}", position);
+        }
+    }
+}
@@ -12,6 +12,7 @@ use super::{
     piece::PieceKind,
     player_color::PlayerColor,
     r#move::Move,
+    zobrist::{card_owner, BLUE_TO_MOVE_KEY, CARD_KEYS, PIECE_KEYS},
 };
 
 // Figure starting positions(SP)
@@ -48,7 +49,7 @@ pub const RED_PAWNS_SP: u32 = 0x0000_0D80;
 pub const BLUE_TEMPLE: usize = 2;
 pub const RED_TEMPLE: usize = 22;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct State {
     pub deck: Deck,
     pub kings: [u32; 2],
@@ -133,6 +134,36 @@ impl State {
         move_result
     }
 
+    /// Zobrist hash of the position for the player who has to make a move.
+    /// Order of the cards in one hand does not change the hash
+    pub fn zobrist_hash(&self, player_color: PlayerColor) -> u64 {
+        let mut hash = 0;
+
+        for color in [PlayerColor::Red, PlayerColor::Blue] {
+            let keys = &PIECE_KEYS[color as usize];
+            let squares = keys[PieceKind::Pawn as usize]
+                .iter()
+                .zip(keys[PieceKind::King as usize].iter());
+            for (n, (pawn_key, king_key)) in squares.enumerate() {
+                if get_bit(self.pawns[color as usize], n) == 1 {
+                    hash ^= pawn_key;
+                } else if get_bit(self.kings[color as usize], n) == 1 {
+                    hash ^= king_key;
+                }
+            }
+        }
+
+        for (slot, card) in self.deck.cards.iter().enumerate() {
+            hash ^= CARD_KEYS[card_owner(slot)][card.index];
+        }
+
+        if player_color == PlayerColor::Blue {
+            hash ^= BLUE_TO_MOVE_KEY;
+        }
+
+        hash
+    }
+
     /// When no legal move was found, pass the turn.
     /// Passing means to choose the card to swap
     /// so that next turn new card is available
@@ -887,4 +918,44 @@ mod tests {
         let moves = state.generate_legal_moves(player_color, &horse);
         assert!(moves.len() == 0);
     }
+
+    #[test]
+    fn zobrist_hash_depends_on_player_to_move() {
+        let deck = Deck::new([DRAGON, RABBIT, TIGER, HORSE, FROG]);
+        let state = State::with_deck(deck);
+
+        assert_ne!(
+            state.zobrist_hash(PlayerColor::Red),
+            state.zobrist_hash(PlayerColor::Blue)
+        );
+    }
+
+    #[test]
+    fn zobrist_hash_ignores_card_order_in_hand() {
+        let state = State::with_deck(Deck::new([DRAGON, RABBIT, TIGER, HORSE, FROG]));
+        let swapped = State::with_deck(Deck::new([RABBIT, DRAGON, HORSE, TIGER, FROG]));
+        // Same cards, but the neutral card is given to the red player
+        let other = State::with_deck(Deck::new([FROG, RABBIT, TIGER, HORSE, DRAGON]));
+
+        let hash = state.zobrist_hash(PlayerColor::Red);
+        assert_eq!(hash, swapped.zobrist_hash(PlayerColor::Red));
+        assert_ne!(hash, other.zobrist_hash(PlayerColor::Red));
+    }
+
+    #[test]
+    fn zobrist_hash_changes_after_move() {
+        let deck = Deck::new([CRAB, RABBIT, DRAGON, TIGER, FROG]);
+        let mut state = State::with_deck(deck);
+        let before = state.zobrist_hash(PlayerColor::Red);
+
+        let mov = Move {
+            from: 20, // a1
+            to: 15,   // a2
+            piece: PieceKind::Pawn,
+        };
+        state.make_move(&mov, PlayerColor::Red, 0);
+
+        assert_ne!(before, state.zobrist_hash(PlayerColor::Blue));
+        assert_ne!(before, state.zobrist_hash(PlayerColor::Red));
+    }
 }
@@ -0,0 +1,83 @@
+use super::card::ORIGINAL_CARDS;
+
+// Card owners used to index the card keys. The order of two cards inside
+// one hand does not matter for the position, therefore the key only depends
+// on who holds the card and not on the deck slot
+pub const RED_OWNER: usize = 0;
+pub const BLUE_OWNER: usize = 1;
+pub const NEUTRAL_OWNER: usize = 2;
+
+/// Keys for every piece: [player color][piece kind][board position]
+pub const PIECE_KEYS: [[[u64; 25]; 2]; 2] = generate_piece_keys();
+/// Keys for every card: [card owner][card index]
+pub const CARD_KEYS: [[u64; 16]; 3] = generate_card_keys();
+/// Key which is applied when the blue player has to make a move
+pub const BLUE_TO_MOVE_KEY: u64 = splitmix64(0x0062_6C75_655F_746F).1;
+
+// Seeds are arbitrary, they just must be different for the pieces and cards
+const PIECE_SEED: u64 = 0x006F_6E69_7461_6D61;
+const CARD_SEED: u64 = 0x0063_6172_6473_5F6B;
+
+/// SplitMix64 generator, returns the next state and the generated value.
+/// Taken from: https://prng.di.unimi.it/splitmix64.c
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+/// Generates random keys for all pieces on all positions
+const fn generate_piece_keys() -> [[[u64; 25]; 2]; 2] {
+    let mut result = [[[0u64; 25]; 2]; 2];
+    let mut state = PIECE_SEED;
+    let mut color = 0;
+
+    // Need to use while to support const fn
+    // for loops are not stabilized yet
+    while color < 2 {
+        let mut kind = 0;
+        while kind < 2 {
+            let mut pos = 0;
+            while pos < 25 {
+                let (next_state, key) = splitmix64(state);
+                result[color][kind][pos] = key;
+                state = next_state;
+                pos += 1;
+            }
+            kind += 1;
+        }
+        color += 1;
+    }
+    result
+}
+
+/// Generates random keys for all cards held by each owner
+const fn generate_card_keys() -> [[u64; 16]; 3] {
+    let mut result = [[0u64; 16]; 3];
+    let mut state = CARD_SEED;
+    let mut owner = 0;
+
+    while owner < 3 {
+        let mut card_idx = 0;
+        while card_idx < ORIGINAL_CARDS.len() {
+            let (next_state, key) = splitmix64(state);
+            result[owner][ORIGINAL_CARDS[card_idx].index] = key;
+            state = next_state;
+            card_idx += 1;
+        }
+        owner += 1;
+    }
+    result
+}
+
+/// Returns an owner of the card which is located in the specific deck slot
+#[inline]
+pub const fn card_owner(deck_slot: usize) -> usize {
+    match deck_slot {
+        0 | 1 => RED_OWNER,
+        2 | 3 => BLUE_OWNER,
+        _ => NEUTRAL_OWNER,
+    }
+}
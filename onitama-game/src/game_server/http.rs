@@ -0,0 +1,167 @@
+//! Minimal HTTP/1.1 with one request per connection, enough for the JSON API of the server
+
+use std::io::{self, BufRead, Write};
+
+use serde::Serialize;
+
+/// Larger bodies are rejected, the requests of the API are much smaller
+const MAX_BODY_LEN: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// Path without the query
+    pub path: String,
+    /// Media type of the body without its parameters, e.g. `application/json`
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn read(reader: &mut impl BufRead) -> io::Result<Self> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method.to_owned(), target),
+            _ => {
+                return Err(invalid_data(format!(
+                    "Invalid request line: {}",
+                    line.trim()
+                )))
+            }
+        };
+        let path = target.split('?').next().unwrap_or_default().to_owned();
+
+        let mut content_len = 0;
+        let mut content_type = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_len = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| invalid_data("Invalid Content-Length"))?;
+                } else if name.trim().eq_ignore_ascii_case("content-type") {
+                    let media_type = value.split(';').next().unwrap_or_default();
+                    content_type = Some(media_type.trim().to_ascii_lowercase());
+                }
+            }
+        }
+        if content_len > MAX_BODY_LEN {
+            return Err(invalid_data("Body is too long"));
+        }
+
+        let mut body = vec![0u8; content_len];
+        reader.read_exact(&mut body)?;
+        let body = String::from_utf8(body).map_err(|e| invalid_data(e.to_string()))?;
+
+        Ok(Self {
+            method,
+            path,
+            content_type,
+            body,
+        })
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.content_type.as_deref() == Some("application/json")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: u16, body: &impl Serialize) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string(body).expect("Responses are serialized"),
+        }
+    }
+
+    /// Error written as `{"error": "..."}`
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        #[derive(Serialize)]
+        struct Error {
+            error: String,
+        }
+
+        Self::json(
+            status,
+            &Error {
+                error: message.into(),
+            },
+        )
+    }
+
+    /// Writes the response, `origin` is the only other origin whose pages may read it
+    pub fn write(&self, writer: &mut impl Write, origin: Option<&str>) -> io::Result<()> {
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n{}",
+            self.status,
+            reason(self.status),
+            self.body.len(),
+            common_headers(origin),
+            self.body
+        )?;
+        writer.flush()
+    }
+}
+
+/// Headers of every response. Browsers let only the pages of the same origin
+/// and of the allowed one call the API
+fn common_headers(origin: Option<&str>) -> String {
+    let mut headers = String::from("Connection: close\r\n");
+    if let Some(origin) = origin {
+        headers += &format!(
+            "Access-Control-Allow-Origin: {}\r\nVary: Origin\r\n\
+             Access-Control-Allow-Methods: GET, POST, DELETE, OPTIONS\r\n\
+             Access-Control-Allow-Headers: Content-Type\r\n",
+            origin
+        );
+    }
+    headers
+}
+
+/// Starts the stream of the server-sent events, the events are written with `write_event`
+pub fn write_event_stream_head(writer: &mut impl Write, origin: Option<&str>) -> io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n{}\r\n",
+        common_headers(origin)
+    )?;
+    writer.flush()
+}
+
+pub fn write_event(writer: &mut impl Write, event: &str, data: &str) -> io::Result<()> {
+    write!(writer, "event: {}\ndata: {}\n\n", event, data)?;
+    writer.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        415 => "Unsupported Media Type",
+        _ => "Internal Server Error",
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
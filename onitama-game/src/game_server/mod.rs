@@ -0,0 +1,813 @@
+//! HTTP server which hosts many games at once for the web and scripted frontends.
+//! The bodies of the requests and the responses are JSON:
+//! - `POST /games` - creates the game from `{"red": "human", "blue": "alphabeta:depth=6"}`,
+//!   the players are agent specifications or `human`. The deck is given
+//!   as 5 card names `"deck": ["tiger", ...]` or as `"seed": 3`, otherwise it is random
+//! - `GET /games` - ids of the games
+//! - `GET /games/<id>` - state of the game
+//! - `DELETE /games/<id>` - removes the game and stops the search of its agent
+//! - `GET /games/<id>/moves` - legal moves of the player to move
+//! - `POST /games/<id>/moves` - move of the human player, e.g. `{"move": "tiger:c1c3"}`
+//! - `POST /games/<id>/ai` - the agent of the player to move starts to think,
+//!   its move is made in the background
+//! - `GET /games/<id>/events` - server-sent events `state` with the state after every change
+//!
+//! The moves are written in the notation of `game::notation`. Errors are `{"error": "..."}`.
+//! Every `POST` must have `Content-Type: application/json`, so the pages of other origins
+//! cannot send them without the permission of the server, which is given only to
+//! the origin of `GameServer::allow_origin`. The clients can create only the agents
+//! of `server_registry` which neither run programs nor read files, and their search
+//! parameters must not exceed `MAX_PARAMS` and `MAX_TIME`
+
+pub mod http;
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    io::{self, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::{
+        agent::{Agent, AgentError},
+        analysis::SearchControl,
+        registry::{format_duration, AgentRegistry, AgentSpec, SpecError},
+    },
+    game::{
+        deck::Deck,
+        done_move::DoneMove,
+        game_state::GameState,
+        notation::{format_move, format_position, parse_card, parse_move},
+        player_color::PlayerColor,
+    },
+};
+
+use self::http::{write_event, write_event_stream_head, Request, Response};
+
+/// Player which makes the moves through the API
+pub const HUMAN: &str = "human";
+/// Event streams write a comment after this time without changes to find the closed clients
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+/// Largest values of the search parameters which the clients may give,
+/// so one game cannot take all threads and memory of the server
+const MAX_PARAMS: [(&str, u64); 4] = [
+    ("threads", 4),
+    ("depth", 32),
+    ("playouts", 1_000_000),
+    ("nodes", 1_000_000),
+];
+/// Longest search time of a move which the clients may give
+const MAX_TIME: Duration = Duration::from_secs(10);
+
+/// Body of `POST /games`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NewGame {
+    red: Option<String>,
+    blue: Option<String>,
+    deck: Option<Vec<String>>,
+    seed: Option<u64>,
+}
+
+/// Body of `POST /games/<id>/moves`
+#[derive(Debug, Deserialize)]
+struct NewMove {
+    #[serde(rename = "move")]
+    notation: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Players {
+    pub red: String,
+    pub blue: String,
+}
+
+/// State of the game in the responses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameView {
+    pub id: u64,
+    pub players: Players,
+    /// Position in the notation of `game::notation`
+    pub position: String,
+    pub turn: PlayerColor,
+    pub moves: Vec<String>,
+    pub winner: Option<PlayerColor>,
+    /// Agent of the player to move is searching the move
+    pub thinking: bool,
+    /// Why the agent has lost the game without the move
+    pub forfeit: Option<String>,
+    /// Grows with every change of the game
+    pub version: u64,
+}
+
+struct GameData {
+    game_state: GameState,
+    moves: Vec<String>,
+    winner: Option<PlayerColor>,
+    thinking: bool,
+    forfeit: Option<String>,
+    version: u64,
+    is_deleted: bool,
+}
+
+struct Game {
+    id: u64,
+    players: Players,
+    /// Agent of every color, the humans do not have them
+    agents: [Option<Mutex<Box<dyn Agent>>>; 2],
+    data: Mutex<GameData>,
+    changed: Condvar,
+    /// Stops the search of the agent when the game is deleted
+    stop: AtomicBool,
+}
+
+impl Game {
+    fn lock(&self) -> MutexGuard<'_, GameData> {
+        self.data.lock().unwrap()
+    }
+
+    fn view(&self, data: &GameData) -> GameView {
+        GameView {
+            id: self.id,
+            players: self.players.clone(),
+            position: format_position(&data.game_state.state, data.game_state.curr_player_color),
+            turn: data.game_state.curr_player_color,
+            moves: data.moves.clone(),
+            winner: data.winner,
+            thinking: data.thinking,
+            forfeit: data.forfeit.clone(),
+            version: data.version,
+        }
+    }
+
+    fn make_move(&self, data: &mut GameData, done_move: DoneMove) {
+        let player_color = data.game_state.curr_player_color;
+        data.moves
+            .push(format_move(&data.game_state.state, &done_move));
+        if data.game_state.progress(done_move).is_win() {
+            data.winner = Some(player_color);
+        }
+        self.notify(data);
+    }
+
+    fn notify(&self, data: &mut GameData) {
+        data.version += 1;
+        self.changed.notify_all();
+    }
+
+    /// Searches the move of the agent and makes it. The agent which fails or panics loses the game.
+    /// The agents with the analysis stop searching when the game is deleted
+    fn think(&self, game_state: GameState) {
+        let player_color = game_state.curr_player_color;
+        let agent = match &self.agents[player_color as usize] {
+            Some(agent) => agent,
+            None => return,
+        };
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            // The agent which has panicked before has already lost, so its state does not matter
+            let agent = agent.lock().unwrap_or_else(PoisonError::into_inner);
+            let control = SearchControl::new(&self.stop);
+            let lines = agent.analyze_until(&game_state, 1, &control, &mut |_| ());
+            match lines.first() {
+                Some(line) => Ok(Some(line.done_move)),
+                None if control.is_stopped() => Ok(None),
+                None => agent
+                    .try_generate_move(&game_state)
+                    .map(|(done_move, _)| Some(done_move)),
+            }
+        }))
+        .unwrap_or_else(|payload| Err(AgentError::Crashed(panic_message(&*payload))));
+
+        let mut data = self.lock();
+        data.thinking = false;
+        if data.is_deleted {
+            return;
+        }
+        match result {
+            Ok(Some(done_move)) => self.make_move(&mut data, done_move),
+            // The search is stopped only in the deleted games
+            Ok(None) => {}
+            Err(e) => {
+                data.winner = Some(player_color.enemy());
+                data.forfeit = Some(e.to_string());
+                self.notify(&mut data);
+            }
+        }
+    }
+}
+
+/// Message of the panic, which is a string unless the panic is raised with another value
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| String::from("unknown panic")),
+    }
+}
+
+/// The default registry without the agents which run programs, read files or the console
+pub fn server_registry() -> AgentRegistry {
+    let mut registry = AgentRegistry::default();
+    registry.unregister("engine");
+    registry.unregister("human");
+    // The book agent reads a file and builds its inner agent with all built-in kinds
+    registry.unregister("book");
+
+    // The weights of the evaluations are read from files
+    let mut alpha_beta = registry
+        .kind("alphabeta")
+        .expect("Default registry has AlphaBeta")
+        .clone();
+    alpha_beta.params.retain(|param| param.name != "file");
+    registry.register(alpha_beta);
+
+    registry
+}
+
+/// Games of the server, every connection is served in its own thread
+pub struct GameServer {
+    registry: AgentRegistry,
+    /// Other origin whose pages may call the API
+    origin: Option<String>,
+    games: Mutex<HashMap<u64, Arc<Game>>>,
+    next_id: AtomicU64,
+}
+
+impl GameServer {
+    /// Server whose clients create the agents of the registry, it must not contain the agents
+    /// which the clients must not run, see `server_registry`
+    pub fn new(registry: AgentRegistry) -> Self {
+        Self {
+            registry,
+            origin: None,
+            games: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Lets the pages of the origin, e.g. `http://localhost:3000`, call the API
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
+
+    /// Accepts the connections until the listener fails
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let server = self.clone();
+            let stream = stream?;
+            thread::spawn(move || {
+                if let Err(e) = server.serve_connection(stream) {
+                    eprintln!("Connection has failed: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn serve_connection(&self, mut stream: TcpStream) -> io::Result<()> {
+        let request = Request::read(&mut BufReader::new(stream.try_clone()?))?;
+
+        let segments = request
+            .path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        if let ("GET", ["games", id, "events"]) = (request.method.as_str(), segments.as_slice()) {
+            if let Some(game) = self.find(id) {
+                return stream_events(&game, &mut stream, self.origin.as_deref());
+            }
+        }
+
+        self.handle(&request)
+            .write(&mut stream, self.origin.as_deref())
+    }
+
+    /// Answers the request which is not an event stream
+    pub fn handle(&self, request: &Request) -> Response {
+        let segments = request
+            .path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+
+        if request.method == "POST" && !request.is_json() {
+            return Response::error(415, "Content-Type must be application/json");
+        }
+
+        match (request.method.as_str(), segments.as_slice()) {
+            ("OPTIONS", _) => Response {
+                status: 204,
+                body: String::new(),
+            },
+            ("GET", ["games"]) => {
+                let mut ids = self
+                    .games
+                    .lock()
+                    .unwrap()
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>();
+                ids.sort_unstable();
+                Response::json(200, &ids)
+            }
+            ("POST", ["games"]) => self.create(&request.body),
+            (method, ["games", id, rest @ ..]) => {
+                let game = match self.find(id) {
+                    Some(game) => game,
+                    None => return Response::error(404, format!("Game {} not found", id)),
+                };
+                match (method, rest) {
+                    ("GET", []) => Response::json(200, &game.view(&game.lock())),
+                    ("DELETE", []) => {
+                        self.games.lock().unwrap().remove(&game.id);
+                        let mut data = game.lock();
+                        data.is_deleted = true;
+                        game.stop.store(true, Ordering::Relaxed);
+                        game.notify(&mut data);
+                        Response::json(200, &game.view(&data))
+                    }
+                    ("GET", ["moves"]) => {
+                        let data = game.lock();
+                        let state = &data.game_state.state;
+                        let moves = match data.winner {
+                            Some(_) => vec![],
+                            None => state
+                                .generate_all_legal_moves(data.game_state.curr_player_color)
+                                .into_iter()
+                                .map(|(card_idx, mov)| {
+                                    let done_move = DoneMove {
+                                        mov,
+                                        used_card_idx: card_idx,
+                                    };
+                                    format_move(state, &done_move)
+                                })
+                                .collect(),
+                        };
+                        Response::json(200, &moves)
+                    }
+                    ("POST", ["moves"]) => human_move(&game, &request.body),
+                    ("POST", ["ai"]) => start_thinking(game),
+                    (_, [] | ["moves"] | ["ai"] | ["events"]) => {
+                        Response::error(405, "Method is not allowed")
+                    }
+                    _ => Response::error(404, "Not found"),
+                }
+            }
+            _ => Response::error(404, "Not found"),
+        }
+    }
+
+    fn find(&self, id: &str) -> Option<Arc<Game>> {
+        let id = id.parse::<u64>().ok()?;
+        self.games.lock().unwrap().get(&id).cloned()
+    }
+
+    fn create(&self, body: &str) -> Response {
+        let new_game = if body.trim().is_empty() {
+            NewGame::default()
+        } else {
+            match serde_json::from_str::<NewGame>(body) {
+                Ok(new_game) => new_game,
+                Err(e) => return Response::error(400, e.to_string()),
+            }
+        };
+
+        let deck = match (&new_game.deck, new_game.seed) {
+            (Some(cards), _) => match parse_deck(cards) {
+                Ok(deck) => deck,
+                Err(e) => return Response::error(400, e),
+            },
+            (None, Some(seed)) => Deck::from_seed(seed),
+            (None, None) => Deck::default(),
+        };
+
+        let players = Players {
+            red: new_game.red.unwrap_or_else(|| HUMAN.to_owned()),
+            blue: new_game.blue.unwrap_or_else(|| HUMAN.to_owned()),
+        };
+        let mut agents = [None, None];
+        for (agent, spec) in agents.iter_mut().zip([&players.red, &players.blue]) {
+            *agent = match self.build_agent(spec) {
+                Ok(built) => built.map(Mutex::new),
+                Err(e) => return Response::error(400, e),
+            };
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let game = Arc::new(Game {
+            id,
+            players,
+            agents,
+            data: Mutex::new(GameData {
+                game_state: GameState::with_deck(deck),
+                moves: vec![],
+                winner: None,
+                thinking: false,
+                forfeit: None,
+                version: 0,
+                is_deleted: false,
+            }),
+            changed: Condvar::new(),
+            stop: AtomicBool::new(false),
+        });
+        let view = game.view(&game.lock());
+        self.games.lock().unwrap().insert(id, game);
+
+        Response::json(201, &view)
+    }
+
+    /// Agent of the specification or nothing for the human
+    fn build_agent(&self, spec: &str) -> Result<Option<Box<dyn Agent>>, String> {
+        let spec = spec.parse::<AgentSpec>().map_err(|e| e.to_string())?;
+        if spec.kind == HUMAN {
+            return Ok(None);
+        }
+        check_limits(&spec).map_err(|e| e.to_string())?;
+        self.registry
+            .build_spec(&spec)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Rejects the search parameters above `MAX_PARAMS` and `MAX_TIME`
+fn check_limits(spec: &AgentSpec) -> Result<(), SpecError> {
+    let exceeded = |param: &str, value: String, max: String| SpecError::InvalidValue {
+        param: param.to_owned(),
+        value,
+        reason: format!("server allows at most {}", max),
+    };
+
+    for (param, max) in MAX_PARAMS {
+        if let Some(value) = spec.parse_opt::<u64>(param)? {
+            if value > max {
+                return Err(exceeded(param, value.to_string(), max.to_string()));
+            }
+        }
+    }
+    let time = spec.duration_or("time", Duration::ZERO)?;
+    if time > MAX_TIME {
+        return Err(exceeded(
+            "time",
+            format_duration(time),
+            format_duration(MAX_TIME),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_deck(cards: &[String]) -> Result<Deck, String> {
+    let cards = cards
+        .iter()
+        .map(|name| parse_card(name))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    if (1..cards.len()).any(|i| cards[..i].contains(&cards[i])) {
+        return Err(String::from("Cards must be different"));
+    }
+    let cards = cards
+        .try_into()
+        .map_err(|_| String::from("Deck must have 5 cards"))?;
+    Ok(Deck::new(cards))
+}
+
+fn human_move(game: &Game, body: &str) -> Response {
+    let new_move = match serde_json::from_str::<NewMove>(body) {
+        Ok(new_move) => new_move,
+        Err(e) => return Response::error(400, e.to_string()),
+    };
+
+    let mut data = game.lock();
+    let player_color = data.game_state.curr_player_color;
+    if data.winner.is_some() {
+        return Response::error(409, "Game is over");
+    }
+    if game.agents[player_color as usize].is_some() {
+        return Response::error(409, "Player to move is not human");
+    }
+
+    match parse_move(&data.game_state.state, player_color, &new_move.notation) {
+        Ok(done_move) => {
+            game.make_move(&mut data, done_move);
+            Response::json(200, &game.view(&data))
+        }
+        Err(e) => Response::error(400, e.to_string()),
+    }
+}
+
+fn start_thinking(game: Arc<Game>) -> Response {
+    let mut data = game.lock();
+    let player_color = data.game_state.curr_player_color;
+    if data.winner.is_some() {
+        return Response::error(409, "Game is over");
+    }
+    if data.thinking {
+        return Response::error(409, "Agent is already thinking");
+    }
+    if game.agents[player_color as usize].is_none() {
+        return Response::error(409, "Player to move is human");
+    }
+
+    data.thinking = true;
+    game.notify(&mut data);
+    let view = game.view(&data);
+    let game_state = data.game_state.clone();
+    drop(data);
+
+    thread::spawn(move || game.think(game_state));
+    Response::json(202, &view)
+}
+
+/// Writes the state after every change until the game is over or deleted
+fn stream_events(game: &Game, stream: &mut impl Write, origin: Option<&str>) -> io::Result<()> {
+    write_event_stream_head(stream, origin)?;
+
+    let mut version = None;
+    loop {
+        let data = game.lock();
+        let (data, _) = game
+            .changed
+            .wait_timeout_while(data, KEEP_ALIVE, |data| Some(data.version) == version)
+            .unwrap();
+        if Some(data.version) == version {
+            drop(data);
+            write!(stream, ": keep-alive\n\n")?;
+            stream.flush()?;
+            continue;
+        }
+
+        version = Some(data.version);
+        let is_over = data.winner.is_some() || data.is_deleted;
+        let view = serde_json::to_string(&game.view(&data)).expect("Views are serialized");
+        drop(data);
+
+        write_event(stream, "state", &view)?;
+        if is_over {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, Read},
+        time::Instant,
+    };
+
+    use super::*;
+
+    #[derive(Clone, Serialize)]
+    struct PanickingAgent;
+
+    impl Agent for PanickingAgent {
+        fn generate_move(&self, _game_state: &GameState) -> (DoneMove, f64) {
+            panic!("search has failed")
+        }
+
+        fn name(&self) -> &'static str {
+            "Panicking AI"
+        }
+
+        fn clone_dyn(&self) -> Box<dyn Agent> {
+            Box::new(self.clone())
+        }
+
+        fn id(&self) -> u64 {
+            0
+        }
+    }
+
+    /// Game where the player to move is the agent, the other one is human
+    fn agent_game(agent: Box<dyn Agent>) -> Game {
+        let game_state = GameState::with_deck(Deck::default());
+        let mut agents = [None, None];
+        agents[game_state.curr_player_color as usize] = Some(Mutex::new(agent));
+        Game {
+            id: 1,
+            players: Players {
+                red: HUMAN.to_owned(),
+                blue: HUMAN.to_owned(),
+            },
+            agents,
+            data: Mutex::new(GameData {
+                game_state,
+                moves: vec![],
+                winner: None,
+                thinking: true,
+                forfeit: None,
+                version: 0,
+                is_deleted: false,
+            }),
+            changed: Condvar::new(),
+            stop: AtomicBool::new(false),
+        }
+    }
+
+    fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = Arc::new(GameServer::new(server_registry()));
+        thread::spawn(move || server.serve(listener));
+        address
+    }
+
+    fn send(address: &str, method: &str, path: &str, body: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            address,
+            body.len(),
+            body
+        )
+        .unwrap();
+        stream
+    }
+
+    /// Makes the request and returns the status and the body
+    fn request(address: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut response = String::new();
+        send(address, method, path, body)
+            .read_to_string(&mut response)
+            .unwrap();
+
+        let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_owned();
+        (status, body)
+    }
+
+    #[test]
+    fn test_human_plays_against_agent() {
+        let address = start_server();
+        let (status, body) = request(
+            &address,
+            "POST",
+            "/games",
+            r#"{"blue": "alphabeta:depth=3,time=200ms",
+                "deck": ["tiger", "dragon", "frog", "rabbit", "horse"]}"#,
+        );
+        assert_eq!(status, 201);
+        let game = serde_json::from_str::<GameView>(&body).unwrap();
+        assert_eq!(game.turn, PlayerColor::Red);
+        let path = format!("/games/{}", game.id);
+
+        let (_, body) = request(&address, "GET", &format!("{}/moves", path), "");
+        let moves = serde_json::from_str::<Vec<String>>(&body).unwrap();
+        assert!(moves.contains(&"tiger:c1c3".to_owned()));
+
+        let moves_path = format!("{}/moves", path);
+        let ai_path = format!("{}/ai", path);
+        assert_eq!(request(&address, "POST", &ai_path, "").0, 409);
+        let illegal = r#"{"move": "tiger:c1c2"}"#;
+        assert_eq!(request(&address, "POST", &moves_path, illegal).0, 400);
+        let legal = r#"{"move": "tiger:c1c3"}"#;
+        let (status, body) = request(&address, "POST", &moves_path, legal);
+        assert_eq!(status, 200);
+        assert_eq!(
+            serde_json::from_str::<GameView>(&body).unwrap().turn,
+            PlayerColor::Blue
+        );
+        assert_eq!(request(&address, "POST", &moves_path, legal).0, 409);
+
+        // The agent answers in the background, its move comes with the events
+        let events = send(&address, "GET", &format!("{}/events", path), "");
+        assert_eq!(request(&address, "POST", &ai_path, "").0, 202);
+        let answered = BufReader::new(events)
+            .lines()
+            .map(Result::unwrap)
+            .filter_map(|line| {
+                line.strip_prefix("data: ")
+                    .map(|data| serde_json::from_str::<GameView>(data).unwrap())
+            })
+            .find(|game| game.moves.len() == 2)
+            .unwrap();
+        assert_eq!(answered.turn, PlayerColor::Red);
+        assert!(!answered.thinking);
+        assert_eq!(answered.forfeit, None);
+    }
+
+    #[test]
+    fn test_panicking_agent_forfeits() {
+        let game = agent_game(Box::new(PanickingAgent));
+        let game_state = game.lock().game_state.clone();
+        let enemy = game_state.curr_player_color.enemy();
+        game.think(game_state);
+
+        let data = game.lock();
+        assert!(!data.thinking);
+        assert_eq!(data.winner, Some(enemy));
+        assert!(data.forfeit.as_ref().unwrap().contains("search has failed"));
+    }
+
+    #[test]
+    fn test_deleted_game_stops_search() {
+        let agent = AgentRegistry::default()
+            .build("alphabeta:depth=32,time=60s")
+            .unwrap();
+        let game = agent_game(agent);
+        let game_state = game.lock().game_state.clone();
+        game.lock().is_deleted = true;
+        game.stop.store(true, Ordering::Relaxed);
+
+        let start = Instant::now();
+        game.think(game_state);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        let data = game.lock();
+        assert!(!data.thinking);
+        assert!(data.moves.is_empty());
+        assert_eq!(data.winner, None);
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        let address = start_server();
+
+        assert_eq!(request(&address, "GET", "/games/7", "").0, 404);
+        assert_eq!(request(&address, "GET", "/players", "").0, 404);
+        assert_eq!(
+            request(&address, "POST", "/games", r#"{"red": "minimax"}"#).0,
+            400
+        );
+        assert_eq!(
+            request(&address, "POST", "/games", r#"{"deck": ["tiger"]}"#).0,
+            400
+        );
+
+        let (status, body) = request(&address, "POST", "/games", r#"{"seed": 3}"#);
+        assert_eq!(status, 201);
+        let game = serde_json::from_str::<GameView>(&body).unwrap();
+        assert_eq!(game.players.red, HUMAN);
+
+        let (_, body) = request(&address, "GET", "/games", "");
+        assert_eq!(
+            serde_json::from_str::<Vec<u64>>(&body).unwrap(),
+            vec![game.id]
+        );
+        let path = format!("/games/{}", game.id);
+        assert_eq!(request(&address, "PUT", &path, "").0, 405);
+        assert_eq!(request(&address, "DELETE", &path, "").0, 200);
+        assert_eq!(request(&address, "GET", &path, "").0, 404);
+    }
+
+    #[test]
+    fn test_untrusted_requests() {
+        let address = start_server();
+
+        for spec in ["engine:cmd=sh", "alphabeta:eval=nnue,file=/etc/passwd"] {
+            let body = format!(r#"{{"red": "{}"}}"#, spec);
+            assert_eq!(request(&address, "POST", "/games", &body).0, 400);
+        }
+
+        // The searches which would take the whole server name the exceeded limit
+        for (spec, limit) in [
+            ("alphabeta:threads=64", "threads"),
+            ("mcts:time=5m", "time"),
+            ("mcts:playouts=100000000", "playouts"),
+            ("solver:nodes=100000000", "nodes"),
+            ("alphabeta:depth=100", "depth"),
+        ] {
+            let body = format!(r#"{{"red": "{}"}}"#, spec);
+            let (status, body) = request(&address, "POST", "/games", &body);
+            assert_eq!(status, 400);
+            assert!(body.contains(&format!("parameter '{}'", limit)), "{}", body);
+            assert!(body.contains("at most"), "{}", body);
+        }
+        let allowed = r#"{"red": "alphabeta:threads=2,time=5s"}"#;
+        let (status, body) = request(&address, "POST", "/games", allowed);
+        assert_eq!(status, 201);
+        let game = serde_json::from_str::<GameView>(&body).unwrap();
+        let path = format!("/games/{}", game.id);
+        assert_eq!(request(&address, "DELETE", &path, "").0, 200);
+
+        // Simple requests of the other origins are rejected and cannot be read
+        let mut stream = TcpStream::connect(&address).unwrap();
+        write!(
+            stream,
+            "POST /games HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n{{}}"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 415"));
+        assert!(!response.contains("Access-Control-Allow-Origin"));
+        assert_eq!(request(&address, "GET", "/games", "").1, "[]");
+    }
+}
@@ -1,3 +1,7 @@
 pub mod ai;
 pub mod common;
 pub mod game;
+pub mod game_server;
+pub mod litama;
+pub mod match_runner;
+pub mod protocol;
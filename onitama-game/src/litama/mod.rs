@@ -0,0 +1,477 @@
+//! Client of the Litama protocol which the online Onitama servers speak: JSON messages
+//! over WebSocket. The client sends the text commands and the server answers with JSON:
+//! - `create <username>` - creates the match, answers with `create` and the token of the player
+//! - `join <match id> <username>` - joins the match, answers with `join` and the token
+//! - `state <match id>` - answers with `state` of the match
+//! - `move <match id> <token> <card> <move>` - makes the move, e.g. `move 4f2a 9c1e tiger c1c3`,
+//!   answers with `state` of the match after it
+//!
+//! Errors are answered with `error`. The board of the state is the string of 25 squares
+//! from a5 to e1 where `0` is empty, `1` and `2` are the blue pawn and king,
+//! `3` and `4` are the red pawn and king. `server` has a local server of this subset for the tests
+
+pub mod server;
+pub mod websocket;
+
+use std::{fmt, io, thread, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::agent::{Agent, AgentError},
+    common::{count_bits, get_bit, set_bit},
+    game::{
+        deck::Deck,
+        done_move::DoneMove,
+        game_state::GameState,
+        notation::{card_name, parse_card},
+        player_color::PlayerColor,
+        r#move::{Move, NotationError},
+        state::State,
+    },
+};
+
+use self::websocket::WebSocket;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LitamaError {
+    /// Connection has failed or has been closed
+    Connection(String),
+    /// Server has answered with an error
+    Server(String),
+    /// Message of the server cannot be understood
+    Protocol(String),
+    Agent(AgentError),
+}
+
+impl fmt::Display for LitamaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LitamaError::Connection(reason) => write!(f, "Connection error: {}", reason),
+            LitamaError::Server(error) => write!(f, "Server error: {}", error),
+            LitamaError::Protocol(reason) => write!(f, "Protocol error: {}", reason),
+            LitamaError::Agent(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for LitamaError {}
+
+impl From<io::Error> for LitamaError {
+    fn from(e: io::Error) -> Self {
+        LitamaError::Connection(e.to_string())
+    }
+}
+
+impl From<NotationError> for LitamaError {
+    fn from(e: NotationError) -> Self {
+        LitamaError::Protocol(e.to_string())
+    }
+}
+
+/// Colors are written in lowercase
+mod color {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::game::player_color::PlayerColor;
+
+    pub fn serialize<S: Serializer>(color: &PlayerColor, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(match color {
+            PlayerColor::Red => "red",
+            PlayerColor::Blue => "blue",
+        })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<PlayerColor, D::Error> {
+        match String::deserialize(d)?.as_str() {
+            "red" => Ok(PlayerColor::Red),
+            "blue" => Ok(PlayerColor::Blue),
+            color => Err(serde::de::Error::custom(format!("unknown color {}", color))),
+        }
+    }
+}
+
+/// Winner is the color or `none`
+mod winner {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::game::player_color::PlayerColor;
+
+    pub fn serialize<S: Serializer>(winner: &Option<PlayerColor>, s: S) -> Result<S::Ok, S::Error> {
+        match winner {
+            Some(color) => super::color::serialize(color, s),
+            None => s.serialize_str("none"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<PlayerColor>, D::Error> {
+        match String::deserialize(d)?.as_str() {
+            "none" => Ok(None),
+            "red" => Ok(Some(PlayerColor::Red)),
+            "blue" => Ok(Some(PlayerColor::Blue)),
+            winner => Err(serde::de::Error::custom(format!(
+                "unknown winner {}",
+                winner
+            ))),
+        }
+    }
+}
+
+/// Messages of the server, the type is written in `messageType`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "messageType", rename_all = "lowercase")]
+pub enum Message {
+    Create(MatchToken),
+    Join(MatchToken),
+    State(MatchState),
+    Error(ServerError),
+}
+
+/// Player of the match, the token authorizes the moves
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchToken {
+    pub match_id: String,
+    pub token: String,
+    #[serde(with = "color")]
+    pub color: PlayerColor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerError {
+    #[serde(default)]
+    pub match_id: Option<String>,
+    pub command: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchStatus {
+    #[serde(rename = "waiting for player")]
+    WaitingForPlayer,
+    #[serde(rename = "in progress")]
+    InProgress,
+    #[serde(rename = "ended")]
+    Ended,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usernames {
+    pub red: String,
+    pub blue: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cards {
+    pub red: Vec<String>,
+    pub blue: Vec<String>,
+    pub side: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchState {
+    pub match_id: String,
+    pub usernames: Usernames,
+    pub game_state: MatchStatus,
+    pub board: String,
+    /// Made moves in the notation of `game::notation`
+    pub moves: Vec<String>,
+    #[serde(with = "color")]
+    pub current_turn: PlayerColor,
+    pub cards: Cards,
+    #[serde(with = "winner")]
+    pub winner: Option<PlayerColor>,
+}
+
+impl MatchState {
+    /// Writes the board and the cards of the state
+    pub fn set_state(&mut self, state: &State, player_color: PlayerColor) {
+        self.board = (0..25)
+            .map(|pos| {
+                let piece =
+                    |bits: &[u32; 2], color: PlayerColor| get_bit(bits[color as usize], pos);
+                if piece(&state.pawns, PlayerColor::Blue) == 1 {
+                    '1'
+                } else if piece(&state.kings, PlayerColor::Blue) == 1 {
+                    '2'
+                } else if piece(&state.pawns, PlayerColor::Red) == 1 {
+                    '3'
+                } else if piece(&state.kings, PlayerColor::Red) == 1 {
+                    '4'
+                } else {
+                    '0'
+                }
+            })
+            .collect();
+
+        let cards = state.deck.cards.iter().map(card_name).collect::<Vec<_>>();
+        self.cards = Cards {
+            red: cards[0..2].to_vec(),
+            blue: cards[2..4].to_vec(),
+            side: cards[4].clone(),
+        };
+        self.current_turn = player_color;
+    }
+
+    /// Position of the match with the player to move
+    pub fn game_state(&self) -> Result<GameState, LitamaError> {
+        if self.board.len() != 25 {
+            return Err(LitamaError::Protocol(format!(
+                "Board must have 25 squares: {}",
+                self.board
+            )));
+        }
+
+        let mut state = State::new();
+        state.kings = [0; 2];
+        state.pawns = [0; 2];
+        for (pos, square) in self.board.chars().enumerate() {
+            match square {
+                '0' => (),
+                '1' => set_bit(&mut state.pawns[PlayerColor::Blue as usize], pos),
+                '2' => set_bit(&mut state.kings[PlayerColor::Blue as usize], pos),
+                '3' => set_bit(&mut state.pawns[PlayerColor::Red as usize], pos),
+                '4' => set_bit(&mut state.kings[PlayerColor::Red as usize], pos),
+                _ => {
+                    return Err(LitamaError::Protocol(format!(
+                        "Unknown square {} of the board",
+                        square
+                    )))
+                }
+            }
+        }
+        // The search expects the kings of both players in the position
+        if state.kings.iter().any(|&king| count_bits(king) != 1) {
+            return Err(LitamaError::Protocol(format!(
+                "Every player must have one king: {}",
+                self.board
+            )));
+        }
+
+        if self.cards.red.len() != 2 || self.cards.blue.len() != 2 {
+            return Err(LitamaError::Protocol(String::from(
+                "Every player must have 2 cards",
+            )));
+        }
+        let cards = self
+            .cards
+            .red
+            .iter()
+            .chain(self.cards.blue.iter())
+            .chain([&self.cards.side])
+            .map(|name| parse_card(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        state.deck = Deck::new(cards.try_into().expect("Deck must have 5 cards"));
+
+        Ok(GameState::from_state(state, self.current_turn))
+    }
+}
+
+/// Card and squares of the move in the `move` command, e.g. `tiger` and `c1c3`
+pub fn format_litama_move(state: &State, done_move: &DoneMove) -> (String, String) {
+    (
+        card_name(state.deck.get_card(done_move.used_card_idx)),
+        format!(
+            "{}{}",
+            Move::convert_idx_to_notation(done_move.mov.from),
+            Move::convert_idx_to_notation(done_move.mov.to)
+        ),
+    )
+}
+
+pub struct LitamaClient {
+    socket: WebSocket,
+}
+
+impl LitamaClient {
+    /// Connects to the server, e.g. `ws://127.0.0.1:5000`
+    pub fn connect(url: &str) -> Result<Self, LitamaError> {
+        Ok(Self {
+            socket: WebSocket::connect(url)?,
+        })
+    }
+
+    pub fn create(&mut self, username: &str) -> Result<MatchToken, LitamaError> {
+        match self.request(&format!("create {}", username))? {
+            Message::Create(token) => Ok(token),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    pub fn join(&mut self, match_id: &str, username: &str) -> Result<MatchToken, LitamaError> {
+        match self.request(&format!("join {} {}", match_id, username))? {
+            Message::Join(token) => Ok(token),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    pub fn state(&mut self, match_id: &str) -> Result<MatchState, LitamaError> {
+        match self.request(&format!("state {}", match_id))? {
+            Message::State(state) => Ok(state),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    /// Makes the move in the position of the state and returns the state after it
+    pub fn make_move(
+        &mut self,
+        token: &MatchToken,
+        state: &State,
+        done_move: &DoneMove,
+    ) -> Result<MatchState, LitamaError> {
+        let (card, squares) = format_litama_move(state, done_move);
+        let command = format!(
+            "move {} {} {} {}",
+            token.match_id, token.token, card, squares
+        );
+        match self.request(&command)? {
+            Message::State(state) => Ok(state),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    fn request(&mut self, command: &str) -> Result<Message, LitamaError> {
+        self.socket.send_text(command)?;
+        let answer = self.socket.receive_text()?.ok_or_else(|| {
+            LitamaError::Connection(String::from("Server has closed the connection"))
+        })?;
+
+        match serde_json::from_str(&answer) {
+            Ok(Message::Error(error)) => Err(LitamaError::Server(error.error)),
+            Ok(message) => Ok(message),
+            Err(e) => Err(LitamaError::Protocol(format!("{}: {}", e, answer))),
+        }
+    }
+}
+
+fn unexpected(message: Message) -> LitamaError {
+    LitamaError::Protocol(format!("Unexpected message {:?}", message))
+}
+
+/// Plays the remote match with any agent
+pub struct LitamaPlayer {
+    pub agent: Box<dyn Agent>,
+    /// Pause between the requests of the state while the opponent thinks
+    pub poll_interval: Duration,
+}
+
+impl LitamaPlayer {
+    pub fn new(agent: Box<dyn Agent>) -> Self {
+        Self {
+            agent,
+            poll_interval: Duration::from_millis(100),
+        }
+    }
+
+    /// Makes the moves of the player until the end of the match and returns the winner
+    pub fn play(
+        &self,
+        client: &mut LitamaClient,
+        token: &MatchToken,
+    ) -> Result<PlayerColor, LitamaError> {
+        let mut match_state = client.state(&token.match_id)?;
+        loop {
+            match match_state.game_state {
+                MatchStatus::Ended => {
+                    return match_state.winner.ok_or_else(|| {
+                        LitamaError::Protocol(String::from("Match has ended without the winner"))
+                    })
+                }
+                MatchStatus::InProgress if match_state.current_turn == token.color => {
+                    let game_state = match_state.game_state()?;
+                    let (done_move, _) = self
+                        .agent
+                        .try_generate_move(&game_state)
+                        .map_err(LitamaError::Agent)?;
+                    match_state = client.make_move(token, &game_state.state, &done_move)?;
+                }
+                _ => {
+                    thread::sleep(self.poll_interval);
+                    match_state = client.state(&token.match_id)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+        notation::{format_move, parse_move},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_state_is_written_back() {
+        let message = r#"{"messageType":"state","matchId":"5f2a","usernames":{"red":"a","blue":"b"},
+            "gameState":"in progress","board":"1121100000000000000033433","moves":[],
+            "currentTurn":"red","cards":{"red":["tiger","dragon"],"blue":["frog","rabbit"],
+            "side":"horse"},"winner":"none"}"#;
+        let match_state = match serde_json::from_str::<Message>(message).unwrap() {
+            Message::State(match_state) => match_state,
+            message => panic!("Message must be the state: {:?}", message),
+        };
+
+        let game_state = match_state.game_state().unwrap();
+        let deck = Deck::new([TIGER, DRAGON, FROG, RABBIT, HORSE]);
+        assert_eq!(game_state.state, State::with_deck(deck));
+        assert_eq!(game_state.curr_player_color, PlayerColor::Red);
+
+        let mut written = match_state.clone();
+        written.board.clear();
+        written.set_state(&game_state.state, game_state.curr_player_color);
+        assert_eq!(written, match_state);
+
+        let done_move = parse_move(&game_state.state, PlayerColor::Red, "tiger:c1c3").unwrap();
+        assert_eq!(
+            format_litama_move(&game_state.state, &done_move),
+            ("tiger".to_owned(), "c1c3".to_owned())
+        );
+        assert_eq!(format_move(&game_state.state, &done_move), "tiger:c1c3");
+    }
+
+    #[test]
+    fn test_board_without_one_king_is_rejected() {
+        let message = r#"{"messageType":"state","matchId":"5f2a","usernames":{"red":"a","blue":"b"},
+            "gameState":"in progress","board":"1121100000000000000033433","moves":[],
+            "currentTurn":"red","cards":{"red":["tiger","dragon"],"blue":["frog","rabbit"],
+            "side":"horse"},"winner":"none"}"#;
+        let match_state = match serde_json::from_str::<Message>(message).unwrap() {
+            Message::State(match_state) => match_state,
+            message => panic!("Message must be the state: {:?}", message),
+        };
+
+        for board in [
+            "1111100000000000000033433",
+            "1121100000000000000033333",
+            "1221100000000000000033433",
+            "1121100000000000000043433",
+        ] {
+            let match_state = MatchState {
+                board: board.to_owned(),
+                ..match_state.clone()
+            };
+            assert!(matches!(
+                match_state.game_state(),
+                Err(LitamaError::Protocol(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_errors_are_parsed() {
+        let message = r#"{"messageType":"error","command":"join","error":"Match not found"}"#;
+        assert_eq!(
+            serde_json::from_str::<Message>(message).unwrap(),
+            Message::Error(ServerError {
+                match_id: None,
+                command: "join".to_owned(),
+                error: "Match not found".to_owned()
+            })
+        );
+    }
+}
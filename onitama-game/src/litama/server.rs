@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use rand::{rngs::SmallRng, Rng};
+
+use crate::{
+    common::seeded_rng,
+    game::{
+        deck::Deck,
+        game_state::GameState,
+        notation::{format_move, parse_move},
+        player_color::PlayerColor,
+    },
+};
+
+use super::{
+    websocket::WebSocket, Cards, MatchState, MatchStatus, MatchToken, Message, ServerError,
+    Usernames,
+};
+
+/// Local server of the Litama subset which the client uses, so the matches can be played
+/// without the network. It serves in the background until the process exits
+pub struct LitamaServer {
+    address: SocketAddr,
+}
+
+impl LitamaServer {
+    /// Starts the server, the seed makes the decks, the colors and the tokens reproducible
+    pub fn start(address: impl ToSocketAddrs, seed: Option<u64>) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let lobby = Arc::new(Mutex::new(Lobby {
+            matches: HashMap::new(),
+            rng: seeded_rng(seed, 0),
+        }));
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let lobby = lobby.clone();
+                thread::spawn(move || {
+                    if let Err(e) = serve(stream, &lobby) {
+                        eprintln!("Litama connection has failed: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { address })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+}
+
+struct Match {
+    /// Username and token of every color, the red player is the first one
+    players: [Option<(String, String)>; 2],
+    game_state: GameState,
+    moves: Vec<String>,
+    winner: Option<PlayerColor>,
+}
+
+impl Match {
+    fn state(&self, match_id: &str) -> MatchState {
+        let username = |color: PlayerColor| {
+            self.players[color as usize]
+                .as_ref()
+                .map(|(username, _)| username.clone())
+                .unwrap_or_default()
+        };
+        let game_state = if self.winner.is_some() {
+            MatchStatus::Ended
+        } else if self.players.iter().any(Option::is_none) {
+            MatchStatus::WaitingForPlayer
+        } else {
+            MatchStatus::InProgress
+        };
+
+        let mut state = MatchState {
+            match_id: match_id.to_owned(),
+            usernames: Usernames {
+                red: username(PlayerColor::Red),
+                blue: username(PlayerColor::Blue),
+            },
+            game_state,
+            board: String::new(),
+            moves: self.moves.clone(),
+            current_turn: self.game_state.curr_player_color,
+            cards: Cards {
+                red: vec![],
+                blue: vec![],
+                side: String::new(),
+            },
+            winner: self.winner,
+        };
+        state.set_state(&self.game_state.state, self.game_state.curr_player_color);
+        state
+    }
+}
+
+struct Lobby {
+    matches: HashMap<String, Match>,
+    rng: SmallRng,
+}
+
+impl Lobby {
+    fn execute(&mut self, command: &str) -> Message {
+        let args = command.split_whitespace().collect::<Vec<_>>();
+        let name = args.first().copied().unwrap_or_default();
+        let result = match args.as_slice() {
+            ["create", username] => Ok(self.create(username)),
+            ["join", match_id, username] => self.join(match_id, username),
+            ["state", match_id] => self
+                .find(match_id)
+                .map(|m| Message::State(m.state(match_id))),
+            ["move", match_id, token, card, squares] => {
+                self.make_move(match_id, token, &format!("{}:{}", card, squares))
+            }
+            ["create" | "join" | "state" | "move", ..] => Err(String::from("Wrong arguments")),
+            _ => Err(format!("Unknown command {}", name)),
+        };
+
+        result.unwrap_or_else(|error| {
+            Message::Error(ServerError {
+                match_id: args.get(1).map(|id| id.to_string()),
+                command: name.to_owned(),
+                error,
+            })
+        })
+    }
+
+    fn create(&mut self, username: &str) -> Message {
+        let match_id = format!("{:08x}", self.rng.gen::<u32>());
+        let token = format!("{:016x}", self.rng.gen::<u64>());
+        let color = if self.rng.gen_bool(0.5) {
+            PlayerColor::Red
+        } else {
+            PlayerColor::Blue
+        };
+
+        let mut players = [None, None];
+        players[color as usize] = Some((username.to_owned(), token.clone()));
+        let new_match = Match {
+            players,
+            game_state: GameState::with_deck(Deck::from_seed(self.rng.gen())),
+            moves: vec![],
+            winner: None,
+        };
+        self.matches.insert(match_id.clone(), new_match);
+
+        Message::Create(MatchToken {
+            match_id,
+            token,
+            color,
+        })
+    }
+
+    fn join(&mut self, match_id: &str, username: &str) -> Result<Message, String> {
+        let token = format!("{:016x}", self.rng.gen::<u64>());
+        let joined = self
+            .matches
+            .get_mut(match_id)
+            .ok_or_else(|| String::from("Match not found"))?;
+        let idx = joined
+            .players
+            .iter()
+            .position(Option::is_none)
+            .ok_or_else(|| String::from("Match is full"))?;
+        joined.players[idx] = Some((username.to_owned(), token.clone()));
+
+        let color = if idx == PlayerColor::Red as usize {
+            PlayerColor::Red
+        } else {
+            PlayerColor::Blue
+        };
+        Ok(Message::Join(MatchToken {
+            match_id: match_id.to_owned(),
+            token,
+            color,
+        }))
+    }
+
+    fn find(&self, match_id: &str) -> Result<&Match, String> {
+        self.matches
+            .get(match_id)
+            .ok_or_else(|| String::from("Match not found"))
+    }
+
+    fn make_move(
+        &mut self,
+        match_id: &str,
+        token: &str,
+        notation: &str,
+    ) -> Result<Message, String> {
+        let played = self
+            .matches
+            .get_mut(match_id)
+            .ok_or_else(|| String::from("Match not found"))?;
+        if played.state(match_id).game_state != MatchStatus::InProgress {
+            return Err(String::from("Match is not in progress"));
+        }
+
+        let player_color = played.game_state.curr_player_color;
+        match &played.players[player_color as usize] {
+            Some((_, player_token)) if player_token == token => (),
+            _ => return Err(String::from("Not your turn")),
+        }
+
+        let state = &played.game_state.state;
+        let done_move = parse_move(state, player_color, notation).map_err(|e| e.to_string())?;
+        played.moves.push(format_move(state, &done_move));
+        if played.game_state.progress(done_move).is_win() {
+            played.winner = Some(player_color);
+        }
+
+        Ok(Message::State(played.state(match_id)))
+    }
+}
+
+fn serve(stream: TcpStream, lobby: &Mutex<Lobby>) -> io::Result<()> {
+    let mut socket = WebSocket::accept(stream)?;
+    while let Some(command) = socket.receive_text()? {
+        let message = lobby.lock().unwrap().execute(&command);
+        let answer = serde_json::to_string(&message).expect("Messages are serialized");
+        socket.send_text(&answer)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,379 @@
+//! Minimal WebSocket (RFC 6455) over a plain TCP stream: the handshake of both sides
+//! and the text messages. TLS is not supported, so the secure servers need a local proxy
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+use rand::Rng;
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Larger messages are rejected, the messages of the game are much smaller
+const MAX_MESSAGE_LEN: u64 = 1 << 24;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+pub struct WebSocket {
+    stream: TcpStream,
+    /// The frames of the client are masked
+    is_client: bool,
+    is_closed: bool,
+}
+
+impl WebSocket {
+    /// Connects to `ws://host:port/path`
+    pub fn connect(url: &str) -> io::Result<Self> {
+        if url.starts_with("wss://") {
+            return Err(invalid_input(
+                "TLS is not supported, connect through a local TLS proxy, see litama_bot --help",
+            ));
+        }
+        let address = url.strip_prefix("ws://").unwrap_or(url);
+        let (host, path) = match address.find('/') {
+            Some(idx) => address.split_at(idx),
+            None => (address, "/"),
+        };
+
+        let mut stream = TcpStream::connect(host)?;
+        stream.set_nodelay(true)?;
+
+        let key = base64(&rand::thread_rng().gen::<[u8; 16]>());
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, host, key
+        )?;
+
+        let response = read_head(&mut stream)?;
+        let status = response.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("101") {
+            return Err(invalid_data(format!(
+                "Server has not switched to WebSocket: {}",
+                status
+            )));
+        }
+        if header(&response, "sec-websocket-accept") != Some(accept_key(&key)) {
+            return Err(invalid_data("Server has answered with a wrong key"));
+        }
+
+        Ok(Self {
+            stream,
+            is_client: true,
+            is_closed: false,
+        })
+    }
+
+    /// Answers the handshake of the client which has connected to the server
+    pub fn accept(mut stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let request = read_head(&mut stream)?;
+        let key = match header(&request, "sec-websocket-key") {
+            Some(key) => key,
+            None => {
+                stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+                return Err(invalid_data("Request is not a WebSocket handshake"));
+            }
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key)
+        )?;
+
+        Ok(Self {
+            stream,
+            is_client: false,
+            is_closed: false,
+        })
+    }
+
+    pub fn send_text(&mut self, text: &str) -> io::Result<()> {
+        self.send_frame(OPCODE_TEXT, text.as_bytes())
+    }
+
+    /// Waits for the next text message. Returns None when the other side closes the connection
+    pub fn receive_text(&mut self) -> io::Result<Option<String>> {
+        let mut message = vec![];
+        let mut is_text = false;
+
+        while !self.is_closed {
+            let (is_final, opcode, payload) = self.read_frame()?;
+            match opcode {
+                OPCODE_TEXT | OPCODE_BINARY => {
+                    is_text = opcode == OPCODE_TEXT;
+                    message = payload;
+                }
+                OPCODE_CONTINUATION => message.extend(payload),
+                OPCODE_PING => {
+                    self.send_frame(OPCODE_PONG, &payload)?;
+                    continue;
+                }
+                OPCODE_PONG => continue,
+                OPCODE_CLOSE => {
+                    self.close()?;
+                    return Ok(None);
+                }
+                _ => return Err(invalid_data(format!("Unknown opcode {}", opcode))),
+            }
+
+            if message.len() as u64 > MAX_MESSAGE_LEN {
+                return Err(invalid_data("Message is too long"));
+            }
+            if is_final {
+                if !is_text {
+                    // The protocol of the game speaks only text
+                    message.clear();
+                    continue;
+                }
+                return String::from_utf8(message)
+                    .map(Some)
+                    .map_err(|e| invalid_data(e.to_string()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn close(&mut self) -> io::Result<()> {
+        if self.is_closed {
+            return Ok(());
+        }
+        self.is_closed = true;
+        self.send_frame(OPCODE_CLOSE, &[])
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        let mask_bit = if self.is_client { 0x80 } else { 0 };
+        match payload.len() {
+            len @ 0..=125 => frame.push(mask_bit | len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(mask_bit | 126);
+                frame.extend((len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(mask_bit | 127);
+                frame.extend((len as u64).to_be_bytes());
+            }
+        }
+
+        if self.is_client {
+            let mask = rand::thread_rng().gen::<[u8; 4]>();
+            frame.extend(mask);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i & 3]));
+        } else {
+            frame.extend(payload);
+        }
+
+        self.stream.write_all(&frame)?;
+        self.stream.flush()
+    }
+
+    /// Reads the frame and returns its final flag, opcode and unmasked payload
+    fn read_frame(&mut self) -> io::Result<(bool, u8, Vec<u8>)> {
+        let mut head = [0u8; 2];
+        self.stream.read_exact(&mut head)?;
+        let is_final = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        let is_masked = head[1] & 0x80 != 0;
+
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0u8; 2];
+                self.stream.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0u8; 8];
+                self.stream.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if len > MAX_MESSAGE_LEN {
+            return Err(invalid_data("Message is too long"));
+        }
+
+        let mut mask = [0u8; 4];
+        if is_masked {
+            self.stream.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0u8; len as usize];
+        self.stream.read_exact(&mut payload)?;
+        if is_masked {
+            for (i, b) in payload.iter_mut().enumerate() {
+                *b ^= mask[i & 3];
+            }
+        }
+
+        Ok((is_final, opcode, payload))
+    }
+}
+
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        self.close().ok();
+    }
+}
+
+fn invalid_input(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Reads the HTTP head up to the empty line. It is read byte by byte,
+/// so the frames after it stay in the stream
+fn read_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = vec![];
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8192 {
+            return Err(invalid_data("HTTP head is too long"));
+        }
+        stream.read_exact(&mut byte)?;
+        head.push(byte[0]);
+    }
+    String::from_utf8(head).map_err(|e| invalid_data(e.to_string()))
+}
+
+/// Value of the HTTP header, the name must be lowercase
+fn header(head: &str, name: &str) -> Option<String> {
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case(name) {
+            Some(value.trim().to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut result = String::new();
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, block[0], block[1], block[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let idx = (bits >> (18 - 6 * i)) & 0x3F;
+                result.push(ALPHABET[idx as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// SHA-1 which the handshake requires
+fn sha1(bytes: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((bytes.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (w, word) in w.iter_mut().zip(block.chunks(4)) {
+            *w = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (i, h) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_handshake_key() {
+        // Example of RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"a"), "YQ==");
+    }
+
+    #[test]
+    fn test_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = WebSocket::accept(stream).unwrap();
+            while let Some(message) = socket.receive_text().unwrap() {
+                socket.send_text(&message.to_uppercase()).unwrap();
+            }
+        });
+
+        let mut socket = WebSocket::connect(&format!("ws://{}/", address)).unwrap();
+        let long_message = "move ".repeat(30_000);
+        for message in ["create player", "", long_message.as_str()] {
+            socket.send_text(message).unwrap();
+            assert_eq!(socket.receive_text().unwrap(), Some(message.to_uppercase()));
+        }
+        socket.close().unwrap();
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,530 @@
+//! Matches of two or more agents which are played in parallel.
+//!
+//! Every pairing of the agents plays the games in pairs: the same opening is played twice
+//! with the colors swapped, so a lucky deck does not decide the match. The openings are
+//! random decks or the ones of an opening suite, see `suite`.
+//! The games which go on for too long or repeat the positions are adjudicated as draws,
+//! the agents which exceed the time limit or fail to move lose the game
+
+pub mod rating;
+pub mod score;
+pub mod sprt;
+pub mod suite;
+pub mod tournament;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs, io,
+    path::PathBuf,
+    sync::{mpsc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::{
+        agent::{Agent, AgentError},
+        registry::{format_duration, AgentRegistry, AgentSpec, SpecError},
+    },
+    common::seeded_rng,
+    game::{
+        deck::Deck, game_record::GameRecord, move_result::MoveResult, player_color::PlayerColor,
+    },
+};
+
+use self::{
+    score::ScoreTable,
+    sprt::{Sprt, SprtConfig, SprtDecision},
+    suite::Opening,
+};
+
+/// Why the game has ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Termination {
+    /// King was captured or has reached the temple
+    Normal,
+    /// Draw after `Adjudication::max_plies`
+    MaxPlies,
+    /// Draw after the position has repeated `Adjudication::repetitions` times
+    Repetition,
+    /// Player has exceeded the time limit
+    Timeout,
+    /// Agent could not make a move, e.g. the external engine has crashed
+    Forfeit,
+}
+
+/// Rules which end the games before a king is captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adjudication {
+    pub max_plies: usize,
+    /// Same position with the same player to move, 0 turns the rule off
+    pub repetitions: usize,
+    /// Player whose move takes longer loses on time
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            max_plies: 200,
+            repetitions: 3,
+            time_limit: None,
+        }
+    }
+}
+
+/// Plays the game between the red agent and the blue agent from the opening.
+/// The record starts with the moves of the opening
+pub fn play_game(
+    agents: [&dyn Agent; 2],
+    opening: &Opening,
+    adjudication: &Adjudication,
+) -> (GameRecord, Termination) {
+    let (mut record, mut game_state) = opening.record();
+    let mut repetitions = HashMap::new();
+
+    loop {
+        if record.moves.len() >= adjudication.max_plies {
+            return (record, Termination::MaxPlies);
+        }
+
+        let player_color = game_state.curr_player_color;
+        let count = repetitions
+            .entry(game_state.state.zobrist_hash(player_color))
+            .or_insert(0);
+        *count += 1;
+        if adjudication.repetitions > 0 && *count >= adjudication.repetitions {
+            return (record, Termination::Repetition);
+        }
+
+        let start = Instant::now();
+        let done_move = match agents[player_color as usize].try_generate_move(&game_state) {
+            Ok((done_move, _)) => done_move,
+            Err(e) => {
+                record.result = MoveResult::forfeit(player_color);
+                let termination = match e {
+                    AgentError::Timeout => Termination::Timeout,
+                    _ => {
+                        eprintln!("{:?} forfeits the game: {}", player_color, e);
+                        Termination::Forfeit
+                    }
+                };
+                return (record, termination);
+            }
+        };
+        if matches!(adjudication.time_limit, Some(limit) if start.elapsed() > limit) {
+            record.result = MoveResult::forfeit(player_color);
+            return (record, Termination::Timeout);
+        }
+
+        let card = *game_state.state.deck.get_card(done_move.used_card_idx);
+        let move_result = game_state.progress(done_move);
+        record.push(&card, done_move.mov, move_result);
+
+        if move_result.is_win() {
+            return (record, Termination::Normal);
+        }
+    }
+}
+
+/// Finished game of the match. It is saved as a game record with the players,
+/// so the other tools can read it as an ordinary `GameRecord`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchGame {
+    /// Number of the game starting from 1
+    pub number: usize,
+    pub red: String,
+    pub blue: String,
+    /// Indices of the red and the blue agent in the runner
+    #[serde(skip)]
+    pub players: [usize; 2],
+    pub termination: Termination,
+    #[serde(flatten)]
+    pub record: GameRecord,
+}
+
+impl MatchGame {
+    /// Result written as `1-0`, `0-1` or `1/2-1/2` from the side of the red player
+    pub fn result(&self) -> &'static str {
+        match self.record.winner() {
+            Some(PlayerColor::Red) => "1-0",
+            Some(PlayerColor::Blue) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchConfig {
+    /// Games of every pairing of the agents, an odd amount is rounded up
+    pub games: usize,
+    /// Games which are played at the same time
+    pub concurrency: usize,
+    /// Search time of a move, it replaces the `time` parameter of every agent which has it
+    pub move_time: Option<Duration>,
+    pub adjudication: Adjudication,
+    /// Openings of the pairs of the games, they are repeated if there are more pairs.
+    /// Without the openings every pair gets a random deck
+    pub openings: Vec<Opening>,
+    /// Seed of the random decks and the agents. Every game gives the agents without their own seed
+    /// the seed increased by the number of the game, it is written to the record
+    pub seed: Option<u64>,
+    /// Every game is saved to this directory as `game_<number>.json`
+    pub record_dir: Option<PathBuf>,
+    /// Test of the first agent against the second one. The match stops
+    /// as soon as it accepts a hypothesis, the games which are being played are finished
+    pub sprt: Option<SprtConfig>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            concurrency: 1,
+            move_time: None,
+            adjudication: Adjudication::default(),
+            openings: vec![],
+            seed: None,
+            record_dir: None,
+            sprt: None,
+        }
+    }
+}
+
+/// Scores of the match after the finished games
+#[derive(Debug, Clone)]
+pub struct MatchProgress {
+    pub table: ScoreTable,
+    pub sprt: Option<Sprt>,
+}
+
+/// Game which is waiting to be played. The agents are indices of the specifications.
+/// Both games of a pair have the consecutive numbers and the first one is odd
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledGame {
+    pub number: usize,
+    pub red: usize,
+    pub blue: usize,
+    pub opening: Opening,
+}
+
+pub struct MatchRunner {
+    registry: AgentRegistry,
+    specs: Vec<AgentSpec>,
+    config: MatchConfig,
+}
+
+impl MatchRunner {
+    /// Checks that every agent can be built. Each game gets new agents built from the specifications
+    pub fn new(
+        registry: AgentRegistry,
+        mut specs: Vec<AgentSpec>,
+        config: MatchConfig,
+    ) -> Result<Self, SpecError> {
+        for spec in specs.iter_mut() {
+            let has_time = registry
+                .kind(&spec.kind)
+                .is_some_and(|kind| kind.params.iter().any(|p| p.name == "time"));
+            if let (Some(move_time), true) = (config.move_time, has_time) {
+                spec.set("time", format_duration(move_time));
+            }
+            registry.build_spec(spec)?;
+        }
+
+        Ok(Self {
+            registry,
+            specs,
+            config,
+        })
+    }
+
+    /// Names of the agents in the score table
+    pub fn agents(&self) -> Vec<String> {
+        self.specs.iter().map(AgentSpec::to_string).collect()
+    }
+
+    /// Games of all pairings. The pairings take turns, so all of them progress together
+    pub fn schedule(&self) -> Vec<ScheduledGame> {
+        let mut rng = seeded_rng(self.config.seed, 0);
+        let pairs = self.config.games.div_ceil(2);
+        let mut games = vec![];
+
+        for pair in 0..pairs {
+            let opening = if self.config.openings.is_empty() {
+                Opening::from(Deck::random(&mut rng))
+            } else {
+                self.config.openings[pair % self.config.openings.len()].clone()
+            };
+
+            for i in 0..self.specs.len() {
+                for j in (i + 1)..self.specs.len() {
+                    for (red, blue) in [(i, j), (j, i)] {
+                        games.push(ScheduledGame {
+                            number: games.len() + 1,
+                            red,
+                            blue,
+                            opening: opening.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        games
+    }
+
+    /// Plays all games and calls `on_game` after each one with the updated scores.
+    /// The games end in any order when they are played in parallel
+    pub fn run(
+        &self,
+        mut on_game: impl FnMut(&MatchGame, &MatchProgress),
+    ) -> io::Result<MatchProgress> {
+        let mut progress = MatchProgress {
+            table: ScoreTable::new(self.agents()),
+            sprt: self.config.sprt.map(Sprt::new),
+        };
+        // Points of the first agent in the pairs which have one finished game
+        let mut unpaired = HashMap::new();
+
+        self.play_games(self.schedule(), |game| {
+            let points = match game.record.winner() {
+                Some(PlayerColor::Red) => 1.,
+                Some(PlayerColor::Blue) => 0.,
+                None => 0.5,
+            };
+            progress.table.add(game.players[0], game.players[1], points);
+
+            let first_points = match game.players {
+                [0, 1] => Some(points),
+                [1, 0] => Some(1. - points),
+                _ => None,
+            };
+            let mut finished = false;
+            if let (Some(sprt), Some(first_points)) = (progress.sprt.as_mut(), first_points) {
+                sprt.add_game(first_points);
+                match unpaired.remove(&((game.number - 1) / 2)) {
+                    Some(other) => sprt.add_pair(other, first_points),
+                    None => {
+                        unpaired.insert((game.number - 1) / 2, first_points);
+                    }
+                }
+                finished = sprt.decision() != SprtDecision::Continue;
+            }
+
+            on_game(game, &progress);
+            !finished
+        })?;
+
+        Ok(progress)
+    }
+
+    /// Plays the games in parallel and calls `on_game` as soon as each one ends.
+    /// When `on_game` returns false the games which have not started are dropped.
+    /// The games are saved to the directory of the records before `on_game` is called
+    pub fn play_games(
+        &self,
+        games: Vec<ScheduledGame>,
+        mut on_game: impl FnMut(&MatchGame) -> bool,
+    ) -> io::Result<()> {
+        if let Some(dir) = &self.config.record_dir {
+            fs::create_dir_all(dir)?;
+        }
+
+        let queue = Mutex::new(games.into_iter().collect::<VecDeque<_>>());
+        let (tx, rx) = mpsc::channel();
+
+        thread::scope(|s| {
+            for _ in 0..self.config.concurrency.max(1) {
+                let tx = tx.clone();
+                let queue = &queue;
+                s.spawn(move || loop {
+                    let scheduled = match queue.lock().unwrap().pop_front() {
+                        Some(scheduled) => scheduled,
+                        None => break,
+                    };
+                    if tx.send(self.play(scheduled)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(tx);
+
+            for game in rx {
+                if let Some(dir) = &self.config.record_dir {
+                    let path = dir.join(format!("game_{:04}.json", game.number));
+                    let saved = serde_json::to_string(&game)
+                        .map_err(io::Error::from)
+                        .and_then(|json| fs::write(&path, json));
+                    if let Err(e) = saved {
+                        eprintln!("Cannot save the game to {}: {}", path.display(), e);
+                    }
+                }
+
+                if !on_game(&game) {
+                    queue.lock().unwrap().clear();
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn play(&self, scheduled: ScheduledGame) -> MatchGame {
+        let seed = self
+            .config
+            .seed
+            .map(|seed| seed.wrapping_add(scheduled.number as u64));
+        let build = |idx: usize| {
+            self.registry
+                .build_spec(&self.registry.seeded(&self.specs[idx], seed))
+                .expect("Specifications are checked when the runner is created")
+        };
+        let (red, blue) = (build(scheduled.red), build(scheduled.blue));
+        let (mut record, termination) = play_game(
+            [red.as_ref(), blue.as_ref()],
+            &scheduled.opening,
+            &self.config.adjudication,
+        );
+        record.seed = seed;
+
+        MatchGame {
+            number: scheduled.number,
+            red: self.specs[scheduled.red].to_string(),
+            blue: self.specs[scheduled.blue].to_string(),
+            players: [scheduled.red, scheduled.blue],
+            termination,
+            record,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        card::{DRAGON, FROG, HORSE, RABBIT, TIGER},
+        notation::parse_deck,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_paired_schedule() {
+        let specs = ["random:seed=1", "random:seed=2", "random:seed=3"]
+            .iter()
+            .map(|spec| spec.parse().unwrap())
+            .collect();
+        let config = MatchConfig {
+            games: 3,
+            openings: vec![parse_deck("tiger,dragon frog,rabbit horse").unwrap().into()],
+            ..Default::default()
+        };
+        let runner = MatchRunner::new(AgentRegistry::default(), specs, config).unwrap();
+        let games = runner.schedule();
+
+        // 2 pairs of games for each of the 3 pairings
+        assert_eq!(games.len(), 12);
+        assert_eq!((games[0].red, games[0].blue), (0, 1));
+        assert_eq!((games[1].red, games[1].blue), (1, 0));
+        assert!(games
+            .iter()
+            .all(|g| g.opening.deck == Deck::new([TIGER, DRAGON, FROG, RABBIT, HORSE])));
+        assert_eq!(games[11].number, 12);
+    }
+
+    #[test]
+    fn test_match() {
+        let specs = vec![
+            "alphabeta:depth=3,time=100ms".parse().unwrap(),
+            "alphabeta:depth=2,time=100ms".parse().unwrap(),
+        ];
+        let dir = std::env::temp_dir().join(format!("onitama_match_{}", std::process::id()));
+        let config = MatchConfig {
+            games: 4,
+            concurrency: 2,
+            seed: Some(7),
+            record_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let runner = MatchRunner::new(AgentRegistry::default(), specs, config).unwrap();
+
+        let mut played = vec![];
+        let progress = runner.run(|game, _| played.push(game.number)).unwrap();
+        played.sort();
+
+        assert_eq!(played, [1, 2, 3, 4]);
+        assert_eq!(progress.table.score(0, 1).games(), 4);
+        let records = GameRecord::load_dir(&dir).unwrap();
+        assert_eq!(records.len(), 4);
+        assert!(records.iter().all(|r| r.positions().is_ok()));
+        let mut seeds = records.iter().map(|r| r.seed).collect::<Vec<_>>();
+        seeds.sort();
+        assert_eq!(seeds, [Some(8), Some(9), Some(10), Some(11)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sprt_stops_match() {
+        let specs = vec![
+            "alphabeta:depth=5,time=1s".parse().unwrap(),
+            "alphabeta:depth=2,time=1s".parse().unwrap(),
+        ];
+        let config = MatchConfig {
+            games: 200,
+            concurrency: 4,
+            seed: Some(3),
+            sprt: Some(SprtConfig {
+                elo0: 0.,
+                elo1: 200.,
+                alpha: 0.1,
+                beta: 0.1,
+                pentanomial: true,
+            }),
+            ..Default::default()
+        };
+        let runner = MatchRunner::new(AgentRegistry::default(), specs, config).unwrap();
+        let progress = runner.run(|_, _| ()).unwrap();
+        let sprt = progress.sprt.unwrap();
+
+        assert_eq!(sprt.decision(), SprtDecision::AcceptH1);
+        assert!(progress.table.score(0, 1).games() < 200);
+        assert_eq!(
+            sprt.games.iter().sum::<usize>(),
+            progress.table.score(0, 1).games()
+        );
+    }
+
+    #[test]
+    fn test_adjudication() {
+        let agent = AgentRegistry::default().build("alphabeta:depth=2").unwrap();
+        let adjudication = Adjudication {
+            max_plies: 2,
+            ..Default::default()
+        };
+        let (record, termination) = play_game(
+            [agent.as_ref(), agent.as_ref()],
+            &Opening::from(Deck::from_seed(1)),
+            &adjudication,
+        );
+        assert_eq!(termination, Termination::MaxPlies);
+        assert_eq!(record.moves.len(), 2);
+        assert_eq!(record.winner(), None);
+
+        let adjudication = Adjudication {
+            time_limit: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let (record, termination) = play_game(
+            [agent.as_ref(), agent.as_ref()],
+            &Opening::from(Deck::from_seed(1)),
+            &adjudication,
+        );
+        assert_eq!(termination, Termination::Timeout);
+        assert!(record.moves.is_empty());
+        assert_eq!(
+            record.result,
+            MoveResult::forfeit(Deck::from_seed(1).neutral_card().player_color)
+        );
+    }
+}
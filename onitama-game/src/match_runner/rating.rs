@@ -0,0 +1,341 @@
+//! Maximum-likelihood ratings of all agents from the results of all their games.
+//!
+//! Unlike the game-by-game Elo updates, the estimate does not depend on the order of
+//! the games. The expected score follows the logistic Elo curve, a draw counts as half
+//! a win and half a loss. One virtual draw is added to every pairing which has played,
+//! like the prior of BayesElo, so an agent which has won every game still gets a finite rating.
+//! The confidence intervals come from the curvature of the likelihood around the estimate
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::score::{Score, ScoreTable};
+
+/// Quantile of the normal distribution for the 95% confidence interval
+const Z_95: f64 = 1.96;
+const VIRTUAL_DRAWS: f64 = 1.;
+const MAX_ITERATIONS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub agent: String,
+    pub elo: f64,
+    /// Half width of the 95% confidence interval. It is unknown if the agent
+    /// has not played with the anchor directly or through the other agents,
+    /// then the rating is only relative to the agents it has played
+    pub margin: Option<f64>,
+    pub games: usize,
+    /// Share of the points in all games
+    pub score: f64,
+}
+
+/// Ratings of the agents which have played, the anchor has the fixed rating
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ratings {
+    pub anchor: String,
+    pub ratings: Vec<Rating>,
+}
+
+impl Ratings {
+    /// Estimates the ratings from the table, `anchor` is the index of the agent
+    /// with the rating `anchor_elo`, e.g. `Random` with 0
+    pub fn estimate(table: &ScoreTable, anchor: usize, anchor_elo: f64) -> Self {
+        let agents = (0..table.agents.len())
+            .filter(|&i| i == anchor || table.total(i).games() > 0)
+            .collect::<Vec<_>>();
+        let n = agents.len();
+
+        // Games and points of the row agent against the column agent with the virtual draws
+        let mut games = vec![vec![0.; n]; n];
+        let mut points = vec![0.; n];
+        for (a, &i) in agents.iter().enumerate() {
+            for (b, &j) in agents.iter().enumerate() {
+                let score = table.score(i, j);
+                if score.games() > 0 {
+                    games[a][b] = score.games() as f64 + VIRTUAL_DRAWS;
+                    points[a] += points_of(&score) + VIRTUAL_DRAWS / 2.;
+                }
+            }
+        }
+
+        let strengths = maximize_likelihood(&games, &points);
+        let position = agents.iter().position(|&i| i == anchor).unwrap();
+        let shift = anchor_elo - elo(strengths[position]);
+        let margins = margins(&games, &strengths, position);
+
+        let ratings = agents
+            .iter()
+            .enumerate()
+            .map(|(a, &i)| {
+                let total = table.total(i);
+                Rating {
+                    agent: table.agents[i].clone(),
+                    elo: elo(strengths[a]) + shift,
+                    margin: margins[a],
+                    games: total.games(),
+                    score: total.ratio(),
+                }
+            })
+            .collect();
+
+        Self {
+            anchor: table.agents[anchor].clone(),
+            ratings,
+        }
+    }
+
+    pub fn get(&self, agent: &str) -> Option<&Rating> {
+        self.ratings.iter().find(|r| r.agent == agent)
+    }
+}
+
+impl fmt::Display for Ratings {
+    /// Table of the agents from the strongest one
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .ratings
+            .iter()
+            .map(|r| r.agent.len())
+            .max()
+            .unwrap_or(0);
+        let mut sorted = self.ratings.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| b.elo.total_cmp(&a.elo));
+
+        writeln!(
+            f,
+            "Rank  {:<width$}  {:>7}  {:>6}  {:>5}  {:>5}",
+            "Agent",
+            "Elo",
+            "+/-",
+            "Games",
+            "Score",
+            width = width
+        )?;
+        for (rank, rating) in sorted.iter().enumerate() {
+            let margin = match rating.margin {
+                Some(margin) => format!("{:.1}", margin),
+                None => String::from("-"),
+            };
+            writeln!(
+                f,
+                "{:>4}  {:<width$}  {:>7.1}  {:>6}  {:>5}  {:>5.3}",
+                rank + 1,
+                rating.agent,
+                rating.elo,
+                margin,
+                rating.games,
+                rating.score,
+                width = width
+            )?;
+        }
+        write!(f, "Ratings are relative to {}", self.anchor)
+    }
+}
+
+fn points_of(score: &Score) -> f64 {
+    score.wins as f64 + 0.5 * score.draws as f64
+}
+
+/// Elo of the strength `gamma` where the expected score is `gamma_a / (gamma_a + gamma_b)`
+fn elo(gamma: f64) -> f64 {
+    400. * gamma.log10()
+}
+
+/// Minorization-maximization of Bradley-Terry which converges to the maximum of the likelihood
+fn maximize_likelihood(games: &[Vec<f64>], points: &[f64]) -> Vec<f64> {
+    let n = points.len();
+    let mut strengths = vec![1.; n];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut updated = (0..n)
+            .map(|i| {
+                let denominator = (0..n)
+                    .map(|j| games[i][j] / (strengths[i] + strengths[j]))
+                    .sum::<f64>();
+                if denominator > 0. {
+                    points[i] / denominator
+                } else {
+                    strengths[i]
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Only the ratios matter, the geometric mean is kept at 1
+        let mean = (updated.iter().map(|s| s.ln()).sum::<f64>() / n as f64).exp();
+        updated.iter_mut().for_each(|s| *s /= mean);
+
+        let change = updated
+            .iter()
+            .zip(strengths.iter())
+            .map(|(a, b)| (elo(*a) - elo(*b)).abs())
+            .fold(0., f64::max);
+        strengths = updated;
+        if change < 1e-6 {
+            break;
+        }
+    }
+
+    strengths
+}
+
+/// Margins from the inverse of the Fisher information where the anchor is fixed
+fn margins(games: &[Vec<f64>], strengths: &[f64], anchor: usize) -> Vec<Option<f64>> {
+    let n = strengths.len();
+    // Derivative of the expected score by the Elo difference is p * (1 - p) * ln(10) / 400
+    let c = 10f64.ln() / 400.;
+    let connected = connected_to(games, anchor);
+    let free = (0..n)
+        .filter(|&i| i != anchor && connected[i])
+        .collect::<Vec<_>>();
+
+    let mut information = vec![vec![0.; free.len()]; free.len()];
+    for (a, &i) in free.iter().enumerate() {
+        for j in 0..n {
+            let p = strengths[i] / (strengths[i] + strengths[j]);
+            let curvature = games[i][j] * p * (1. - p) * c * c;
+            information[a][a] += curvature;
+            if let Some(b) = free.iter().position(|&k| k == j) {
+                information[a][b] -= curvature;
+            }
+        }
+    }
+
+    let mut margins = vec![None; n];
+    margins[anchor] = Some(0.);
+    if let Some(variances) = invert_diagonal(information) {
+        for (a, &i) in free.iter().enumerate() {
+            margins[i] = Some(Z_95 * variances[a].sqrt());
+        }
+    }
+    margins
+}
+
+/// Agents which have played with the anchor directly or through the other agents
+fn connected_to(games: &[Vec<f64>], anchor: usize) -> Vec<bool> {
+    let mut connected = vec![false; games.len()];
+    connected[anchor] = true;
+    let mut stack = vec![anchor];
+
+    while let Some(i) = stack.pop() {
+        for j in 0..games.len() {
+            if games[i][j] > 0. && !connected[j] {
+                connected[j] = true;
+                stack.push(j);
+            }
+        }
+    }
+
+    connected
+}
+
+/// Diagonal of the inverse matrix by the Gauss-Jordan elimination
+fn invert_diagonal(mut matrix: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let n = matrix.len();
+    let mut inverse = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| if i == j { 1. } else { 0. })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let value = matrix[col][col];
+        for k in 0..n {
+            matrix[col][k] /= value;
+            inverse[col][k] /= value;
+        }
+        for row in 0..n {
+            let factor = matrix[row][col];
+            if row == col || factor == 0. {
+                continue;
+            }
+            for k in 0..n {
+                matrix[row][k] -= factor * matrix[col][k];
+                inverse[row][k] -= factor * inverse[col][k];
+            }
+        }
+    }
+
+    Some((0..n).map(|i| inverse[i][i]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(results: &[(usize, usize, Score)], agents: usize) -> ScoreTable {
+        let mut table = ScoreTable::new((0..agents).map(|i| format!("agent{}", i)).collect());
+        for (agent, opponent, score) in results {
+            table.add_score(*agent, *opponent, *score);
+        }
+        table
+    }
+
+    fn score(wins: usize, draws: usize, losses: usize) -> Score {
+        Score {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    #[test]
+    fn test_ratings_do_not_depend_on_order() {
+        // 75% score is +190.8 Elo, the virtual draw pulls it slightly to 0
+        let ratings = Ratings::estimate(&table(&[(1, 0, score(300, 0, 100))], 2), 0, 0.);
+        let rating = ratings.get("agent1").unwrap();
+        assert!((rating.elo - 190.).abs() < 1., "{}", rating.elo);
+        assert!(rating.margin.unwrap() > 20. && rating.margin.unwrap() < 50.);
+        assert_eq!(ratings.get("agent0").unwrap().elo, 0.);
+        assert_eq!(ratings.get("agent0").unwrap().margin, Some(0.));
+
+        let anchored = Ratings::estimate(&table(&[(1, 0, score(300, 0, 100))], 2), 1, 1000.);
+        assert!((anchored.get("agent0").unwrap().elo - 810.).abs() < 1.);
+    }
+
+    #[test]
+    fn test_transitive_ratings() {
+        // Agent 2 has never played agent 0, its rating comes through agent 1
+        let results = [
+            (1, 0, score(60, 20, 20)),
+            (2, 1, score(60, 20, 20)),
+            (2, 0, score(0, 0, 0)),
+        ];
+        let ratings = Ratings::estimate(&table(&results, 3), 0, 0.);
+        let first = ratings.get("agent1").unwrap();
+        let second = ratings.get("agent2").unwrap();
+        assert!(
+            (second.elo - 2. * first.elo).abs() < 5.,
+            "{} {}",
+            first.elo,
+            second.elo
+        );
+        assert!(second.margin.unwrap() > first.margin.unwrap());
+
+        // Perfect score is finite
+        let ratings = Ratings::estimate(&table(&[(1, 0, score(10, 0, 0))], 2), 0, 0.);
+        assert!(ratings.get("agent1").unwrap().elo.is_finite());
+    }
+
+    #[test]
+    fn test_disconnected_agents() {
+        let results = [(1, 0, score(5, 0, 5)), (3, 2, score(5, 0, 5))];
+        let ratings = Ratings::estimate(&table(&results, 5), 0, 0.);
+
+        // Agent 4 has not played at all
+        assert_eq!(ratings.ratings.len(), 4);
+        assert!(ratings.get("agent1").unwrap().margin.is_some());
+        assert!(ratings.get("agent3").unwrap().margin.is_none());
+        assert!(ratings.to_string().contains("agent3"));
+    }
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Quantile of the normal distribution for the 95% confidence interval
+const Z_95: f64 = 1.96;
+
+/// Results of one agent against another one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Score {
+    /// Adds the game with 1 point for a win, 0.5 for a draw and 0 for a loss
+    pub fn add(&mut self, points: f64) {
+        if points > 0.5 {
+            self.wins += 1;
+        } else if points < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Share of the points from 0 to 1, it is 0.5 without games
+    pub fn ratio(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    /// Same games from the side of the opponent
+    pub fn reversed(&self) -> Self {
+        Self {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    /// Elo difference to the opponent and the half width of its 95% confidence interval.
+    /// Both are infinite if one side has scored all the points
+    pub fn elo(&self) -> (f64, f64) {
+        let games = self.games() as f64;
+        let ratio = self.ratio();
+        if games == 0. {
+            return (0., f64::INFINITY);
+        }
+        if ratio == 0. || ratio == 1. {
+            return (elo_from_ratio(ratio), f64::INFINITY);
+        }
+
+        let variance = (self.wins as f64 * (1. - ratio).powi(2)
+            + self.draws as f64 * (0.5 - ratio).powi(2)
+            + self.losses as f64 * ratio.powi(2))
+            / games;
+        let deviation = (variance / games).sqrt();
+        let low = elo_from_ratio(ratio - Z_95 * deviation);
+        let high = elo_from_ratio(ratio + Z_95 * deviation);
+
+        (elo_from_ratio(ratio), (high - low) / 2.)
+    }
+}
+
+impl fmt::Display for Score {
+    /// Written as `wins - losses - draws [ratio]` like in the other match runners
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} - {} - {} [{:.3}]",
+            self.wins,
+            self.losses,
+            self.draws,
+            self.ratio()
+        )
+    }
+}
+
+/// Elo difference which gives the expected share of the points
+pub fn elo_from_ratio(ratio: f64) -> f64 {
+    if ratio <= 0. {
+        f64::NEG_INFINITY
+    } else if ratio >= 1. {
+        f64::INFINITY
+    } else {
+        400. * (ratio / (1. - ratio)).log10()
+    }
+}
+
+/// Scores of every agent against every other one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreTable {
+    pub agents: Vec<String>,
+    /// Score of the agent of the row against the agent of the column
+    pub scores: Vec<Vec<Score>>,
+}
+
+impl ScoreTable {
+    pub fn new(agents: Vec<String>) -> Self {
+        let scores = vec![vec![Score::default(); agents.len()]; agents.len()];
+        Self { agents, scores }
+    }
+
+    /// Adds the game where the first agent has got the points, the second one the rest
+    pub fn add(&mut self, agent: usize, opponent: usize, points: f64) {
+        self.scores[agent][opponent].add(points);
+        self.scores[opponent][agent].add(1. - points);
+    }
+
+    /// Adds all games of the agent against the opponent at once
+    pub fn add_score(&mut self, agent: usize, opponent: usize, score: Score) {
+        let games = &mut self.scores[agent][opponent];
+        games.wins += score.wins;
+        games.draws += score.draws;
+        games.losses += score.losses;
+        self.scores[opponent][agent] = self.scores[agent][opponent].reversed();
+    }
+
+    pub fn score(&self, agent: usize, opponent: usize) -> Score {
+        self.scores[agent][opponent]
+    }
+
+    /// Score of the agent against all opponents together
+    pub fn total(&self, agent: usize) -> Score {
+        self.scores[agent]
+            .iter()
+            .fold(Score::default(), |total, score| Score {
+                wins: total.wins + score.wins,
+                draws: total.draws + score.draws,
+                losses: total.losses + score.losses,
+            })
+    }
+}
+
+impl fmt::Display for ScoreTable {
+    /// Every pairing which has games, then the total of every agent
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.agents.iter().map(String::len).max().unwrap_or(0);
+
+        for i in 0..self.agents.len() {
+            for j in (i + 1)..self.agents.len() {
+                let score = self.score(i, j);
+                if score.games() == 0 {
+                    continue;
+                }
+                writeln!(
+                    f,
+                    "{:<width$} vs {:<width$}  {}  {}  {} games",
+                    self.agents[i],
+                    self.agents[j],
+                    score,
+                    format_elo(score.elo()),
+                    score.games(),
+                    width = width
+                )?;
+            }
+        }
+
+        if self.agents.len() > 2 {
+            writeln!(f)?;
+            for (i, agent) in self.agents.iter().enumerate() {
+                let total = self.total(i);
+                writeln!(
+                    f,
+                    "{:<width$}  {}  {}  {} games",
+                    agent,
+                    total,
+                    format_elo(total.elo()),
+                    total.games(),
+                    width = width
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Elo difference with its error bar, e.g. `Elo +35.2 +/- 20.1`
+pub fn format_elo((elo, margin): (f64, f64)) -> String {
+    format!("Elo {:+.1} +/- {:.1}", elo, margin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_elo() {
+        let even = Score {
+            wins: 10,
+            draws: 0,
+            losses: 10,
+        };
+        let (elo, margin) = even.elo();
+        assert_eq!(elo, 0.);
+        assert!(margin > 100. && margin < 200., "{}", margin);
+
+        let stronger = Score {
+            wins: 75,
+            draws: 0,
+            losses: 25,
+        };
+        let (elo, margin) = stronger.elo();
+        assert!((elo - 190.8).abs() < 0.1, "{}", elo);
+        assert!(margin < 100., "{}", margin);
+        assert!((stronger.reversed().elo().0 + elo).abs() < 1e-9);
+
+        let all_wins = Score {
+            wins: 4,
+            draws: 0,
+            losses: 0,
+        };
+        assert_eq!(all_wins.elo(), (f64::INFINITY, f64::INFINITY));
+    }
+
+    #[test]
+    fn test_score_table() {
+        let mut table = ScoreTable::new(vec![String::from("a"), String::from("b")]);
+        table.add(0, 1, 1.);
+        table.add(1, 0, 0.5);
+        table.add(1, 0, 1.);
+
+        assert_eq!(
+            table.score(0, 1),
+            Score {
+                wins: 1,
+                draws: 1,
+                losses: 1
+            }
+        );
+        assert_eq!(table.score(1, 0), table.score(0, 1).reversed());
+        assert_eq!(table.total(1).games(), 3);
+        assert!(table.to_string().starts_with("a vs b  1 - 1 - 1 [0.500]"));
+    }
+}
@@ -0,0 +1,247 @@
+//! Sequential probability ratio test of the Elo difference between two agents.
+//!
+//! H0 is that the difference is `elo0`, H1 that it is `elo1`. After every game the
+//! log-likelihood ratio of the results is compared with the bounds given by `alpha` and
+//! `beta`, so the match stops as soon as one hypothesis is accepted.
+//! The ratio is the generalized one: the results follow the most likely distribution
+//! which has the expected score of the hypothesis. The trinomial model counts the wins,
+//! draws and losses of single games, the pentanomial one counts the scores of the pairs
+//! of games played with the same deck and swapped colors, which removes the luck of the deck
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SprtConfig {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Probability to accept H1 when H0 is true
+    pub alpha: f64,
+    /// Probability to accept H0 when H1 is true
+    pub beta: f64,
+    /// Counts the pairs of games instead of the single games
+    pub pentanomial: bool,
+}
+
+impl Default for SprtConfig {
+    fn default() -> Self {
+        Self {
+            elo0: 0.,
+            elo1: 20.,
+            alpha: 0.05,
+            beta: 0.05,
+            pentanomial: true,
+        }
+    }
+}
+
+impl SprtConfig {
+    /// Lower and upper bound of the log-likelihood ratio
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1. - self.alpha)).ln(),
+            ((1. - self.beta) / self.alpha).ln(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SprtDecision {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sprt {
+    pub config: SprtConfig,
+    /// Losses, draws and wins of the first agent
+    pub games: [usize; 3],
+    /// Pairs where the first agent has scored 0, 0.5, 1, 1.5 and 2 points
+    pub pairs: [usize; 5],
+}
+
+impl Sprt {
+    pub fn new(config: SprtConfig) -> Self {
+        Self {
+            config,
+            games: [0; 3],
+            pairs: [0; 5],
+        }
+    }
+
+    /// Adds the game where the first agent has scored 0, 0.5 or 1 point
+    pub fn add_game(&mut self, points: f64) {
+        self.games[(points * 2.).round() as usize] += 1;
+    }
+
+    /// Adds both games of the pair. The games are also counted by `add_game`
+    pub fn add_pair(&mut self, first: f64, second: f64) {
+        self.pairs[((first + second) * 2.).round() as usize] += 1;
+    }
+
+    /// Log-likelihood ratio of H1 against H0
+    pub fn llr(&self) -> f64 {
+        let (counts, scores): (&[usize], &[f64]) = if self.config.pentanomial {
+            (&self.pairs, &[0., 0.25, 0.5, 0.75, 1.])
+        } else {
+            (&self.games, &[0., 0.5, 1.])
+        };
+        let total = counts.iter().sum::<usize>();
+        if total == 0 {
+            return 0.;
+        }
+
+        // Outcomes which have not happened yet get a tiny probability,
+        // otherwise a few equal results would make the score certain
+        let counts = counts
+            .iter()
+            .map(|&count| (count as f64).max(1e-3))
+            .collect::<Vec<_>>();
+        let sum = counts.iter().sum::<f64>();
+        let probs = counts.iter().map(|count| count / sum).collect::<Vec<_>>();
+        let score0 = score_from_elo(self.config.elo0);
+        let score1 = score_from_elo(self.config.elo1);
+        let (probs0, probs1) = (
+            most_likely(&probs, scores, score0),
+            most_likely(&probs, scores, score1),
+        );
+
+        total as f64
+            * probs
+                .iter()
+                .zip(probs0.iter().zip(probs1.iter()))
+                .map(|(p, (p0, p1))| p * (p1 / p0).ln())
+                .sum::<f64>()
+    }
+
+    pub fn decision(&self) -> SprtDecision {
+        let (lower, upper) = self.config.bounds();
+        let llr = self.llr();
+        if llr >= upper {
+            SprtDecision::AcceptH1
+        } else if llr <= lower {
+            SprtDecision::AcceptH0
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
+
+impl fmt::Display for Sprt {
+    /// Written as `SPRT elo0=0 elo1=20: LLR 1.23 [-2.94, 2.94] Continue`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (lower, upper) = self.config.bounds();
+        write!(
+            f,
+            "SPRT elo0={} elo1={}: LLR {:.2} [{:.2}, {:.2}] {:?}",
+            self.config.elo0,
+            self.config.elo1,
+            self.llr(),
+            lower,
+            upper,
+            self.decision()
+        )
+    }
+}
+
+/// Expected score of the logistic Elo difference
+pub fn score_from_elo(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+/// Distribution which is the closest to the observed one and has the given expected score.
+/// It is `p / (1 + lambda * (score - expected))` where lambda is found by the bisection
+fn most_likely(probs: &[f64], scores: &[f64], expected: f64) -> Vec<f64> {
+    let deviations = scores.iter().map(|s| s - expected).collect::<Vec<_>>();
+    let max = deviations.iter().cloned().fold(f64::MIN, f64::max);
+    let min = deviations.iter().cloned().fold(f64::MAX, f64::min);
+
+    // Every probability must stay positive
+    let (mut low, mut high) = (-1. / max, -1. / min);
+    for _ in 0..100 {
+        let lambda = (low + high) / 2.;
+        let mean = probs
+            .iter()
+            .zip(deviations.iter())
+            .map(|(p, d)| p * d / (1. + lambda * d))
+            .sum::<f64>();
+        if mean > 0. {
+            low = lambda;
+        } else {
+            high = lambda;
+        }
+    }
+
+    let lambda = (low + high) / 2.;
+    probs
+        .iter()
+        .zip(deviations.iter())
+        .map(|(p, d)| p / (1. + lambda * d))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_likely_distribution() {
+        let probs = [0.2, 0.3, 0.5];
+        let scores = [0., 0.5, 1.];
+        let distribution = most_likely(&probs, &scores, 0.5);
+
+        let sum = distribution.iter().sum::<f64>();
+        let mean = distribution
+            .iter()
+            .zip(scores.iter())
+            .map(|(p, s)| p * s)
+            .sum::<f64>();
+        assert!((sum - 1.).abs() < 1e-6, "{}", sum);
+        assert!((mean - 0.5).abs() < 1e-6, "{}", mean);
+    }
+
+    #[test]
+    fn test_sprt_decisions() {
+        let config = SprtConfig {
+            pentanomial: false,
+            ..Default::default()
+        };
+        let mut sprt = Sprt::new(config);
+        assert_eq!(sprt.llr(), 0.);
+
+        // One win is not enough
+        sprt.add_game(1.);
+        assert_eq!(sprt.decision(), SprtDecision::Continue);
+
+        // Much stronger agent
+        for _ in 0..300 {
+            sprt.add_game(1.);
+            sprt.add_game(0.5);
+            sprt.add_game(0.);
+            sprt.add_game(1.);
+        }
+        assert_eq!(sprt.decision(), SprtDecision::AcceptH1);
+
+        // Equal agents
+        let mut sprt = Sprt::new(config);
+        for _ in 0..1000 {
+            sprt.add_game(1.);
+            sprt.add_game(0.);
+        }
+        assert_eq!(sprt.decision(), SprtDecision::AcceptH0);
+    }
+
+    #[test]
+    fn test_pentanomial() {
+        let mut sprt = Sprt::new(SprtConfig::default());
+        for _ in 0..200 {
+            sprt.add_pair(1., 0.5);
+            sprt.add_pair(1., 0.);
+            sprt.add_pair(1., 1.);
+        }
+        assert_eq!(sprt.pairs, [0, 0, 200, 200, 200]);
+        assert_eq!(sprt.decision(), SprtDecision::AcceptH1);
+    }
+}
@@ -0,0 +1,346 @@
+//! Opening suites: curated starting positions which every pairing plays with both colors.
+//!
+//! A suite file has an opening per line, the text after `#` is a comment. An opening is
+//! either a deck or a whole position in the notation, optionally followed by the moves
+//! which are played before the agents take over:
+//! `tiger,dragon frog,rabbit horse tiger:c1c3 frog:a5b4` or
+//! `bbBbb/5/5/5/rrRrr r tiger,dragon frog,rabbit horse`.
+//! Random decks often favour one color, `measure_balance` plays the candidate decks with
+//! the colors swapped and keeps the ones where red and blue score about the same
+
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    deck::Deck,
+    game_record::GameRecord,
+    game_state::GameState,
+    move_result::MoveResult,
+    notation::{format_deck, format_move, format_position, parse_deck, parse_move, parse_position},
+    player_color::PlayerColor,
+    r#move::NotationError,
+};
+
+use super::{score::Score, MatchRunner, ScheduledGame};
+
+#[derive(Debug)]
+pub enum SuiteError {
+    Io(io::Error),
+    /// Opening at the given line cannot be read
+    Notation(usize, NotationError),
+}
+
+impl fmt::Display for SuiteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuiteError::Io(e) => write!(f, "Cannot read the suite: {}", e),
+            SuiteError::Notation(line, e) => write!(f, "Incorrect opening at line {}: {}", line, e),
+        }
+    }
+}
+
+impl std::error::Error for SuiteError {}
+
+impl From<io::Error> for SuiteError {
+    fn from(e: io::Error) -> Self {
+        SuiteError::Io(e)
+    }
+}
+
+/// Starting position of a game. It is saved in the notation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Opening {
+    pub deck: Deck,
+    /// Position in the notation if the opening does not start from the starting position of the deck
+    pub position: Option<String>,
+    /// Legal moves from the position written in the notation, none of them ends the game
+    pub moves: Vec<String>,
+}
+
+impl Opening {
+    /// Game before the moves of the opening
+    pub fn start(&self) -> GameState {
+        match &self.position {
+            Some(position) => {
+                let (state, player_color) =
+                    parse_position(position).expect("Position is checked when it is read");
+                GameState::from_state(state, player_color)
+            }
+            None => GameState::with_deck(self.deck.clone()),
+        }
+    }
+
+    /// Game after the moves of the opening
+    pub fn game_state(&self) -> GameState {
+        self.record().1
+    }
+
+    /// Record of the opening moves and the game where the agents take over
+    pub fn record(&self) -> (GameRecord, GameState) {
+        let mut game_state = self.start();
+        let mut record = GameRecord::new(self.deck.clone());
+        record.position = self.position.clone();
+
+        for notation in self.moves.iter() {
+            let done_move = parse_move(&game_state.state, game_state.curr_player_color, notation)
+                .expect("Moves are checked when the opening is read");
+            let card = *game_state.state.deck.get_card(done_move.used_card_idx);
+            let move_result = game_state.progress(done_move);
+            record.push(&card, done_move.mov, move_result);
+        }
+
+        (record, game_state)
+    }
+}
+
+impl From<Deck> for Opening {
+    fn from(deck: Deck) -> Self {
+        Self {
+            deck,
+            position: None,
+            moves: vec![],
+        }
+    }
+}
+
+impl FromStr for Opening {
+    type Err = NotationError;
+
+    /// Reads the deck or the position and replays the moves to check them
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split_whitespace().collect::<Vec<_>>();
+        let mut opening = if parts.first().is_some_and(|part| part.contains('/')) {
+            let position = parts.iter().take(5).cloned().collect::<Vec<_>>().join(" ");
+            let (state, player_color) = parse_position(&position)?;
+            Opening {
+                deck: state.deck.clone(),
+                position: Some(format_position(&state, player_color)),
+                moves: vec![],
+            }
+        } else {
+            let deck = parts.iter().take(3).cloned().collect::<Vec<_>>().join(" ");
+            Opening::from(parse_deck(&deck)?)
+        };
+
+        let skipped = if opening.position.is_some() { 5 } else { 3 };
+        let mut game_state = opening.start();
+        for notation in parts.iter().skip(skipped) {
+            let player_color = game_state.curr_player_color;
+            let done_move = parse_move(&game_state.state, player_color, notation)?;
+            let normalized = format_move(&game_state.state, &done_move);
+            if game_state.progress(done_move) != MoveResult::InProgress {
+                return Err(NotationError(format!(
+                    "Move {} of the opening ends the game",
+                    notation
+                )));
+            }
+            opening.moves.push(normalized);
+        }
+
+        Ok(opening)
+    }
+}
+
+impl fmt::Display for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.position {
+            Some(position) => write!(f, "{}", position)?,
+            None => write!(f, "{}", format_deck(&self.deck))?,
+        }
+        for notation in self.moves.iter() {
+            write!(f, " {}", notation)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Opening {
+    type Error = NotationError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Opening> for String {
+    fn from(opening: Opening) -> Self {
+        opening.to_string()
+    }
+}
+
+/// Reads the openings written one per line
+pub fn parse_suite(content: &str) -> Result<Vec<Opening>, SuiteError> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| line.parse().map_err(|e| SuiteError::Notation(number, e)))
+        .collect()
+}
+
+pub fn load_suite(path: impl AsRef<Path>) -> Result<Vec<Opening>, SuiteError> {
+    parse_suite(&fs::read_to_string(path)?)
+}
+
+/// Writes the suite with a comment after every opening, e.g. its balance
+pub fn save_suite(path: impl AsRef<Path>, openings: &[(Opening, String)]) -> io::Result<()> {
+    let content = openings
+        .iter()
+        .map(|(opening, comment)| format!("{}  # {}\n", opening, comment))
+        .collect::<String>();
+    fs::write(path, content)
+}
+
+/// Results of the opening played by both agents with both colors
+#[derive(Debug, Clone, PartialEq)]
+pub struct Balance {
+    pub opening: Opening,
+    /// Score of the red player
+    pub red: Score,
+}
+
+impl Balance {
+    /// Distance of the red score from the even one, from 0 to 0.5
+    pub fn imbalance(&self) -> f64 {
+        (self.red.ratio() - 0.5).abs()
+    }
+}
+
+/// Plays every opening `pairs` times with the first two agents of the runner swapping the colors.
+/// The same agent can be given twice to measure the openings in self-play.
+/// `on_opening` is called with the amount of the measured openings
+pub fn measure_balance(
+    runner: &MatchRunner,
+    openings: Vec<Opening>,
+    pairs: usize,
+    mut on_opening: impl FnMut(usize),
+) -> io::Result<Vec<Balance>> {
+    let games_per_opening = pairs.max(1) * 2;
+    let mut games = vec![];
+    for opening in openings.iter() {
+        for _ in 0..games_per_opening / 2 {
+            for (red, blue) in [(0, 1), (1, 0)] {
+                games.push(ScheduledGame {
+                    number: games.len() + 1,
+                    red,
+                    blue,
+                    opening: opening.clone(),
+                });
+            }
+        }
+    }
+
+    let mut balances = openings
+        .into_iter()
+        .map(|opening| Balance {
+            opening,
+            red: Score::default(),
+        })
+        .collect::<Vec<_>>();
+    let mut finished = 0;
+    runner.play_games(games, |game| {
+        let balance = &mut balances[(game.number - 1) / games_per_opening];
+        let points = match game.record.winner() {
+            Some(PlayerColor::Red) => 1.,
+            Some(PlayerColor::Blue) => 0.,
+            None => 0.5,
+        };
+        balance.red.add(points);
+        if balance.red.games() == games_per_opening {
+            finished += 1;
+            on_opening(finished);
+        }
+        true
+    })?;
+
+    Ok(balances)
+}
+
+/// The most even openings whose imbalance is at most `tolerance`
+pub fn balanced(mut balances: Vec<Balance>, size: usize, tolerance: f64) -> Vec<Balance> {
+    balances.retain(|balance| balance.imbalance() <= tolerance);
+    balances.sort_by(|a, b| a.imbalance().total_cmp(&b.imbalance()));
+    balances.truncate(size);
+    balances
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::registry::AgentRegistry;
+
+    use super::*;
+
+    use crate::match_runner::MatchConfig;
+
+    #[test]
+    fn test_parse_suite() {
+        let suite = parse_suite(
+            "# Openings\n\
+             tiger,dragon frog,rabbit horse\n\
+             \n\
+             tiger,dragon frog,rabbit horse tiger:c1c3 Frog:a5b4  # two moves\n\
+             bbBbb/5/5/5/rrRrr b tiger,dragon frog,rabbit horse frog:a5b4\n",
+        )
+        .unwrap();
+
+        assert_eq!(suite.len(), 3);
+        assert_eq!(suite[0], Opening::from(suite[0].deck.clone()));
+        assert_eq!(suite[1].moves, ["tiger:c1c3", "frog:a5b4"]);
+        assert_eq!(
+            suite[1].to_string(),
+            "tiger,dragon frog,rabbit horse tiger:c1c3 frog:a5b4"
+        );
+        let (record, game_state) = suite[1].record();
+        assert_eq!(record.moves.len(), 2);
+        assert_eq!(record.positions().unwrap().len(), 3);
+        assert_eq!(game_state.curr_player_color, PlayerColor::Red);
+
+        let game_state = suite[2].game_state();
+        assert_eq!(game_state.curr_player_color, PlayerColor::Red);
+        let (record, _) = suite[2].record();
+        assert!(record.position.is_some());
+        assert_eq!(record.positions().unwrap().len(), 2);
+
+        let json = serde_json::to_string(&suite).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Opening>>(&json).unwrap(), suite);
+
+        // Illegal move
+        assert!(matches!(
+            parse_suite(
+                "tiger,dragon frog,rabbit horse\ntiger,dragon frog,rabbit horse tiger:c1c4"
+            ),
+            Err(SuiteError::Notation(2, _))
+        ));
+    }
+
+    #[test]
+    fn test_measure_balance() {
+        let specs = vec![
+            "alphabeta:depth=2".parse().unwrap(),
+            "alphabeta:depth=3".parse().unwrap(),
+        ];
+        let config = MatchConfig {
+            concurrency: 2,
+            ..Default::default()
+        };
+        let runner = MatchRunner::new(AgentRegistry::default(), specs, config).unwrap();
+        let openings = (0..3)
+            .map(|seed| Opening::from(Deck::from_seed(seed)))
+            .collect();
+
+        let mut measured = vec![];
+        let balances = measure_balance(&runner, openings, 1, |n| measured.push(n)).unwrap();
+        assert_eq!(measured, [1, 2, 3]);
+        assert!(balances.iter().all(|b| b.red.games() == 2));
+
+        let chosen = balanced(balances.clone(), 2, 0.5);
+        assert_eq!(chosen.len(), 2);
+        assert!(chosen[0].imbalance() <= chosen[1].imbalance());
+        assert!(balanced(balances, 3, 0.)
+            .iter()
+            .all(|b| b.red.wins == b.red.losses));
+    }
+}
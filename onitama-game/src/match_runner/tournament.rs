@@ -0,0 +1,807 @@
+//! Tournaments of many agents in the round-robin, gauntlet or Swiss format.
+//!
+//! Every pairing plays its games in pairs with the same deck and the colors swapped,
+//! the games of a round are played in parallel by the `MatchRunner`. The state of the
+//! tournament is saved after every game, so an interrupted tournament is resumed from
+//! the saved file and plays only the missing games
+
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::registry::{AgentRegistry, AgentSpec, SpecError},
+    common::seeded_rng,
+    game::{deck::Deck, player_color::PlayerColor},
+};
+
+use super::{
+    score::{Score, ScoreTable},
+    suite::Opening,
+    Adjudication, MatchConfig, MatchGame, MatchRunner, ScheduledGame, Termination,
+};
+
+#[derive(Debug)]
+pub enum TournamentError {
+    Spec(SpecError),
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// The saved tournament has other agents, format or amount of games
+    Mismatch(PathBuf),
+}
+
+impl fmt::Display for TournamentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TournamentError::Spec(e) => write!(f, "{}", e),
+            TournamentError::Io(e) => write!(f, "Cannot save the tournament: {}", e),
+            TournamentError::Parse(e) => write!(f, "Cannot read the saved tournament: {}", e),
+            TournamentError::Mismatch(path) => write!(
+                f,
+                "Tournament saved in {} has other settings",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TournamentError {}
+
+impl From<SpecError> for TournamentError {
+    fn from(e: SpecError) -> Self {
+        TournamentError::Spec(e)
+    }
+}
+
+impl From<io::Error> for TournamentError {
+    fn from(e: io::Error) -> Self {
+        TournamentError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for TournamentError {
+    fn from(e: serde_json::Error) -> Self {
+        TournamentError::Parse(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Format {
+    /// Every agent plays every other one
+    RoundRobin,
+    /// The first agent plays every other one, the rest do not play each other
+    Gauntlet,
+    /// Agents with similar points meet in every round and do not meet twice while it is
+    /// possible. With an odd amount of agents the lowest one without a bye sits out and
+    /// gets the points of all games of the round
+    Swiss { rounds: usize },
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    /// Written as `round-robin`, `gauntlet` or `swiss:ROUNDS`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("swiss", rounds)) => rounds
+                .parse()
+                .map(|rounds| Format::Swiss { rounds })
+                .map_err(|e| format!("Rounds of the Swiss tournament {}: {}", rounds, e)),
+            None if s == "round-robin" => Ok(Format::RoundRobin),
+            None if s == "gauntlet" => Ok(Format::Gauntlet),
+            _ => Err(format!(
+                "Unknown format {}, expected round-robin, gauntlet or swiss:ROUNDS",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::RoundRobin => write!(f, "round-robin"),
+            Format::Gauntlet => write!(f, "gauntlet"),
+            Format::Swiss { rounds } => write!(f, "swiss:{}", rounds),
+        }
+    }
+}
+
+/// Games of two agents in a round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pairing {
+    /// The first agent is red in the first game of every pair
+    pub agents: [usize; 2],
+    /// Opening of every pair of games
+    pub openings: Vec<Opening>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Round {
+    pub pairings: Vec<Pairing>,
+    /// Agent without an opponent in the Swiss round
+    pub bye: Option<usize>,
+}
+
+/// Finished game of the tournament
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameResult {
+    pub number: usize,
+    pub red: usize,
+    pub blue: usize,
+    /// Points of the red agent: 1, 0.5 or 0
+    pub points: f64,
+    pub termination: Termination,
+}
+
+impl From<&MatchGame> for GameResult {
+    fn from(game: &MatchGame) -> Self {
+        Self {
+            number: game.number,
+            red: game.players[0],
+            blue: game.players[1],
+            points: match game.record.winner() {
+                Some(PlayerColor::Red) => 1.,
+                Some(PlayerColor::Blue) => 0.,
+                None => 0.5,
+            },
+            termination: game.termination,
+        }
+    }
+}
+
+/// Place of the agent in the tournament
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub agent: usize,
+    /// Points of the games together with the points of the byes
+    pub points: f64,
+    pub score: Score,
+    pub byes: usize,
+    /// Tie-break: points against every opponent multiplied by the points of the opponent
+    pub sonneborn_berger: f64,
+}
+
+/// Schedule and results of the tournament. It is saved as JSON after every game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TournamentState {
+    pub agents: Vec<String>,
+    pub format: Format,
+    /// Games of every pairing, an odd amount is rounded up
+    pub games: usize,
+    /// Rounds which are scheduled, a Swiss round is added when the previous one has finished
+    pub rounds: Vec<Round>,
+    pub results: Vec<GameResult>,
+}
+
+impl TournamentState {
+    pub fn new(agents: Vec<String>, format: Format, games: usize) -> Self {
+        Self {
+            agents,
+            format,
+            games,
+            rounds: vec![],
+            results: vec![],
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TournamentError> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Writes a temporary file first, so an interruption does not leave a broken save
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::from)?;
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, json)?;
+        fs::rename(temporary, path)
+    }
+
+    /// Adds the rounds which can be played now. `opening` gets the number of the pair
+    /// of games in the tournament and returns its opening. Returns false if there are no new rounds
+    pub fn schedule(&mut self, mut opening: impl FnMut(usize) -> Opening) -> bool {
+        let agents = self.agents.len();
+        let rounds = match self.format {
+            _ if agents < 2 => vec![],
+            Format::RoundRobin if self.rounds.is_empty() => round_robin(agents),
+            Format::Gauntlet if self.rounds.is_empty() => (1..agents)
+                .map(|opponent| (vec![[0, opponent]], None))
+                .collect(),
+            Format::Swiss { rounds } if self.rounds.len() < rounds && self.pending().is_empty() => {
+                vec![self.swiss_round()]
+            }
+            _ => vec![],
+        };
+        if rounds.is_empty() {
+            return false;
+        }
+
+        let mut pair = self.pairs_scheduled();
+        for (pairings, bye) in rounds {
+            let pairings = pairings
+                .into_iter()
+                .map(|agents| Pairing {
+                    agents,
+                    openings: (0..self.games.div_ceil(2))
+                        .map(|_| {
+                            pair += 1;
+                            opening(pair - 1)
+                        })
+                        .collect(),
+                })
+                .collect();
+            self.rounds.push(Round { pairings, bye });
+        }
+        true
+    }
+
+    /// All games of the scheduled rounds with their round starting from 0
+    pub fn scheduled(&self) -> Vec<(usize, ScheduledGame)> {
+        let mut games = vec![];
+        for (round, pairings) in self.rounds.iter().enumerate() {
+            for pairing in pairings.pairings.iter() {
+                let [first, second] = pairing.agents;
+                for opening in pairing.openings.iter() {
+                    for (red, blue) in [(first, second), (second, first)] {
+                        let game = ScheduledGame {
+                            number: games.len() + 1,
+                            red,
+                            blue,
+                            opening: opening.clone(),
+                        };
+                        games.push((round, game));
+                    }
+                }
+            }
+        }
+        games
+    }
+
+    /// Scheduled games which do not have a result yet
+    pub fn pending(&self) -> Vec<ScheduledGame> {
+        let finished = self
+            .results
+            .iter()
+            .map(|result| result.number)
+            .collect::<HashSet<_>>();
+        self.scheduled()
+            .into_iter()
+            .map(|(_, game)| game)
+            .filter(|game| !finished.contains(&game.number))
+            .collect()
+    }
+
+    pub fn add_result(&mut self, result: GameResult) {
+        self.results.push(result);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        let can_schedule = match self.format {
+            _ if self.agents.len() < 2 => false,
+            Format::Swiss { rounds } => self.rounds.len() < rounds,
+            _ => self.rounds.is_empty(),
+        };
+        !can_schedule && self.pending().is_empty()
+    }
+
+    pub fn table(&self) -> ScoreTable {
+        let mut table = ScoreTable::new(self.agents.clone());
+        for result in self.results.iter() {
+            table.add(result.red, result.blue, result.points);
+        }
+        table
+    }
+
+    /// Agents from the first place to the last one
+    pub fn standings(&self) -> Vec<Standing> {
+        let table = self.table();
+        let bye_points = self.games.div_ceil(2) as f64 * 2.;
+        let points = (0..self.agents.len())
+            .map(|agent| {
+                let byes = self.byes(agent);
+                points_of(&table.total(agent)) + byes as f64 * bye_points
+            })
+            .collect::<Vec<_>>();
+
+        let mut standings = (0..self.agents.len())
+            .map(|agent| Standing {
+                agent,
+                points: points[agent],
+                score: table.total(agent),
+                byes: self.byes(agent),
+                sonneborn_berger: (0..self.agents.len())
+                    .map(|opponent| points_of(&table.score(agent, opponent)) * points[opponent])
+                    .sum(),
+            })
+            .collect::<Vec<_>>();
+        standings.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger))
+                .then(a.agent.cmp(&b.agent))
+        });
+        standings
+    }
+
+    fn byes(&self, agent: usize) -> usize {
+        self.rounds
+            .iter()
+            .filter(|round| round.bye == Some(agent))
+            .count()
+    }
+
+    fn pairs_scheduled(&self) -> usize {
+        self.rounds
+            .iter()
+            .flat_map(|round| round.pairings.iter())
+            .map(|pairing| pairing.openings.len())
+            .sum()
+    }
+
+    /// Pairs the agents in the order of the standings with the closest opponent
+    /// they have not played yet
+    fn swiss_round(&self) -> (Vec<[usize; 2]>, Option<usize>) {
+        let mut order = self
+            .standings()
+            .into_iter()
+            .map(|standing| standing.agent)
+            .collect::<Vec<_>>();
+
+        let bye = if order.len() & 1 == 0 {
+            None
+        } else {
+            let fewest = order.iter().map(|&agent| self.byes(agent)).min();
+            let position = order
+                .iter()
+                .rposition(|&agent| Some(self.byes(agent)) == fewest)
+                .unwrap();
+            Some(order.remove(position))
+        };
+
+        let played = self
+            .rounds
+            .iter()
+            .flat_map(|round| round.pairings.iter())
+            .flat_map(|pairing| {
+                let [a, b] = pairing.agents;
+                [(a, b), (b, a)]
+            })
+            .collect::<HashSet<_>>();
+        // Everybody has played everybody, the neighbours in the standings meet again
+        let pairings = pair_without_repeats(&order, &played)
+            .unwrap_or_else(|| order.chunks(2).map(|pair| [pair[0], pair[1]]).collect());
+
+        (pairings, bye)
+    }
+}
+
+impl fmt::Display for TournamentState {
+    /// Standings with the crosstable, every cell is the points of the row agent against
+    /// the column agent, the columns are numbered by the rank
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let standings = self.standings();
+        let table = self.table();
+        let width = self.agents.iter().map(String::len).max().unwrap_or(0);
+
+        write!(
+            f,
+            "Rank  {:<width$}  {:>6}  {:>5}  {:>7}",
+            "Agent",
+            "Points",
+            "Games",
+            "S-B",
+            width = width
+        )?;
+        for rank in 1..=standings.len() {
+            write!(f, "  {:>5}", rank)?;
+        }
+        writeln!(f)?;
+
+        for (rank, standing) in standings.iter().enumerate() {
+            write!(
+                f,
+                "{:>4}  {:<width$}  {:>6.1}  {:>5}  {:>7.2}",
+                rank + 1,
+                self.agents[standing.agent],
+                standing.points,
+                standing.score.games(),
+                standing.sonneborn_berger,
+                width = width
+            )?;
+            for opponent in standings.iter() {
+                let score = table.score(standing.agent, opponent.agent);
+                let cell = if opponent.agent == standing.agent {
+                    String::from("x")
+                } else if score.games() == 0 {
+                    String::from("-")
+                } else {
+                    format!("{:.1}", points_of(&score))
+                };
+                write!(f, "  {:>5}", cell)?;
+            }
+            writeln!(f)?;
+        }
+
+        let byes = standings.iter().filter(|s| s.byes > 0).collect::<Vec<_>>();
+        if !byes.is_empty() {
+            let byes = byes
+                .iter()
+                .map(|s| format!("{} ({})", self.agents[s.agent], s.byes))
+                .collect::<Vec<_>>();
+            writeln!(f, "Byes: {}", byes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn points_of(score: &Score) -> f64 {
+    score.wins as f64 + 0.5 * score.draws as f64
+}
+
+/// Rounds of the circle method where every agent plays once in a round.
+/// The first agent of a pairing alternates, so every agent starts about half of the pairings
+fn round_robin(agents: usize) -> Vec<(Vec<[usize; 2]>, Option<usize>)> {
+    let mut circle = (0..agents).map(Some).collect::<Vec<_>>();
+    if agents & 1 == 1 {
+        circle.push(None);
+    }
+    let size = circle.len();
+
+    let mut rounds = vec![];
+    for round in 0..size - 1 {
+        let pairings = (0..size / 2)
+            .filter_map(|i| match (circle[i], circle[size - 1 - i]) {
+                (Some(a), Some(b)) if (round + i) & 1 == 0 => Some([a, b]),
+                (Some(a), Some(b)) => Some([b, a]),
+                _ => None,
+            })
+            .collect();
+        rounds.push((pairings, None));
+
+        // The first agent stays in place, the rest move by one
+        let last = circle.pop().unwrap();
+        circle.insert(1, last);
+    }
+    rounds
+}
+
+/// Pairs every agent with the highest one below it which it has not played,
+/// it backtracks if the rest cannot be paired
+fn pair_without_repeats(
+    order: &[usize],
+    played: &HashSet<(usize, usize)>,
+) -> Option<Vec<[usize; 2]>> {
+    let (&first, rest) = match order.split_first() {
+        Some(split) => split,
+        None => return Some(vec![]),
+    };
+
+    for (i, &opponent) in rest.iter().enumerate() {
+        if played.contains(&(first, opponent)) {
+            continue;
+        }
+        let remaining = rest
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, &agent)| agent)
+            .collect::<Vec<_>>();
+        if let Some(mut pairings) = pair_without_repeats(&remaining, played) {
+            pairings.insert(0, [first, opponent]);
+            return Some(pairings);
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentConfig {
+    pub format: Format,
+    /// Games of every pairing, an odd amount is rounded up
+    pub games: usize,
+    /// Games which are played at the same time
+    pub concurrency: usize,
+    /// Search time of a move, it replaces the `time` parameter of every agent which has it
+    pub move_time: Option<Duration>,
+    pub adjudication: Adjudication,
+    /// Openings of the pairs of the games, they are repeated if there are more pairs.
+    /// Without the openings every pair gets a random deck
+    pub openings: Vec<Opening>,
+    /// Seed of the random decks
+    pub seed: Option<u64>,
+    /// Every game is saved to this directory as `game_<number>.json`
+    pub record_dir: Option<PathBuf>,
+    /// The state is saved to this file after every game. If the file exists
+    /// the tournament is resumed from it
+    pub state_file: Option<PathBuf>,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            format: Format::RoundRobin,
+            games: 10,
+            concurrency: 1,
+            move_time: None,
+            adjudication: Adjudication::default(),
+            openings: vec![],
+            seed: None,
+            record_dir: None,
+            state_file: None,
+        }
+    }
+}
+
+pub struct Tournament {
+    runner: MatchRunner,
+    config: TournamentConfig,
+    state: TournamentState,
+}
+
+impl Tournament {
+    /// Checks the agents and resumes the saved state if there is one
+    pub fn new(
+        registry: AgentRegistry,
+        specs: Vec<AgentSpec>,
+        config: TournamentConfig,
+    ) -> Result<Self, TournamentError> {
+        let match_config = MatchConfig {
+            games: config.games,
+            concurrency: config.concurrency,
+            move_time: config.move_time,
+            adjudication: config.adjudication,
+            openings: config.openings.clone(),
+            seed: config.seed,
+            record_dir: config.record_dir.clone(),
+            sprt: None,
+        };
+        let runner = MatchRunner::new(registry, specs, match_config)?;
+        let mut state = TournamentState::new(runner.agents(), config.format, config.games);
+
+        if let Some(path) = config.state_file.as_ref().filter(|path| path.exists()) {
+            let saved = TournamentState::load(path)?;
+            if (&saved.agents, saved.format, saved.games)
+                != (&state.agents, state.format, state.games)
+            {
+                return Err(TournamentError::Mismatch(path.clone()));
+            }
+            state = saved;
+        }
+
+        Ok(Self {
+            runner,
+            config,
+            state,
+        })
+    }
+
+    pub fn state(&self) -> &TournamentState {
+        &self.state
+    }
+
+    /// Plays the missing games round by round and calls `on_game` after each one.
+    /// The rounds which do not depend on the results are played together
+    pub fn run(
+        &mut self,
+        mut on_game: impl FnMut(&MatchGame, &TournamentState),
+    ) -> Result<&TournamentState, TournamentError> {
+        let Self {
+            runner,
+            config,
+            state,
+        } = self;
+
+        loop {
+            state.schedule(|pair| {
+                if config.openings.is_empty() {
+                    Opening::from(Deck::random(&mut seeded_rng(config.seed, pair as u64)))
+                } else {
+                    config.openings[pair % config.openings.len()].clone()
+                }
+            });
+            let pending = state.pending();
+            if pending.is_empty() {
+                break;
+            }
+
+            let mut error = None;
+            runner.play_games(pending, |game| {
+                state.add_result(GameResult::from(game));
+                if let Some(path) = &config.state_file {
+                    if let Err(e) = state.save(path) {
+                        error = Some(e);
+                        return false;
+                    }
+                }
+                on_game(game, state);
+                true
+            })?;
+            if let Some(e) = error {
+                return Err(e.into());
+            }
+        }
+
+        Ok(&self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(agents: usize, format: Format) -> TournamentState {
+        let mut state = TournamentState::new(
+            (0..agents).map(|i| format!("agent{}", i)).collect(),
+            format,
+            2,
+        );
+        state.schedule(|pair| Deck::from_seed(pair as u64).into());
+        state
+    }
+
+    fn pairings(state: &TournamentState) -> Vec<[usize; 2]> {
+        let mut pairings = state
+            .rounds
+            .iter()
+            .flat_map(|round| round.pairings.iter())
+            .map(|pairing| {
+                let [a, b] = pairing.agents;
+                [a.min(b), a.max(b)]
+            })
+            .collect::<Vec<_>>();
+        pairings.sort();
+        pairings
+    }
+
+    #[test]
+    fn test_round_robin_and_gauntlet() {
+        let round_robin = state(5, Format::RoundRobin);
+        assert_eq!(round_robin.rounds.len(), 5);
+        let expected = (0..5)
+            .flat_map(|i| ((i + 1)..5).map(move |j| [i, j]))
+            .collect::<Vec<_>>();
+        assert_eq!(pairings(&round_robin), expected);
+        // Every agent plays at most once in a round
+        for round in round_robin.rounds.iter() {
+            let agents = round
+                .pairings
+                .iter()
+                .flat_map(|pairing| pairing.agents)
+                .collect::<HashSet<_>>();
+            assert_eq!(agents.len(), round.pairings.len() * 2);
+        }
+        assert_eq!(round_robin.pending().len(), 20);
+        assert!(!round_robin.is_finished());
+
+        let gauntlet = state(4, Format::Gauntlet);
+        assert_eq!(pairings(&gauntlet), [[0, 1], [0, 2], [0, 3]]);
+        let games = gauntlet.scheduled();
+        assert_eq!((games[0].1.red, games[0].1.blue), (0, 1));
+        assert_eq!((games[1].1.red, games[1].1.blue), (1, 0));
+        assert_eq!(games[1].1.opening, games[0].1.opening);
+        assert_eq!(games[5].0, 2);
+    }
+
+    #[test]
+    fn test_swiss_pairings() {
+        let mut state = state(5, Format::Swiss { rounds: 3 });
+        assert_eq!(state.rounds.len(), 1);
+        assert_eq!(state.rounds[0].bye, Some(4));
+        // Next round waits for the results
+        assert!(!state.schedule(|pair| Deck::from_seed(pair as u64).into()));
+
+        for round in 0..3 {
+            for game in state.pending() {
+                let points = if game.red.min(game.blue) == game.red {
+                    1.
+                } else {
+                    0.
+                };
+                state.add_result(GameResult {
+                    number: game.number,
+                    red: game.red,
+                    blue: game.blue,
+                    points,
+                    termination: Termination::Normal,
+                });
+            }
+            assert_eq!(
+                state.schedule(|pair| Deck::from_seed(pair as u64).into()),
+                round < 2
+            );
+        }
+
+        assert!(state.is_finished());
+        let pairs = pairings(&state);
+        let unique = pairs.iter().collect::<HashSet<_>>();
+        assert_eq!(pairs.len(), 6);
+        assert_eq!(unique.len(), 6);
+        let byes = state
+            .rounds
+            .iter()
+            .filter_map(|round| round.bye)
+            .collect::<HashSet<_>>();
+        assert_eq!(byes.len(), 3);
+
+        let standings = state.standings();
+        assert_eq!(standings.iter().map(|s| s.score.games()).sum::<usize>(), 24);
+        assert!(standings
+            .windows(2)
+            .all(|pair| pair[0].points >= pair[1].points));
+        let table = state.to_string();
+        assert!(table.starts_with("Rank  Agent "));
+        assert!(table.contains("Byes: "));
+    }
+
+    #[test]
+    fn test_resumed_tournament() {
+        let specs = || {
+            [
+                "alphabeta:depth=2",
+                "alphabeta:depth=3",
+                "alphabeta:depth=2,time=1s",
+            ]
+            .iter()
+            .map(|spec| spec.parse().unwrap())
+            .collect::<Vec<_>>()
+        };
+        let path =
+            std::env::temp_dir().join(format!("onitama_tournament_{}.json", std::process::id()));
+        let config = TournamentConfig {
+            games: 2,
+            concurrency: 2,
+            seed: Some(5),
+            state_file: Some(path.clone()),
+            ..Default::default()
+        };
+
+        let mut tournament =
+            Tournament::new(AgentRegistry::default(), specs(), config.clone()).unwrap();
+        let finished = tournament.run(|_, _| ()).unwrap();
+        assert!(finished.is_finished());
+        assert_eq!(finished.results.len(), 6);
+        let standings = finished.standings();
+        assert_eq!(standings.iter().map(|s| s.points).sum::<f64>(), 6.);
+
+        // Interrupted after 4 games
+        let mut saved = TournamentState::load(&path).unwrap();
+        saved.results.truncate(4);
+        saved.save(&path).unwrap();
+
+        let mut tournament =
+            Tournament::new(AgentRegistry::default(), specs(), config.clone()).unwrap();
+        let mut played = 0;
+        let resumed = tournament.run(|_, _| played += 1).unwrap();
+        assert_eq!(played, 2);
+        assert_eq!(resumed.results.len(), 6);
+
+        let other = TournamentConfig {
+            format: Format::Gauntlet,
+            ..config
+        };
+        assert!(matches!(
+            Tournament::new(AgentRegistry::default(), specs(), other),
+            Err(TournamentError::Mismatch(_))
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_format() {
+        for format in [
+            Format::RoundRobin,
+            Format::Gauntlet,
+            Format::Swiss { rounds: 4 },
+        ] {
+            assert_eq!(format.to_string().parse::<Format>(), Ok(format));
+        }
+        assert!("swiss".parse::<Format>().is_err());
+    }
+}
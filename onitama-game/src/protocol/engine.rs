@@ -0,0 +1,557 @@
+use std::{
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use crate::{
+    ai::{
+        agent::Agent,
+        analysis::{AnalysisLine, SearchControl},
+        registry::{format_duration, AgentRegistry, AgentSpec, SpecError},
+    },
+    game::{
+        deck::Deck,
+        game_state::GameState,
+        notation::{format_move, format_position, parse_move},
+        r#move::NotationError,
+    },
+};
+
+use super::{Command, Info, Response, SearchLimits, StartPosition};
+
+pub const ENGINE_NAME: &str = "Onitama AlphaZero";
+
+/// Agent of the last search with its specification
+type CachedAgent = (AgentSpec, Box<dyn Agent>);
+
+/// Engine which answers the commands of the protocol with the agents of the registry
+pub struct Engine {
+    registry: AgentRegistry,
+    /// Kind of the agent which searches the moves
+    agent_kind: String,
+    /// Parameters set with `setoption` for every agent kind
+    options: Vec<AgentSpec>,
+    /// Amount of the analyzed moves
+    lines: usize,
+    game_state: GameState,
+    /// The agent is reused while its specification is the same, so MCTS keeps its tree
+    agent: Option<CachedAgent>,
+}
+
+impl Engine {
+    pub fn new(registry: AgentRegistry) -> Self {
+        let agent_kind = match registry.kind("alphabeta") {
+            Some(kind) => kind.name.to_owned(),
+            None => registry
+                .kinds()
+                .first()
+                .map(|kind| kind.name.to_owned())
+                .unwrap_or_default(),
+        };
+
+        Self {
+            registry,
+            agent_kind,
+            options: vec![],
+            lines: 1,
+            game_state: GameState::with_deck(Deck::from_seed(0)),
+            agent: None,
+        }
+    }
+
+    /// Reads the commands until `quit` or the end of the input.
+    /// The search runs in its own thread, so `stop` and `isready` are answered during it
+    pub fn run(&mut self, input: impl BufRead, output: impl Write + Send) -> io::Result<()> {
+        let output = Mutex::new(output);
+        let stop = AtomicBool::new(false);
+
+        std::thread::scope(|s| {
+            let mut search = None;
+
+            for line in input.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let command = match line.parse::<Command>() {
+                    Ok(command) => command,
+                    Err(e) => {
+                        send(&output, &Response::Message(e.to_string()))?;
+                        continue;
+                    }
+                };
+
+                match command {
+                    Command::Stop => {
+                        stop.store(true, Ordering::Relaxed);
+                        continue;
+                    }
+                    Command::IsReady => {
+                        send(&output, &Response::ReadyOk)?;
+                        continue;
+                    }
+                    Command::Quit => {
+                        stop.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                    _ => (),
+                }
+
+                // Other commands change the engine, so they wait for the search
+                if let Some(handle) = search.take() {
+                    self.agent = join_search(handle);
+                }
+
+                match command {
+                    Command::Go(limits) => {
+                        stop.store(false, Ordering::Relaxed);
+                        let job = self.search_job(limits);
+                        let (output, stop) = (&output, &stop);
+                        search = Some(s.spawn(move || job.run(output, stop)));
+                    }
+                    command => {
+                        for response in self.execute(command) {
+                            send(&output, &response)?;
+                        }
+                    }
+                }
+            }
+
+            if let Some(handle) = search.take() {
+                self.agent = join_search(handle);
+            }
+            Ok(())
+        })
+    }
+
+    /// Executes the command which does not search
+    fn execute(&mut self, command: Command) -> Vec<Response> {
+        match command {
+            Command::Onitama => {
+                let mut responses = vec![
+                    Response::Id(ENGINE_NAME.to_owned()),
+                    Response::Option {
+                        name: "Agent".to_owned(),
+                        default: self.agent_kind.clone(),
+                    },
+                    Response::Option {
+                        name: "MultiPV".to_owned(),
+                        default: self.lines.to_string(),
+                    },
+                ];
+                for kind in self.registry.kinds() {
+                    for param in kind.params.iter() {
+                        responses.push(Response::Option {
+                            name: format!("{}.{}", kind.name, param.name),
+                            default: param.default.to_owned(),
+                        });
+                    }
+                }
+                responses.push(Response::OnitamaOk);
+                responses
+            }
+            Command::NewGame => {
+                self.agent = None;
+                vec![]
+            }
+            Command::SetOption { name, value } => match self.set_option(&name, &value) {
+                Ok(()) => vec![],
+                Err(message) => vec![Response::Message(message)],
+            },
+            Command::Position { start, moves } => match self.set_position(start, &moves) {
+                Ok(()) => vec![],
+                Err(e) => vec![Response::Message(e.to_string())],
+            },
+            Command::Show => {
+                let position =
+                    format_position(&self.game_state.state, self.game_state.curr_player_color);
+                let mut responses = vec![Response::Message(format!("position {}", position))];
+                responses.extend(
+                    self.game_state
+                        .state
+                        .display()
+                        .lines()
+                        .map(|line| Response::Message(line.to_owned())),
+                );
+                responses
+            }
+            Command::IsReady | Command::Stop | Command::Quit | Command::Go(_) => vec![],
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if name.eq_ignore_ascii_case("multipv") {
+            self.lines = value
+                .parse::<usize>()
+                .map_err(|e| format!("Invalid MultiPV {}: {}", value, e))?
+                .max(1);
+            return Ok(());
+        }
+
+        let spec = if name.eq_ignore_ascii_case("agent") {
+            // The value is a kind or a whole specification which overrides the parameters
+            let given = value.parse::<AgentSpec>().map_err(|e| e.to_string())?;
+            let mut spec = self.kind_options(&given.kind);
+            for (key, value) in given.params {
+                spec.set(&key, value);
+            }
+            spec
+        } else {
+            let (kind, param) = name
+                .split_once('.')
+                .ok_or_else(|| format!("Unknown option {}", name))?;
+            let mut spec = self.kind_options(&kind.to_lowercase());
+            spec.set(param, value);
+            spec
+        };
+
+        // The agent is created to check the parameters
+        self.registry.build_spec(&spec).map_err(|e| e.to_string())?;
+
+        if name.eq_ignore_ascii_case("agent") {
+            self.agent_kind = spec.kind.clone();
+        }
+        match self.options.iter_mut().find(|s| s.kind == spec.kind) {
+            Some(options) => *options = spec,
+            None => self.options.push(spec),
+        }
+        Ok(())
+    }
+
+    fn kind_options(&self, kind: &str) -> AgentSpec {
+        self.options
+            .iter()
+            .find(|spec| spec.kind == kind)
+            .cloned()
+            .unwrap_or_else(|| AgentSpec::new(kind))
+    }
+
+    fn set_position(
+        &mut self,
+        start: StartPosition,
+        moves: &[String],
+    ) -> Result<(), NotationError> {
+        let mut game_state = match start {
+            StartPosition::Deck(deck) => GameState::with_deck(deck),
+            StartPosition::Seed(seed) => GameState::with_deck(Deck::from_seed(seed)),
+            StartPosition::Position(state, player_color) => {
+                GameState::from_state(state, player_color)
+            }
+        };
+
+        for notation in moves {
+            if game_state.state.is_terminal() {
+                return Err(NotationError(format!(
+                    "Game is over before the move {}",
+                    notation
+                )));
+            }
+            let done_move = parse_move(&game_state.state, game_state.curr_player_color, notation)?;
+            game_state.progress(done_move);
+        }
+
+        self.game_state = game_state;
+        Ok(())
+    }
+
+    fn search_job(&mut self, limits: SearchLimits) -> SearchJob {
+        SearchJob {
+            registry: self.registry.clone(),
+            spec: self.kind_options(&self.agent_kind),
+            limits,
+            lines: self.lines,
+            game_state: self.game_state.clone(),
+            agent: self.agent.take(),
+        }
+    }
+}
+
+fn join_search(handle: std::thread::ScopedJoinHandle<Option<CachedAgent>>) -> Option<CachedAgent> {
+    handle.join().expect("Search thread must not panic")
+}
+
+fn send(output: &Mutex<impl Write>, response: &Response) -> io::Result<()> {
+    let mut output = output.lock().unwrap();
+    writeln!(output, "{}", response)?;
+    output.flush()
+}
+
+/// Everything the search thread needs, so the engine can read the commands during the search
+struct SearchJob {
+    registry: AgentRegistry,
+    spec: AgentSpec,
+    limits: SearchLimits,
+    lines: usize,
+    game_state: GameState,
+    agent: Option<CachedAgent>,
+}
+
+impl SearchJob {
+    /// Searches the position, streams the analysis and finishes with the best move.
+    /// Returns the agent, so the next search can reuse it
+    fn run(mut self, output: &Mutex<impl Write>, stop: &AtomicBool) -> Option<CachedAgent> {
+        let start = Instant::now();
+        let player_color = self.game_state.curr_player_color;
+        let has_moves = !self
+            .game_state
+            .state
+            .generate_all_legal_moves(player_color)
+            .is_empty();
+
+        if self.game_state.state.is_terminal() || !has_moves {
+            send(output, &Response::BestMove(None)).ok();
+            return self.agent;
+        }
+
+        let spec = match self.limited_spec() {
+            Ok(spec) => spec,
+            Err(e) => {
+                send(output, &Response::Message(e.to_string())).ok();
+                send(output, &Response::BestMove(None)).ok();
+                return self.agent;
+            }
+        };
+
+        let searcher = match self.agent.take() {
+            Some((cached_spec, cached)) if cached_spec == spec => cached,
+            _ => match self.registry.build_spec(&spec) {
+                Ok(searcher) => searcher,
+                Err(e) => {
+                    send(output, &Response::Message(e.to_string())).ok();
+                    send(output, &Response::BestMove(None)).ok();
+                    return None;
+                }
+            },
+        };
+
+        // One deadline for all lines, the agent streams them during the search
+        let control = SearchControl {
+            stop,
+            deadline: self.limits.time.and_then(|time| start.checked_add(time)),
+        };
+        let lines = searcher.analyze_until(&self.game_state, self.lines, &control, &mut |lines| {
+            self.send_lines(output, lines, start)
+        });
+        let best_move = match lines.first() {
+            Some(line) => line.done_move,
+            // Agents without the analysis only play
+            None => searcher.generate_move(&self.game_state).0,
+        };
+
+        let best_move = format_move(&self.game_state.state, &best_move);
+        send(output, &Response::BestMove(Some(best_move))).ok();
+        Some((spec, searcher))
+    }
+
+    /// Specification of the agent with the limits of the `go` command
+    fn limited_spec(&self) -> Result<AgentSpec, SpecError> {
+        let kind = self
+            .registry
+            .kind(&self.spec.kind)
+            .ok_or_else(|| SpecError::UnknownKind(self.spec.kind.clone()))?;
+        let has_param = |name: &str| kind.params.iter().any(|p| p.name == name);
+        let mut spec = self.spec.clone();
+
+        if let Some(time) = self.limits.time.filter(|_| has_param("time")) {
+            spec.set("time", format_duration(time));
+        }
+        if let Some(depth) = self.limits.depth.filter(|_| has_param("depth")) {
+            // The maximal depth of the agents is not searched
+            spec.set("depth", depth.saturating_add(1).max(2));
+        }
+        if let Some(nodes) = self.limits.nodes {
+            if has_param("playouts") {
+                spec.set("playouts", nodes);
+            } else if has_param("nodes") {
+                spec.set("nodes", nodes);
+            }
+        }
+
+        // The defaults are written out, so the search knows the depth of the agent
+        for param in kind.params.iter() {
+            if spec.get(param.name).is_none() && param.default != "none" {
+                spec.set(param.name, param.default);
+            }
+        }
+
+        Ok(spec)
+    }
+
+    fn send_lines(&self, output: &Mutex<impl Write>, lines: &[AnalysisLine], start: Instant) {
+        for (i, line) in lines.iter().enumerate() {
+            // The moves of the line are written from the positions they are made in
+            let mut game_state = self.game_state.clone();
+            let pv = line
+                .pv
+                .iter()
+                .map(|done_move| {
+                    let notation = format_move(&game_state.state, done_move);
+                    game_state.progress(*done_move);
+                    notation
+                })
+                .collect();
+
+            let info = Info {
+                depth: line.depth,
+                multipv: i + 1,
+                score: line.score,
+                visits: line.visits,
+                time: start.elapsed(),
+                pv,
+            };
+            send(output, &Response::Info(info)).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Runs the engine with the commands and returns its responses
+    fn run_engine(commands: &[&str]) -> Vec<Response> {
+        let input = commands.join("\n");
+        let mut output = vec![];
+        Engine::new(AgentRegistry::default())
+            .run(input.as_bytes(), &mut output)
+            .unwrap();
+
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_handshake() {
+        let responses = run_engine(&["onitama", "isready"]);
+
+        assert_eq!(responses[0], Response::Id(ENGINE_NAME.to_owned()));
+        assert!(responses.contains(&Response::Option {
+            name: "alphabeta.depth".to_owned(),
+            default: "6".to_owned()
+        }));
+        assert_eq!(responses[responses.len() - 2], Response::OnitamaOk);
+        assert_eq!(responses[responses.len() - 1], Response::ReadyOk);
+    }
+
+    #[test]
+    fn test_alpha_beta_finds_king_capture() {
+        // Blue eats the Red king with the Dragon card, like in the AlphaBeta test
+        let responses = run_engine(&[
+            "setoption name MultiPV value 2",
+            "position fen bbBbb/3R1/5/5/rr1rr b rabbit,frog tiger,dragon horse",
+            "go depth 3",
+        ]);
+
+        let infos = responses
+            .iter()
+            .filter_map(|r| match r {
+                Response::Info(info) => Some(info),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(infos
+            .iter()
+            .any(|info| info.depth == 3 && info.multipv == 2));
+        assert_eq!(infos.last().unwrap().multipv, 2);
+        assert_eq!(
+            responses.last(),
+            Some(&Response::BestMove(Some("dragon:b5d4".to_owned())))
+        );
+    }
+
+    #[test]
+    fn test_mcts_plays_legal_move() {
+        let responses = run_engine(&[
+            "setoption name Agent value mcts:seed=1,threads=1",
+            "position startpos seed 5 moves",
+            "go nodes 200 time 10000",
+        ]);
+
+        let game_state = GameState::with_deck(Deck::from_seed(5));
+        match responses.last() {
+            Some(Response::BestMove(Some(notation))) => {
+                assert!(
+                    parse_move(&game_state.state, game_state.curr_player_color, notation).is_ok()
+                );
+            }
+            response => panic!("Engine must answer with the move, got {:?}", response),
+        }
+        assert!(responses
+            .iter()
+            .any(|r| matches!(r, Response::Info(info) if info.visits > 0)));
+    }
+
+    #[test]
+    fn test_stop_ends_search() {
+        for agent in ["alphabeta:depth=30", "mcts:playouts=100000000"] {
+            let now = Instant::now();
+            let responses = run_engine(&[
+                &format!("setoption name Agent value {}", agent),
+                "setoption name MultiPV value 3",
+                "position startpos seed 3",
+                "go time 60000",
+                "stop",
+            ]);
+
+            assert!(now.elapsed() < Duration::from_secs(10), "{}", agent);
+            assert!(matches!(
+                responses.last(),
+                Some(Response::BestMove(Some(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_deadline_is_shared_by_lines() {
+        let now = Instant::now();
+        let responses = run_engine(&[
+            "setoption name alphabeta.depth value 30",
+            "setoption name MultiPV value 5",
+            "position startpos seed 3",
+            "go time 300",
+        ]);
+
+        assert!(now.elapsed() < Duration::from_secs(2));
+        let depths = responses
+            .iter()
+            .filter_map(|r| match r {
+                Response::Info(info) => Some(info.depth),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // Iterative deepening reports every depth
+        assert_eq!(depths[..5], [1; 5]);
+        assert!(depths.windows(2).all(|w| w[0] <= w[1]));
+        assert!(matches!(
+            responses.last(),
+            Some(Response::BestMove(Some(_)))
+        ));
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let responses = run_engine(&[
+            "search",
+            "setoption name alphabeta.depth value 1",
+            "setoption name mcts.speed value 2",
+            "position startpos seed 1 moves tiger:a1a5",
+            "isready",
+        ]);
+
+        assert_eq!(responses.len(), 5);
+        assert!(responses[..4]
+            .iter()
+            .all(|r| matches!(r, Response::Message(_))));
+        assert_eq!(responses[4], Response::ReadyOk);
+    }
+}
@@ -0,0 +1,423 @@
+//! Line based text protocol which drives the engines through stdin and stdout, similar to UCI.
+//!
+//! Commands of the client:
+//! - `onitama` - engine answers with `id name ...`, its options and `onitamaok`
+//! - `isready` - engine answers with `readyok`, even during the search
+//! - `newgame` - forgets the data of the previous game, e.g. the MCTS tree
+//! - `setoption name <name> value <value>` - `Agent` takes an agent kind or a whole specification,
+//!   `MultiPV` sets the amount of analyzed moves and `<kind>.<param>` sets a parameter of the kind
+//! - `position startpos deck <5 cards> [moves ...]`, `position startpos seed <seed> [moves ...]`
+//!   or `position fen <position> [moves ...]`, see `game::notation` for the notation
+//! - `go [time <ms>] [depth <plies>] [nodes <nodes>]` - engine streams `info` lines
+//!   and finishes with `bestmove <move>` or `bestmove none` if there are no moves
+//! - `stop` - finishes the search at once with the best move of the last finished iteration
+//! - `show` - prints the current position
+//! - `quit`
+
+pub mod engine;
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::game::{
+    deck::Deck,
+    notation::{card_name, format_position, parse_deck, parse_position},
+    player_color::PlayerColor,
+    state::State,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    Empty,
+    UnknownCommand(String),
+    InvalidArguments { command: String, reason: String },
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Empty => write!(f, "Line is empty"),
+            ProtocolError::UnknownCommand(command) => write!(f, "Unknown command: {}", command),
+            ProtocolError::InvalidArguments { command, reason } => {
+                write!(f, "Invalid arguments of {}: {}", command, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+fn invalid(command: &str, reason: impl fmt::Display) -> ProtocolError {
+    ProtocolError::InvalidArguments {
+        command: command.to_owned(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Position from which the moves of the `position` command are made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPosition {
+    /// Starting position with the given deck
+    Deck(Deck),
+    /// Starting position with the deck generated from the seed, see `Deck::from_seed`
+    Seed(u64),
+    Position(State, PlayerColor),
+}
+
+/// Limits of the `go` command. Missing limits are taken from the agent parameters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub time: Option<Duration>,
+    /// Plies which are searched by the agents with the depth
+    pub depth: Option<u8>,
+    /// Playouts of MCTS or nodes of the solver
+    pub nodes: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Onitama,
+    IsReady,
+    NewGame,
+    SetOption {
+        name: String,
+        value: String,
+    },
+    Position {
+        start: StartPosition,
+        moves: Vec<String>,
+    },
+    Go(SearchLimits),
+    Stop,
+    Show,
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let command = tokens.next().ok_or(ProtocolError::Empty)?;
+        let args = tokens.collect::<Vec<_>>();
+
+        match command {
+            "onitama" => Ok(Command::Onitama),
+            "isready" => Ok(Command::IsReady),
+            "newgame" => Ok(Command::NewGame),
+            "stop" => Ok(Command::Stop),
+            "show" => Ok(Command::Show),
+            "quit" => Ok(Command::Quit),
+            "setoption" => {
+                let value_pos = args.iter().position(|&t| t == "value");
+                match (args.first(), value_pos) {
+                    (Some(&"name"), Some(value_pos)) if value_pos > 1 => Ok(Command::SetOption {
+                        name: args[1..value_pos].join(" "),
+                        value: args[value_pos + 1..].join(" "),
+                    }),
+                    _ => Err(invalid(command, "expected name <name> value <value>")),
+                }
+            }
+            "position" => parse_position_command(&args),
+            "go" => {
+                let mut limits = SearchLimits::default();
+                for pair in args.chunks(2) {
+                    let value = pair
+                        .get(1)
+                        .ok_or_else(|| invalid(command, "missing value"))?;
+                    let number = value
+                        .parse::<u64>()
+                        .map_err(|e| invalid(command, format!("{}: {}", value, e)))?;
+                    match pair[0] {
+                        "time" => limits.time = Some(Duration::from_millis(number)),
+                        "depth" => {
+                            limits.depth =
+                                Some(u8::try_from(number).map_err(|e| invalid(command, e))?)
+                        }
+                        "nodes" => {
+                            limits.nodes =
+                                Some(u32::try_from(number).map_err(|e| invalid(command, e))?)
+                        }
+                        limit => return Err(invalid(command, format!("unknown limit {}", limit))),
+                    }
+                }
+                Ok(Command::Go(limits))
+            }
+            _ => Err(ProtocolError::UnknownCommand(command.to_owned())),
+        }
+    }
+}
+
+fn parse_position_command(args: &[&str]) -> Result<Command, ProtocolError> {
+    let command = "position";
+    let moves_pos = args
+        .iter()
+        .position(|&t| t == "moves")
+        .unwrap_or(args.len());
+    let (setup, moves) = args.split_at(moves_pos);
+    let moves = moves.iter().skip(1).map(|m| m.to_string()).collect();
+
+    let start = match setup {
+        ["startpos", "deck", cards @ ..] => {
+            // The 5 cards go in the order of the deck notation: 2 red, 2 blue and 1 neutral
+            let deck = match cards {
+                [red1, red2, blue1, blue2, neutral] => {
+                    format!("{},{} {},{} {}", red1, red2, blue1, blue2, neutral)
+                }
+                _ => cards.join(" "),
+            };
+            StartPosition::Deck(parse_deck(&deck).map_err(|e| invalid(command, e))?)
+        }
+        ["startpos", "seed", seed] => StartPosition::Seed(
+            seed.parse()
+                .map_err(|e| invalid(command, format!("{}: {}", seed, e)))?,
+        ),
+        ["fen", position @ ..] => {
+            let (state, player_color) =
+                parse_position(&position.join(" ")).map_err(|e| invalid(command, e))?;
+            StartPosition::Position(state, player_color)
+        }
+        _ => {
+            return Err(invalid(
+                command,
+                "expected startpos deck, startpos seed or fen",
+            ))
+        }
+    };
+
+    Ok(Command::Position { start, moves })
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Onitama => write!(f, "onitama"),
+            Command::IsReady => write!(f, "isready"),
+            Command::NewGame => write!(f, "newgame"),
+            Command::SetOption { name, value } => {
+                write!(f, "setoption name {} value {}", name, value)
+            }
+            Command::Position { start, moves } => {
+                match start {
+                    StartPosition::Deck(deck) => {
+                        let cards = deck.cards.iter().map(card_name).collect::<Vec<_>>();
+                        write!(f, "position startpos deck {}", cards.join(" "))?;
+                    }
+                    StartPosition::Seed(seed) => write!(f, "position startpos seed {}", seed)?,
+                    StartPosition::Position(state, player_color) => {
+                        write!(f, "position fen {}", format_position(state, *player_color))?
+                    }
+                }
+                if !moves.is_empty() {
+                    write!(f, " moves {}", moves.join(" "))?;
+                }
+                Ok(())
+            }
+            Command::Go(limits) => {
+                write!(f, "go")?;
+                if let Some(time) = limits.time {
+                    write!(f, " time {}", time.as_millis())?;
+                }
+                if let Some(depth) = limits.depth {
+                    write!(f, " depth {}", depth)?;
+                }
+                if let Some(nodes) = limits.nodes {
+                    write!(f, " nodes {}", nodes)?;
+                }
+                Ok(())
+            }
+            Command::Stop => write!(f, "stop"),
+            Command::Show => write!(f, "show"),
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+/// One of the analyzed moves during the search
+#[derive(Debug, Clone, PartialEq)]
+pub struct Info {
+    /// Searched depth, zero for the agents without the depth
+    pub depth: u8,
+    /// Rank of the move starting from 1
+    pub multipv: usize,
+    /// Score for the player to move in the units of the agent
+    pub score: f64,
+    pub visits: u32,
+    /// Time since the start of the search
+    pub time: Duration,
+    /// Moves in the notation starting with the analyzed move
+    pub pv: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    Id(String),
+    /// Option of `setoption` with its default value
+    Option {
+        name: String,
+        default: String,
+    },
+    OnitamaOk,
+    ReadyOk,
+    Info(Info),
+    /// Free text, e.g. an error of the command
+    Message(String),
+    /// Best move in the notation or nothing if there are no legal moves
+    BestMove(Option<String>),
+}
+
+impl FromStr for Response {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let response = tokens.next().ok_or(ProtocolError::Empty)?;
+        let args = tokens.collect::<Vec<_>>();
+
+        match (response, args.as_slice()) {
+            ("id", ["name", name @ ..]) => Ok(Response::Id(name.join(" "))),
+            ("option", ["name", name, "default", default @ ..]) => Ok(Response::Option {
+                name: name.to_string(),
+                default: default.join(" "),
+            }),
+            ("onitamaok", []) => Ok(Response::OnitamaOk),
+            ("readyok", []) => Ok(Response::ReadyOk),
+            ("info", ["string", message @ ..]) => Ok(Response::Message(message.join(" "))),
+            ("info", args) => parse_info(args).map(Response::Info),
+            ("bestmove", ["none"]) => Ok(Response::BestMove(None)),
+            ("bestmove", [done_move]) => Ok(Response::BestMove(Some(done_move.to_string()))),
+            ("id" | "option" | "onitamaok" | "readyok" | "bestmove", _) => {
+                Err(invalid(response, args.join(" ")))
+            }
+            _ => Err(ProtocolError::UnknownCommand(response.to_owned())),
+        }
+    }
+}
+
+fn parse_info(args: &[&str]) -> Result<Info, ProtocolError> {
+    fn number<T: FromStr>(key: &str, value: Option<&&str>) -> Result<T, ProtocolError>
+    where
+        T::Err: fmt::Display,
+    {
+        let value = value.ok_or_else(|| invalid("info", format!("missing {}", key)))?;
+        value
+            .parse()
+            .map_err(|e| invalid("info", format!("{} {}: {}", key, value, e)))
+    }
+
+    let mut info = Info {
+        depth: 0,
+        multipv: 1,
+        score: 0.,
+        visits: 0,
+        time: Duration::ZERO,
+        pv: vec![],
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match args[i] {
+            "depth" => info.depth = number("depth", value)?,
+            "multipv" => info.multipv = number("multipv", value)?,
+            "score" => info.score = number("score", value)?,
+            "visits" => info.visits = number("visits", value)?,
+            "time" => info.time = Duration::from_millis(number("time", value)?),
+            "pv" => {
+                info.pv = args[i + 1..].iter().map(|m| m.to_string()).collect();
+                break;
+            }
+            key => return Err(invalid("info", format!("unknown key {}", key))),
+        }
+        i += 2;
+    }
+
+    Ok(info)
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Response::Id(name) => write!(f, "id name {}", name),
+            Response::Option { name, default } => {
+                write!(f, "option name {} default {}", name, default)
+            }
+            Response::OnitamaOk => write!(f, "onitamaok"),
+            Response::ReadyOk => write!(f, "readyok"),
+            Response::Info(info) => write!(
+                f,
+                "info depth {} multipv {} score {} visits {} time {} pv {}",
+                info.depth,
+                info.multipv,
+                info.score,
+                info.visits,
+                info.time.as_millis(),
+                info.pv.join(" ")
+            ),
+            Response::Message(message) => write!(f, "info string {}", message),
+            Response::BestMove(Some(done_move)) => write!(f, "bestmove {}", done_move),
+            Response::BestMove(None) => write!(f, "bestmove none"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_are_written_back() {
+        for line in [
+            "onitama",
+            "setoption name alphabeta.depth value 8",
+            "setoption name Agent value mcts:time=1s,c=1.4",
+            "position startpos deck tiger dragon frog rabbit horse",
+            "position startpos seed 42 moves tiger:c1c3",
+            "position fen bbBbb/5/5/5/rrRrr b tiger,dragon frog,rabbit horse moves frog:a5b4",
+            "go time 500 depth 6 nodes 1000",
+            "go",
+            "stop",
+        ] {
+            let command = line.parse::<Command>().unwrap();
+            assert_eq!(command.to_string(), line);
+        }
+    }
+
+    #[test]
+    fn test_invalid_commands() {
+        for line in [
+            "",
+            "search",
+            "position startpos",
+            "setoption depth 8",
+            "position startpos deck tiger dragon",
+            "position startpos deck tiger tiger frog rabbit horse",
+            "position startpos seed x",
+            "position fen 5/5/5/5/5 r tiger,dragon frog,rabbit horse",
+            "go time",
+            "go time fast",
+            "go plies 4",
+        ] {
+            assert!(line.parse::<Command>().is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_responses_are_written_back() {
+        for line in [
+            "id name Onitama engine",
+            "option name alphabeta.depth default 6",
+            "onitamaok",
+            "readyok",
+            "info depth 5 multipv 2 score -12.5 visits 0 time 340 pv tiger:c1c3 frog:a5b4",
+            "info string Unknown command: search",
+            "bestmove tiger:c1c3",
+            "bestmove none",
+        ] {
+            let response = line.parse::<Response>().unwrap();
+            assert_eq!(response.to_string(), line);
+        }
+
+        assert!("bestmove".parse::<Response>().is_err());
+        assert!("info depth x".parse::<Response>().is_err());
+    }
+}
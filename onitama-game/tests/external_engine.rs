@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use onitama_game::{
+    ai::{
+        agent::{Agent, AgentError},
+        external_engine::ExternalEngine,
+        random::Random,
+        registry::AgentRegistry,
+    },
+    game::{
+        deck::Deck,
+        game_state::GameState,
+        notation::{format_move, parse_position},
+    },
+};
+
+fn stub_engine(mode: &str) -> ExternalEngine {
+    let mut engine = ExternalEngine::new(env!("CARGO_BIN_EXE_stub_engine"));
+    engine.args = vec![mode.to_owned()];
+    engine.search_time = Duration::from_millis(50);
+    engine.timeout = Duration::from_millis(500);
+    engine
+}
+
+#[test]
+fn test_engine_finds_king_capture() {
+    let spec = format!(
+        "engine:cmd={},agent=alphabeta:threads=1,time=1s,depth=3",
+        env!("CARGO_BIN_EXE_onitama_engine")
+    );
+    let engine = AgentRegistry::default().build(&spec).unwrap();
+    let (state, player_color) =
+        parse_position("bbBbb/3R1/5/5/rr1rr b rabbit,frog tiger,dragon horse").unwrap();
+    let game_state = GameState::from_state(state, player_color);
+
+    let (done_move, score) = engine.try_generate_move(&game_state).unwrap();
+    assert_eq!(format_move(&game_state.state, &done_move), "dragon:b5d4");
+    // Blue wins, so the score is negative from the red player perspective
+    assert!(score < 0., "{}", score);
+}
+
+#[test]
+fn test_engine_plays_whole_game() {
+    let agents: [Box<dyn Agent>; 2] = [Box::new(stub_engine("first")), Box::new(Random::default())];
+    let mut game_state = GameState::with_deck(Deck::from_seed(2));
+
+    for _ in 0..40 {
+        if game_state.state.is_terminal() {
+            break;
+        }
+        let agent = &agents[game_state.curr_agent_idx];
+        let (done_move, _) = agent.try_generate_move(&game_state).unwrap();
+        game_state.progress(done_move);
+    }
+}
+
+#[test]
+fn test_failures_are_errors() {
+    let game_state = GameState::with_deck(Deck::from_seed(1));
+
+    assert!(matches!(
+        stub_engine("crash").try_generate_move(&game_state),
+        Err(AgentError::Crashed(_))
+    ));
+    assert_eq!(
+        stub_engine("hang").try_generate_move(&game_state),
+        Err(AgentError::Timeout)
+    );
+    assert_eq!(
+        stub_engine("illegal").try_generate_move(&game_state),
+        Err(AgentError::InvalidMove("tiger:a1a1".to_owned()))
+    );
+    assert!(matches!(
+        ExternalEngine::new("./missing_engine").try_generate_move(&game_state),
+        Err(AgentError::Crashed(_))
+    ));
+}
+
+#[test]
+fn test_engine_rejects_invalid_agent() {
+    let mut engine = ExternalEngine::new(env!("CARGO_BIN_EXE_onitama_engine"));
+    engine.agent = Some("minimax".to_owned());
+
+    let game_state = GameState::with_deck(Deck::from_seed(1));
+    assert!(matches!(
+        engine.try_generate_move(&game_state),
+        Err(AgentError::Crashed(_))
+    ));
+}
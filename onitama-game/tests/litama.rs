@@ -0,0 +1,97 @@
+use std::{thread, time::Duration};
+
+use onitama_game::{
+    ai::alpha_beta::AlphaBeta,
+    game::done_move::DoneMove,
+    litama::{server::LitamaServer, LitamaClient, LitamaError, LitamaPlayer, MatchStatus},
+};
+
+#[test]
+fn test_agents_play_remote_match() {
+    let server = LitamaServer::start("127.0.0.1:0", Some(3)).unwrap();
+
+    let mut client = LitamaClient::connect(&server.url()).unwrap();
+    let token = client.create("deep_alphabeta").unwrap();
+    assert_eq!(
+        client.state(&token.match_id).unwrap().game_state,
+        MatchStatus::WaitingForPlayer
+    );
+
+    let url = server.url();
+    let match_id = token.match_id.clone();
+    let opponent = thread::spawn(move || {
+        let mut client = LitamaClient::connect(&url).unwrap();
+        let token = client.join(&match_id, "alphabeta").unwrap();
+        let player = LitamaPlayer::new(Box::new(AlphaBeta {
+            max_depth: 2,
+            search_time: Duration::from_millis(200),
+            ..Default::default()
+        }));
+        player.play(&mut client, &token).unwrap()
+    });
+
+    let player = LitamaPlayer::new(Box::new(AlphaBeta {
+        max_depth: 4,
+        search_time: Duration::from_millis(200),
+        ..Default::default()
+    }));
+    let winner = player.play(&mut client, &token).unwrap();
+    assert_eq!(winner, token.color);
+    assert_eq!(opponent.join().unwrap(), winner);
+
+    let state = client.state(&token.match_id).unwrap();
+    assert_eq!(state.game_state, MatchStatus::Ended);
+    assert_eq!(state.winner, Some(winner));
+    assert!(!state.moves.is_empty());
+}
+
+#[test]
+fn test_server_errors() {
+    let server = LitamaServer::start("127.0.0.1:0", Some(5)).unwrap();
+    let mut client = LitamaClient::connect(&server.url()).unwrap();
+
+    assert!(matches!(
+        client.join("missing", "player"),
+        Err(LitamaError::Server(_))
+    ));
+
+    let token = client.create("first").unwrap();
+    let joined = client.join(&token.match_id, "second").unwrap();
+    assert_ne!(joined.color, token.color);
+    assert!(matches!(
+        client.join(&token.match_id, "third"),
+        Err(LitamaError::Server(_))
+    ));
+
+    let state = client.state(&token.match_id).unwrap();
+    assert_eq!(state.game_state, MatchStatus::InProgress);
+    let game_state = state.game_state().unwrap();
+    let (card_idx, mov) = game_state
+        .state
+        .generate_all_legal_moves(game_state.curr_player_color)[0];
+    let done_move = DoneMove {
+        mov,
+        used_card_idx: card_idx,
+    };
+
+    // Only the player to move can make the move
+    let (moving, waiting) = if token.color == game_state.curr_player_color {
+        (&token, &joined)
+    } else {
+        (&joined, &token)
+    };
+    assert!(matches!(
+        client.make_move(waiting, &game_state.state, &done_move),
+        Err(LitamaError::Server(_))
+    ));
+
+    let state = client
+        .make_move(moving, &game_state.state, &done_move)
+        .unwrap();
+    assert_eq!(state.moves.len(), 1);
+    assert_eq!(state.current_turn, waiting.color);
+    assert!(matches!(
+        client.make_move(moving, &game_state.state, &done_move),
+        Err(LitamaError::Server(_))
+    ));
+}
@@ -1,22 +1,40 @@
 use std::time::Duration;
 
-use alphazero_training::{
-    alphazero_mcts::{AlphaZeroMcts, AlphaZeroMctsConfig},
-    common::Options,
-    net::ConvResNetConfig,
-};
+use alphazero_training::registry::registry;
 use egui::{Align, Layout, RichText, Slider, Ui};
 use onitama_game::ai::{
-    agent::Agent, alpha_beta::AlphaBeta, human_gui::HumanGui, mcts::Mcts, random::Random,
+    agent::Agent,
+    human_gui::HumanGui,
+    mcts::ParallelMode,
+    registry::{format_duration, AgentKind, AgentRegistry, AgentSpec, SpecError},
+    skill_level::SkillLevel,
 };
-use tch::{kind, nn, Device};
 
 use crate::player::PlayerType;
 
+/// Registry of all agents where the human makes the moves with the GUI
+pub fn agent_registry() -> AgentRegistry {
+    let mut registry = registry();
+    registry.register(AgentKind {
+        name: "human",
+        description: "Makes the moves with the GUI",
+        params: vec![],
+        build: |_| Ok(Box::new(HumanGui)),
+    });
+    registry
+}
+
 pub trait PlayerSetup: Send {
     fn show(&mut self, ui: &mut Ui);
     fn player_type(&self) -> PlayerType;
-    fn create_agent(&self) -> Box<dyn Agent>;
+    /// Agent with the selected parameters
+    fn spec(&self) -> AgentSpec;
+
+    /// Agent of the game, the seed goes to the agents which have randomness
+    fn create_agent(&self, seed: Option<u64>) -> Result<Box<dyn Agent>, SpecError> {
+        let registry = agent_registry();
+        registry.build_spec(&registry.seeded(&self.spec(), seed))
+    }
 }
 
 pub fn create_player_setup(player_type: &PlayerType) -> Box<dyn PlayerSetup> {
@@ -26,6 +44,8 @@ pub fn create_player_setup(player_type: &PlayerType) -> Box<dyn PlayerSetup> {
         PlayerType::AlphaBeta => Box::new(AlphaBetaSetup::default()),
         PlayerType::Mcts => Box::new(MctsSetup::default()),
         PlayerType::AlphaZero => Box::new(AlphaZeroSetup::default()),
+        PlayerType::Skilled => Box::new(SkilledSetup::default()),
+        PlayerType::Engine => Box::new(EngineSetup::default()),
     }
 }
 
@@ -40,8 +60,8 @@ impl PlayerSetup for HumanSetup {
         ui.label("Human does not have any parameters!");
     }
 
-    fn create_agent(&self) -> Box<dyn Agent> {
-        Box::new(HumanGui)
+    fn spec(&self) -> AgentSpec {
+        AgentSpec::new("human")
     }
 
     fn player_type(&self) -> PlayerType {
@@ -60,8 +80,8 @@ impl PlayerSetup for RandomSetup {
         ui.label("Random does not have any parameters!");
     }
 
-    fn create_agent(&self) -> Box<dyn Agent> {
-        Box::new(Random)
+    fn spec(&self) -> AgentSpec {
+        AgentSpec::new("random")
     }
 
     fn player_type(&self) -> PlayerType {
@@ -69,9 +89,44 @@ impl PlayerSetup for RandomSetup {
     }
 }
 
+pub struct SkilledSetup {
+    pub level: u8,
+}
+
+impl Default for SkilledSetup {
+    fn default() -> Self {
+        Self {
+            level: SkillLevel::default().level(),
+        }
+    }
+}
+
+impl PlayerSetup for SkilledSetup {
+    fn show(&mut self, ui: &mut Ui) {
+        ui.add_space(20.);
+        ui.label(RichText::new("Skill level parameters").text_style(egui::TextStyle::Heading));
+        ui.with_layout(Layout::left_to_right(Align::Max), |ui| {
+            ui.label("Level: ");
+            ui.add(Slider::new(
+                &mut self.level,
+                SkillLevel::MIN..=SkillLevel::MAX,
+            ));
+        });
+    }
+
+    fn spec(&self) -> AgentSpec {
+        AgentSpec::new("skill").with("level", self.level)
+    }
+
+    fn player_type(&self) -> PlayerType {
+        PlayerType::Skilled
+    }
+}
+
 pub struct AlphaBetaSetup {
     pub max_depth: u8,
     pub search_time: u64,
+    pub threads: usize,
 }
 
 impl Default for AlphaBetaSetup {
@@ -79,6 +134,7 @@ impl Default for AlphaBetaSetup {
         Self {
             max_depth: 6,
             search_time: 1000,
+            threads: 1,
         }
     }
 }
@@ -89,20 +145,31 @@ impl PlayerSetup for AlphaBetaSetup {
         ui.label(RichText::new("AlphaBeta parameters").text_style(egui::TextStyle::Heading));
         ui.with_layout(Layout::left_to_right(Align::Max), |ui| {
             ui.label("Max search depth: ");
-            ui.add(Slider::new(&mut self.max_depth, 1..=15));
+            ui.add(Slider::new(&mut self.max_depth, 2..=15));
 
             ui.add_space(20.);
 
             ui.label("Search time(ms): ");
             ui.add(Slider::new(&mut self.search_time, 100..=15000));
+
+            ui.add_space(20.);
+
+            let max_threads = std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1);
+            ui.label("Threads: ");
+            ui.add(Slider::new(&mut self.threads, 1..=max_threads));
         });
     }
 
-    fn create_agent(&self) -> Box<dyn Agent> {
-        Box::new(AlphaBeta {
-            max_depth: self.max_depth,
-            search_time: Duration::from_millis(self.search_time),
-        })
+    fn spec(&self) -> AgentSpec {
+        AgentSpec::new("alphabeta")
+            .with("depth", self.max_depth)
+            .with(
+                "time",
+                format_duration(Duration::from_millis(self.search_time)),
+            )
+            .with("threads", self.threads)
     }
 
     fn player_type(&self) -> PlayerType {
@@ -115,6 +182,9 @@ pub struct MctsSetup {
     pub min_node_visits: u32,
     pub exploration_c: f32,
     pub max_playouts: u32,
+    pub reuse_tree: bool,
+    pub threads: usize,
+    pub parallel_mode: ParallelMode,
 }
 
 impl Default for MctsSetup {
@@ -124,6 +194,9 @@ impl Default for MctsSetup {
             min_node_visits: 5,
             exploration_c: 2f32.sqrt(),
             max_playouts: 5000,
+            reuse_tree: true,
+            threads: 1,
+            parallel_mode: ParallelMode::Tree,
         }
     }
 }
@@ -150,16 +223,42 @@ impl PlayerSetup for MctsSetup {
 
             ui.label("Max playouts: ");
             ui.add(Slider::new(&mut self.max_playouts, 1..=1000000));
+
+            ui.add_space(20.);
+
+            ui.checkbox(&mut self.reuse_tree, "Reuse tree");
+
+            ui.add_space(20.);
+
+            let max_threads = std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1);
+            ui.label("Threads: ");
+            ui.add(Slider::new(&mut self.threads, 1..=max_threads));
+
+            ui.add_space(20.);
+
+            ui.selectable_value(&mut self.parallel_mode, ParallelMode::Tree, "Tree parallel");
+            ui.selectable_value(&mut self.parallel_mode, ParallelMode::Root, "Root parallel");
         });
     }
 
-    fn create_agent(&self) -> Box<dyn Agent> {
-        Box::new(Mcts {
-            search_time: Duration::from_millis(self.search_time),
-            min_node_visits: self.min_node_visits,
-            exploration_c: self.exploration_c,
-            max_playouts: self.max_playouts,
-        })
+    fn spec(&self) -> AgentSpec {
+        let parallel_mode = match self.parallel_mode {
+            ParallelMode::Tree => "tree",
+            ParallelMode::Root => "root",
+        };
+        AgentSpec::new("mcts")
+            .with(
+                "time",
+                format_duration(Duration::from_millis(self.search_time)),
+            )
+            .with("visits", self.min_node_visits)
+            .with("c", self.exploration_c)
+            .with("playouts", self.max_playouts)
+            .with("reuse", self.reuse_tree)
+            .with("threads", self.threads)
+            .with("parallel", parallel_mode)
     }
 
     fn player_type(&self) -> PlayerType {
@@ -167,11 +266,65 @@ impl PlayerSetup for MctsSetup {
     }
 }
 
+pub struct EngineSetup {
+    pub command: String,
+    /// Agent kind or specification of the engine, empty for its default agent
+    pub agent: String,
+    pub search_time: u64,
+}
+
+impl Default for EngineSetup {
+    fn default() -> Self {
+        Self {
+            command: "./target/release/onitama_engine".to_owned(),
+            agent: String::new(),
+            search_time: 1000,
+        }
+    }
+}
+
+impl PlayerSetup for EngineSetup {
+    fn show(&mut self, ui: &mut Ui) {
+        ui.add_space(20.);
+        ui.label(RichText::new("External engine parameters").text_style(egui::TextStyle::Heading));
+        ui.with_layout(Layout::left_to_right(Align::Max), |ui| {
+            ui.label("Program: ");
+            ui.text_edit_singleline(&mut self.command);
+
+            ui.add_space(20.);
+
+            ui.label("Agent: ");
+            ui.text_edit_singleline(&mut self.agent);
+
+            ui.add_space(20.);
+
+            ui.label("Search time(ms): ");
+            ui.add(Slider::new(&mut self.search_time, 100..=15000));
+        });
+    }
+
+    fn spec(&self) -> AgentSpec {
+        let spec = AgentSpec::new("engine").with("cmd", &self.command).with(
+            "time",
+            format_duration(Duration::from_millis(self.search_time)),
+        );
+        match self.agent.trim() {
+            "" => spec,
+            // Commas separate the parameters of the engine specification
+            agent => spec.with("agent", agent.replace(',', ";")),
+        }
+    }
+
+    fn player_type(&self) -> PlayerType {
+        PlayerType::Engine
+    }
+}
+
 pub struct AlphaZeroSetup {
     pub search_time: u64,
     pub exploration_c: f64,
     pub max_playouts: u32,
-    // pub model_path: String,
+    pub model_path: String,
 }
 
 impl Default for AlphaZeroSetup {
@@ -180,7 +333,7 @@ impl Default for AlphaZeroSetup {
             search_time: 1000,
             exploration_c: 2f64.sqrt(),
             max_playouts: 5000,
-            // model_path: "".to_owned(),
+            model_path: "./models/model_5e-3.ot".to_owned(),
         }
     }
 }
@@ -205,29 +358,15 @@ impl PlayerSetup for AlphaZeroSetup {
         });
     }
 
-    fn create_agent(&self) -> Box<dyn Agent> {
-        // TODO: This thing must be receved from the application
-        let mut vs = nn::VarStore::new(Device::Cpu);
-
-        let config = AlphaZeroMctsConfig {
-            search_time: Duration::from_millis(self.search_time),
-            exploration_c: self.exploration_c,
-            max_playouts: self.max_playouts,
-            train: false,
-        };
-        let net_config = ConvResNetConfig {
-            hidden_channels: 64,
-            input_channels: 21,
-            resnet_block_amnt: 5,
-        };
-        let options = Options::new(kind::FLOAT_CPU);
-        Box::new(AlphaZeroMcts::from_model_file(
-            &mut vs,
-            "./models/model_5e-3.ot",
-            config,
-            net_config,
-            options,
-        ))
+    fn spec(&self) -> AgentSpec {
+        AgentSpec::new("alphazero")
+            .with("model", &self.model_path)
+            .with(
+                "time",
+                format_duration(Duration::from_millis(self.search_time)),
+            )
+            .with("c", self.exploration_c)
+            .with("playouts", self.max_playouts)
     }
 
     fn player_type(&self) -> PlayerType {
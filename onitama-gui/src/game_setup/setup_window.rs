@@ -1,13 +1,12 @@
-use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
-};
-
 use egui::*;
 use egui_extras::{Size, StripBuilder};
-use onitama_game::game::{
-    card::{Card, CARD_NAMES, ORIGINAL_CARDS},
-    deck::Deck,
+use onitama_game::{
+    common::seeded_rng,
+    game::{
+        card::{Card, CARD_NAMES, ORIGINAL_CARDS},
+        deck::Deck,
+    },
+    match_runner::tournament::Format,
 };
 use rand::{thread_rng, Rng};
 
@@ -55,6 +54,8 @@ pub struct SetupWindow<'a> {
     selected_players: &'a mut [(PlayerType, Box<dyn PlayerSetup>); 2],
     players: &'a mut [Player; 2],
     tournament: &'a mut Tournament,
+    /// Seed of the random cards and the agents, every game is random without it
+    seed: &'a mut Option<u64>,
 }
 
 impl<'a> SetupWindow<'a> {
@@ -64,6 +65,7 @@ impl<'a> SetupWindow<'a> {
         selected_players: &'a mut [(PlayerType, Box<dyn PlayerSetup>); 2],
         players: &'a mut [Player; 2],
         tournament: &'a mut Tournament,
+        seed: &'a mut Option<u64>,
     ) -> Self {
         Self {
             selected_cards,
@@ -71,6 +73,7 @@ impl<'a> SetupWindow<'a> {
             selected_players,
             players,
             tournament,
+            seed,
         }
     }
 
@@ -171,6 +174,8 @@ impl<'a> SetupWindow<'a> {
                 ui.selectable_value(player_type, PlayerType::AlphaBeta, "AlphaBeta");
                 ui.selectable_value(player_type, PlayerType::Mcts, "MCTS");
                 ui.selectable_value(player_type, PlayerType::AlphaZero, "AlphaZero");
+                ui.selectable_value(player_type, PlayerType::Skilled, "Skill level");
+                ui.selectable_value(player_type, PlayerType::Engine, "External engine");
             });
     }
 
@@ -189,7 +194,7 @@ impl<'a> SetupWindow<'a> {
 
             let random_btn = ui.button("Random cards!");
             if random_btn.clicked() {
-                self.fill_random();
+                self.fill_random(&mut thread_rng());
             }
             random_btn.on_hover_text("Take random cards in addition to already chosen cards");
             ui.add_space(15.);
@@ -237,13 +242,14 @@ impl<'a> SetupWindow<'a> {
     fn show_bottom_panel(&mut self, ui: &mut Ui, should_start_new_game: &'a mut bool) {
         ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
             let start_game_btn = ui.button("Start a game");
-            if start_game_btn.clicked() {
+            if start_game_btn.clicked() && self.assign_players() {
                 self.create_deck();
-                self.assign_players();
                 *should_start_new_game = true;
             }
             ui.add_space(10.);
             ui.checkbox(&mut true, "Save my choice");
+            ui.add_space(10.);
+            self.seed_setup(ui);
         });
 
         ui.separator();
@@ -256,23 +262,86 @@ impl<'a> SetupWindow<'a> {
         r.on_hover_text("Setup a tournament between agents");
 
         ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
-            ui.label("Round amount");
+            if ui.button("Add red player").clicked() {
+                self.add_entrant(0);
+            }
+            if ui.button("Add blue player").clicked() {
+                self.add_entrant(1);
+            }
+            ui.label(format!("{} agents", self.tournament.entrants.len()))
+                .on_hover_text("The agents are added with the parameters of the player setup");
+        });
+
+        let mut removed = None;
+        for (idx, (_, spec)) in self.tournament.entrants.iter().enumerate() {
+            ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(idx);
+                }
+                ui.label(format!("{}. {}", idx + 1, spec));
+            });
+        }
+        if let Some(idx) = removed {
+            self.tournament.entrants.remove(idx);
+        }
+
+        ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+            ui.label("Format");
+            egui::ComboBox::from_id_source("tournament_format_combo_box")
+                .selected_text(self.tournament.format.to_string())
+                .show_ui(ui, |ui| {
+                    let format = &mut self.tournament.format;
+                    ui.selectable_value(format, Format::RoundRobin, "Round-robin");
+                    ui.selectable_value(format, Format::Gauntlet, "Gauntlet")
+                        .on_hover_text("The first agent plays every other one");
+                    ui.selectable_value(format, Format::Swiss { rounds: 5 }, "Swiss");
+                });
+            if let Format::Swiss { rounds } = &mut self.tournament.format {
+                ui.label("Rounds");
+                ui.add(Slider::new(rounds, 1..=50));
+            }
+
+            ui.add_space(20.);
+
+            ui.label("Games of every pairing");
             ui.add(Slider::new(&mut self.tournament.round_amnt, 1..=1000));
+        });
 
-            ui.add_space(100.);
+        ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+            ui.label("State file");
+            ui.text_edit_singleline(&mut self.tournament.state_file)
+                .on_hover_text(
+                    "The tournament is saved to the file after every game and resumed from it",
+                );
+        });
 
+        for (rank, standing) in self.tournament.state.standings().iter().enumerate() {
             ui.label(format!(
-                "{} won {} games",
-                self.tournament.players[0].to_string(),
-                self.tournament.result.wins[0]
+                "{}. {}: {} points of {} games",
+                rank + 1,
+                self.tournament.state.agents[standing.agent],
+                standing.points,
+                standing.score.games()
             ));
-            ui.add_space(100.);
+        }
 
-            ui.label(format!(
-                "{} won {} games",
-                self.tournament.players[1].to_string(),
-                self.tournament.result.wins[1]
-            ));
+        ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+            ui.label("Opening suite");
+            ui.text_edit_singleline(&mut self.tournament.suite_path)
+                .on_hover_text("File with an opening per line, e.g. made by opening_suite");
+            if ui.button("Load suite").clicked() {
+                match self.tournament.load_suite() {
+                    Ok(amount) => tracing::info!("Loaded {} openings", amount),
+                    Err(e) => {
+                        tracing::error!("Cannot load the suite: {}", e);
+                        self.tournament.suite.clear();
+                    }
+                }
+            }
+            if ui.button("Clear").clicked() {
+                self.tournament.suite.clear();
+            }
+            ui.label(format!("{} openings", self.tournament.suite.len()));
         });
 
         ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
@@ -280,47 +349,44 @@ impl<'a> SetupWindow<'a> {
             ui.add_space(20.);
 
             if start_tournament_btn.clicked() {
-                self.tournament.is_tournament_on = true;
-                self.create_deck();
-                self.assign_players();
-                self.tournament.deck = self.deck.clone();
-
-                let players = self
-                    .selected_players
-                    .iter()
-                    .map(|&(p, _)| p)
-                    .collect::<Vec<_>>();
-                self.tournament.players_hash = self
-                    .players
-                    .iter()
-                    .map(|p| {
-                        let mut state = DefaultHasher::new();
-                        p.agent.hash(&mut state);
-                        state.finish()
-                    })
-                    .collect::<Vec<_>>()
-                    .try_into()
-                    .unwrap();
-                self.tournament.players = players.try_into().unwrap();
-                *should_start_new_game = true;
+                if self.tournament.entrants.len() < 2 {
+                    tracing::error!("The tournament needs at least two agents");
+                } else {
+                    self.tournament.is_tournament_on = true;
+                    self.tournament.seed = *self.seed;
+                    self.create_deck();
+                    *should_start_new_game = true;
+                }
             }
 
             ui.checkbox(&mut self.tournament.save_games, "Save tournament games?");
             ui.add_space(20.);
 
-            ui.checkbox(&mut self.tournament.do_player_swap, "Do players swap?");
-            ui.add_space(20.);
-
             ui.checkbox(
                 &mut self.tournament.random_deck_each_turn,
-                "Use random deck each turn?",
-            );
+                "Use random deck each pair of games?",
+            )
+            .on_hover_text("Every deck is played twice with the colors swapped");
             ui.add_space(20.);
         });
     }
 
-    fn fill_random(&mut self) {
-        let mut rng = thread_rng();
+    /// Checkbox and value of the seed which makes the games reproducible
+    fn seed_setup(&mut self, ui: &mut Ui) {
+        if let Some(seed) = self.seed.as_mut() {
+            ui.add(DragValue::new(seed));
+        }
+        let mut is_seeded = self.seed.is_some();
+        ui.checkbox(&mut is_seeded, "Seed")
+            .on_hover_text("The same seed gives the same random cards and moves of the agents");
+        match (is_seeded, self.seed.is_some()) {
+            (true, false) => *self.seed = Some(0),
+            (false, true) => *self.seed = None,
+            _ => (),
+        }
+    }
+
+    fn fill_random(&mut self, rng: &mut impl Rng) {
         for card_idx in 0..self.selected_cards.len() {
             if let None = self.selected_cards[card_idx] {
                 loop {
@@ -336,7 +402,7 @@ impl<'a> SetupWindow<'a> {
     }
 
     fn create_deck(&mut self) {
-        self.fill_random();
+        self.fill_random(&mut seeded_rng(*self.seed, 0));
 
         let cards = self
             .selected_cards
@@ -347,14 +413,35 @@ impl<'a> SetupWindow<'a> {
         *self.deck = Deck::new(cards.try_into().expect("Must be 5 cards"));
     }
 
-    fn assign_players(&mut self) {
-        let player = |(p, s): &(PlayerType, Box<dyn PlayerSetup>)| {
-            let agent = s.create_agent();
-            Player { typ: *p, agent }
-        };
+    /// Adds the agent of the player setup to the tournament
+    fn add_entrant(&mut self, idx: usize) {
+        let (_, setup) = &self.selected_players[idx];
+        self.tournament
+            .entrants
+            .push((setup.player_type(), setup.spec()));
+    }
 
-        self.players[0] = player(&self.selected_players[0]);
-        self.players[1] = player(&self.selected_players[1]);
+    /// Returns false and keeps the old players if an agent cannot be created, e.g. without the model file
+    fn assign_players(&mut self) -> bool {
+        let mut agents = vec![];
+        for (_, setup) in self.selected_players.iter() {
+            match setup.create_agent(*self.seed) {
+                Ok(agent) => agents.push(agent),
+                Err(e) => {
+                    tracing::error!("Cannot create the agent {}: {}", setup.spec(), e);
+                    return false;
+                }
+            }
+        }
+
+        for (player, ((typ, _), agent)) in self
+            .players
+            .iter_mut()
+            .zip(self.selected_players.iter().zip(agents))
+        {
+            *player = Player { typ: *typ, agent };
+        }
+        true
     }
 
     fn clear_selected_cards(&mut self) {
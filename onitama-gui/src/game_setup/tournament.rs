@@ -1,120 +1,200 @@
 use std::{fs, io, path::PathBuf};
 
-use onitama_game::game::deck::Deck;
-use serde::Serialize;
+use onitama_game::{
+    ai::registry::{AgentSpec, SpecError},
+    common::seeded_rng,
+    game::{deck::Deck, game_state::GameState, move_result::MoveResult},
+    match_runner::{
+        suite::{load_suite, Opening, SuiteError},
+        tournament::{Format, GameResult, TournamentError, TournamentState},
+        ScheduledGame, Termination,
+    },
+};
 
-use crate::player::PlayerType;
+use crate::player::{Player, PlayerType};
 
-#[derive(Serialize)]
-pub struct TournamentResult {
-    // First and a second player winrate
-    // Their sum must be the same as round amount
-    pub wins: [u32; 2],
-}
-
-impl Default for TournamentResult {
-    fn default() -> Self {
-        Self { wins: [0; 2] }
-    }
-}
+use super::player_setups::agent_registry;
 
-#[derive(Serialize)]
-pub struct RoundResult {
-    pub round: u32,
-    pub move_amnt: usize,
-    pub winning_player: PlayerType,
-    pub winning_player_hash: u64,
-}
-
-#[derive(Serialize)]
+/// Tournament of the agents added in the setup, played on the board one game after another.
+/// The schedule and the results are kept by the tournament library,
+/// so every deck is played twice with the colors swapped.
+/// With an opening suite the pairs of games take the openings of the suite in turn
 pub struct Tournament {
+    /// Games of every pairing
     pub round_amnt: u32,
-    pub curr_round: u32,
     pub save_games: bool,
-    pub do_player_swap: bool,
     pub is_tournament_on: bool,
     pub random_deck_each_turn: bool,
-    // TODO: identify player by hash
-    pub players: [PlayerType; 2],
-    pub players_hash: [u64; 2],
-    pub result: TournamentResult,
-    pub round_result_history: Vec<RoundResult>,
+    /// File of the opening suite written in the setup window
+    pub suite_path: String,
+    pub suite: Vec<Opening>,
+    /// Agents of the tournament, the first one is the challenger of the gauntlet
+    pub entrants: Vec<(PlayerType, AgentSpec)>,
+    pub format: Format,
+    /// The state is saved to this file after every game and resumed from it, empty without saving
+    pub state_file: String,
+    /// Seed of the random decks and the agents, every game gets it increased by the number of the game
+    pub seed: Option<u64>,
+    /// Deck of the game which is played now
     pub deck: Deck,
-    pub deck_history: Vec<Deck>,
+    pub state: TournamentState,
+    /// Game which is played now, the agents are the indices of `entrants`
+    pub current: Option<ScheduledGame>,
 }
 
 impl Default for Tournament {
     fn default() -> Self {
-        let deck = Deck::default();
-        let deck_history = vec![deck.clone()];
         Self {
             round_amnt: 10,
-            curr_round: 1,
             save_games: false,
             is_tournament_on: false,
-            do_player_swap: true,
             random_deck_each_turn: true,
-            players: [PlayerType::Human, PlayerType::Mcts],
-            players_hash: [0; 2],
-            deck,
-            result: TournamentResult::default(),
-            deck_history,
-            round_result_history: Vec::new(),
+            suite_path: String::new(),
+            suite: vec![],
+            entrants: vec![],
+            format: Format::RoundRobin,
+            state_file: String::new(),
+            seed: None,
+            deck: Deck::default(),
+            state: TournamentState::new(vec![], Format::RoundRobin, 0),
+            current: None,
         }
     }
 }
 
 impl Tournament {
-    pub fn progress(
-        &mut self,
-        move_amnt: usize,
-        winning_player: PlayerType,
-        winning_player_hash: u64,
-    ) {
-        if self.random_deck_each_turn {
-            self.deck = Deck::default();
-            self.deck_history.push(self.deck.clone());
+    /// Schedules the games of the entrants or resumes the saved tournament of the state file.
+    /// The loaded suite goes first, without the random decks every game is played with the given deck
+    pub fn start(&mut self, deck: Deck) -> Result<(), TournamentError> {
+        let mut state = TournamentState::new(self.names(), self.format, self.round_amnt as usize);
+        if let Some(path) = self.state_path().filter(|path| path.exists()) {
+            let saved = TournamentState::load(&path)?;
+            if (&saved.agents, saved.format, saved.games)
+                != (&state.agents, state.format, state.games)
+            {
+                return Err(TournamentError::Mismatch(path));
+            }
+            state = saved;
         }
-        self.round_result_history.push(RoundResult {
-            round: self.curr_round,
-            move_amnt,
-            winning_player,
-            winning_player_hash,
+
+        self.state = state;
+        self.deck = deck;
+        self.next_game();
+        Ok(())
+    }
+
+    /// Adds the result of the game which is played now, saves the state and takes the next game
+    pub fn progress(&mut self, result: MoveResult) -> io::Result<()> {
+        let finished = match self.current.take() {
+            Some(game) => game,
+            None => return Ok(()),
+        };
+        let points = match result {
+            MoveResult::RedWin => 1.,
+            MoveResult::BlueWin => 0.,
+            _ => 0.5,
+        };
+        self.state.add_result(GameResult {
+            number: finished.number,
+            red: finished.red,
+            blue: finished.blue,
+            points,
+            termination: Termination::Normal,
         });
-        let index = self
-            .players_hash
-            .iter()
-            .position(|&p| p == winning_player_hash)
-            .unwrap();
-        self.result.wins[index] += 1;
-        self.curr_round += 1;
+
+        self.next_game();
+        match self.state_path() {
+            Some(path) => self.state.save(&path),
+            None => Ok(()),
+        }
+    }
+
+    /// New players of the game which is played now, the red one goes first
+    pub fn players(&self, game: &ScheduledGame) -> Result<[Player; 2], SpecError> {
+        let registry = agent_registry();
+        let seed = self.game_seed(game);
+        let player = |idx: usize| -> Result<Player, SpecError> {
+            let (typ, spec) = &self.entrants[idx];
+            Ok(Player {
+                typ: *typ,
+                agent: registry.build_spec(&registry.seeded(spec, seed))?,
+            })
+        };
+        Ok([player(game.red)?, player(game.blue)?])
+    }
+
+    pub fn game_seed(&self, game: &ScheduledGame) -> Option<u64> {
+        self.seed.map(|seed| seed.wrapping_add(game.number as u64))
+    }
+
+    /// Reads the suite from `suite_path` and returns the amount of the openings
+    pub fn load_suite(&mut self) -> Result<usize, SuiteError> {
+        self.suite = load_suite(&self.suite_path)?;
+        Ok(self.suite.len())
+    }
+
+    /// Game of the current opening after its moves
+    pub fn game_state(&self) -> GameState {
+        match &self.current {
+            Some(game) => game.opening.game_state(),
+            None => GameState::with_deck(self.deck.clone()),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.is_finished()
     }
 
     pub fn get_filename(&self) -> String {
         let now = chrono::offset::Local::now();
         let datetime = now.format("%Y%m%y_%H%M%S");
-        format!(
-            "tour_{}_vs_{}_{}.json",
-            self.players[0].to_string().to_lowercase().replace(" ", "_"),
-            self.players[1].to_string().to_lowercase().replace(" ", "_"),
-            datetime
-        )
+        format!("tournament_{}.json", datetime)
     }
 
     pub fn save_to_folder(&self, folder: &String) -> io::Result<()> {
         let path = PathBuf::from(format!("{}/{}", folder, self.get_filename()));
-        fs::write(
-            path,
-            serde_json::to_string_pretty(&self)
-                .expect("Serde must serialize move history with no problem"),
-        )?;
-        Ok(())
+        fs::create_dir_all(folder)?;
+        self.state.save(&path)
     }
 
     pub fn clear(&mut self) {
-        self.curr_round = 1;
-        self.result = TournamentResult::default();
-        self.deck_history = vec![];
-        self.round_result_history = vec![];
+        self.state = TournamentState::new(self.names(), self.format, 0);
+        self.current = None;
+    }
+
+    /// Schedules the rounds which can be played now, e.g. the next Swiss round, and takes the first pending game
+    fn next_game(&mut self) {
+        let random_deck_each_turn = self.random_deck_each_turn;
+        let suite = &self.suite;
+        let deck = &self.deck;
+        let seed = self.seed;
+        self.state.schedule(|pair| {
+            if !suite.is_empty() {
+                suite[pair % suite.len()].clone()
+            } else if random_deck_each_turn {
+                Opening::from(Deck::random(&mut seeded_rng(seed, pair as u64)))
+            } else {
+                Opening::from(deck.clone())
+            }
+        });
+
+        self.current = self.state.pending().into_iter().next();
+        if let Some(game) = &self.current {
+            self.deck = game.opening.deck.clone();
+        }
+    }
+
+    fn state_path(&self) -> Option<PathBuf> {
+        match self.state_file.trim() {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.entrants
+            .iter()
+            .map(|(_, spec)| spec.to_string())
+            .collect()
     }
 }
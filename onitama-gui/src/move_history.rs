@@ -18,6 +18,8 @@ pub struct MoveHistory {
     // no need to own player objects
     red_player: Box<dyn Agent>,
     blue_player: Box<dyn Agent>,
+    /// Seed of the game if it was played with one
+    pub seed: Option<u64>,
     history: Vec<MoveInformation>,
 }
 
@@ -26,6 +28,7 @@ impl MoveHistory {
         Self {
             red_player,
             blue_player,
+            seed: None,
             history: vec![],
         }
     }
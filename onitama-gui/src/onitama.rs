@@ -1,6 +1,4 @@
-use std::collections::hash_map::DefaultHasher;
 use std::fs;
-use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
 use std::sync::mpsc::{sync_channel, Receiver};
 use std::thread::{self, JoinHandle};
@@ -14,6 +12,7 @@ use egui::{
 };
 use egui_extras::{Size, StripBuilder};
 use egui_toast::{Toast, ToastOptions, Toasts};
+use onitama_game::ai::agent::{Agent, AgentError};
 use onitama_game::ai::human_gui::HumanGui;
 use onitama_game::ai::mcts::Mcts;
 use onitama_game::game::piece::{Piece, PieceKind};
@@ -62,14 +61,17 @@ pub struct Onitama {
     should_start_new_game: bool,
     selected_players: [(PlayerType, Box<dyn PlayerSetup>); 2],
     players: [Player; 2],
-    mov_rx: Option<Receiver<(DoneMove, f64)>>,
+    mov_rx: Option<Receiver<Result<(DoneMove, f64), AgentError>>>,
     do_ai_move_generation: bool,
-    // Needed to stop calculation when it is needed
-    move_generation_thread: Option<JoinHandle<()>>,
+    // Needed to stop calculation when it is needed.
+    // The thread returns the agent, so the agent keeps its state, e.g. the MCTS tree
+    move_generation_thread: Option<JoinHandle<Box<dyn Agent>>>,
     evaluation_score: f64,
     move_history: MoveHistory,
     tournament: Tournament,
     tournament_folder: Option<String>,
+    /// Seed of the games set in the setup
+    seed: Option<u64>,
     toasts: Toasts,
 }
 
@@ -130,6 +132,7 @@ impl Onitama {
             evaluation_score: 0.,
             tournament: Tournament::default(),
             tournament_folder: None,
+            seed: None,
             toasts,
         }
     }
@@ -224,16 +227,53 @@ impl Onitama {
                     let agent = self.players[game_state.curr_agent_idx].agent.clone();
 
                     self.move_generation_thread = Some(thread::spawn(move || {
-                        let mov = agent.generate_move(&game_state);
+                        let mov = agent.try_generate_move(&game_state);
                         if let Err(e) = mov_tx.send(mov) {
                             tracing::error!("Error sending a move: {}", e);
                         }
+                        agent
                     }));
                 }
 
                 if let Some(rx) = &self.mov_rx {
                     if let Ok(mov) = rx.try_recv() {
-                        let (mov, score) = mov;
+                        let (mov, score) = match mov {
+                            Ok(mov) => mov,
+                            Err(e) => {
+                                let player_color = self.game_state.curr_player_color;
+                                tracing::error!(
+                                    "{} forfeits the game: {}",
+                                    player_color.to_string(),
+                                    e
+                                );
+                                self.toasts.add(Toast {
+                                    kind: egui_toast::ToastKind::Error,
+                                    text: format!(
+                                        "{} forfeits the game: {}",
+                                        player_color.to_string(),
+                                        e
+                                    )
+                                    .into(),
+                                    options: ToastOptions::default(),
+                                });
+
+                                self.move_result = Some(MoveResult::forfeit(player_color));
+                                self.mov_rx = None;
+                                self.do_ai_move_generation = true;
+                                ctx.request_repaint();
+                                return;
+                            }
+                        };
+
+                        // The agent which has searched the move replaces its clone
+                        if let Some(thread) = self.move_generation_thread.take() {
+                            match thread.join() {
+                                Ok(agent) => {
+                                    self.players[self.game_state.curr_agent_idx].agent = agent
+                                }
+                                Err(_) => tracing::error!("Move generation thread has panicked"),
+                            }
+                        }
 
                         self.evaluation_score = score;
                         self.last_played_move = Some(Move::convert_to_2d(mov.mov.to));
@@ -614,10 +654,8 @@ impl Onitama {
 
     // TODO: in the last round an agent still makes one move
     fn organize_tournament(&mut self, ctx: &Context) {
-        if self.tournament.is_tournament_on
-            && self.tournament.curr_round == self.tournament.round_amnt + 1
-        {
-            tracing::info!("Tournament ended!");
+        if self.tournament.is_tournament_on && self.tournament.is_finished() {
+            tracing::info!("Tournament ended!\n{}", self.tournament.state);
 
             if self.tournament.save_games {
                 let folder = match &self.tournament_folder {
@@ -659,13 +697,9 @@ impl Onitama {
 
                     tracing::info!("Winning player: {:?}", winning_player.typ);
 
-                    let mut hasher = DefaultHasher::new();
-                    winning_player.agent.hash(&mut hasher);
-                    let hash = hasher.finish();
-
-                    self.tournament
-                        .progress(self.move_history.len(), winning_player.typ, hash);
-
+                    if let Err(e) = self.tournament.progress(result) {
+                        tracing::error!("Cannot save the tournament state: {}", e);
+                    }
                     self.deck = self.tournament.deck.clone();
 
                     // Save a match
@@ -689,26 +723,49 @@ impl Onitama {
                         }
                     }
 
-                    // Clear the game
-                    if self.tournament.do_player_swap {
-                        self.players.swap(0, 1);
-
-                        self.move_history.update_players(
-                            self.players[0].agent.clone(),
-                            self.players[1].agent.clone(),
-                        );
-                    }
-
-                    self.game_state = GameState::with_deck(self.deck.clone());
-
                     self.clear_game();
+                    self.start_tournament_game();
 
-                    tracing::info!("Current tournament round: {}", self.tournament.curr_round);
+                    if let Some(game) = &self.tournament.current {
+                        tracing::info!("Current tournament game: {}", game.number);
+                    }
                 }
             }
         }
     }
 
+    /// Takes the opening and new players of the current tournament game.
+    /// The tournament stops if an agent cannot be created
+    fn start_tournament_game(&mut self) {
+        let players = match &self.tournament.current {
+            Some(game) => self.tournament.players(game),
+            None => return,
+        };
+        match players {
+            Ok(players) => self.players = players,
+            Err(e) => {
+                tracing::error!("Cannot create a tournament player: {}", e);
+                self.toasts.add(Toast {
+                    kind: egui_toast::ToastKind::Error,
+                    text: format!("Cannot create a tournament player: {}", e).into(),
+                    options: ToastOptions::default(),
+                });
+                self.tournament.is_tournament_on = false;
+                self.end_game = true;
+                return;
+            }
+        }
+
+        self.game_state = self.tournament.game_state();
+        self.move_history
+            .update_players(self.players[0].agent.clone(), self.players[1].agent.clone());
+        self.move_history.seed = self
+            .tournament
+            .current
+            .as_ref()
+            .and_then(|game| self.tournament.game_seed(game));
+    }
+
     fn clear_game(&mut self) {
         self.game_state.clear();
         self.selected_card = SelectedCard::default();
@@ -751,7 +808,22 @@ impl App for Onitama {
                     Err(e) => tracing::error!("Was not able to create a folder: {}", e),
                 }
             }
-            self.tournament.clear();
+            if self.tournament.is_tournament_on {
+                match self.tournament.start(self.deck.clone()) {
+                    Ok(()) => self.deck = self.tournament.deck.clone(),
+                    Err(e) => {
+                        tracing::error!("Cannot start the tournament: {}", e);
+                        self.toasts.add(Toast {
+                            kind: egui_toast::ToastKind::Error,
+                            text: format!("Cannot start the tournament: {}", e).into(),
+                            options: ToastOptions::default(),
+                        });
+                        self.tournament.is_tournament_on = false;
+                    }
+                }
+            } else {
+                self.tournament.clear();
+            }
 
             // Close game setup window
             self.show_game_setup = false;
@@ -759,6 +831,11 @@ impl App for Onitama {
             self.clear_game();
             self.move_history
                 .update_players(self.players[0].agent.clone(), self.players[1].agent.clone());
+            self.move_history.seed = self.seed;
+            // The opening of the suite can start after some moves
+            if self.tournament.is_tournament_on {
+                self.start_tournament_game();
+            }
             // Do not make a new game
             self.should_start_new_game = false;
         }
@@ -796,6 +873,7 @@ impl App for Onitama {
             &mut self.selected_players,
             &mut self.players,
             &mut self.tournament,
+            &mut self.seed,
         )
         .show_setup(
             ctx,
@@ -13,6 +13,8 @@ pub enum PlayerType {
     AlphaBeta,
     Mcts,
     AlphaZero,
+    Skilled,
+    Engine,
 }
 
 impl PlayerType {
@@ -32,6 +34,8 @@ impl ToString for PlayerType {
             PlayerType::AlphaBeta => "AlphaBeta".to_owned(),
             PlayerType::Mcts => "MCTS".to_owned(),
             PlayerType::AlphaZero => "AlphaZero".to_owned(),
+            PlayerType::Skilled => "Skill level".to_owned(),
+            PlayerType::Engine => "External engine".to_owned(),
         }
     }
 }
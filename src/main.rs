@@ -1,14 +1,29 @@
-use onitama_game::ai::agent::Agent;
-use onitama_game::ai::{human_console::HumanConsole, random::Random};
+use onitama_game::ai::{agent::Agent, registry::AgentRegistry};
 use onitama_game::game::{game_state::GameState, move_result::MoveResult};
 
 fn main() {
     let mut progress = MoveResult::InProgress;
 
-    let red_agent = HumanConsole;
-    let blue_agent = Random;
+    // Red and blue agents are given as the specifications, e.g. `human alphabeta:depth=8`
+    let registry = AgentRegistry::default();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("Usage: onitama-alphazero [RED] [BLUE]\n");
+        println!("{}", registry.help());
+        return;
+    }
 
-    let agents: [Box<dyn Agent>; 2] = [Box::new(red_agent), Box::new(blue_agent)];
+    let specs = [
+        args.first().map(String::as_str).unwrap_or("human"),
+        args.get(1).map(String::as_str).unwrap_or("random"),
+    ];
+    let agents: [Box<dyn Agent>; 2] = specs.map(|spec| match registry.build(spec) {
+        Ok(agent) => agent,
+        Err(e) => {
+            eprintln!("{}: {}", spec, e);
+            std::process::exit(1);
+        }
+    });
     let mut game = GameState::new();
     let mut max_plies = 200;
 